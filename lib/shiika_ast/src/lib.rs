@@ -44,6 +44,7 @@ pub enum Definition {
         typarams: Vec<AstTyParam>,
         supers: Vec<UnresolvedTypeName>,
         defs: Vec<Definition>,
+        annotations: Vec<Annotation>,
     },
     ModuleDefinition {
         name: ModuleFirstname,
@@ -56,6 +57,12 @@ pub enum Definition {
         cases: Vec<EnumCase>,
         defs: Vec<Definition>,
     },
+    /// `extension Foo ... end`
+    ExtensionDefinition {
+        name: UnresolvedTypeName,
+        typarams: Vec<AstTyParam>,
+        defs: Vec<Definition>,
+    },
     InstanceMethodDefinition {
         sig: AstMethodSignature,
         body_exprs: Vec<AstExpression>,
@@ -69,9 +76,16 @@ pub enum Definition {
     MethodRequirementDefinition {
         sig: AstMethodSignature,
     },
+    /// `abstract def foo(x: Int) -> String`
+    AbstractMethodDefinition {
+        sig: AstMethodSignature,
+    },
     ConstDefinition {
         name: String,
         expr: AstExpression,
+        /// true if defined with `const` (the value must be known at compile
+        /// time)
+        compile_time: bool,
     },
 }
 
@@ -95,6 +109,23 @@ pub fn find_class_initializer(defs: &[Definition]) -> Option<&InitializerDefinit
     })
 }
 
+/// An annotation placed before a definition
+/// eg. `@derive(Printable)`
+#[derive(Debug, PartialEq, Clone)]
+pub struct Annotation {
+    /// eg. "derive"
+    pub name: String,
+    /// eg. `["Printable"]`
+    pub args: Vec<String>,
+}
+
+impl Annotation {
+    /// Returns true if this is `@name(.., arg, ..)`
+    pub fn has_arg(&self, arg: &str) -> bool {
+        self.args.iter().any(|x| x == arg)
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct EnumCase {
     pub name: ClassFirstname,
@@ -107,6 +138,9 @@ pub struct AstMethodSignature {
     pub typarams: Vec<AstTyParam>,
     pub params: Vec<Param>,
     pub ret_typ: Option<UnresolvedTypeName>,
+    pub annotations: Vec<Annotation>,
+    /// The method name (including `self.`)
+    pub locs: LocationSpan,
 }
 
 /// A type parameter
@@ -114,6 +148,8 @@ pub struct AstMethodSignature {
 pub struct AstTyParam {
     pub name: String,
     pub variance: AstVariance,
+    /// eg. `Printable` of `def foo<T: Printable>`
+    pub upper_bound: Option<UnresolvedTypeName>,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -127,7 +163,12 @@ pub enum AstVariance {
 pub struct Param {
     pub name: String,
     pub typ: UnresolvedTypeName,
-    pub is_iparam: bool, // eg. `def initialize(@a: Int)`
+    pub is_iparam: bool,   // eg. `def initialize(@a: Int)`
+    pub is_variadic: bool, // eg. `def log(*args: Object)`
+    /// Default value (eg. `def greet(name: String = "World")`)
+    pub default_expr: Option<AstExpression>,
+    /// The name and the type (i.e. does not include the default value)
+    pub locs: LocationSpan,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -178,7 +219,24 @@ pub enum AstExpressionBody {
         cond_expr: Box<AstExpression>,
         body_exprs: Vec<AstExpression>,
     },
-    Break,
+    /// `for x in collection do ... end`
+    ForIn {
+        var: String,
+        collection: Box<AstExpression>,
+        body: Vec<AstExpression>,
+    },
+    /// `guard let PATTERN = EXPR else ... end`
+    GuardLet {
+        pattern: AstPattern,
+        expr: Box<AstExpression>,
+        else_exprs: Vec<AstExpression>,
+    },
+    Break {
+        arg: Option<Box<AstExpression>>,
+    },
+    Continue,
+    /// `expr?` (returns the `Fail` from the method, if `expr` is a `Fail`)
+    Propagate(Box<AstExpression>),
     Return {
         arg: Option<Box<AstExpression>>,
     },
@@ -187,6 +245,12 @@ pub enum AstExpressionBody {
         rhs: Box<AstExpression>,
         readonly: bool,
     },
+    /// `let (a, b) = ...`, `let Pair(x, y) = ...`, etc.
+    PatternDecl {
+        pattern: AstPattern,
+        rhs: Box<AstExpression>,
+        readonly: bool,
+    },
     LVarAssign {
         name: String,
         rhs: Box<AstExpression>,
@@ -205,6 +269,15 @@ pub enum AstExpressionBody {
         rhs: Box<AstExpression>,
     },
     MethodCall(AstMethodCall),
+    /// Keyword argument of a method call (eg. `x: 1` of `foo(x: 1)`)
+    KeywordArg {
+        name: String,
+        expr: Box<AstExpression>,
+    },
+    /// Splat argument of a method call (eg. `*x` of `foo(*x)`)
+    Splat {
+        expr: Box<AstExpression>,
+    },
     LambdaExpr {
         params: Vec<BlockParam>,
         exprs: Vec<AstExpression>,
@@ -220,7 +293,26 @@ pub enum AstExpressionBody {
         args: Vec<AstExpression>,
     },
     PseudoVariable(Token),
+    /// `super(args)` (calls the method of the same name in the superclass)
+    Super {
+        args: Vec<AstExpression>,
+    },
+    /// `assert_type(expr, Type)` (checks the type of `expr` at compile time)
+    AssertType {
+        expr: Box<AstExpression>,
+        typ: UnresolvedTypeName,
+    },
     ArrayLiteral(Vec<AstExpression>),
+    /// `(a, b)`, `(a, b, c)`, etc. (up to 8 items)
+    TupleLiteral(Vec<AstExpression>),
+    /// `{a, b, c}` (an instance of `Set`)
+    SetLiteral(Vec<AstExpression>),
+    /// `a..b` (inclusive) or `a...b` (exclusive)
+    RangeLiteral {
+        start: Box<AstExpression>,
+        end: Box<AstExpression>,
+        inclusive: bool,
+    },
     FloatLiteral {
         value: f64,
     },
@@ -229,6 +321,26 @@ pub enum AstExpressionBody {
     },
     StringLiteral {
         content: String,
+        /// True if written in a file with `# frozen_string_literal: true`
+        frozen: bool,
+    },
+    CharLiteral {
+        value: char,
+    },
+    /// String literal with `#{}` (or `\{}`)
+    InterpolatedString {
+        parts: Vec<StringPart>,
+    },
+}
+
+/// A part of `AstExpressionBody::InterpolatedString`
+#[derive(Debug, PartialEq, Clone)]
+pub enum StringPart {
+    Literal(String),
+    /// `#{expr}`. `inspect` is true if this is `\{expr}`
+    Expr {
+        expr: Box<AstExpression>,
+        inspect: bool,
     },
 }
 
@@ -249,6 +361,9 @@ impl AstMethodCall {
     }
 }
 
+/// Max number of the items of a tuple (there are `Tuple2` to `Tuple8`)
+pub const MAX_TUPLE_SIZE: usize = 8;
+
 /// Patterns of match expression
 #[derive(Debug, PartialEq, Clone)]
 pub enum AstPattern {
@@ -261,9 +376,26 @@ pub enum AstPattern {
     IntegerLiteralPattern(i64),
     FloatLiteralPattern(f64),
     StringLiteralPattern(String),
+    /// `(a, b)`, etc.
+    TuplePattern(Vec<AstPattern>),
+    /// `A | B`
+    OrPattern(Vec<AstPattern>),
+    /// `1..5`, `0.0...1.0`, etc. `start` and `end` are number literal patterns
+    RangePattern {
+        start: Box<AstPattern>,
+        end: Box<AstPattern>,
+        inclusive: bool,
+    },
 }
 
-pub type AstMatchClause = (AstPattern, Vec<AstExpression>);
+/// `when pattern if guard then body`
+/// (The span is the location of the pattern)
+pub type AstMatchClause = (
+    AstPattern,
+    LocationSpan,
+    Option<AstExpression>,
+    Vec<AstExpression>,
+);
 
 impl AstExpression {
     pub fn may_have_paren_wo_args(&self) -> bool {
@@ -274,6 +406,10 @@ impl AstExpression {
         }
     }
 
+    pub fn is_keyword_arg(&self) -> bool {
+        matches!(self.body, AstExpressionBody::KeywordArg { .. })
+    }
+
     /// True if this can be the left hand side of an assignment
     pub fn is_lhs(&self) -> bool {
         if self.may_have_paren_wo_args() {
@@ -293,6 +429,7 @@ impl AstExpression {
             Some(Definition::ConstDefinition {
                 name: names.join("::"),
                 expr: *rhs.clone(),
+                compile_time: false,
             })
         } else {
             None
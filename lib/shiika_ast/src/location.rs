@@ -84,10 +84,4 @@ impl LocationSpan {
     pub fn internal() -> LocationSpan {
         LocationSpan::Empty
     }
-
-    // Used as placeholder.
-    // TODO: remove this
-    pub fn todo() -> LocationSpan {
-        LocationSpan::Empty
-    }
 }
@@ -9,9 +9,11 @@ pub enum Token {
     IVar(String),
     Number(String),
     Str(String),
+    CharLiteral(char),
     StrWithInterpolation {
         head: String,  // Contents before `#{'
         inspect: bool, // true if `\{}', which calls .inspect instead of .to_s
+        heredoc: bool, // true if in a heredoc
     },
     // Symbols
     LParen,      //  (
@@ -26,6 +28,7 @@ pub enum Token {
     UnaryMinus,  //  -a
     BinaryMinus, //  a - b
     Mul,         //  *
+    Pow,         //  **
     Div,         //  /
     Mod,         //  %
     EqEq,        //  ==
@@ -37,6 +40,8 @@ pub enum Token {
     Equal,       //  =
     Bang,        //  !
     Dot,         //  .
+    DotDot,      //  ..
+    DotDotDot,   //  ...
     At,          //  @
     Tilde,       //  ~
     Question,    //  ?
@@ -73,7 +78,10 @@ pub enum Token {
     KwClass,
     KwModule,
     KwRequirement,
+    KwAbstract,
     KwEnum,
+    KwExtension,
+    KwConst,
     KwCase,
     KwIn,
     KwOut,
@@ -89,7 +97,11 @@ pub enum Token {
     KwMatch,
     KwWhen,
     KwWhile,
+    KwUntil,
+    KwFor,
+    KwGuard,
     KwBreak,
+    KwContinue,
     KwReturn,
     KwThen,
     KwElse,
@@ -97,11 +109,14 @@ pub enum Token {
     KwFn,
     KwDo,
     KwSelf,
+    KwSuper,
+    KwAssertType,
     KwTrue,
     KwFalse,
     // Keywords (modifier version)
     ModIf,
     ModUnless,
+    ModUntil,
 }
 
 impl Token {
@@ -124,6 +139,32 @@ impl Token {
         )
     }
 
+    /// Returns the name of the method called by this operator
+    /// (eg. `a + b` is `a.+(b)`), if this is a binary operator
+    pub fn binary_operator_name(&self) -> Option<&'static str> {
+        let name = match self {
+            Token::BinaryPlus => "+",
+            Token::BinaryMinus => "-",
+            Token::Mul => "*",
+            Token::Pow => "**",
+            Token::Div => "/",
+            Token::Mod => "%",
+            Token::And => "&",
+            Token::Or => "|",
+            Token::Xor => "^",
+            Token::LShift => "<<",
+            Token::RShift => ">>",
+            Token::LessThan => "<",
+            Token::LessEq => "<=",
+            Token::GreaterThan => ">",
+            Token::GreaterEq => ">=",
+            Token::EqEq => "==",
+            Token::NotEq => "!=",
+            _ => return None,
+        };
+        Some(name)
+    }
+
     /// Return true if a value may start with this token
     ///
     /// Must not be called on `Token::Space`
@@ -138,6 +179,7 @@ impl Token {
             Token::IVar(_) => true,
             Token::Number(_) => true,
             Token::Str(_) => true,
+            Token::CharLiteral(_) => true,
             Token::StrWithInterpolation { .. } => true,
             // Symbols
             Token::LParen => true,       //  (
@@ -152,6 +194,7 @@ impl Token {
             Token::UnaryMinus => true,   //  -
             Token::BinaryMinus => false, //  -
             Token::Mul => false,         //  *
+            Token::Pow => false,         //  **
             Token::Div => false,         //  /
             Token::Mod => false,         //  %
             Token::EqEq => false,        //  ==
@@ -163,6 +206,8 @@ impl Token {
             Token::Equal => false,       //  =
             Token::Bang => true,         //  !
             Token::Dot => false,         //  .
+            Token::DotDot => false,      //  ..
+            Token::DotDotDot => false,   //  ...
             Token::At => true,           //  @
             Token::Tilde => true,        //  ~
             Token::Question => false,    //  ?
@@ -199,7 +244,10 @@ impl Token {
             Token::KwClass => false,
             Token::KwModule => false,
             Token::KwRequirement => false,
+            Token::KwAbstract => false,
             Token::KwEnum => false,
+            Token::KwExtension => false,
+            Token::KwConst => false,
             Token::KwCase => false,
             Token::KwIn => false,
             Token::KwOut => false,
@@ -215,7 +263,11 @@ impl Token {
             Token::KwMatch => true,
             Token::KwWhen => false,
             Token::KwWhile => true,
+            Token::KwUntil => true,
+            Token::KwFor => true,
+            Token::KwGuard => true,
             Token::KwBreak => false,
+            Token::KwContinue => false,
             Token::KwReturn => false,
             Token::KwThen => false,
             Token::KwElse => false,
@@ -223,11 +275,14 @@ impl Token {
             Token::KwFn => true,
             Token::KwDo => false,
             Token::KwSelf => true,
+            Token::KwSuper => true,
+            Token::KwAssertType => true,
             Token::KwTrue => true,
             Token::KwFalse => true,
             // Keywords (modifier version)
             Token::ModIf => false,
             Token::ModUnless => false,
+            Token::ModUntil => false,
         }
     }
 }
@@ -1,5 +1,6 @@
 use super::class_name::*;
 use super::namespace::*;
+use super::type_name::*;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, PartialEq, Clone, Eq, Hash, Serialize, Deserialize)]
@@ -11,6 +12,14 @@ impl std::fmt::Display for ConstFullname {
     }
 }
 
+impl ConstFullname {
+    /// Returns the name of the type which has the same name as this constant
+    /// (eg. `Foo::Bar` for `::Foo::Bar`)
+    pub fn to_type_fullname(&self) -> TypeFullname {
+        type_fullname(self.0.trim_start_matches("::"))
+    }
+}
+
 pub fn const_fullname(s_: impl Into<String>) -> ConstFullname {
     let s = s_.into();
     debug_assert!(!s.starts_with("::"));
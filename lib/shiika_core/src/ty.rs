@@ -74,7 +74,7 @@ pub fn typarams_to_typaram_refs(typarams: &[TyParam], kind: TyParamKind) -> Vec<
     typarams
         .iter()
         .enumerate()
-        .map(|(i, t)| typaram_ref(&t.name, kind.clone(), i))
+        .map(|(i, t)| t.to_ref(kind.clone(), i))
         .collect()
 }
 
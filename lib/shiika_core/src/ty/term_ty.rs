@@ -253,7 +253,7 @@ impl TermTy {
     ///   can have a generic method)
     /// - method_tyargs: None if not in a method context (eg. when creating
     ///   `Array<Int>` from `Array<T>`)
-    pub fn substitute(&self, class_tyargs: &[TermTy], method_tyargs: &[TermTy]) -> TermTy {
+    pub fn substitute_both(&self, class_tyargs: &[TermTy], method_tyargs: &[TermTy]) -> TermTy {
         match &self.body {
             TyPara(TyParamRef { kind, idx, .. }) => match kind {
                 TyParamKind::Class => {
@@ -278,13 +278,23 @@ impl TermTy {
             }) => {
                 let args = type_args
                     .iter()
-                    .map(|t| t.substitute(class_tyargs, method_tyargs))
+                    .map(|t| t.substitute_both(class_tyargs, method_tyargs))
                     .collect();
                 ty::new(base_name, args, *is_meta)
             }
         }
     }
 
+    /// Apply type arguments into class type parameters only
+    pub fn substitute_class(&self, class_tyargs: &[TermTy]) -> TermTy {
+        self.substitute_both(class_tyargs, &[])
+    }
+
+    /// Apply type arguments into method type parameters only
+    pub fn substitute_method(&self, method_tyargs: &[TermTy]) -> TermTy {
+        self.substitute_both(&[], method_tyargs)
+    }
+
     /// Name for vtable when invoking a method on an object of this type
     pub fn vtable_name(&self) -> ClassFullname {
         match &self.body {
@@ -1,3 +1,4 @@
+use crate::ty::{typaram_ref, LitTy, TyParamKind, TyParamRef};
 use serde::{Deserialize, Serialize};
 
 /// A type parameter
@@ -5,6 +6,8 @@ use serde::{Deserialize, Serialize};
 pub struct TyParam {
     pub name: String,
     pub variance: Variance,
+    /// eg. `Printable` for `def foo<T: Printable>` (`Object` if not given)
+    pub upper_bound: LitTy,
 }
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
@@ -19,6 +22,14 @@ impl TyParam {
         TyParam {
             name: name.into(),
             variance: Variance::Invariant,
+            upper_bound: LitTy::raw("Object"),
         }
     }
+
+    /// Returns a reference to this type parameter
+    pub fn to_ref(&self, kind: TyParamKind, idx: usize) -> TyParamRef {
+        let mut r = typaram_ref(&self.name, kind, idx);
+        r.upper_bound = self.upper_bound.clone();
+        r
+    }
 }
@@ -13,6 +13,10 @@ pub fn mangle_method(method_name: &str) -> String {
         .replace('*', "mul_")
         .replace('/', "div_")
         .replace('%', "mod_")
+        .replace('&', "and_")
+        .replace('|', "or_")
+        .replace('^', "xor_")
+        .replace('!', "not_")
         .replace("==", "eq_")
         .replace("<=", "le_")
         .replace(">=", "ge_")
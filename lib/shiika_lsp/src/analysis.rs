@@ -0,0 +1,349 @@
+//! Build the information of a program used to answer the requests
+use shiika_ast::LocationSpan;
+use shiika_core::names::{method_firstname, MethodFirstname};
+use shiika_core::ty::TermTy;
+use shiika_parser::{loader, Parser};
+use skc_ast2hir::class_dict::ClassDict;
+use skc_hir::visitor::walk_expr;
+use skc_hir::{Hir, HirExpression, HirExpressionBase, SkMethodBody};
+use skc_mir::LibraryExports;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Position in a document. Both are 0-origin and the column is counted in
+/// chars
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub line: usize,
+    pub col: usize,
+}
+
+/// Range in a document (end-exclusive)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Range {
+    fn contains(&self, pos: Position) -> bool {
+        self.start <= pos && pos < self.end
+    }
+}
+
+/// An error found in a program
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic {
+    pub range: Range,
+    pub message: String,
+}
+
+/// Something located on a range of the document
+#[derive(Debug)]
+struct Entry<T> {
+    range: Range,
+    /// Length of the range in bytes
+    size: usize,
+    value: T,
+}
+
+/// Types and methods in a document
+#[derive(Debug, Default)]
+pub struct Index {
+    path: PathBuf,
+    /// The expressions and their types
+    exprs: Vec<Entry<TermTy>>,
+    /// The method calls and where the methods are defined
+    calls: Vec<Entry<LocationSpan>>,
+    /// The calls of the methods annotated with `@deprecated` and the notes
+    /// shown on hover
+    deprecated: Vec<Entry<String>>,
+    /// Method names of the types in `exprs` (for completion)
+    methods: HashMap<String, Vec<String>>,
+}
+
+/// Analyze the program `path` (and the files required from it.) Returns
+/// None instead of the index if the program is too broken to analyze
+pub fn analyze(path: &Path, imports: &LibraryExports) -> (Option<Index>, Vec<Diagnostic>) {
+    let files = match loader::load(path) {
+        Ok(files) => files,
+        Err(e) => return (None, vec![diagnostic(&e.to_string(), path)]),
+    };
+    let ast = match Parser::parse_files(&files) {
+        Ok(ast) => ast,
+        Err(e) => return (None, vec![diagnostic(&e.to_string(), path)]),
+    };
+    match skc_ast2hir::analyze(ast, imports, |hir, class_dict| {
+        Index::build(path, hir, class_dict)
+    }) {
+        Ok((errors, index)) => {
+            let diags = errors
+                .iter()
+                .map(|e| diagnostic(&e.to_string(), path))
+                .collect();
+            (Some(index), diags)
+        }
+        Err(e) => (None, vec![diagnostic(&e.to_string(), path)]),
+    }
+}
+
+impl Index {
+    pub fn new(path: &Path) -> Index {
+        Index {
+            path: path.to_path_buf(),
+            ..Default::default()
+        }
+    }
+
+    fn build(path: &Path, hir: &Hir, class_dict: &ClassDict) -> Index {
+        let mut index = Index::new(path);
+        let method_bodies = hir
+            .sk_methods
+            .values()
+            .flatten()
+            .filter_map(|m| match &m.body {
+                SkMethodBody::Normal { exprs } => Some(exprs.exprs()),
+                _ => None,
+            });
+        let roots = method_bodies
+            .chain([hir.main_exprs.exprs(), &hir.const_inits[..]])
+            .flatten();
+        for root in roots {
+            index.collect_types(root);
+            index.collect_methods(root, class_dict);
+            index.collect_deprecated(root, hir);
+        }
+        index
+    }
+
+    /// Save the types of `expr` and its subexpressions
+    pub fn collect_types(&mut self, expr: &HirExpression) {
+        walk_expr(expr, &mut |e| {
+            if let Some((range, size)) = self.range_of(&e.locs) {
+                self.exprs.push(Entry {
+                    range,
+                    size,
+                    value: e.ty.clone(),
+                });
+            }
+        });
+    }
+
+    /// Save the method calls in `expr` and the methods of its types
+    fn collect_methods(&mut self, expr: &HirExpression, class_dict: &ClassDict) {
+        walk_expr(expr, &mut |e| {
+            let key = e.ty.to_string();
+            if !self.methods.contains_key(&key) {
+                let mut names = class_dict
+                    .method_names(&e.ty)
+                    .into_iter()
+                    .map(|name| name.0)
+                    .collect::<Vec<_>>();
+                names.sort();
+                names.dedup();
+                self.methods.insert(key, names);
+            }
+
+            let (receiver_expr, method_name) = match &e.node {
+                HirExpressionBase::HirMethodCall {
+                    receiver_expr,
+                    method_fullname,
+                    ..
+                }
+                | HirExpressionBase::HirSuperMethodCall {
+                    receiver_expr,
+                    method_fullname,
+                    ..
+                } => (receiver_expr, &method_fullname.first_name),
+                HirExpressionBase::HirModuleMethodCall {
+                    receiver_expr,
+                    method_name,
+                    ..
+                } => (receiver_expr, method_name),
+                _ => return,
+            };
+            if let Some((range, size)) = self.range_of(&e.locs) {
+                if let Some(locs) = definition_of(class_dict, &receiver_expr.ty, method_name) {
+                    self.calls.push(Entry {
+                        range,
+                        size,
+                        value: locs,
+                    });
+                }
+            }
+        });
+    }
+
+    /// Save the calls in `expr` of the methods annotated with `@deprecated`
+    pub fn collect_deprecated(&mut self, expr: &HirExpression, hir: &Hir) {
+        walk_expr(expr, &mut |e| {
+            let method_fullname = match &e.node {
+                HirExpressionBase::HirMethodCall {
+                    method_fullname, ..
+                }
+                | HirExpressionBase::HirSuperMethodCall {
+                    method_fullname, ..
+                } => method_fullname,
+                _ => return,
+            };
+            let found = hir
+                .annotations_on(method_fullname)
+                .iter()
+                .find(|a| a.name == "deprecated");
+            if let (Some(annotation), Some((range, size))) = (found, self.range_of(&e.locs)) {
+                let mut note = format!("{} is deprecated", method_fullname);
+                if !annotation.args.is_empty() {
+                    note += &format!(" ({})", annotation.args.join(", "));
+                }
+                self.deprecated.push(Entry {
+                    range,
+                    size,
+                    value: note,
+                });
+            }
+        });
+    }
+
+    /// Returns the range of `locs` if it is in this document
+    fn range_of(&self, locs: &LocationSpan) -> Option<(Range, usize)> {
+        match locs {
+            LocationSpan::Just {
+                filepath,
+                begin,
+                end,
+            } if **filepath == self.path => {
+                let range = Range {
+                    start: Position {
+                        line: begin.line,
+                        col: begin.col,
+                    },
+                    end: Position {
+                        line: end.line,
+                        col: end.col,
+                    },
+                };
+                Some((range, end.pos - begin.pos))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the type of the innermost expression at `pos` (with a note
+    /// if it calls a deprecated method)
+    pub fn hover(&self, pos: Position) -> Option<(Range, String)> {
+        let found = innermost(&self.exprs, pos)?;
+        let mut text = found.value.to_string();
+        if let Some(d) = self.deprecated.iter().find(|d| d.range == found.range) {
+            text += &format!("\n{}", d.value);
+        }
+        Some((found.range, text))
+    }
+
+    /// Returns where the method called at `pos` is defined
+    pub fn definition(&self, pos: Position) -> Option<&LocationSpan> {
+        innermost(&self.calls, pos).map(|e| &e.value)
+    }
+
+    /// Returns the names of the methods of the expression which ends at
+    /// `receiver_end`
+    pub fn completion(&self, receiver_end: Position) -> &[String] {
+        self.exprs
+            .iter()
+            .filter(|e| e.range.end == receiver_end)
+            .max_by_key(|e| e.size)
+            .and_then(|e| self.methods.get(&e.value.to_string()))
+            .map(|names| names.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+/// Returns the smallest entry which contains `pos`. Prefers inner one if
+/// the sizes are the same (eg. the expression in a bitcast)
+fn innermost<T>(entries: &[Entry<T>], pos: Position) -> Option<&Entry<T>> {
+    entries
+        .iter()
+        .filter(|e| e.range.contains(pos))
+        .fold(None, |best: Option<&Entry<T>>, e| match best {
+            Some(b) if b.size < e.size => Some(b),
+            _ => Some(e),
+        })
+}
+
+/// Returns where the method is defined
+fn definition_of(
+    class_dict: &ClassDict,
+    receiver_ty: &TermTy,
+    method_name: &MethodFirstname,
+) -> Option<LocationSpan> {
+    let found = class_dict
+        .lookup_method(receiver_ty, method_name, &[])
+        .ok()?;
+    match found.sig.locs {
+        LocationSpan::Just { .. } => Some(found.sig.locs),
+        // `.new` is generated from `#initialize`
+        LocationSpan::Empty if method_name.0 == "new" => {
+            let init = method_firstname("initialize");
+            let found = class_dict
+                .lookup_method(&found.sig.ret_ty, &init, &[])
+                .ok()?;
+            matches!(found.sig.locs, LocationSpan::Just { .. }).then(|| found.sig.locs)
+        }
+        LocationSpan::Empty => None,
+    }
+}
+
+/// Make a diagnostic from an error message. The location is taken from the
+/// report (eg. `╭─[foo.sk:3:5]`) if any
+fn diagnostic(msg: &str, path: &Path) -> Diagnostic {
+    let msg = strip_ansi(msg);
+    let first_line = msg.lines().next().unwrap_or_default();
+    let message = first_line
+        .strip_prefix("Error: ")
+        .unwrap_or(first_line)
+        .to_string();
+    let start = report_location(&msg, path).unwrap_or(Position { line: 0, col: 0 });
+    let end = Position {
+        line: start.line,
+        col: start.col + 1,
+    };
+    Diagnostic {
+        range: Range { start, end },
+        message,
+    }
+}
+
+/// Returns the (0-origin) position in the header of the report if it is in
+/// `path`
+fn report_location(report: &str, path: &Path) -> Option<Position> {
+    let header = report.split("─[").nth(1)?.split(']').next()?;
+    let mut parts = header.rsplitn(3, ':');
+    let col = parts.next()?.parse::<usize>().ok()?;
+    let line = parts.next()?.parse::<usize>().ok()?;
+    let file = parts.next()?;
+    if Path::new(file) != path {
+        return None;
+    }
+    Some(Position {
+        line: line.checked_sub(1)?,
+        col: col.checked_sub(1)?,
+    })
+}
+
+/// Remove the escape sequences for colours
+fn strip_ansi(s: &str) -> String {
+    let mut out = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip until the end of `\x1b[...m`
+            for c in chars.by_ref() {
+                if c == 'm' {
+                    break;
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
@@ -0,0 +1,23 @@
+//! Language server of Shiika
+//!
+//! The program is analyzed when a document is opened or saved and the
+//! types of the expressions and the locations of the methods are saved
+//! in `analysis::Index`.
+pub mod analysis;
+pub mod server;
+mod transport;
+use anyhow::{Context, Result};
+use skc_mir::LibraryExports;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// Load builtin/exports.json (under `SHIIKA_ROOT` if set)
+pub fn load_builtin_exports() -> Result<LibraryExports> {
+    let root = env::var("SHIIKA_ROOT").unwrap_or_else(|_| ".".to_string());
+    let path = PathBuf::from(root).join("builtin/exports.json");
+    let json = fs::read_to_string(&path)
+        .with_context(|| format!("builtin exports not found ({})", path.display()))?;
+    let exports = serde_json::from_str(&json).context("builtin exports is broken")?;
+    Ok(exports)
+}
@@ -0,0 +1,10 @@
+use anyhow::Result;
+use shiika_lsp::server::Server;
+use std::io;
+
+fn main() -> Result<()> {
+    let imports = shiika_lsp::load_builtin_exports()?;
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    Server::new(imports).run(&mut stdin.lock(), &mut stdout.lock())
+}
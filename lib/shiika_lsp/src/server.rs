@@ -0,0 +1,386 @@
+//! Handle the LSP messages
+use crate::analysis::{self, Diagnostic, Index, Position, Range};
+use crate::transport;
+use anyhow::Result;
+use serde_json::{json, Value};
+use shiika_ast::LocationSpan;
+use skc_mir::LibraryExports;
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+/// Error code of JSON-RPC
+const METHOD_NOT_FOUND: i64 = -32601;
+
+pub struct Server {
+    imports: LibraryExports,
+    /// Contents of the open documents (by URI)
+    documents: HashMap<String, String>,
+    /// The result of the last analysis of the documents (by URI)
+    indexes: HashMap<String, Index>,
+}
+
+impl Server {
+    pub fn new(imports: LibraryExports) -> Server {
+        Server {
+            imports,
+            documents: Default::default(),
+            indexes: Default::default(),
+        }
+    }
+
+    /// Process the messages until `exit` is sent
+    pub fn run(&mut self, input: &mut impl BufRead, output: &mut impl Write) -> Result<()> {
+        while let Some(msg) = transport::read_message(input)? {
+            if msg["method"] == "exit" {
+                break;
+            }
+            for out in self.handle(&msg) {
+                transport::write_message(output, &out)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Process a message and returns the messages to send
+    pub fn handle(&mut self, msg: &Value) -> Vec<Value> {
+        let method = msg["method"].as_str().unwrap_or_default();
+        let params = &msg["params"];
+        match msg.get("id") {
+            // Request
+            Some(id) => {
+                let result = match method {
+                    "initialize" => capabilities(),
+                    "shutdown" => Value::Null,
+                    "textDocument/hover" => self.hover(params),
+                    "textDocument/definition" => self.definition(params),
+                    "textDocument/completion" => self.completion(params),
+                    _ => {
+                        return vec![json!({
+                            "jsonrpc": "2.0",
+                            "id": id,
+                            "error": {
+                                "code": METHOD_NOT_FOUND,
+                                "message": format!("unsupported method: {}", method),
+                            },
+                        })]
+                    }
+                };
+                vec![json!({ "jsonrpc": "2.0", "id": id, "result": result })]
+            }
+            // Notification
+            None => match method {
+                "textDocument/didOpen" => {
+                    let doc = &params["textDocument"];
+                    let uri = doc["uri"].as_str().unwrap_or_default();
+                    let text = doc["text"].as_str().unwrap_or_default();
+                    self.documents.insert(uri.to_string(), text.to_string());
+                    self.analyze(uri)
+                }
+                "textDocument/didChange" => {
+                    let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                    // The whole text is sent (`TextDocumentSyncKind.Full`)
+                    if let Some(change) = params["contentChanges"].as_array().and_then(|a| a.last())
+                    {
+                        let text = change["text"].as_str().unwrap_or_default();
+                        self.documents.insert(uri.to_string(), text.to_string());
+                    }
+                    vec![]
+                }
+                "textDocument/didSave" => {
+                    let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                    self.analyze(uri)
+                }
+                "textDocument/didClose" => {
+                    let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                    self.documents.remove(uri);
+                    self.indexes.remove(uri);
+                    vec![]
+                }
+                _ => vec![],
+            },
+        }
+    }
+
+    /// Analyze the saved document and returns the diagnostics to publish
+    fn analyze(&mut self, uri: &str) -> Vec<Value> {
+        let path = match uri_to_path(uri) {
+            Some(path) => path,
+            None => return vec![],
+        };
+        let (index, diags) = analysis::analyze(&path, &self.imports);
+        // Keep the last one for hover etc. if the program is broken
+        if let Some(index) = index {
+            self.indexes.insert(uri.to_string(), index);
+        }
+        vec![json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": {
+                "uri": uri,
+                "diagnostics": diags.iter().map(diagnostic_json).collect::<Vec<_>>(),
+            },
+        })]
+    }
+
+    fn hover(&self, params: &Value) -> Value {
+        let found = self
+            .index_and_pos(params)
+            .and_then(|(index, pos)| index.hover(pos));
+        match found {
+            Some((range, text)) => json!({
+                "contents": { "kind": "plaintext", "value": text },
+                "range": range_json(&range),
+            }),
+            None => Value::Null,
+        }
+    }
+
+    fn definition(&self, params: &Value) -> Value {
+        let found = self
+            .index_and_pos(params)
+            .and_then(|(index, pos)| index.definition(pos));
+        match found {
+            Some(LocationSpan::Just {
+                filepath,
+                begin,
+                end,
+            }) => json!({
+                "uri": path_to_uri(filepath),
+                "range": range_json(&Range {
+                    start: Position { line: begin.line, col: begin.col },
+                    end: Position { line: end.line, col: end.col },
+                }),
+            }),
+            _ => Value::Null,
+        }
+    }
+
+    /// Returns the methods of the receiver if the cursor is after `.`
+    fn completion(&self, params: &Value) -> Value {
+        let names = self
+            .index_and_pos(params)
+            .and_then(|(index, pos)| {
+                let uri = params["textDocument"]["uri"].as_str()?;
+                let line = self.documents.get(uri)?.lines().nth(pos.line)?;
+                let receiver_end = receiver_end(line, pos)?;
+                Some(index.completion(receiver_end))
+            })
+            .unwrap_or_default();
+        let items = names
+            .iter()
+            .map(|name| json!({ "label": name, "kind": 2 }))
+            .collect::<Vec<_>>();
+        json!({ "isIncomplete": false, "items": items })
+    }
+
+    /// Returns the index of the document and the position in `params`
+    fn index_and_pos(&self, params: &Value) -> Option<(&Index, Position)> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        let index = self.indexes.get(uri)?;
+        let pos = Position {
+            line: params["position"]["line"].as_u64()? as usize,
+            col: params["position"]["character"].as_u64()? as usize,
+        };
+        Some((index, pos))
+    }
+}
+
+fn capabilities() -> Value {
+    json!({
+        "capabilities": {
+            "textDocumentSync": {
+                "openClose": true,
+                "change": 1,
+                "save": { "includeText": false },
+            },
+            "hoverProvider": true,
+            "definitionProvider": true,
+            "completionProvider": { "triggerCharacters": ["."] },
+        },
+        "serverInfo": { "name": "shiika-lsp" },
+    })
+}
+
+/// Returns the position of `.` if `pos` is after `foo.` or `foo.ba`
+fn receiver_end(line: &str, pos: Position) -> Option<Position> {
+    let before = line.chars().take(pos.col).collect::<Vec<_>>();
+    let prefix_len = before
+        .iter()
+        .rev()
+        .take_while(|c| c.is_alphanumeric() || **c == '_')
+        .count();
+    let dot = before.len().checked_sub(prefix_len + 1)?;
+    (before[dot] == '.').then(|| Position {
+        line: pos.line,
+        col: dot,
+    })
+}
+
+fn diagnostic_json(diag: &Diagnostic) -> Value {
+    json!({
+        "range": range_json(&diag.range),
+        "severity": 1,
+        "source": "shiika",
+        "message": diag.message,
+    })
+}
+
+fn range_json(range: &Range) -> Value {
+    json!({
+        "start": { "line": range.start.line, "character": range.start.col },
+        "end": { "line": range.end.line, "character": range.end.col },
+    })
+}
+
+/// eg. `file:///foo/a%20b.sk` -> `/foo/a b.sk`
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let s = uri.strip_prefix("file://")?;
+    let mut bytes = vec![];
+    let mut iter = s.bytes();
+    while let Some(b) = iter.next() {
+        if b == b'%' {
+            let hex = [iter.next()?, iter.next()?];
+            bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            bytes.push(b);
+        }
+    }
+    Some(PathBuf::from(String::from_utf8(bytes).ok()?))
+}
+
+/// eg. `/foo/a b.sk` -> `file:///foo/a%20b.sk`
+fn path_to_uri(path: &Path) -> String {
+    let mut uri = "file://".to_string();
+    for b in path.to_string_lossy().bytes() {
+        if b.is_ascii_alphanumeric() || b"/-_.~".contains(&b) {
+            uri.push(b as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", b));
+        }
+    }
+    uri
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shiika_ast::{Annotation, Location};
+    use shiika_core::names::method_fullname_raw;
+    use shiika_core::ty;
+    use skc_hir::{Hir, HirExpressions, SkTypes};
+    use std::collections::{HashMap, HashSet};
+    use std::rc::Rc;
+
+    fn locs(path: &Rc<PathBuf>, begin: usize, end: usize) -> LocationSpan {
+        LocationSpan::new(
+            path,
+            Location::new(0, begin, begin),
+            Location::new(0, end, end),
+        )
+    }
+
+    fn hover_at(server: &mut Server, col: usize) -> Value {
+        let req = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "textDocument/hover",
+            "params": {
+                "textDocument": { "uri": "file:///tmp/hover.sk" },
+                "position": { "line": 0, "character": col },
+            },
+        });
+        server.handle(&req).remove(0)["result"].clone()
+    }
+
+    #[test]
+    fn test_hover() {
+        // s.split(",")
+        let path = Rc::new(PathBuf::from("/tmp/hover.sk"));
+        let receiver = Hir::lvar_ref(ty::raw("String"), "s".to_string(), locs(&path, 0, 1));
+        let arg = Hir::string_literal(0, locs(&path, 8, 11));
+        let call = Hir::method_call(
+            ty::ary(ty::raw("String")),
+            receiver,
+            method_fullname_raw("String", "split"),
+            vec![arg],
+        );
+        let mut index = Index::new(&path);
+        index.collect_types(&call);
+        let mut server = Server::new(Default::default());
+        server
+            .indexes
+            .insert("file:///tmp/hover.sk".to_string(), index);
+
+        let result = hover_at(&mut server, 0);
+        assert_eq!(result["contents"]["value"], "String");
+        assert_eq!(result["range"]["end"]["character"], 1);
+        let result = hover_at(&mut server, 3);
+        assert_eq!(result["contents"]["value"], "Array<String>");
+        assert_eq!(result["range"]["start"]["character"], 0);
+        assert_eq!(result["range"]["end"]["character"], 11);
+        let result = hover_at(&mut server, 9);
+        assert_eq!(result["contents"]["value"], "String");
+        assert_eq!(hover_at(&mut server, 11), Value::Null);
+    }
+
+    #[test]
+    fn test_hover_deprecated() {
+        // s.split(",")
+        let path = Rc::new(PathBuf::from("/tmp/hover.sk"));
+        let receiver = Hir::lvar_ref(ty::raw("String"), "s".to_string(), locs(&path, 0, 1));
+        let arg = Hir::string_literal(0, locs(&path, 8, 11));
+        let method_fullname = method_fullname_raw("String", "split");
+        let call = Hir::method_call(
+            ty::ary(ty::raw("String")),
+            receiver,
+            method_fullname.clone(),
+            vec![arg],
+        );
+        let annotation = Annotation {
+            name: "deprecated".to_string(),
+            args: vec![],
+        };
+        let hir = Hir {
+            sk_types: SkTypes::new(HashMap::new()),
+            sk_methods: HashMap::new(),
+            constants: HashMap::new(),
+            str_literals: vec![],
+            const_inits: vec![],
+            main_exprs: HirExpressions::new(vec![]),
+            main_lvars: vec![],
+            annotations: HashMap::from([(method_fullname, vec![annotation])]),
+            extension_methods: HashSet::new(),
+        };
+        let mut index = Index::new(&path);
+        index.collect_types(&call);
+        index.collect_deprecated(&call, &hir);
+        let mut server = Server::new(Default::default());
+        server
+            .indexes
+            .insert("file:///tmp/hover.sk".to_string(), index);
+
+        let result = hover_at(&mut server, 0);
+        assert_eq!(result["contents"]["value"], "String");
+        let result = hover_at(&mut server, 3);
+        assert_eq!(
+            result["contents"]["value"],
+            "Array<String>\nString#split is deprecated"
+        );
+    }
+
+    #[test]
+    fn test_receiver_end() {
+        let pos = |col| Position { line: 0, col };
+        assert_eq!(receiver_end("  foo.", pos(6)), Some(pos(5)));
+        assert_eq!(receiver_end("  foo.ba", pos(8)), Some(pos(5)));
+        assert_eq!(receiver_end("  foo", pos(5)), None);
+    }
+
+    #[test]
+    fn test_uri() {
+        let path = uri_to_path("file:///tmp/a%20b.sk").unwrap();
+        assert_eq!(path, PathBuf::from("/tmp/a b.sk"));
+        assert_eq!(path_to_uri(&path), "file:///tmp/a%20b.sk");
+    }
+}
@@ -0,0 +1,35 @@
+//! Reading and writing LSP messages (JSON with the `Content-Length` header)
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+use std::io::{BufRead, Write};
+
+/// Read a message. Returns None at EOF
+pub fn read_message(input: &mut impl BufRead) -> Result<Option<Value>> {
+    let mut content_length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(len) = line.strip_prefix("Content-Length:") {
+            content_length = Some(len.trim().parse::<usize>()?);
+        }
+    }
+    let len = content_length.ok_or_else(|| anyhow!("missing Content-Length"))?;
+    let mut buf = vec![0; len];
+    input.read_exact(&mut buf)?;
+    let msg = serde_json::from_slice(&buf).context("broken message")?;
+    Ok(Some(msg))
+}
+
+/// Write a message
+pub fn write_message(output: &mut impl Write, msg: &Value) -> Result<()> {
+    let body = msg.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()?;
+    Ok(())
+}
@@ -1,6 +1,6 @@
 use shiika_ast::{
-    AstExpression, AstExpressionBody, AstMatchClause, AstMethodCall, BlockParam, Location,
-    LocationSpan, Token, UnresolvedTypeName,
+    AstExpression, AstExpressionBody, AstMatchClause, AstMethodCall, AstPattern, BlockParam,
+    Location, LocationSpan, StringPart, Token, UnresolvedTypeName,
 };
 use shiika_core::names::{method_firstname, UnresolvedConstName};
 use std::path::{Path, PathBuf};
@@ -8,22 +8,26 @@ use std::rc::Rc;
 
 pub struct AstBuilder {
     pub filepath: Rc<PathBuf>,
+    /// True if string literals should be frozen (see `SourceFile::frozen_string_literal`)
+    frozen_string_literal: bool,
 }
 
 impl AstBuilder {
-    pub fn new(filepath: &Rc<PathBuf>) -> AstBuilder {
+    pub fn new(filepath: &Rc<PathBuf>, frozen_string_literal: bool) -> AstBuilder {
         AstBuilder {
             filepath: filepath.clone(),
+            frozen_string_literal,
         }
     }
 
     pub fn empty() -> AstBuilder {
         AstBuilder {
             filepath: Rc::new(Path::new("").to_path_buf()),
+            frozen_string_literal: false,
         }
     }
 
-    fn locs(&self, begin: Location, end: Location) -> LocationSpan {
+    pub fn locs(&self, begin: Location, end: Location) -> LocationSpan {
         LocationSpan::new(&self.filepath, begin, end)
     }
 
@@ -142,8 +146,65 @@ impl AstBuilder {
         )
     }
 
-    pub fn break_expr(&self, begin: Location, end: Location) -> AstExpression {
-        self.non_primary_expression(begin, end, AstExpressionBody::Break {})
+    pub fn for_in_expr(
+        &self,
+        var: String,
+        collection: AstExpression,
+        body: Vec<AstExpression>,
+        begin: Location,
+        end: Location,
+    ) -> AstExpression {
+        self.non_primary_expression(
+            begin,
+            end,
+            AstExpressionBody::ForIn {
+                var,
+                collection: Box::new(collection),
+                body,
+            },
+        )
+    }
+
+    pub fn guard_let(
+        &self,
+        pattern: AstPattern,
+        expr: AstExpression,
+        else_exprs: Vec<AstExpression>,
+        begin: Location,
+        end: Location,
+    ) -> AstExpression {
+        self.non_primary_expression(
+            begin,
+            end,
+            AstExpressionBody::GuardLet {
+                pattern,
+                expr: Box::new(expr),
+                else_exprs,
+            },
+        )
+    }
+
+    pub fn break_expr(
+        &self,
+        arg: Option<AstExpression>,
+        begin: Location,
+        end: Location,
+    ) -> AstExpression {
+        self.non_primary_expression(
+            begin,
+            end,
+            AstExpressionBody::Break {
+                arg: arg.map(Box::new),
+            },
+        )
+    }
+
+    pub fn propagate(&self, expr: AstExpression, begin: Location, end: Location) -> AstExpression {
+        self.primary_expression(begin, end, AstExpressionBody::Propagate(Box::new(expr)))
+    }
+
+    pub fn continue_expr(&self, begin: Location, end: Location) -> AstExpression {
+        self.non_primary_expression(begin, end, AstExpressionBody::Continue)
     }
 
     pub fn return_expr(
@@ -180,6 +241,25 @@ impl AstBuilder {
         )
     }
 
+    pub fn pattern_decl(
+        &self,
+        pattern: AstPattern,
+        rhs: AstExpression,
+        readonly: bool,
+        begin: Location,
+        end: Location,
+    ) -> AstExpression {
+        self.non_primary_expression(
+            begin,
+            end,
+            AstExpressionBody::PatternDecl {
+                pattern,
+                rhs: Box::new(rhs),
+                readonly,
+            },
+        )
+    }
+
     pub fn ivar_decl(
         &self,
         name: String,
@@ -235,6 +315,33 @@ impl AstBuilder {
         )
     }
 
+    pub fn keyword_arg(
+        &self,
+        name: String,
+        expr: AstExpression,
+        begin: Location,
+        end: Location,
+    ) -> AstExpression {
+        self.non_primary_expression(
+            begin,
+            end,
+            AstExpressionBody::KeywordArg {
+                name,
+                expr: Box::new(expr),
+            },
+        )
+    }
+
+    pub fn splat(&self, expr: AstExpression, begin: Location, end: Location) -> AstExpression {
+        self.non_primary_expression(
+            begin,
+            end,
+            AstExpressionBody::Splat {
+                expr: Box::new(expr),
+            },
+        )
+    }
+
     pub fn lambda_expr(
         &self,
         params: Vec<BlockParam>,
@@ -296,6 +403,32 @@ impl AstBuilder {
         self.primary_expression(begin, end, AstExpressionBody::PseudoVariable(token))
     }
 
+    pub fn super_call(
+        &self,
+        args: Vec<AstExpression>,
+        begin: Location,
+        end: Location,
+    ) -> AstExpression {
+        self.primary_expression(begin, end, AstExpressionBody::Super { args })
+    }
+
+    pub fn assert_type(
+        &self,
+        expr: AstExpression,
+        typ: UnresolvedTypeName,
+        begin: Location,
+        end: Location,
+    ) -> AstExpression {
+        self.primary_expression(
+            begin,
+            end,
+            AstExpressionBody::AssertType {
+                expr: Box::new(expr),
+                typ,
+            },
+        )
+    }
+
     pub fn array_literal(
         &self,
         exprs: Vec<AstExpression>,
@@ -305,12 +438,67 @@ impl AstBuilder {
         self.primary_expression(begin, end, AstExpressionBody::ArrayLiteral(exprs))
     }
 
+    pub fn set_literal(
+        &self,
+        exprs: Vec<AstExpression>,
+        begin: Location,
+        end: Location,
+    ) -> AstExpression {
+        self.primary_expression(begin, end, AstExpressionBody::SetLiteral(exprs))
+    }
+
+    pub fn tuple_literal(
+        &self,
+        exprs: Vec<AstExpression>,
+        begin: Location,
+        end: Location,
+    ) -> AstExpression {
+        self.primary_expression(begin, end, AstExpressionBody::TupleLiteral(exprs))
+    }
+
+    pub fn range_literal(
+        &self,
+        start: AstExpression,
+        end: AstExpression,
+        inclusive: bool,
+    ) -> AstExpression {
+        self.non_primary_expression_(
+            &start.locs.clone(),
+            &end.locs.clone(),
+            AstExpressionBody::RangeLiteral {
+                start: Box::new(start),
+                end: Box::new(end),
+                inclusive,
+            },
+        )
+    }
+
     pub fn float_literal(&self, value: f64, begin: Location, end: Location) -> AstExpression {
         self.primary_expression(begin, end, AstExpressionBody::FloatLiteral { value })
     }
 
     pub fn string_literal(&self, content: String, begin: Location, end: Location) -> AstExpression {
-        self.primary_expression(begin, end, AstExpressionBody::StringLiteral { content })
+        self.primary_expression(
+            begin,
+            end,
+            AstExpressionBody::StringLiteral {
+                content,
+                frozen: self.frozen_string_literal,
+            },
+        )
+    }
+
+    pub fn char_literal(&self, value: char, begin: Location, end: Location) -> AstExpression {
+        self.primary_expression(begin, end, AstExpressionBody::CharLiteral { value })
+    }
+
+    pub fn interpolated_string(
+        &self,
+        parts: Vec<StringPart>,
+        begin: Location,
+        end: Location,
+    ) -> AstExpression {
+        self.primary_expression(begin, end, AstExpressionBody::InterpolatedString { parts })
     }
 
     pub fn decimal_literal(&self, value: i64, begin: Location, end: Location) -> AstExpression {
@@ -19,13 +19,101 @@ impl<'a> Parser<'a> {
             Token::KwClass => Ok(Some(self.parse_class_definition()?)),
             Token::KwModule => Ok(Some(self.parse_module_definition()?)),
             Token::KwEnum => Ok(Some(self.parse_enum_definition()?)),
+            Token::KwExtension => Ok(Some(self.parse_extension_definition()?)),
             Token::KwRequirement => Ok(Some(self.parse_requirement_definition()?)),
+            Token::KwAbstract => Ok(Some(self.parse_abstract_definition()?)),
             Token::KwDef => Ok(Some(self.parse_method_definition()?)),
-            Token::UpperWord(_) => Ok(Some(self.parse_const_definition()?)),
+            Token::UpperWord(_) | Token::KwConst => Ok(Some(self.parse_const_definition()?)),
+            Token::IVar(_) => Ok(Some(self.parse_annotated_definition()?)),
             _ => Ok(None),
         }
     }
 
+    /// Parse annotations (eg. `@derive(Printable)`) and the definition
+    /// following them
+    pub fn parse_annotated_definition(&mut self) -> Result<shiika_ast::Definition, Error> {
+        self.debug_log("parse_annotated_definition");
+        self.lv += 1;
+        let annotations = self.parse_annotations()?;
+        let def = match self.current_token() {
+            Token::KwClass => {
+                let mut def = self.parse_class_definition()?;
+                if let shiika_ast::Definition::ClassDefinition { annotations: a, .. } = &mut def {
+                    *a = annotations;
+                }
+                def
+            }
+            Token::KwDef => {
+                let mut def = self.parse_method_definition()?;
+                match &mut def {
+                    shiika_ast::Definition::InstanceMethodDefinition { sig, .. }
+                    | shiika_ast::Definition::ClassMethodDefinition { sig, .. } => {
+                        sig.annotations = annotations;
+                    }
+                    shiika_ast::Definition::InitializerDefinition(d)
+                    | shiika_ast::Definition::ClassInitializerDefinition(d) => {
+                        d.sig.annotations = annotations;
+                    }
+                    _ => panic!("[BUG] unexpected method definition: {:?}", def),
+                }
+                def
+            }
+            token => {
+                return Err(parse_error!(
+                    self,
+                    "annotations must be followed by a class or method definition but got {:?}",
+                    token
+                ))
+            }
+        };
+        self.lv -= 1;
+        Ok(def)
+    }
+
+    /// Parse a list of annotations like
+    /// ```text
+    /// @foo
+    /// @bar(Baz, Quux)
+    /// ```
+    fn parse_annotations(&mut self) -> Result<Vec<shiika_ast::Annotation>, Error> {
+        let mut annotations = vec![];
+        while let Token::IVar(s) = self.current_token() {
+            let name = s.trim_start_matches('@').to_string();
+            self.consume_token()?;
+            let mut args = vec![];
+            if self.consume(Token::LParen)? {
+                self.skip_wsn()?;
+                loop {
+                    match self.current_token() {
+                        Token::UpperWord(s) | Token::LowerWord(s) => {
+                            args.push(s.to_string());
+                            self.consume_token()?;
+                        }
+                        token => {
+                            return Err(parse_error!(
+                                self,
+                                "unexpected token in the annotation `@{}': {:?}",
+                                name,
+                                token
+                            ))
+                        }
+                    }
+                    self.skip_wsn()?;
+                    if self.consume(Token::Comma)? {
+                        self.skip_wsn()?;
+                    } else {
+                        break;
+                    }
+                }
+                self.expect(Token::RParen)?;
+            }
+            self.skip_ws()?;
+            self.expect_sep()?;
+            annotations.push(shiika_ast::Annotation { name, args });
+        }
+        Ok(annotations)
+    }
+
     pub fn parse_class_definition(&mut self) -> Result<shiika_ast::Definition, Error> {
         self.debug_log("parse_class_definition");
         self.lv += 1;
@@ -51,7 +139,7 @@ impl<'a> Parser<'a> {
         }
 
         // Type parameters (optional)
-        let typarams = self.parse_opt_typarams()?;
+        let typarams = self.parse_opt_typarams(true)?;
 
         // Superclass and included modules (optional)
         self.skip_ws()?;
@@ -89,6 +177,7 @@ impl<'a> Parser<'a> {
             typarams,
             supers,
             defs,
+            annotations: vec![],
         })
     }
 
@@ -117,7 +206,7 @@ impl<'a> Parser<'a> {
         }
 
         // Type parameters (optional)
-        let typarams = self.parse_opt_typarams()?;
+        let typarams = self.parse_opt_typarams(false)?;
 
         // Module does not have a superclass
         self.skip_ws()?;
@@ -177,7 +266,7 @@ impl<'a> Parser<'a> {
         }
 
         // Type parameters (optional)
-        let typarams = self.parse_opt_typarams()?;
+        let typarams = self.parse_opt_typarams(false)?;
         self.expect_sep()?;
 
         // Enum cases
@@ -280,6 +369,77 @@ impl<'a> Parser<'a> {
         Ok(typs)
     }
 
+    pub fn parse_extension_definition(&mut self) -> Result<shiika_ast::Definition, Error> {
+        self.debug_log("parse_extension_definition");
+        self.lv += 1;
+
+        // `extension'
+        assert!(self.consume(Token::KwExtension)?);
+        self.skip_ws()?;
+
+        // Name of the class to extend (eg. `Foo::Bar`)
+        let begin = self.lexer.location();
+        let mut names = vec![];
+        loop {
+            match self.current_token() {
+                Token::UpperWord(s) => {
+                    names.push(s.to_string());
+                    self.consume_token()?;
+                }
+                token => {
+                    return Err(parse_error!(
+                        self,
+                        "class name must start with A-Z but got {:?}",
+                        token
+                    ))
+                }
+            }
+            if self.current_token_is(Token::ColonColon) {
+                self.consume_token()?;
+            } else {
+                break;
+            }
+        }
+        let end = self.lexer.location();
+        let name = self.ast.unresolved_type_name(names, vec![], begin, end);
+
+        // Type parameters of the class (optional)
+        let typarams = self.parse_opt_typarams(false)?;
+        self.skip_ws()?;
+        if self.current_token_is(Token::Colon) {
+            return Err(parse_error!(
+                self,
+                "extension cannot change the superclass or the modules"
+            ));
+        }
+        self.expect_sep()?;
+
+        // Internal definitions
+        let defs = self.parse_definitions()?;
+
+        // `end'
+        match self.current_token() {
+            Token::KwEnd => {
+                self.consume_token()?;
+            }
+            token => {
+                return Err(parse_error!(
+                    self,
+                    "missing `end' for extension {:?}; got {:?}",
+                    name.names.join("::"),
+                    token
+                ))
+            }
+        }
+
+        self.lv -= 1;
+        Ok(shiika_ast::Definition::ExtensionDefinition {
+            name,
+            typarams,
+            defs,
+        })
+    }
+
     /// Parse a method requirement. (must appear only in module definitions)
     fn parse_requirement_definition(&mut self) -> Result<shiika_ast::Definition, Error> {
         self.debug_log("parse_requirement_definition");
@@ -301,6 +461,29 @@ impl<'a> Parser<'a> {
         Ok(shiika_ast::Definition::MethodRequirementDefinition { sig })
     }
 
+    /// Parse an abstract method. (must appear only in class definitions)
+    fn parse_abstract_definition(&mut self) -> Result<shiika_ast::Definition, Error> {
+        self.debug_log("parse_abstract_definition");
+        self.lv += 1;
+        // `abstract def'
+        assert!(self.consume(Token::KwAbstract)?);
+        self.skip_ws()?;
+        self.set_lexer_state(LexerState::MethodName);
+        self.expect(Token::KwDef)?;
+        self.skip_ws()?;
+
+        // `foo(bar) -> Baz`
+        let (sig, with_self) = self.parse_method_signature()?;
+        self.skip_ws()?;
+        self.expect_sep()?;
+        if with_self {
+            return Err(parse_error!(self, "abstract method must not have .self"));
+        }
+
+        self.lv -= 1;
+        Ok(shiika_ast::Definition::AbstractMethodDefinition { sig })
+    }
+
     /// Parse a method definition.
     pub fn parse_method_definition(&mut self) -> Result<shiika_ast::Definition, Error> {
         self.debug_log("parse_method_definition");
@@ -383,6 +566,7 @@ impl<'a> Parser<'a> {
     pub fn parse_method_signature(
         &mut self,
     ) -> Result<(shiika_ast::AstMethodSignature, bool), Error> {
+        let begin = self.lexer.location();
         let mut name = None;
         let ret_typ;
         let mut is_class_method = false;
@@ -404,9 +588,10 @@ impl<'a> Parser<'a> {
             name = Some(method_firstname(self.get_method_name()?));
             self.consume_token()?;
         }
+        let end = self.lexer.location();
 
         // Method-wise type parameters (Optional)
-        let typarams = self.parse_opt_typarams()?;
+        let typarams = self.parse_opt_typarams(true)?;
 
         // Params (optional)
         let params = match self.current_token() {
@@ -439,6 +624,8 @@ impl<'a> Parser<'a> {
             typarams,
             params,
             ret_typ,
+            annotations: vec![],
+            locs: self.ast.locs(begin, end),
         };
         Ok((sig, is_class_method))
     }
@@ -449,6 +636,8 @@ impl<'a> Parser<'a> {
             // Keywords
             Token::KwClass => "class",
             Token::KwEnum => "enum",
+            Token::KwExtension => "extension",
+            Token::KwConst => "const",
             Token::KwCase => "case",
             Token::KwIn => "in",
             Token::KwOut => "out",
@@ -462,7 +651,11 @@ impl<'a> Parser<'a> {
             Token::KwMatch => "match",
             Token::KwWhen => "when",
             Token::KwWhile => "while",
+            Token::KwUntil => "until",
+            Token::KwFor => "for",
+            Token::KwGuard => "guard",
             Token::KwBreak => "break",
+            Token::KwContinue => "continue",
             Token::KwReturn => "return",
             Token::KwThen => "then",
             Token::KwElse => "else",
@@ -478,23 +671,12 @@ impl<'a> Parser<'a> {
             Token::GetMethod => "[]",
             Token::SetMethod => "[]=",
             Token::Specialize => "<>",
-            Token::BinaryPlus => "+",
-            Token::BinaryMinus => "-",
-            Token::Mul => "*",
-            Token::Div => "/",
-            Token::Mod => "%",
-            Token::And => "&",
-            Token::Or => "|",
-            Token::Xor => "^",
-            Token::LShift => "<<",
-            Token::RShift => ">>",
-            Token::LessThan => "<",
-            Token::LessEq => "<=",
-            Token::GreaterThan => ">",
-            Token::GreaterEq => ">=",
-            Token::EqEq => "==",
-            Token::NotEq => "!=",
-            token => return Err(parse_error!(self, "invalid method name {:?}", token)),
+            Token::Bang => "!",
+            Token::Tilde => "~",
+            token => match token.binary_operator_name() {
+                Some(name) => name,
+                None => return Err(parse_error!(self, "invalid method name {:?}", token)),
+            },
         };
         Ok(name)
     }
@@ -502,7 +684,9 @@ impl<'a> Parser<'a> {
     // Parse type parameters of a class or a method
     // - `class Foo<A, B, C>`
     // - `def foo<A, B, C>( ... )`
-    fn parse_opt_typarams(&mut self) -> Result<Vec<AstTyParam>, Error> {
+    // - `def foo<A: Printable>( ... )`, `class Foo<A: Printable>` (only if
+    //   `allow_bounds` is true)
+    fn parse_opt_typarams(&mut self, allow_bounds: bool) -> Result<Vec<AstTyParam>, Error> {
         if !self.current_token_is(Token::LessThan) {
             return Ok(Default::default());
         }
@@ -524,12 +708,27 @@ impl<'a> Parser<'a> {
                         Some(Token::KwIn) => AstVariance::Contravariant,
                         _ => panic!("[BUG] unexpected variance token"),
                     };
+                    let name = s.to_string();
+                    variance = None;
+                    self.consume_token()?;
+                    let upper_bound = if self.current_token_is(Token::Colon) {
+                        if !allow_bounds {
+                            return Err(parse_error!(
+                                self,
+                                "bounds are only allowed for class and method type parameters"
+                            ));
+                        }
+                        self.consume_token()?;
+                        self.skip_ws()?;
+                        Some(self.parse_typ()?)
+                    } else {
+                        None
+                    };
                     typarams.push(AstTyParam {
-                        name: s.to_string(),
+                        name,
                         variance: v,
+                        upper_bound,
                     });
-                    variance = None;
-                    self.consume_token()?;
                     self.skip_wsn()?;
                 }
                 Token::Comma => {
@@ -580,7 +779,15 @@ impl<'a> Parser<'a> {
                             return Err(parse_error!(self, "@ is only used in `initialize'"));
                         }
                     }
-                    Token::LowerWord(_) => params.push(self.parse_param()?),
+                    Token::LowerWord(_) | Token::Mul => {
+                        // Only the block param may follow the variadic one
+                        let n = params.iter().skip_while(|p| !p.is_variadic).count();
+                        let param = self.parse_param()?;
+                        if n >= 2 || (n == 1 && !is_block_param(&param)) {
+                            return Err(parse_error!(self, "variadic parameter must be the last"));
+                        }
+                        params.push(param);
+                    }
                     token => {
                         return Err(parse_error!(
                             self,
@@ -614,9 +821,13 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_param(&mut self) -> Result<shiika_ast::Param, Error> {
+        let begin = self.lexer.location();
         let name;
         let is_iparam;
 
+        // `*' (Optional)
+        let is_variadic = self.consume(Token::Mul)?;
+
         // Name
         match self.current_token() {
             Token::LowerWord(s) => {
@@ -645,11 +856,30 @@ impl<'a> Parser<'a> {
 
         // Type
         let typ = self.parse_typ()?;
+        let end = self.lexer.location();
+        self.skip_ws()?;
+
+        // Default value
+        let default_expr = if self.consume(Token::Equal)? {
+            if is_variadic {
+                return Err(parse_error!(
+                    self,
+                    "variadic parameter cannot have a default value"
+                ));
+            }
+            self.skip_wsn()?;
+            Some(self.parse_expr()?)
+        } else {
+            None
+        };
 
         Ok(shiika_ast::Param {
             name,
             typ,
             is_iparam,
+            is_variadic,
+            default_expr,
+            locs: self.ast.locs(begin, end),
         })
     }
 
@@ -664,6 +894,12 @@ impl<'a> Parser<'a> {
                 self.set_lexer_gtgt_mode(false); // End special mode
                 Ok(name)
             }
+            Token::LParen => {
+                self.set_lexer_gtgt_mode(true);
+                let name = self._parse_tuple_typ()?;
+                self.set_lexer_gtgt_mode(false);
+                Ok(name)
+            }
             Token::ColonColon => Err(parse_error!(self, "TODO: parse types starting with `::'")),
             token => Err(parse_error!(self, "invalid token as type: {:?}", token)),
         }
@@ -720,6 +956,11 @@ impl<'a> Parser<'a> {
                         names.push(name);
                     }
                 }
+                Token::LParen if lessthan_seen => {
+                    // `A<(B, C)>`
+                    args.push(self._parse_tuple_typ()?);
+                    self.skip_wsn()?;
+                }
                 token => {
                     if lessthan_seen {
                         return Err(parse_error!(self, "unexpected token: {:?}", token));
@@ -734,11 +975,59 @@ impl<'a> Parser<'a> {
         Ok(self.ast.unresolved_type_name(names, args, begin, end))
     }
 
+    /// Parse a tuple type `(A, B, ...)` as `TupleN<A, B, ...>`
+    fn _parse_tuple_typ(&mut self) -> Result<UnresolvedTypeName, Error> {
+        self.lv += 1;
+        self.debug_log("_parse_tuple_typ");
+        let begin = self.lexer.location();
+        assert!(self.consume(Token::LParen)?);
+        self.skip_wsn()?;
+        let mut args = vec![];
+        loop {
+            let arg = match self.current_token() {
+                Token::UpperWord(s) => {
+                    let inner_begin = self.lexer.location();
+                    let name = s.to_string();
+                    self.consume_token()?;
+                    self._parse_typ(name, inner_begin)?
+                }
+                Token::LParen => self._parse_tuple_typ()?,
+                token => return Err(parse_error!(self, "invalid token as type: {:?}", token)),
+            };
+            args.push(arg);
+            self.skip_wsn()?;
+            if !self.consume(Token::Comma)? {
+                break;
+            }
+            self.skip_wsn()?;
+        }
+        self.expect(Token::RParen)?;
+        if args.len() < 2 || args.len() > MAX_TUPLE_SIZE {
+            return Err(parse_error!(
+                self,
+                "a tuple type must have 2 to {} items",
+                MAX_TUPLE_SIZE
+            ));
+        }
+        self.lv -= 1;
+        let end = self.lexer.location();
+        let names = vec![format!("Tuple{}", args.len())];
+        Ok(self.ast.unresolved_type_name(names, args, begin, end))
+    }
+
+    /// `A = 1` or `const A = 1`
     pub fn parse_const_definition(&mut self) -> Result<shiika_ast::Definition, Error> {
         self.debug_log("parse_const_definition");
         self.lv += 1;
+        let compile_time = self.consume(Token::KwConst)?;
+        if compile_time {
+            self.skip_ws()?;
+        }
         let name = match self.current_token() {
             Token::UpperWord(s) => s.to_string(),
+            token if compile_time => {
+                return Err(parse_error!(self, "invalid constant name: {:?}", token))
+            }
             _ => panic!("must be called on an UpperWord"),
         };
         self.consume_token()?;
@@ -750,6 +1039,20 @@ impl<'a> Parser<'a> {
         let expr = self.parse_expr()?;
 
         self.lv -= 1;
-        Ok(shiika_ast::Definition::ConstDefinition { name, expr })
+        Ok(shiika_ast::Definition::ConstDefinition {
+            name,
+            expr,
+            compile_time,
+        })
+    }
+}
+
+/// Returns true if the type of `param` is `Fn0`, `Fn1`, etc.
+fn is_block_param(param: &shiika_ast::Param) -> bool {
+    match param.typ.names.as_slice() {
+        [name] => name
+            .strip_prefix("Fn")
+            .is_some_and(|n| n.parse::<usize>().is_ok()),
+        _ => false,
     }
 }
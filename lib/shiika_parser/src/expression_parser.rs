@@ -3,7 +3,6 @@ use crate::error::Error;
 use crate::lexer::LexerState;
 use shiika_ast::*;
 use shiika_core::names::method_firstname;
-use std::collections::HashMap;
 
 impl<'a> Parser<'a> {
     /// Parse successive expressions
@@ -63,10 +62,20 @@ impl<'a> Parser<'a> {
                     let end = self.lexer.location();
                     expr = self.ast.ivar_decl(name, rhs, readonly, begin, end);
                 }
+                Token::LParen | Token::UpperWord(_) => {
+                    let pattern = self.parse_pattern()?;
+                    self.skip_ws()?;
+                    self.expect(Token::Equal)?;
+                    self.skip_wsn()?;
+                    let rhs = self.parse_operator_expr()?;
+                    let readonly = token == Token::KwLet;
+                    let end = self.lexer.location();
+                    expr = self.ast.pattern_decl(pattern, rhs, readonly, begin, end);
+                }
                 token => return Err(parse_error!(self, "invalid var name: {:?}", token)),
             }
         } else {
-            expr = self.parse_if_unless_modifier()?;
+            expr = self.parse_modifier_expr()?;
         }
         self.lv -= 1;
         Ok(expr)
@@ -74,9 +83,10 @@ impl<'a> Parser<'a> {
 
     /// a if b
     /// a unless b
-    pub fn parse_if_unless_modifier(&mut self) -> Result<AstExpression, Error> {
+    /// a until b
+    pub fn parse_modifier_expr(&mut self) -> Result<AstExpression, Error> {
         self.lv += 1;
-        self.debug_log("parse_if_unless_modifier");
+        self.debug_log("parse_modifier_expr");
         let begin = self.lexer.location();
         let mut expr = self.parse_call_wo_paren()?;
         if self.next_nonspace_token()? == Token::ModIf {
@@ -94,6 +104,14 @@ impl<'a> Parser<'a> {
             let cond = self.ast.wrap_with_logical_not(cond_inner);
             let end = self.lexer.location();
             expr = self.ast.if_expr(cond, vec![expr], None, begin, end)
+        } else if self.next_nonspace_token()? == Token::ModUntil {
+            self.skip_ws()?;
+            assert!(self.consume(Token::ModUntil)?);
+            self.skip_ws()?;
+            let cond_inner = self.parse_call_wo_paren()?;
+            let cond = self.ast.wrap_with_logical_not(cond_inner);
+            let end = self.lexer.location();
+            expr = self.ast.while_expr(cond, vec![expr], begin, end)
         }
         self.lv -= 1;
         Ok(expr)
@@ -134,7 +152,7 @@ impl<'a> Parser<'a> {
                     has_block = true;
                 }
                 expr = self.ast.set_method_call_args(expr, args, has_block);
-            } else if self.next_nonspace_token()? == Token::KwDo {
+            } else if !self.no_do_block && self.next_nonspace_token()? == Token::KwDo {
                 has_block = true;
                 self.skip_ws()?;
                 let lambda = self.parse_do_block()?;
@@ -212,22 +230,61 @@ impl<'a> Parser<'a> {
     fn parse_operator_exprs(&mut self) -> Result<Vec<AstExpression>, Error> {
         self.lv += 1;
         self.debug_log("parse_operator_exprs");
-        let mut v = vec![];
-        if self.next_nonspace_token()?.value_starts() {
-            v.push(self.parse_operator_expr()?);
-            loop {
-                self.skip_ws()?;
-                if !self.consume(Token::Comma)? {
-                    break;
-                }
-                self.skip_wsn()?;
-                v.push(self.parse_operator_expr()?);
+        let v = if self.next_nonspace_token()?.value_starts() {
+            self.parse_args()?
+        } else {
+            vec![]
+        };
+        self.lv -= 1;
+        Ok(v)
+    }
+
+    /// Parse one or more method call arguments separated by `,`
+    fn parse_args(&mut self) -> Result<Vec<AstExpression>, Error> {
+        let mut v = vec![self.parse_arg()?];
+        loop {
+            self.skip_ws()?;
+            if !self.consume(Token::Comma)? {
+                break;
+            }
+            self.skip_wsn()?;
+            let arg = self.parse_arg()?;
+            if v.last().unwrap().is_keyword_arg() && !arg.is_keyword_arg() {
+                return Err(parse_error!(
+                    self,
+                    "positional argument must not follow keyword arguments"
+                ));
             }
+            v.push(arg);
         }
-        self.lv -= 1;
         Ok(v)
     }
 
+    /// Parse a method call argument, which may be a keyword argument
+    /// (eg. `x: 1`) or a splat (eg. `*x`)
+    fn parse_arg(&mut self) -> Result<AstExpression, Error> {
+        if self.current_token_is(Token::Mul) {
+            let begin = self.lexer.location();
+            self.consume_token()?;
+            let expr = self.parse_operator_expr()?;
+            let end = self.lexer.location();
+            return Ok(self.ast.splat(expr, begin, end));
+        }
+        if let Token::LowerWord(s) = self.current_token() {
+            let name = s.to_string();
+            if self.peek_next_token()? == Token::Colon {
+                let begin = self.lexer.location();
+                self.consume_token()?;
+                self.consume_token()?;
+                self.skip_wsn()?;
+                let expr = self.parse_operator_expr()?;
+                let end = self.lexer.location();
+                return Ok(self.ast.keyword_arg(name, expr, begin, end));
+            }
+        }
+        self.parse_operator_expr()
+    }
+
     // operatorExpression:
     //   assignmentExpression |
     //   conditionalOperatorExpression (removed; next one is range_expr)
@@ -294,24 +351,25 @@ impl<'a> Parser<'a> {
         })
     }
 
-    // TODO: decide the symbol
-    // Maybe `a..=b` and `a..<b` ?
+    /// `a..b` (inclusive) and `a...b` (exclusive)
     fn parse_range_expr(&mut self) -> Result<AstExpression, Error> {
         self.lv += 1;
         self.debug_log("parse_range_expr");
         let expr = self.parse_operator_or()?;
-        //        self.skip_ws();
-        //        match self.current_token() {
-        //            Token::symbol(s @ "..") | Token::symbol(s @ "...") => {
-        //                let inclusive = (s == "..");
-        //                self.skip_wsn();
-        //                let end_expr = self.parse_operator_or()?;
-        //                Ok(shiika_ast::range_expr(Some(expr), Some(end_expr), inclusive))
-        //            },
-        //            _ => Ok(expr)
-        //        }
+        let inclusive = match self.next_nonspace_token()? {
+            Token::DotDot => true,
+            Token::DotDotDot => false,
+            _ => {
+                self.lv -= 1;
+                return Ok(expr);
+            }
+        };
+        self.skip_ws()?;
+        self.consume_token()?;
+        self.skip_wsn()?;
+        let end_expr = self.parse_operator_or()?;
         self.lv -= 1;
-        Ok(expr)
+        Ok(self.ast.range_literal(expr, end_expr, inclusive))
     }
 
     /// `or`
@@ -367,8 +425,7 @@ impl<'a> Parser<'a> {
         let begin = self.lexer.location();
         let left = self.parse_relational_expr()?;
         let op = match self.next_nonspace_token()? {
-            Token::EqEq => "==",
-            Token::NotEq => "!=",
+            t @ (Token::EqEq | Token::NotEq) => t.binary_operator_name().unwrap(),
             _ => {
                 self.lv -= 1;
                 return Ok(left);
@@ -401,10 +458,9 @@ impl<'a> Parser<'a> {
         let mut nesting = false;
         loop {
             let op = match self.next_nonspace_token()? {
-                Token::LessThan => "<",
-                Token::GreaterThan => ">",
-                Token::LessEq => "<=",
-                Token::GreaterEq => ">=",
+                t @ (Token::LessThan | Token::GreaterThan | Token::LessEq | Token::GreaterEq) => {
+                    t.binary_operator_name().unwrap()
+                }
                 _ => break,
             };
             self.skip_ws()?;
@@ -434,69 +490,79 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_bitwise_or(&mut self) -> Result<AstExpression, Error> {
-        let mut symbols = HashMap::new();
-        symbols.insert(Token::Or, "|");
-        symbols.insert(Token::Xor, "^");
-        self.parse_binary_operator("parse_bitwise_or", Parser::parse_bitwise_and, symbols)
+        self.parse_binary_operator(
+            "parse_bitwise_or",
+            Parser::parse_bitwise_and,
+            &[Token::Or, Token::Xor],
+        )
     }
 
     fn parse_bitwise_and(&mut self) -> Result<AstExpression, Error> {
-        let mut symbols = HashMap::new();
-        symbols.insert(Token::And, "&");
-        self.parse_binary_operator("parse_bitwise_and", Parser::parse_bitwise_shift, symbols)
+        self.parse_binary_operator(
+            "parse_bitwise_and",
+            Parser::parse_bitwise_shift,
+            &[Token::And],
+        )
     }
 
     fn parse_bitwise_shift(&mut self) -> Result<AstExpression, Error> {
-        let mut symbols = HashMap::new();
-        symbols.insert(Token::LShift, "<<");
-        symbols.insert(Token::RShift, ">>");
-        self.parse_binary_operator("parse_bitwise_shift", Parser::parse_additive_expr, symbols)
+        self.parse_binary_operator(
+            "parse_bitwise_shift",
+            Parser::parse_additive_expr,
+            &[Token::LShift, Token::RShift],
+        )
     }
 
     fn parse_additive_expr(&mut self) -> Result<AstExpression, Error> {
-        let mut symbols = HashMap::new();
-        symbols.insert(Token::BinaryPlus, "+");
-        symbols.insert(Token::BinaryMinus, "-");
         self.parse_binary_operator(
             "parse_additive_expr",
             Parser::parse_multiplicative_expr,
-            symbols,
+            &[Token::BinaryPlus, Token::BinaryMinus],
         )
     }
 
     fn parse_multiplicative_expr(&mut self) -> Result<AstExpression, Error> {
-        let mut symbols = HashMap::new();
-        symbols.insert(Token::Mul, "*");
-        symbols.insert(Token::Div, "/");
-        symbols.insert(Token::Mod, "%");
         self.parse_binary_operator(
             "parse_multiplicative_expr",
             Parser::parse_unary_minus_expr,
-            symbols,
+            &[Token::Mul, Token::Div, Token::Mod],
         )
     }
 
     fn parse_unary_minus_expr(&mut self) -> Result<AstExpression, Error> {
         self.lv += 1;
         self.debug_log("parse_unary_minus_expr");
-        //TODO:
-        //  parse_unary_minus_expr
-        //  parse_power_expr
-        //  parse_unary_expr
         let begin = self.lexer.location();
         let expr = if self.consume(Token::UnaryMinus)? {
-            let target = self.parse_unary_expr()?;
+            // `-a ** b` is `-(a ** b)`
+            let target = self.parse_power_expr()?;
             let end = self.lexer.location();
             self.ast
                 .simple_method_call(Some(target), "-@", Default::default(), begin, end)
         } else {
-            self.parse_unary_expr()?
+            self.parse_power_expr()?
         };
         self.lv -= 1;
         Ok(expr)
     }
 
-    // TODO: Parse ~, +
+    /// `a ** b` (right associative)
+    fn parse_power_expr(&mut self) -> Result<AstExpression, Error> {
+        self.lv += 1;
+        self.debug_log("parse_power_expr");
+        let mut expr = self.parse_unary_expr()?;
+        if self.next_nonspace_token()? == Token::Pow {
+            self.skip_ws()?;
+            self.consume_token()?;
+            self.skip_wsn()?;
+            let right = self.parse_unary_minus_expr()?;
+            expr = self.ast.bin_op_expr(expr, "**", right);
+        }
+        self.lv -= 1;
+        Ok(expr)
+    }
+
+    // TODO: Parse +
     fn parse_unary_expr(&mut self) -> Result<AstExpression, Error> {
         self.lv += 1;
         self.debug_log("parse_unary_expr");
@@ -506,6 +572,18 @@ impl<'a> Parser<'a> {
             let target = self.parse_secondary_expr()?;
             let end = self.lexer.location();
             self.ast.logical_not(target, begin, end)
+        } else if self.consume(Token::Bang)? {
+            // `!a` is `a.!`
+            let target = self.parse_unary_expr()?;
+            let end = self.lexer.location();
+            self.ast
+                .simple_method_call(Some(target), "!", Default::default(), begin, end)
+        } else if self.consume(Token::Tilde)? {
+            // `~a` is `a.~`
+            let target = self.parse_unary_expr()?;
+            let end = self.lexer.location();
+            self.ast
+                .simple_method_call(Some(target), "~", Default::default(), begin, end)
         } else {
             self.parse_secondary_expr()?
         };
@@ -524,10 +602,14 @@ impl<'a> Parser<'a> {
         self.debug_log("parse_secondary_expr");
         let expr = match self.current_token() {
             Token::KwBreak => self.parse_break_expr(),
+            Token::KwContinue => self.parse_continue_expr(),
             Token::KwIf => self.parse_if_expr(),
             Token::KwUnless => self.parse_unless_expr(),
             Token::KwMatch => self.parse_match_expr(),
             Token::KwWhile => self.parse_while_expr(),
+            Token::KwUntil => self.parse_until_expr(),
+            Token::KwFor => self.parse_for_in_expr(),
+            Token::KwGuard => self.parse_guard_let_expr(),
             _ => self.parse_primary_expr(),
         }?;
         self.lv -= 1;
@@ -539,9 +621,28 @@ impl<'a> Parser<'a> {
         self.debug_log("parse_break_expr");
         let begin = self.lexer.location();
         assert!(self.consume(Token::KwBreak)?);
+        // `break 1` (but not `break if ...`)
+        let mut arg = None;
+        if self.current_token_is(Token::Space) {
+            self.set_lexer_state(LexerState::ExprArg);
+            self.consume_token()?;
+            if self.current_token().value_starts() {
+                arg = Some(self.parse_operator_expr()?);
+            }
+        }
+        self.lv -= 1;
+        let end = self.lexer.location();
+        Ok(self.ast.break_expr(arg, begin, end))
+    }
+
+    fn parse_continue_expr(&mut self) -> Result<AstExpression, Error> {
+        self.lv += 1;
+        self.debug_log("parse_continue_expr");
+        let begin = self.lexer.location();
+        assert!(self.consume(Token::KwContinue)?);
         self.lv -= 1;
         let end = self.lexer.location();
-        Ok(self.ast.break_expr(begin, end))
+        Ok(self.ast.continue_expr(begin, end))
     }
 
     fn parse_if_expr(&mut self) -> Result<AstExpression, Error> {
@@ -626,16 +727,21 @@ impl<'a> Parser<'a> {
         }
         let then_exprs = self.parse_exprs(vec![Token::KwEnd, Token::KwElse])?;
         self.skip_wsn()?;
-        if self.consume(Token::KwElse)? {
-            return Err(parse_error!(self, "unless cannot have a else clause"));
-        }
+        let else_exprs = if self.consume(Token::KwElse)? {
+            self.skip_wsn()?;
+            let exprs = self.parse_exprs(vec![Token::KwEnd])?;
+            self.skip_wsn()?;
+            Some(exprs)
+        } else {
+            None
+        };
         self.expect(Token::KwEnd)?;
         self.lv -= 1;
         let end = self.lexer.location();
         Ok(self.ast.if_expr(
             self.ast.wrap_with_logical_not(cond_expr),
             then_exprs,
-            None,
+            else_exprs,
             begin,
             end,
         ))
@@ -656,8 +762,20 @@ impl<'a> Parser<'a> {
                 Token::KwWhen => {
                     self.consume_token()?;
                     self.skip_ws()?;
+                    let pattern_begin = self.lexer.location();
                     let pattern = self.parse_pattern()?;
+                    let pattern_locs = self.ast.locs(pattern_begin, self.lexer.location());
                     self.skip_ws()?;
+                    let guard = match self.current_token() {
+                        Token::KwIf | Token::ModIf => {
+                            self.consume_token()?;
+                            self.skip_ws()?;
+                            let expr = self.parse_call_wo_paren()?;
+                            self.skip_ws()?;
+                            Some(expr)
+                        }
+                        _ => None,
+                    };
                     if self.current_token_is(Token::KwThen) {
                         self.consume_token()?;
                     } else {
@@ -665,13 +783,15 @@ impl<'a> Parser<'a> {
                     }
                     let exprs =
                         self.parse_exprs(vec![Token::KwEnd, Token::KwWhen, Token::KwElse])?;
-                    clauses.push((pattern, exprs));
+                    clauses.push((pattern, pattern_locs, guard, exprs));
                 }
                 Token::KwElse => {
+                    let else_begin = self.lexer.location();
                     self.consume_token()?;
+                    let else_locs = self.ast.locs(else_begin, self.lexer.location());
                     let exprs = self.parse_exprs(vec![Token::KwEnd])?;
                     let pattern = shiika_ast::AstPattern::VariablePattern("_".to_string());
-                    clauses.push((pattern, exprs));
+                    clauses.push((pattern, else_locs, None, exprs));
                 }
                 Token::KwEnd => {
                     self.consume_token()?;
@@ -697,9 +817,13 @@ impl<'a> Parser<'a> {
         let begin = self.lexer.location();
         assert!(self.consume(Token::KwWhile)?);
         self.skip_ws()?;
+        self.no_do_block = true;
         let cond_expr = self.parse_call_wo_paren()?;
+        self.no_do_block = false;
         self.skip_ws()?;
-        self.expect(Token::Separator)?;
+        if !self.consume(Token::KwDo)? {
+            self.expect(Token::Separator)?;
+        }
         let body_exprs = self.parse_exprs(vec![Token::KwEnd])?;
         self.skip_wsn()?;
         self.expect(Token::KwEnd)?;
@@ -708,6 +832,81 @@ impl<'a> Parser<'a> {
         Ok(self.ast.while_expr(cond_expr, body_exprs, begin, end))
     }
 
+    /// `until x do ... end` (same as `while not x do ... end`)
+    fn parse_until_expr(&mut self) -> Result<AstExpression, Error> {
+        self.lv += 1;
+        self.debug_log("parse_until_expr");
+        let begin = self.lexer.location();
+        assert!(self.consume(Token::KwUntil)?);
+        self.skip_ws()?;
+        self.no_do_block = true;
+        let cond_expr = self.parse_call_wo_paren()?;
+        self.no_do_block = false;
+        self.skip_ws()?;
+        if !self.consume(Token::KwDo)? {
+            self.expect(Token::Separator)?;
+        }
+        let body_exprs = self.parse_exprs(vec![Token::KwEnd])?;
+        self.skip_wsn()?;
+        self.expect(Token::KwEnd)?;
+        self.lv -= 1;
+        let end = self.lexer.location();
+        let cond = self.ast.wrap_with_logical_not(cond_expr);
+        Ok(self.ast.while_expr(cond, body_exprs, begin, end))
+    }
+
+    fn parse_guard_let_expr(&mut self) -> Result<AstExpression, Error> {
+        self.lv += 1;
+        self.debug_log("parse_guard_let_expr");
+        let begin = self.lexer.location();
+        assert!(self.consume(Token::KwGuard)?);
+        self.skip_ws()?;
+        self.expect(Token::KwLet)?;
+        self.skip_ws()?;
+        let pattern = self.parse_pattern()?;
+        self.skip_ws()?;
+        self.expect(Token::Equal)?;
+        self.skip_wsn()?;
+        let expr = self.parse_call_wo_paren()?;
+        self.skip_ws()?;
+        self.expect(Token::KwElse)?;
+        let else_exprs = self.parse_exprs(vec![Token::KwEnd])?;
+        self.skip_wsn()?;
+        self.expect(Token::KwEnd)?;
+        self.lv -= 1;
+        let end = self.lexer.location();
+        Ok(self.ast.guard_let(pattern, expr, else_exprs, begin, end))
+    }
+
+    fn parse_for_in_expr(&mut self) -> Result<AstExpression, Error> {
+        self.lv += 1;
+        self.debug_log("parse_for_in_expr");
+        let begin = self.lexer.location();
+        assert!(self.consume(Token::KwFor)?);
+        self.skip_ws()?;
+        let var = match self.current_token() {
+            Token::LowerWord(s) => s.to_string(),
+            token => return Err(parse_error!(self, "invalid loop variable: {:?}", token)),
+        };
+        self.consume_token()?;
+        self.skip_ws()?;
+        self.expect(Token::KwIn)?;
+        self.skip_ws()?;
+        self.no_do_block = true;
+        let collection = self.parse_operator_expr()?;
+        self.no_do_block = false;
+        self.skip_ws()?;
+        if !self.consume(Token::KwDo)? {
+            self.expect(Token::Separator)?;
+        }
+        let body = self.parse_exprs(vec![Token::KwEnd])?;
+        self.skip_wsn()?;
+        self.expect(Token::KwEnd)?;
+        self.lv -= 1;
+        let end = self.lexer.location();
+        Ok(self.ast.for_in_expr(var, collection, body, begin, end))
+    }
+
     // prim . methodName argumentWithParentheses? block?
     // prim [ indexingArgumentList? ] not(EQUAL)
     fn parse_primary_expr(&mut self) -> Result<AstExpression, Error> {
@@ -739,6 +938,10 @@ impl<'a> Parser<'a> {
                 // TODO: Newline should also be allowed here (but Semicolon is not)
                 self.skip_ws()?;
                 expr = self.parse_method_chain(expr)?;
+            } else if self.consume(Token::Question)? {
+                // `foo(x)?` (Note that `foo?` is a method name)
+                let end = self.lexer.location();
+                expr = self.ast.propagate(expr, begin.clone(), end);
             } else {
                 break;
             }
@@ -848,14 +1051,16 @@ impl<'a> Parser<'a> {
         self.debug_log("parse_paren_and_args");
         assert!(self.consume(Token::LParen)?);
         self.skip_wsn()?;
+        let no_do_block = std::mem::replace(&mut self.no_do_block, false);
         let args;
         if self.consume(Token::RParen)? {
             args = vec![]
         } else {
-            args = self.parse_operator_exprs()?;
+            args = self.parse_args()?;
             self.skip_wsn()?;
             self.expect(Token::RParen)?;
         }
+        self.no_do_block = no_do_block;
         self.lv -= 1;
         Ok(args)
     }
@@ -885,6 +1090,18 @@ impl<'a> Parser<'a> {
                 let end = self.lexer.location();
                 Ok(self.ast.pseudo_variable(t, begin, end))
             }
+            Token::KwSuper => {
+                self.consume_token()?;
+                // `super` is the same as `super()`
+                let args = if self.current_token_is(Token::LParen) {
+                    self.parse_paren_and_args()?
+                } else {
+                    vec![]
+                };
+                let end = self.lexer.location();
+                Ok(self.ast.super_call(args, begin, end))
+            }
+            Token::KwAssertType => self.parse_assert_type(),
             Token::IVar(s) => {
                 let name = s.to_string();
                 self.consume_token()?;
@@ -892,9 +1109,16 @@ impl<'a> Parser<'a> {
                 Ok(self.ast.ivar_ref(name, begin, end))
             }
             Token::LSqBracket => self.parse_array_literal(),
+            Token::LBrace => self.parse_set_literal(),
             Token::Number(_) => self.parse_decimal_literal(),
             Token::Str(_) => self.parse_string_literal(),
             Token::StrWithInterpolation { .. } => self.parse_string_with_interpolation(),
+            Token::CharLiteral(c) => {
+                let value = *c;
+                self.consume_token()?;
+                let end = self.lexer.location();
+                Ok(self.ast.char_literal(value, begin, end))
+            }
             Token::LParen => self.parse_parenthesized_expr(),
             token => Err(parse_error!(self, "unexpected token: {:?}", token)),
         }?;
@@ -1050,16 +1274,57 @@ impl<'a> Parser<'a> {
         Ok(self.ast.lambda_expr(params, exprs, true, begin, end))
     }
 
+    /// `assert_type(expr, Type)`
+    fn parse_assert_type(&mut self) -> Result<AstExpression, Error> {
+        self.lv += 1;
+        self.debug_log("parse_assert_type");
+        let begin = self.lexer.location();
+        assert!(self.consume(Token::KwAssertType)?);
+        self.expect(Token::LParen)?;
+        self.skip_wsn()?;
+        let expr = self.parse_expr()?;
+        self.skip_wsn()?;
+        self.expect(Token::Comma)?;
+        self.skip_wsn()?;
+        let typ = self.parse_typ()?;
+        self.skip_wsn()?;
+        self.expect(Token::RParen)?;
+        let end = self.lexer.location();
+        self.lv -= 1;
+        Ok(self.ast.assert_type(expr, typ, begin, end))
+    }
+
+    /// `(expr)` or a tuple literal `(expr, expr, ...)`
     fn parse_parenthesized_expr(&mut self) -> Result<AstExpression, Error> {
         self.lv += 1;
         self.debug_log("parse_parenthesized_expr");
+        let begin = self.lexer.location();
         assert!(self.consume(Token::LParen)?);
         self.skip_wsn()?;
         let expr = self.parse_expr()?; // Should be parse_exprs() ?
         self.skip_wsn()?;
+        if !self.current_token_is(Token::Comma) {
+            self.expect(Token::RParen)?;
+            self.lv -= 1;
+            return Ok(expr);
+        }
+        let mut exprs = vec![expr];
+        while self.consume(Token::Comma)? {
+            self.skip_wsn()?;
+            exprs.push(self.parse_expr()?);
+            self.skip_wsn()?;
+        }
         self.expect(Token::RParen)?;
+        if exprs.len() > MAX_TUPLE_SIZE {
+            return Err(parse_error!(
+                self,
+                "a tuple can have at most {} items",
+                MAX_TUPLE_SIZE
+            ));
+        }
+        let end = self.lexer.location();
         self.lv -= 1;
-        Ok(expr)
+        Ok(self.ast.tuple_literal(exprs, begin, end))
     }
 
     fn parse_array_literal(&mut self) -> Result<AstExpression, Error> {
@@ -1067,17 +1332,48 @@ impl<'a> Parser<'a> {
         self.debug_log("parse_array_literal");
         let begin = self.lexer.location();
         assert!(self.consume(Token::LSqBracket)?);
+        let exprs = self.parse_literal_items(Token::RSqBracket, "an array literal")?;
+        let end = self.lexer.location();
+        self.lv -= 1;
+        Ok(self.ast.array_literal(exprs, begin, end))
+    }
+
+    /// Parse `{a, b, ...}`
+    fn parse_set_literal(&mut self) -> Result<AstExpression, Error> {
+        self.lv += 1;
+        self.debug_log("parse_set_literal");
+        let begin = self.lexer.location();
+        assert!(self.consume(Token::LBrace)?);
+        let no_do_block = std::mem::replace(&mut self.no_do_block, false);
+        let exprs = self.parse_literal_items(Token::RBrace, "a set literal")?;
+        self.no_do_block = no_do_block;
+        if exprs.is_empty() {
+            return Err(parse_error!(
+                self,
+                "empty set literal is not allowed (use `Set<T>.new` instead)"
+            ));
+        }
+        let end = self.lexer.location();
+        self.lv -= 1;
+        Ok(self.ast.set_literal(exprs, begin, end))
+    }
+
+    /// Parse `a, b, ...` of an array literal, etc. up to `closing` (which
+    /// is also consumed)
+    fn parse_literal_items(
+        &mut self,
+        closing: Token,
+        what: &str,
+    ) -> Result<Vec<AstExpression>, Error> {
         let mut exprs = vec![];
         self.skip_wsn()?;
         loop {
             match self.current_token() {
-                Token::RSqBracket => {
+                t if *t == closing => {
                     self.consume_token()?;
                     break;
                 }
-                Token::Comma => {
-                    return Err(parse_error!(self, "unexpected comma in an array literal"))
-                }
+                Token::Comma => return Err(parse_error!(self, "unexpected comma in {}", what)),
                 _ => {
                     let expr = self.parse_call_wo_paren()?;
                     exprs.push(expr);
@@ -1087,21 +1383,20 @@ impl<'a> Parser<'a> {
                             self.consume_token()?;
                             self.skip_wsn()?;
                         }
-                        Token::RSqBracket => (),
+                        t if *t == closing => (),
                         token => {
                             return Err(parse_error!(
                                 self,
-                                "unexpected token `{:?}' in an array literal",
-                                token
+                                "unexpected token `{:?}' in {}",
+                                token,
+                                what
                             ))
                         }
                     }
                 }
             }
         }
-        let end = self.lexer.location();
-        self.lv -= 1;
-        Ok(self.ast.array_literal(exprs, begin, end))
+        Ok(exprs)
     }
 
     fn parse_decimal_literal(&mut self) -> Result<AstExpression, Error> {
@@ -1143,60 +1438,61 @@ impl<'a> Parser<'a> {
     fn parse_string_with_interpolation(&mut self) -> Result<AstExpression, Error> {
         self.lv += 1;
         self.debug_log("parse_string_with_interpolation");
-        let mut begin = self.lexer.location();
-        let (head, inspect1) =
-            if let Token::StrWithInterpolation { head, inspect } = self.consume_token()? {
-                (head, inspect)
-            } else {
-                panic!("invalid call")
-            };
-        let mut end = self.lexer.location();
-        let mut inspect = inspect1;
-        let mut expr = self.ast.string_literal(head, begin, end);
-        begin = self.lexer.location();
+        let begin = self.lexer.location();
+        let (head, mut inspect, heredoc) = if let Token::StrWithInterpolation {
+            head,
+            inspect,
+            heredoc,
+        } = self.consume_token()?
+        {
+            (head, inspect, heredoc)
+        } else {
+            panic!("invalid call")
+        };
+        let mut parts = vec![];
+        if !head.is_empty() {
+            parts.push(StringPart::Literal(head));
+        }
         loop {
             self.skip_wsn()?;
-            let inner_expr = self.parse_expr()?;
-            end = self.lexer.location();
-            let arg = self.ast.simple_method_call(
-                Some(inner_expr),
-                if inspect { "inspect" } else { "to_s" },
-                vec![],
-                begin.clone(),
-                end.clone(),
-            );
-            expr = self
-                .ast
-                .simple_method_call(Some(expr), "+", vec![arg], begin, end);
-            self.set_lexer_state(LexerState::StrLiteral);
+            // Empty interpolation (`#{}`) is allowed and yields nothing
+            if *self.current_token() != Token::RBrace {
+                let expr = self.parse_expr()?;
+                self.skip_wsn()?;
+                parts.push(StringPart::Expr {
+                    expr: Box::new(expr),
+                    inspect,
+                });
+            }
+            self.set_lexer_state(if heredoc {
+                LexerState::HeredocLiteral
+            } else {
+                LexerState::StrLiteral
+            });
             self.expect(Token::RBrace)?;
             self.set_lexer_state(LexerState::ExprEnd);
-            begin = self.lexer.location();
             let (s, finish) = match self.consume_token()? {
                 Token::Str(tail) => (tail, true),
                 Token::StrWithInterpolation {
                     head,
                     inspect: inspect2,
+                    ..
                 } => {
                     inspect = inspect2;
                     (head, false)
                 }
                 _ => panic!("unexpeced token in LexerState::StrLiteral"),
             };
-            let end = self.lexer.location();
-            expr = self.ast.simple_method_call(
-                Some(expr),
-                "+",
-                vec![self.ast.string_literal(s, begin.clone(), end.clone())],
-                begin.clone(),
-                end,
-            );
+            if !s.is_empty() {
+                parts.push(StringPart::Literal(s));
+            }
             if finish {
                 break;
             };
         }
+        let end = self.lexer.location();
         self.lv -= 1;
-        Ok(expr)
+        Ok(self.ast.interpolated_string(parts, begin, end))
     }
 
     // func: parse_xx
@@ -1205,16 +1501,16 @@ impl<'a> Parser<'a> {
         &mut self,
         name: &str,
         func: F,
-        symbols: HashMap<Token, &str>,
+        operators: &[Token],
     ) -> Result<AstExpression, Error> {
         self.lv += 1;
         self.debug_log(name);
         let mut left = func(self)?;
         loop {
             let t = self.next_nonspace_token()?;
-            let op = match symbols.get(&t) {
-                Some(s) => s,
-                None => {
+            let op = match t.binary_operator_name() {
+                Some(s) if operators.contains(&t) => s,
+                _ => {
                     self.lv -= 1;
                     return Ok(left);
                 }
@@ -1230,7 +1526,7 @@ impl<'a> Parser<'a> {
     /// Parse `do |..| ...end` or `{|..| ...}`, if any
     fn parse_opt_block(&mut self) -> Result<Option<AstExpression>, Error> {
         match self.next_nonspace_token()? {
-            Token::KwDo => {
+            Token::KwDo if !self.no_do_block => {
                 self.skip_ws()?;
                 Ok(Some(self.parse_do_block()?))
             }
@@ -1245,13 +1541,13 @@ impl<'a> Parser<'a> {
     /// Parse `do |..| ...end`, if any
     fn parse_opt_do_block(&mut self) -> Result<Option<AstExpression>, Error> {
         match self.current_token() {
-            Token::KwDo => Ok(Some(self.parse_do_block()?)),
+            Token::KwDo if !self.no_do_block => Ok(Some(self.parse_do_block()?)),
             _ => Ok(None),
         }
     }
 
     /// Parse `do |..| ...end`
-    fn parse_do_block(&mut self) -> Result<AstExpression, Error> {
+    pub(super) fn parse_do_block(&mut self) -> Result<AstExpression, Error> {
         self.lv += 1;
         self.debug_log("parse_do_block");
         let begin = self.lexer.location();
@@ -1273,7 +1569,7 @@ impl<'a> Parser<'a> {
     }
 
     /// Parse `{|..| ...}`
-    fn parse_brace_block(&mut self) -> Result<AstExpression, Error> {
+    pub(super) fn parse_brace_block(&mut self) -> Result<AstExpression, Error> {
         self.lv += 1;
         self.debug_log("parse_brace_block");
         let begin = self.lexer.location();
@@ -1285,7 +1581,9 @@ impl<'a> Parser<'a> {
             vec![]
         };
         self.skip_wsn()?;
+        let no_do_block = std::mem::replace(&mut self.no_do_block, false);
         let body_exprs = self.parse_exprs(vec![Token::RBrace])?;
+        self.no_do_block = no_do_block;
         self.expect(Token::RBrace)?;
         let end = self.lexer.location();
         self.lv -= 1;
@@ -1378,9 +1676,28 @@ impl<'a> Parser<'a> {
     }
 
     /// Parse pattern of match expr
+    /// Parse a pattern (including or-patterns like `A | B`)
     fn parse_pattern(&mut self) -> Result<AstPattern, Error> {
         self.lv += 1;
         self.debug_log("parse_pattern");
+        let mut patterns = vec![self.parse_pattern_item()?];
+        while self.next_nonspace_token()? == Token::Or {
+            self.skip_ws()?;
+            self.consume_token()?;
+            self.skip_wsn()?;
+            patterns.push(self.parse_pattern_item()?);
+        }
+        self.lv -= 1;
+        if patterns.len() == 1 {
+            Ok(patterns.pop().unwrap())
+        } else {
+            Ok(shiika_ast::AstPattern::OrPattern(patterns))
+        }
+    }
+
+    fn parse_pattern_item(&mut self) -> Result<AstPattern, Error> {
+        self.lv += 1;
+        self.debug_log("parse_pattern_item");
         let token = self.current_token();
         let item = match token {
             Token::LowerWord(s) => {
@@ -1398,15 +1715,24 @@ impl<'a> Parser<'a> {
                 self.consume_token()?;
                 shiika_ast::AstPattern::BooleanLiteralPattern(b)
             }
-            Token::Number(s) => {
-                if s.contains('.') {
-                    let value = s.parse().unwrap();
-                    self.consume_token()?;
-                    shiika_ast::AstPattern::FloatLiteralPattern(value)
-                } else {
-                    let value = s.parse().unwrap();
-                    self.consume_token()?;
-                    shiika_ast::AstPattern::IntegerLiteralPattern(value)
+            Token::Number(_) => {
+                let start = self.parse_number_pattern()?;
+                let inclusive = match self.next_nonspace_token()? {
+                    Token::DotDot => true,
+                    Token::DotDotDot => false,
+                    _ => {
+                        self.lv -= 1;
+                        return Ok(start);
+                    }
+                };
+                self.skip_ws()?;
+                self.consume_token()?;
+                self.skip_ws()?;
+                let end = self.parse_number_pattern()?;
+                shiika_ast::AstPattern::RangePattern {
+                    start: Box::new(start),
+                    end: Box::new(end),
+                    inclusive,
                 }
             }
             Token::Str(content) => {
@@ -1417,6 +1743,7 @@ impl<'a> Parser<'a> {
             Token::StrWithInterpolation { .. } => {
                 todo!()
             }
+            Token::LParen => self.parse_tuple_pattern()?,
             _ => {
                 return Err(parse_error!(self, "expected a pattern but got {:?}", token));
             }
@@ -1425,6 +1752,53 @@ impl<'a> Parser<'a> {
         Ok(item)
     }
 
+    /// Parse integer or float literal pattern
+    fn parse_number_pattern(&mut self) -> Result<AstPattern, Error> {
+        let token = self.current_token();
+        let item = match token {
+            Token::Number(s) => {
+                if s.contains('.') {
+                    let value = s.parse().unwrap();
+                    self.consume_token()?;
+                    shiika_ast::AstPattern::FloatLiteralPattern(value)
+                } else {
+                    let value = s.parse().unwrap();
+                    self.consume_token()?;
+                    shiika_ast::AstPattern::IntegerLiteralPattern(value)
+                }
+            }
+            _ => {
+                return Err(parse_error!(self, "expected a number but got {:?}", token));
+            }
+        };
+        Ok(item)
+    }
+
+    /// Parse `(a, b, ...)`
+    fn parse_tuple_pattern(&mut self) -> Result<AstPattern, Error> {
+        self.lv += 1;
+        self.debug_log("parse_tuple_pattern");
+        assert!(self.consume(Token::LParen)?);
+        self.skip_wsn()?;
+        let mut params = vec![self.parse_pattern()?];
+        self.skip_wsn()?;
+        while self.consume(Token::Comma)? {
+            self.skip_wsn()?;
+            params.push(self.parse_pattern()?);
+            self.skip_wsn()?;
+        }
+        self.expect(Token::RParen)?;
+        if params.len() < 2 || params.len() > MAX_TUPLE_SIZE {
+            return Err(parse_error!(
+                self,
+                "a tuple pattern must have 2 to {} items",
+                MAX_TUPLE_SIZE
+            ));
+        }
+        self.lv -= 1;
+        Ok(shiika_ast::AstPattern::TuplePattern(params))
+    }
+
     /// Parse pattern like `Some(val)`
     fn parse_extractor_pattern(&mut self, upper_word: String) -> Result<AstPattern, Error> {
         self.lv += 1;
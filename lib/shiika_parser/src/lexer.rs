@@ -18,6 +18,27 @@ pub struct Lexer<'a> {
     space_seen: bool,
     /// If true, parse `>>` as `>` + `>`
     pub rshift_is_gtgt: bool,
+    /// Heredocs found so far
+    heredocs: Vec<Heredoc>,
+}
+
+/// A heredoc (`<<~ID ... ID`)
+#[derive(Debug)]
+struct Heredoc {
+    /// Position of the `<<~`
+    opener_pos: usize,
+    /// Next position of the `<<~ID`
+    resume_cur: Cursor,
+    /// Position of the newline which ends the line of the opener
+    line_end_pos: usize,
+    /// Position of the first line of the body
+    body_pos: usize,
+    /// Position of the line of the terminator
+    terminator_pos: usize,
+    /// Number of the spaces removed from each line of the body
+    indent: usize,
+    /// Next position of the line of the terminator
+    end_cur: Cursor,
 }
 
 /// Flags to decide a `-`, `+`, etc. is unary or binary.
@@ -48,6 +69,8 @@ pub enum LexerState {
     MethodName,
     /// In a string literal (with interpolation)
     StrLiteral,
+    /// In a heredoc (with interpolation)
+    HeredocLiteral,
 }
 
 #[derive(Debug, PartialEq, Clone, Default)]
@@ -111,6 +134,7 @@ enum CharType {
     Symbol,    // '+', '(', etc.
     Number,    // '0'~'9'
     Str,       // '"'
+    Char,      // '\''
     Eof,
 }
 
@@ -129,6 +153,7 @@ impl<'a> Lexer<'a> {
             state,
             space_seen: false,
             rshift_is_gtgt: false,
+            heredocs: vec![],
         };
         lexer.read_token().unwrap();
         lexer
@@ -246,6 +271,7 @@ impl<'a> Lexer<'a> {
             CharType::Symbol => self.read_symbol(&mut next_next_cur)?,
             CharType::Number => (self.read_number(&mut next_next_cur, Some(&next_cur))?, None),
             CharType::Str => (self.read_str(&mut next_next_cur, false)?, None),
+            CharType::Char => (self.read_char(&mut next_next_cur)?, None),
             CharType::Eof => (self.read_eof(), None),
         };
         Ok(token)
@@ -261,6 +287,9 @@ impl<'a> Lexer<'a> {
         if self.state == LexerState::StrLiteral {
             token = self.read_str(&mut next_cur, true)?;
             new_state = None;
+        } else if self.state == LexerState::HeredocLiteral {
+            token = self.read_heredoc_body(&mut next_cur)?;
+            new_state = None;
         } else {
             let (t, s) = match self.char_type(c) {
                 CharType::Space => (self.read_space(&mut next_cur), None),
@@ -287,6 +316,7 @@ impl<'a> Lexer<'a> {
                     self.read_str(&mut next_cur, false)?,
                     Some(LexerState::ExprEnd),
                 ),
+                CharType::Char => (self.read_char(&mut next_cur)?, Some(LexerState::ExprEnd)),
                 CharType::Eof => (self.read_eof(), None),
             };
             token = t;
@@ -309,7 +339,7 @@ impl<'a> Lexer<'a> {
 
     fn read_separator(&mut self, next_cur: &mut Cursor) -> Token {
         while let CharType::Space | CharType::Separator = self.char_type(next_cur.peek(self.src)) {
-            next_cur.proceed(self.src);
+            self.proceed_skipping_heredoc(next_cur);
         }
         Token::Separator
     }
@@ -317,7 +347,7 @@ impl<'a> Lexer<'a> {
     fn read_comment(&mut self, next_cur: &mut Cursor) -> Token {
         next_cur.proceed(self.src); // Skip the `#'
         loop {
-            let c = next_cur.proceed(self.src);
+            let c = self.proceed_skipping_heredoc(next_cur);
             if c == '\n' {
                 break;
             }
@@ -325,6 +355,28 @@ impl<'a> Lexer<'a> {
         Token::Separator
     }
 
+    /// Consume the current char. If it is the end of a line which has
+    /// heredoc(s), also skip their bodies.
+    fn proceed_skipping_heredoc(&self, next_cur: &mut Cursor) -> char {
+        let pos = next_cur.pos;
+        let c = next_cur.proceed(self.src);
+        if let Some(end_cur) = self.heredoc_end(pos, None) {
+            *next_cur = end_cur;
+        }
+        c
+    }
+
+    /// Returns the end of the last heredoc (before `opener_pos`, if given)
+    /// whose opener is on the line ending at `line_end_pos`
+    fn heredoc_end(&self, line_end_pos: usize, opener_pos: Option<usize>) -> Option<Cursor> {
+        self.heredocs
+            .iter()
+            .filter(|h| h.line_end_pos == line_end_pos)
+            .filter(|h| h.opener_pos < opener_pos.unwrap_or(usize::MAX))
+            .max_by_key(|h| h.opener_pos)
+            .map(|h| h.end_cur.clone())
+    }
+
     fn read_upper_word(&mut self, next_cur: &mut Cursor, cur: Option<&Cursor>) -> Token {
         while let CharType::UpperWord | CharType::LowerWord | CharType::Number =
             self.char_type(next_cur.peek(self.src))
@@ -369,7 +421,10 @@ impl<'a> Lexer<'a> {
             "class" => (Token::KwClass, LexerState::ExprBegin),
             "module" => (Token::KwModule, LexerState::ExprBegin),
             "requirement" => (Token::KwRequirement, LexerState::ExprBegin),
+            "abstract" => (Token::KwAbstract, LexerState::ExprBegin),
             "enum" => (Token::KwEnum, LexerState::ExprBegin),
+            "extension" => (Token::KwExtension, LexerState::ExprBegin),
+            "const" => (Token::KwConst, LexerState::ExprBegin),
             "case" => (Token::KwCase, LexerState::ExprBegin),
             "in" => (Token::KwIn, LexerState::ExprBegin),
             "out" => (Token::KwOut, LexerState::ExprBegin),
@@ -397,7 +452,17 @@ impl<'a> Lexer<'a> {
             "match" => (Token::KwMatch, LexerState::ExprBegin),
             "when" => (Token::KwWhen, LexerState::ExprBegin),
             "while" => (Token::KwWhile, LexerState::ExprBegin),
+            "until" => {
+                if self.state == LexerState::ExprBegin {
+                    (Token::KwUntil, LexerState::ExprBegin)
+                } else {
+                    (Token::ModUntil, LexerState::ExprBegin)
+                }
+            }
+            "for" => (Token::KwFor, LexerState::ExprBegin),
+            "guard" => (Token::KwGuard, LexerState::ExprBegin),
             "break" => (Token::KwBreak, LexerState::ExprEnd),
+            "continue" => (Token::KwContinue, LexerState::ExprEnd),
             "return" => (Token::KwReturn, LexerState::ExprBegin),
             "then" => (Token::KwThen, LexerState::ExprBegin),
             "else" => (Token::KwElse, LexerState::ExprBegin),
@@ -405,6 +470,8 @@ impl<'a> Lexer<'a> {
             "fn" => (Token::KwFn, LexerState::ExprBegin),
             "do" => (Token::KwDo, LexerState::ExprBegin),
             "self" => (Token::KwSelf, LexerState::ExprEnd),
+            "super" => (Token::KwSuper, LexerState::ExprEnd),
+            "assert_type" => (Token::KwAssertType, LexerState::ExprEnd),
             "true" => (Token::KwTrue, LexerState::ExprEnd),
             "false" => (Token::KwFalse, LexerState::ExprEnd),
             _ => (Token::LowerWord(s.to_string()), LexerState::ExprEnd),
@@ -483,7 +550,10 @@ impl<'a> Lexer<'a> {
                 }
             }
             '*' => {
-                if c2 == Some('=') {
+                if c2 == Some('*') {
+                    next_cur.proceed(self.src);
+                    Ok((Token::Pow, Some(LexerState::ExprBegin)))
+                } else if c2 == Some('=') {
                     next_cur.proceed(self.src);
                     Ok((Token::MulEq, Some(LexerState::ExprBegin)))
                 } else {
@@ -526,6 +596,9 @@ impl<'a> Lexer<'a> {
                 if c2 == Some('=') {
                     next_cur.proceed(self.src);
                     Ok((Token::LessEq, Some(LexerState::ExprBegin)))
+                } else if c2 == Some('<') && self.heredoc_starts(next_cur) {
+                    let token = self.read_heredoc(next_cur)?;
+                    Ok((token, Some(LexerState::ExprEnd)))
                 } else if c2 == Some('<') {
                     next_cur.proceed(self.src);
                     let c3 = next_cur.peek(self.src);
@@ -564,10 +637,22 @@ impl<'a> Lexer<'a> {
                     Ok((Token::GreaterThan, Some(LexerState::ExprBegin)))
                 }
             }
-            '.' => Ok((Token::Dot, Some(LexerState::ExprBegin))),
+            '.' => {
+                if c2 == Some('.') {
+                    next_cur.proceed(self.src);
+                    if next_cur.peek(self.src) == Some('.') {
+                        next_cur.proceed(self.src);
+                        Ok((Token::DotDotDot, Some(LexerState::ExprBegin)))
+                    } else {
+                        Ok((Token::DotDot, Some(LexerState::ExprBegin)))
+                    }
+                } else {
+                    Ok((Token::Dot, Some(LexerState::ExprBegin)))
+                }
+            }
             '@' => Ok((Token::At, Some(LexerState::ExprBegin))),
             '~' => Ok((Token::Tilde, Some(LexerState::ExprBegin))),
-            '?' => Ok((Token::Question, Some(LexerState::ExprBegin))),
+            '?' => Ok((Token::Question, Some(LexerState::ExprEnd))),
             ',' => Ok((Token::Comma, Some(LexerState::ExprBegin))),
             ':' => {
                 if c2 == Some(':') {
@@ -598,6 +683,159 @@ impl<'a> Lexer<'a> {
         }
     }
 
+    /// Returns true if `<<~ID` (Note: the first `<` is already consumed)
+    fn heredoc_starts(&self, next_cur: &Cursor) -> bool {
+        if self.state == LexerState::ExprEnd {
+            // eg. `a <<~B` is `a << ~B`
+            return false;
+        }
+        let s = next_cur.peek_n(self.src, 3);
+        let mut chars = s.chars().skip(1);
+        chars.next() == Some('~') && matches!(chars.next(), Some('A'..='Z'))
+    }
+
+    /// Read a heredoc (`<<~ID ... ID`) and returns its content as a string
+    /// literal.
+    ///
+    /// Only `<<~ID` and the body (from the next line to the terminator `ID`)
+    /// are read here and the rest of the line is read as usual; the body is
+    /// skipped when the lexer reaches the end of the line.
+    /// Like Ruby's `<<~`, the common indentation of the body is removed.
+    /// Escape sequences and interpolation are processed like `"..."`.
+    fn read_heredoc(&mut self, next_cur: &mut Cursor) -> Result<Token, Error> {
+        let opener_pos = next_cur.pos - 1;
+        next_cur.proceed(self.src); // Skip `<`
+        next_cur.proceed(self.src); // Skip `~`
+        let id_begin = next_cur.pos;
+        while let CharType::UpperWord | CharType::LowerWord | CharType::Number =
+            self.char_type(next_cur.peek(self.src))
+        {
+            next_cur.proceed(self.src);
+        }
+        let id = &self.src[id_begin..next_cur.pos];
+
+        // Find the beginning of the body
+        let mut body_cur = next_cur.clone();
+        loop {
+            match body_cur.peek(self.src) {
+                None => return Err(self.lex_error("found unterminated heredoc")),
+                Some('\n') => break,
+                Some(_) => {
+                    body_cur.proceed(self.src);
+                }
+            }
+        }
+        let line_end_pos = body_cur.pos;
+        body_cur.proceed(self.src);
+        // There may be another heredoc on the same line (eg. `f(<<~A, <<~B)`)
+        if let Some(end_cur) = self.heredoc_end(line_end_pos, Some(opener_pos)) {
+            body_cur = end_cur;
+        }
+        let body_start = body_cur.clone();
+
+        let mut lines = vec![];
+        let terminator_pos;
+        loop {
+            if body_cur.peek(self.src).is_none() {
+                return Err(self.lex_error(&format!("heredoc terminator {} not found", id)));
+            }
+            let begin = body_cur.pos;
+            while !matches!(body_cur.peek(self.src), None | Some('\n')) {
+                body_cur.proceed(self.src);
+            }
+            let line = &self.src[begin..body_cur.pos];
+            if body_cur.peek(self.src).is_some() {
+                body_cur.proceed(self.src);
+            }
+            if line.trim() == id {
+                terminator_pos = begin;
+                break;
+            }
+            lines.push(line);
+        }
+
+        self.heredocs.retain(|h| h.opener_pos != opener_pos);
+        self.heredocs.push(Heredoc {
+            opener_pos,
+            resume_cur: next_cur.clone(),
+            line_end_pos,
+            body_pos: body_start.pos,
+            terminator_pos,
+            indent: common_indent(&lines),
+            end_cur: body_cur,
+        });
+        *next_cur = body_start;
+        self.read_heredoc_body(next_cur)
+    }
+
+    /// Read the body of a heredoc until the terminator or `#{`.
+    /// When the terminator is reached, `next_cur` is moved back to the line
+    /// of the opener
+    fn read_heredoc_body(&mut self, next_cur: &mut Cursor) -> Result<Token, Error> {
+        let pos = next_cur.pos;
+        let heredoc = self
+            .heredocs
+            .iter()
+            .filter(|h| h.body_pos <= pos && pos <= h.terminator_pos)
+            .min_by_key(|h| h.terminator_pos - h.body_pos)
+            .expect("[BUG] not in a heredoc");
+        let mut buf = String::new();
+        loop {
+            if next_cur.pos == heredoc.terminator_pos {
+                *next_cur = heredoc.resume_cur.clone();
+                break;
+            }
+            if next_cur.pos == heredoc.body_pos || self.src.as_bytes()[next_cur.pos - 1] == b'\n' {
+                // Remove the indentation
+                for _ in 0..heredoc.indent {
+                    if !matches!(next_cur.peek(self.src), Some(' ' | '\t')) {
+                        break;
+                    }
+                    next_cur.proceed(self.src);
+                }
+            }
+            match next_cur.peek(self.src) {
+                None => {
+                    return Err(self.lex_error("found unterminated heredoc"));
+                }
+                Some('\\') => {
+                    next_cur.proceed(self.src);
+                    let c2 = next_cur.peek(self.src);
+                    if c2 == Some('{') {
+                        next_cur.proceed(self.src);
+                        return Ok(Token::StrWithInterpolation {
+                            head: buf,
+                            inspect: true,
+                            heredoc: true,
+                        });
+                    } else {
+                        let c = self._read_escape_sequence(c2)?;
+                        next_cur.proceed(self.src);
+                        buf.push(c);
+                    }
+                }
+                Some('#') => {
+                    next_cur.proceed(self.src);
+                    if next_cur.peek(self.src) == Some('{') {
+                        next_cur.proceed(self.src);
+                        return Ok(Token::StrWithInterpolation {
+                            head: buf,
+                            inspect: false,
+                            heredoc: true,
+                        });
+                    } else {
+                        buf.push('#');
+                    }
+                }
+                Some(c) => {
+                    next_cur.proceed(self.src);
+                    buf.push(c);
+                }
+            }
+        }
+        Ok(Token::Str(buf))
+    }
+
     fn is_unary(&self, next_char: Option<char>) -> bool {
         match self.state {
             LexerState::ExprBegin => true,
@@ -607,15 +845,43 @@ impl<'a> Lexer<'a> {
             // is_unary does not make sense at these states. Just return false
             LexerState::MethodName => false,
             LexerState::StrLiteral => false,
+            LexerState::HeredocLiteral => false,
         }
     }
 
+    /// Read an integer or float literal. `_` can be used as a separator
+    /// between digits (eg. `1_000_000`) and integers can be written in
+    /// hexadecimal (`0xff`), binary (`0b1010`) or octal (`0o77`).
+    /// The token has the literal in decimal without the separators.
     fn read_number(&mut self, next_cur: &mut Cursor, cur: Option<&Cursor>) -> Result<Token, Error> {
+        let begin = match cur {
+            Some(c) => c.pos,
+            None => self.cur.pos,
+        };
+        if next_cur.peek(self.src) == Some('0') {
+            let radix = match next_cur.peek2(self.src) {
+                Some('x') => Some(16),
+                Some('b') => Some(2),
+                Some('o') => Some(8),
+                _ => None,
+            };
+            if let Some(radix) = radix {
+                next_cur.proceed(self.src);
+                next_cur.proceed(self.src);
+                return self.read_radix_number(next_cur, begin, radix);
+            }
+        }
         loop {
             match self.char_type(next_cur.peek(self.src)) {
                 CharType::Number => {
                     next_cur.proceed(self.src);
                 }
+                CharType::LowerWord
+                    if next_cur.peek(self.src) == Some('_')
+                        && self.char_type(next_cur.peek2(self.src)) == CharType::Number =>
+                {
+                    next_cur.proceed(self.src);
+                }
                 CharType::UpperWord | CharType::LowerWord => {
                     // TODO: this should be lexing error
                     return Err(self.lex_error("need space after a number"));
@@ -635,11 +901,36 @@ impl<'a> Lexer<'a> {
                 _ => break,
             }
         }
-        let begin = match cur {
-            Some(c) => c.pos,
-            None => self.cur.pos,
-        };
-        Ok(Token::Number(self.src[begin..next_cur.pos].to_string()))
+        Ok(Token::Number(
+            self.src[begin..next_cur.pos].replace('_', ""),
+        ))
+    }
+
+    /// Read the digits of `0x`, `0b` or `0o` literal
+    fn read_radix_number(
+        &mut self,
+        next_cur: &mut Cursor,
+        begin: usize,
+        radix: u32,
+    ) -> Result<Token, Error> {
+        let digits_begin = next_cur.pos;
+        while let CharType::UpperWord | CharType::LowerWord | CharType::Number =
+            self.char_type(next_cur.peek(self.src))
+        {
+            next_cur.proceed(self.src);
+        }
+        let literal = &self.src[begin..next_cur.pos];
+        let digits = self.src[digits_begin..next_cur.pos].replace('_', "");
+        if digits.is_empty() {
+            return Err(self.lex_error(&format!("no digits after `{}'", literal)));
+        }
+        if let Some(c) = digits.chars().find(|c| !c.is_digit(radix)) {
+            return Err(self.lex_error(&format!("invalid digit `{}' in `{}'", c, literal)));
+        }
+        match i64::from_str_radix(&digits, radix) {
+            Ok(n) => Ok(Token::Number(n.to_string())),
+            Err(_) => Err(self.lex_error(&format!("integer literal `{}' is too large", literal))),
+        }
     }
 
     /// Read a string literal
@@ -668,6 +959,7 @@ impl<'a> Lexer<'a> {
                         return Ok(Token::StrWithInterpolation {
                             head: buf,
                             inspect: true,
+                            heredoc: false,
                         });
                     } else {
                         let c = self._read_escape_sequence(next_cur.peek(self.src))?;
@@ -683,6 +975,7 @@ impl<'a> Lexer<'a> {
                         return Ok(Token::StrWithInterpolation {
                             head: buf,
                             inspect: false,
+                            heredoc: false,
                         });
                     } else {
                         buf.push('#');
@@ -697,6 +990,34 @@ impl<'a> Lexer<'a> {
         Ok(Token::Str(buf))
     }
 
+    /// Read a char literal (eg. `'a'`, `'\n'`)
+    fn read_char(&mut self, next_cur: &mut Cursor) -> Result<Token, Error> {
+        // Consume the beginning `'`
+        next_cur.proceed(self.src);
+        let c = match next_cur.peek(self.src) {
+            None | Some('\n') => return Err(self.lex_error("found unterminated char literal")),
+            Some('\'') => return Err(self.lex_error("empty char literal")),
+            Some('\\') => {
+                next_cur.proceed(self.src);
+                let c = match next_cur.peek(self.src) {
+                    Some('\'') => '\'',
+                    c2 => self._read_escape_sequence(c2)?,
+                };
+                next_cur.proceed(self.src);
+                c
+            }
+            Some(c) => {
+                next_cur.proceed(self.src);
+                c
+            }
+        };
+        if next_cur.peek(self.src) != Some('\'') {
+            return Err(self.lex_error("char literal must contain exactly one character"));
+        }
+        next_cur.proceed(self.src);
+        Ok(Token::CharLiteral(c))
+    }
+
     /// Return special char written with '\'
     fn _read_escape_sequence(&self, c: Option<char>) -> Result<char, Error> {
         match c {
@@ -723,6 +1044,7 @@ impl<'a> Lexer<'a> {
             '\n' | ';' => CharType::Separator,
             '#' => CharType::Comment,
             '"' => CharType::Str,
+            '\'' => CharType::Char,
             '0'..='9' => CharType::Number,
             '@' => CharType::IVar,
             '(' | ')' | '[' | ']' | '<' | '>' | '{' | '}' | '+' | '-' | '*' | '/' | '%' | '='
@@ -739,3 +1061,14 @@ impl<'a> Lexer<'a> {
         }
     }
 }
+
+/// Returns the common indentation of `lines` (blank lines are not
+/// considered)
+fn common_indent(lines: &[&str]) -> usize {
+    lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start_matches([' ', '\t']).len())
+        .min()
+        .unwrap_or(0)
+}
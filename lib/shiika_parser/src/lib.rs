@@ -17,6 +17,7 @@ mod definition_parser;
 mod error;
 mod expression_parser;
 pub mod lexer;
+pub mod loader;
 mod source_file;
 use crate::ast_builder::AstBuilder;
 pub use crate::error::Error;
@@ -31,14 +32,18 @@ pub struct Parser<'a> {
     ast: AstBuilder,
     /// For debug print
     pub lv: usize,
+    /// True while parsing the condition of `while` or the collection of `for`
+    /// (`do` there is not a block)
+    no_do_block: bool,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(file: &'a SourceFile) -> Parser<'a> {
         Parser {
             lexer: Lexer::new(&file.content),
-            ast: AstBuilder::new(&file.path),
+            ast: AstBuilder::new(&file.path, file.frozen_string_literal()),
             lv: 0,
+            no_do_block: false,
         }
     }
 
@@ -49,6 +54,7 @@ impl<'a> Parser<'a> {
             lexer,
             ast: AstBuilder::empty(),
             lv: 0,
+            no_do_block: false,
         };
         let (ast_sig, _) = parser.parse_method_signature()?;
         // Check if entire string is consumed
@@ -56,6 +62,27 @@ impl<'a> Parser<'a> {
         Ok(ast_sig)
     }
 
+    /// Parse a block (`do |x| ... end` or `{ |x| ... }`)
+    /// Returns an `AstExpressionBody::LambdaExpr`
+    pub fn parse_block(src: &str) -> Result<ast::AstExpression, Error> {
+        let mut parser = Parser {
+            lexer: Lexer::new(src),
+            ast: AstBuilder::empty(),
+            lv: 0,
+            no_do_block: false,
+        };
+        parser.skip_wsn()?;
+        let block = match parser.current_token() {
+            Token::KwDo => parser.parse_do_block()?,
+            Token::LBrace => parser.parse_brace_block()?,
+            token => return Err(parse_error!(parser, "expected a block but got {:?}", token)),
+        };
+        parser.skip_wsn()?;
+        // Check if entire string is consumed
+        parser.expect_eof()?;
+        Ok(block)
+    }
+
     pub fn parse_files(files: &[SourceFile]) -> Result<ast::Program, Error> {
         let mut program = ast::Program::default();
         for file in files {
@@ -99,6 +126,15 @@ impl<'a> Parser<'a> {
                 Token::KwEnum => {
                     items.push(ast::TopLevelItem::Def(self.parse_enum_definition()?));
                 }
+                Token::KwExtension => {
+                    items.push(ast::TopLevelItem::Def(self.parse_extension_definition()?));
+                }
+                Token::KwConst => {
+                    items.push(ast::TopLevelItem::Def(self.parse_const_definition()?));
+                }
+                Token::IVar(_) => {
+                    items.push(ast::TopLevelItem::Def(self.parse_annotated_definition()?));
+                }
                 Token::KwDef => {
                     return Err(parse_error!(
                         self,
@@ -131,3 +167,54 @@ impl<'a> Parser<'a> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shiika_ast::AstExpressionBody;
+
+    /// Returns the names of the params and the number of the exprs
+    fn parse_block(src: &str) -> (Vec<String>, usize) {
+        let block = Parser::parse_block(src).unwrap();
+        match block.body {
+            AstExpressionBody::LambdaExpr {
+                params,
+                exprs,
+                is_fn,
+            } => {
+                assert!(!is_fn);
+                (params.into_iter().map(|p| p.name).collect(), exprs.len())
+            }
+            _ => panic!("not a lambda: {:?}", block),
+        }
+    }
+
+    #[test]
+    fn test_parse_do_block() {
+        let (params, n) = parse_block("do |x, y|\n  p x\n  p y\nend");
+        assert_eq!(params, vec!["x", "y"]);
+        assert_eq!(n, 2);
+    }
+
+    #[test]
+    fn test_parse_brace_block() {
+        let (params, n) = parse_block("  { |x: Int| x + 1 }\n");
+        assert_eq!(params, vec!["x"]);
+        assert_eq!(n, 1);
+    }
+
+    #[test]
+    fn test_parse_block_without_params() {
+        let (params, n) = parse_block("{ 1 }");
+        assert!(params.is_empty());
+        assert_eq!(n, 1);
+    }
+
+    #[test]
+    fn test_parse_block_errors() {
+        // Not a block
+        assert!(Parser::parse_block("fn(x){ x }").is_err());
+        // Trailing garbage
+        assert!(Parser::parse_block("{ 1 } 2").is_err());
+    }
+}
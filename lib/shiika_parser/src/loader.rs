@@ -1,6 +1,6 @@
 // Resolve "require"
+use crate::SourceFile;
 use anyhow::{Context, Result};
-use shiika_parser::SourceFile;
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -33,8 +33,8 @@ fn load_file(
     Ok(())
 }
 
-/// Read require'd files into `files`
-fn resolve_requires(path: &Path, content: &str) -> Vec<PathBuf> {
+/// Returns the paths of the files require'd by `path`
+pub fn resolve_requires(path: &Path, content: &str) -> Vec<PathBuf> {
     let mut paths = vec![];
     for line in content.lines() {
         if line.trim_start().starts_with("require") {
@@ -14,4 +14,13 @@ impl SourceFile {
             content,
         }
     }
+
+    /// Returns true if the leading comments of the file contain
+    /// `# frozen_string_literal: true`
+    pub fn frozen_string_literal(&self) -> bool {
+        self.content
+            .lines()
+            .take_while(|line| line.starts_with('#'))
+            .any(|line| line.trim_end() == "# frozen_string_literal: true")
+    }
 }
@@ -1,4 +1,5 @@
 use crate::hir_maker::HirMaker;
+use shiika_ast::LocationSpan;
 use shiika_core::names::*;
 use skc_hir::*;
 
@@ -20,7 +21,9 @@ impl<'hir_maker> HirMaker<'hir_maker> {
                 }
             })
             .collect::<Vec<_>>();
-        for ivar in ivars.values() {
+        let mut ivars = ivars.values().collect::<Vec<_>>();
+        ivars.sort_by_key(|ivar| ivar.idx);
+        for ivar in ivars {
             let accessor_name = ivar.accessor_name();
             if !method_names.iter().any(|x| ***x == accessor_name) {
                 let getter = create_getter(clsname, ivar);
@@ -48,6 +51,10 @@ fn create_getter(clsname: &ClassFullname, ivar: &SkIVar) -> SkMethod {
         ret_ty: ivar.ty.clone(),
         params: vec![],
         typarams: vec![],
+        pure: false,
+        only_for: vec![],
+        self_type: false,
+        locs: LocationSpan::internal(),
     };
     SkMethod {
         signature: sig,
@@ -68,8 +75,16 @@ fn create_setter(clsname: &ClassFullname, ivar: &SkIVar) -> SkMethod {
         params: vec![MethodParam {
             name: ivar.accessor_name(),
             ty: ivar.ty.clone(),
+            is_variadic: false,
+            has_default: false,
+            default_expr: None,
+            locs: LocationSpan::internal(),
         }],
         typarams: vec![],
+        pure: false,
+        only_for: vec![],
+        self_type: false,
+        locs: LocationSpan::internal(),
     };
     SkMethod {
         signature: sig,
@@ -0,0 +1,321 @@
+use crate::convert_exprs::method_call;
+use crate::error;
+use crate::hir_maker::HirMaker;
+use anyhow::Result;
+use shiika_ast::{Annotation, LocationSpan};
+use shiika_core::{names::*, ty, ty::TermTy};
+use skc_hir::*;
+
+impl<'hir_maker> HirMaker<'hir_maker> {
+    /// Process an annotation on a class definition
+    pub(super) fn _process_class_annotation(
+        &mut self,
+        fullname: &ClassFullname,
+        annotation: &Annotation,
+        defs: &[shiika_ast::Definition],
+    ) -> Result<()> {
+        match annotation.name.as_str() {
+            "derive" => self._process_derive_annotation(fullname, annotation, defs),
+            _ => Err(error::program_error(&format!(
+                "unknown annotation `@{}' on class {}",
+                annotation.name, fullname
+            ))),
+        }
+    }
+
+    /// Check the annotations on a method definition
+    pub(super) fn _validate_method_annotations(
+        &self,
+        fullname: &MethodFullname,
+        annotations: &[Annotation],
+    ) -> Result<()> {
+        for annotation in annotations {
+            match annotation.name.as_str() {
+                // Not checked that the method returns an object of the
+                // receiver's class, so only for the core library
+                "self_type" if !self.is_corelib => {
+                    return Err(error::program_error(&format!(
+                        "`@self_type' is only available in the core library (on method {})",
+                        fullname
+                    )))
+                }
+                "deprecated" | "inline" | "pure" | "only_for" | "self_type" => (),
+                _ => {
+                    return Err(error::program_error(&format!(
+                        "unknown annotation `@{}' on method {}",
+                        annotation.name, fullname
+                    )))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Process `@derive(...)`
+    fn _process_derive_annotation(
+        &mut self,
+        fullname: &ClassFullname,
+        annotation: &Annotation,
+        defs: &[shiika_ast::Definition],
+    ) -> Result<()> {
+        for arg in &annotation.args {
+            match arg.as_str() {
+                "Printable" => {
+                    // Do not overwrite the user-defined one
+                    if !defines_instance_method(defs, "to_s") {
+                        self.generate_to_s(fullname)?;
+                    }
+                }
+                "Eq" | "Hash" if !self.class_dict.get_class(fullname).base.typarams.is_empty() => {
+                    return Err(error::program_error(&format!(
+                        "cannot derive `{}' for generic class {}",
+                        arg, fullname
+                    )))
+                }
+                "Eq" => {
+                    if !defines_instance_method(defs, "==") {
+                        self.generate_eq(fullname)?;
+                    }
+                }
+                "Hash" => {
+                    if !defines_instance_method(defs, "hash") {
+                        self.generate_hash(fullname)?;
+                    }
+                }
+                _ => {
+                    return Err(error::program_error(&format!(
+                        "cannot derive `{}' for class {}",
+                        arg, fullname
+                    )))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Define `#to_s` which returns a string like `Foo(x: 1, y: 2)`
+    fn generate_to_s(&mut self, fullname: &ClassFullname) -> Result<()> {
+        let self_ty = ty::raw(&fullname.0);
+        let ivars = self._sorted_ivars(fullname);
+        let mut expr = self._to_s_str_literal(&format!("{}(", fullname));
+        for (i, ivar) in ivars.iter().enumerate() {
+            let label = if i == 0 {
+                format!("{}: ", ivar.accessor_name())
+            } else {
+                format!(", {}: ", ivar.accessor_name())
+            };
+            let label_expr = self._to_s_str_literal(&label);
+            expr = self._to_s_concat(expr, label_expr)?;
+
+            // `self.x.to_s`
+            let ivar_expr = ivar_ref(ivar, &self_ty);
+            let found = self
+                .class_dict
+                .lookup_method(&ivar.ty, &method_firstname("to_s"), &[])?;
+            let str_expr = method_call::build_simple(self, found, ivar_expr)?;
+            expr = self._to_s_concat(expr, str_expr)?;
+        }
+        let close_expr = self._to_s_str_literal(")");
+        expr = self._to_s_concat(expr, close_expr)?;
+
+        let signature = MethodSignature {
+            fullname: method_fullname(fullname.to_type_fullname(), "to_s"),
+            ret_ty: ty::raw("String"),
+            params: vec![],
+            typarams: vec![],
+            pure: false,
+            only_for: vec![],
+            self_type: false,
+            locs: LocationSpan::internal(),
+        };
+        self._add_derived_method(fullname, signature, expr);
+        Ok(())
+    }
+
+    /// Define `#==` which returns true if all the ivars are equal (compared
+    /// with `==`)
+    fn generate_eq(&mut self, fullname: &ClassFullname) -> Result<()> {
+        let self_ty = ty::raw(&fullname.0);
+        let mut expr = None;
+        for ivar in self._sorted_ivars(fullname) {
+            // `@x == other.x`
+            let other = Hir::arg_ref(self_ty.clone(), 0, LocationSpan::internal());
+            let getter = self.class_dict.lookup_method(
+                &self_ty,
+                &method_firstname(&ivar.accessor_name()),
+                &[],
+            )?;
+            let other_ivar = method_call::build_simple(self, getter, other)?;
+            let found = self
+                .class_dict
+                .lookup_method(&ivar.ty, &method_firstname("=="), &[])?;
+            let cmp = method_call::build(
+                self,
+                found,
+                ivar_ref(&ivar, &self_ty),
+                vec![other_ivar],
+                None,
+            )?;
+            expr = Some(match expr {
+                Some(left) => Hir::logical_and(left, cmp, LocationSpan::internal()),
+                None => cmp,
+            });
+        }
+        let expr = expr.unwrap_or_else(|| Hir::boolean_literal(true, LocationSpan::internal()));
+        self._add_derived_method(fullname, eq_signature(fullname), expr);
+        Ok(())
+    }
+
+    /// Define `#hash` which combines the hash values of the ivars like
+    /// `((17 * 31) ^ @x.hash) * 31 ^ @y.hash`
+    fn generate_hash(&mut self, fullname: &ClassFullname) -> Result<()> {
+        let self_ty = ty::raw(&fullname.0);
+        let int_ty = ty::raw("Int");
+        let mut expr = Hir::decimal_literal(17, LocationSpan::internal());
+        for ivar in self._sorted_ivars(fullname) {
+            let mul = self
+                .class_dict
+                .lookup_method(&int_ty, &method_firstname("*"), &[])?;
+            let prime = Hir::decimal_literal(31, LocationSpan::internal());
+            let left = method_call::build(self, mul, expr, vec![prime], None)?;
+            let found = self
+                .class_dict
+                .lookup_method(&ivar.ty, &method_firstname("hash"), &[])?;
+            let hash = method_call::build_simple(self, found, ivar_ref(&ivar, &self_ty))?;
+            let xor = self
+                .class_dict
+                .lookup_method(&int_ty, &method_firstname("^"), &[])?;
+            expr = method_call::build(self, xor, left, vec![hash], None)?;
+        }
+        self._add_derived_method(fullname, hash_signature(fullname), expr);
+        Ok(())
+    }
+
+    /// Returns the ivars of the class in the order of definition
+    fn _sorted_ivars(&self, fullname: &ClassFullname) -> Vec<SkIVar> {
+        let mut ivars = self
+            .class_dict
+            .get_class(fullname)
+            .ivars
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        ivars.sort_by_key(|ivar| ivar.idx);
+        ivars
+    }
+
+    /// Register a method made by `@derive`
+    fn _add_derived_method(
+        &mut self,
+        fullname: &ClassFullname,
+        signature: MethodSignature,
+        expr: HirExpression,
+    ) {
+        let method = SkMethod {
+            signature: signature.clone(),
+            body: SkMethodBody::Normal {
+                exprs: HirExpressions::new(vec![expr]),
+            },
+            lvars: vec![],
+        };
+        self.method_dict
+            .add_method(fullname.to_type_fullname(), method);
+        self.class_dict.add_method(fullname, signature);
+    }
+
+    fn _to_s_str_literal(&mut self, s: &str) -> HirExpression {
+        let idx = self.register_string_literal(s);
+        Hir::string_literal(idx, LocationSpan::internal())
+    }
+
+    /// `left + right`
+    fn _to_s_concat(&self, left: HirExpression, right: HirExpression) -> Result<HirExpression> {
+        let found =
+            self.class_dict
+                .lookup_method(&ty::raw("String"), &method_firstname("+"), &[])?;
+        method_call::build(self, found, left, vec![right], None)
+    }
+}
+
+/// Returns the signatures of the methods generated by `@derive(Eq)` and
+/// `@derive(Hash)`. They are indexed before the bodies are made so that the
+/// class can include `Hashable`
+pub(crate) fn derived_method_sigs(
+    fullname: &ClassFullname,
+    annotations: &[Annotation],
+    defs: &[shiika_ast::Definition],
+) -> Vec<MethodSignature> {
+    let mut sigs = vec![];
+    for annotation in annotations.iter().filter(|a| a.name == "derive") {
+        if annotation.has_arg("Eq") && !defines_instance_method(defs, "==") {
+            sigs.push(eq_signature(fullname));
+        }
+        if annotation.has_arg("Hash") && !defines_instance_method(defs, "hash") {
+            sigs.push(hash_signature(fullname));
+        }
+    }
+    sigs
+}
+
+/// Returns the type arguments given to `@only_for(...)`
+pub(crate) fn only_for_tyargs(annotations: &[Annotation]) -> Vec<TermTy> {
+    annotations
+        .iter()
+        .filter(|a| a.name == "only_for")
+        .flat_map(|a| a.args.iter().map(|name| ty::raw(name)))
+        .collect()
+}
+
+/// `def ==(other: Foo) -> Bool`
+fn eq_signature(fullname: &ClassFullname) -> MethodSignature {
+    MethodSignature {
+        fullname: method_fullname(fullname.to_type_fullname(), "=="),
+        ret_ty: ty::raw("Bool"),
+        params: vec![MethodParam {
+            name: "other".to_string(),
+            ty: ty::raw(&fullname.0),
+            is_variadic: false,
+            has_default: false,
+            default_expr: None,
+            locs: LocationSpan::internal(),
+        }],
+        typarams: vec![],
+        pure: false,
+        only_for: vec![],
+        self_type: false,
+        locs: LocationSpan::internal(),
+    }
+}
+
+/// `def hash -> Int`
+fn hash_signature(fullname: &ClassFullname) -> MethodSignature {
+    MethodSignature {
+        fullname: method_fullname(fullname.to_type_fullname(), "hash"),
+        ret_ty: ty::raw("Int"),
+        params: vec![],
+        typarams: vec![],
+        pure: false,
+        only_for: vec![],
+        self_type: false,
+        locs: LocationSpan::internal(),
+    }
+}
+
+/// `@x` in a method of `self_ty`
+fn ivar_ref(ivar: &SkIVar, self_ty: &TermTy) -> HirExpression {
+    Hir::ivar_ref(
+        ivar.ty.clone(),
+        ivar.name.clone(),
+        ivar.idx,
+        self_ty.clone(),
+        LocationSpan::internal(),
+    )
+}
+
+/// Returns true if `defs` contains an instance method named `name`
+fn defines_instance_method(defs: &[shiika_ast::Definition], name: &str) -> bool {
+    defs.iter().any(|def| {
+        matches!(def, shiika_ast::Definition::InstanceMethodDefinition { sig, .. }
+            if sig.name.0 == name)
+    })
+}
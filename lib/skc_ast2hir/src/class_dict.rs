@@ -23,6 +23,8 @@ pub struct ClassDict<'hir_maker> {
     pub sk_types: SkTypes,
     /// Imported classes
     imported_classes: &'hir_maker SkTypes,
+    /// Methods added to imported classes by `extension`
+    extension_methods: HashMap<TypeFullname, MethodSignatures>,
     rust_methods: RustMethods,
 }
 
@@ -35,6 +37,7 @@ pub fn create<'hir_maker>(
         type_index,
         sk_types: Default::default(),
         imported_classes,
+        extension_methods: Default::default(),
         rust_methods: Default::default(),
     };
     dict.index_program(&defs)?;
@@ -52,6 +55,7 @@ pub fn create_for_corelib<'hir_maker>(
         type_index,
         sk_types,
         imported_classes,
+        extension_methods: Default::default(),
         rust_methods: index_rust_method_sigs(rust_method_sigs),
     };
     dict.index_program(&defs)?;
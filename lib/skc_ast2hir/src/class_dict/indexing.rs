@@ -1,3 +1,4 @@
+use crate::annotations::{derived_method_sigs, only_for_tyargs};
 use crate::class_dict::build_wtable::build_wtable;
 use crate::class_dict::*;
 use crate::convert_exprs::params;
@@ -6,7 +7,7 @@ use crate::parse_typarams;
 use anyhow::Result;
 use shiika_ast::{self, LocationSpan, UnresolvedTypeName};
 use shiika_core::{names::*, ty, ty::*};
-use skc_error::{self, Label};
+use skc_error::Diagnostic;
 use skc_hir::signature::*;
 use skc_hir::*;
 use std::collections::HashMap;
@@ -31,6 +32,7 @@ impl<'hir_maker> ClassDict<'hir_maker> {
 
     pub fn index_program(&mut self, toplevel_defs: &[&shiika_ast::Definition]) -> Result<()> {
         let namespace = Namespace::root();
+        let mut extensions = vec![];
         for def in toplevel_defs {
             match def {
                 shiika_ast::Definition::ClassDefinition {
@@ -38,7 +40,11 @@ impl<'hir_maker> ClassDict<'hir_maker> {
                     typarams,
                     supers,
                     defs,
-                } => self.index_class(&namespace, name, parse_typarams(typarams), supers, defs)?,
+                    annotations,
+                } => {
+                    let typarams = self.resolve_class_typarams(&namespace, typarams)?;
+                    self.index_class(&namespace, name, typarams, supers, defs, annotations)?
+                }
                 shiika_ast::Definition::ModuleDefinition {
                     name,
                     typarams,
@@ -50,6 +56,12 @@ impl<'hir_maker> ClassDict<'hir_maker> {
                     cases,
                     defs,
                 } => self.index_enum(&namespace, name, parse_typarams(typarams), cases, defs)?,
+                // Indexed after all the classes are indexed
+                shiika_ast::Definition::ExtensionDefinition {
+                    name,
+                    typarams,
+                    defs,
+                } => extensions.push((name, typarams, defs)),
                 shiika_ast::Definition::ConstDefinition { .. } => (),
                 _ => {
                     return Err(error::syntax_error(&format!(
@@ -59,6 +71,98 @@ impl<'hir_maker> ClassDict<'hir_maker> {
                 }
             }
         }
+        for (name, typarams, defs) in extensions {
+            self.index_extension(name, typarams, defs)?;
+        }
+        Ok(())
+    }
+
+    /// Add the methods in `extension Foo ... end` to the class `Foo`
+    fn index_extension(
+        &mut self,
+        name: &UnresolvedTypeName,
+        ast_typarams: &[shiika_ast::AstTyParam],
+        defs: &[shiika_ast::Definition],
+    ) -> Result<()> {
+        let namespace = Namespace::root();
+        let (resolved, class_typarams) =
+            self._resolve_simple_typename(&namespace, &name.names, &name.locs)?;
+        let fullname = class_fullname(resolved.join("::"));
+        if class_typarams.len() != ast_typarams.len() {
+            let msg = format!(
+                "extension of {} must have {} type parameter(s) but got {}",
+                fullname,
+                class_typarams.len(),
+                ast_typarams.len()
+            );
+            let names = class_typarams
+                .iter()
+                .map(|t| t.name.clone())
+                .collect::<Vec<_>>();
+            let report = Diagnostic::new(msg, &name.locs, "wrong number of type parameters")
+                .note(format!("write `{}<{}>'", fullname, names.join(", ")))
+                .render();
+            return Err(error::program_error(report));
+        }
+        if self.lookup_class(&fullname).is_none() {
+            let msg = format!(
+                "extension of {} is not allowed (only classes can be extended)",
+                fullname
+            );
+            let report = Diagnostic::new(msg, &name.locs, "not a class").render();
+            return Err(error::program_error(report));
+        }
+        for def in defs {
+            match def {
+                shiika_ast::Definition::InstanceMethodDefinition { .. }
+                | shiika_ast::Definition::ClassMethodDefinition { .. } => (),
+                shiika_ast::Definition::InitializerDefinition(_)
+                | shiika_ast::Definition::ClassInitializerDefinition(_) => {
+                    return Err(error::program_error(&format!(
+                        "extension of {} cannot define `initialize'",
+                        fullname
+                    )))
+                }
+                _ => {
+                    return Err(error::program_error(&format!(
+                        "extension of {} can only have method definitions",
+                        fullname
+                    )))
+                }
+            }
+        }
+
+        let typarams = parse_typarams(ast_typarams);
+        let inner_namespace = Namespace::new(resolved);
+        let (instance_methods, class_methods, _) =
+            self.index_defs_in_class(&inner_namespace, &fullname, &typarams, defs)?;
+        let instance_ty = ty::return_type_of_new(&fullname.to_type_fullname(), &typarams);
+        self.add_extension_methods(&instance_ty, instance_methods)?;
+        self.add_extension_methods(&ty::meta(&fullname.0), class_methods)?;
+        Ok(())
+    }
+
+    /// Add methods defined in an extension to the class `ty`
+    fn add_extension_methods(&mut self, ty: &TermTy, sigs: MethodSignatures) -> Result<()> {
+        let type_name = ty.erasure().to_type_fullname();
+        for (sig, _) in sigs.to_ordered() {
+            let name = &sig.fullname.first_name;
+            if let Ok(found) = self.lookup_method(ty, name, Default::default()) {
+                return Err(error::program_error(&format!(
+                    "extension cannot redefine the existing method {}",
+                    found.sig.fullname
+                )));
+            }
+        }
+        match self.sk_types.0.get_mut(&type_name) {
+            Some(sk_type) => sk_type.base_mut().method_sigs.append(sigs),
+            // Methods of an imported class cannot be added to its vtable
+            None => self
+                .extension_methods
+                .entry(type_name)
+                .or_default()
+                .append(sigs),
+        }
         Ok(())
     }
 
@@ -69,6 +173,7 @@ impl<'hir_maker> ClassDict<'hir_maker> {
         typarams: Vec<ty::TyParam>,
         supers: &[UnresolvedTypeName],
         defs: &[shiika_ast::Definition],
+        annotations: &[shiika_ast::Annotation],
     ) -> Result<()> {
         let inner_namespace = namespace.add(firstname.to_string());
         let fullname = namespace.class_fullname(firstname);
@@ -84,8 +189,16 @@ impl<'hir_maker> ClassDict<'hir_maker> {
             ))
         };
 
-        let (instance_methods, class_methods) =
+        let (mut instance_methods, class_methods, abstract_methods) =
             self.index_defs_in_class(&inner_namespace, &fullname, &typarams, defs)?;
+        // Methods in skc_rustlib may be required by the modules (eg. `Int#==`)
+        self.transfer_rust_method_sigs(&fullname.to_type_fullname(), &mut instance_methods);
+        // `@derive(Eq)`, etc. (the bodies are made in HirMaker)
+        if typarams.is_empty() {
+            for sig in derived_method_sigs(&fullname, annotations, defs) {
+                instance_methods.insert(sig);
+            }
+        }
 
         let wtable = build_wtable(self, &instance_methods, &includes)?;
         match self.sk_types.0.get_mut(&fullname.to_type_fullname()) {
@@ -95,13 +208,13 @@ impl<'hir_maker> ClassDict<'hir_maker> {
                 if let SkType::Class(sk_class) = sk_type {
                     sk_class.wtable = wtable;
                     sk_class.includes = includes;
+                    sk_class.abstract_methods = abstract_methods;
                 }
+                // Inject the bounds of the typarams
+                sk_type.base_mut().typarams = typarams.clone();
                 // Inject instance methods
                 let method_sigs = &mut sk_type.base_mut().method_sigs;
                 method_sigs.append(instance_methods);
-                if let Some(sigs) = self.rust_methods.remove(&fullname.to_type_fullname()) {
-                    method_sigs.append_vec(sigs);
-                }
                 // Inject class methods
                 let metaclass = self
                     .sk_types
@@ -144,6 +257,7 @@ impl<'hir_maker> ClassDict<'hir_maker> {
                     Some(false),
                     false,
                 )?;
+                self.get_class_mut(&fullname).abstract_methods = abstract_methods;
             }
         }
         Ok(())
@@ -158,38 +272,46 @@ impl<'hir_maker> ClassDict<'hir_maker> {
     ) -> Result<(Superclass, Vec<Superclass>)> {
         let mut modules = vec![];
         let mut superclass = None;
+        // The location of the first module and the superclass
+        let mut module_locs = None;
+        let mut superclass_locs = None;
         for name in supers {
             let ty = self.resolve_typename(namespace, class_typarams, Default::default(), name)?;
             match self.find_type(&ty.erasure().to_type_fullname()) {
                 Some(SkType::Class(c)) => {
-                    if !modules.is_empty() {
-                        return Err(error::program_error(&format!(
-                            "superclass {} must be the first",
-                            ty
-                        )));
+                    if let Some(locs) = &module_locs {
+                        let msg = format!("superclass {} must be the first", ty);
+                        let report = Diagnostic::new(msg, &name.locs, "superclass")
+                            .label(locs, "module")
+                            .note(format!("move {} before the modules", ty))
+                            .render();
+                        return Err(error::program_error(report));
                     }
-                    if superclass.is_some() {
-                        return Err(error::program_error(&format!(
-                            "only one superclass is allowed but got {}",
-                            ty
-                        )));
+                    if let Some(locs) = &superclass_locs {
+                        let msg = format!("only one superclass is allowed but got {}", ty);
+                        let report = Diagnostic::new(msg, &name.locs, "second superclass")
+                            .label(locs, "first superclass")
+                            .render();
+                        return Err(error::program_error(report));
                     }
                     if c.is_final.unwrap() {
-                        return Err(error::program_error(&format!(
-                            "inheriting {} is not allowed",
-                            ty
-                        )));
+                        let msg = format!("inheriting {} is not allowed", ty);
+                        let report =
+                            Diagnostic::new(msg, &name.locs, format!("{} is final", ty)).render();
+                        return Err(error::program_error(report));
                     }
-                    superclass = Some(Superclass::from_ty(ty))
+                    superclass = Some(Superclass::from_ty(ty));
+                    superclass_locs = Some(name.locs.clone());
                 }
                 Some(SkType::Module(_)) => {
                     modules.push(Superclass::from_ty(ty));
+                    module_locs.get_or_insert_with(|| name.locs.clone());
                 }
                 None => {
-                    return Err(error::program_error(&format!(
-                        "unknown class or module {}",
-                        ty
-                    )));
+                    let msg = format!("unknown class or module {}", ty);
+                    let report =
+                        Diagnostic::new(msg, &name.locs, "unknown class or module").render();
+                    return Err(error::program_error(report));
                 }
             }
         }
@@ -255,7 +377,7 @@ impl<'hir_maker> ClassDict<'hir_maker> {
     ) -> Result<()> {
         let fullname = namespace.class_fullname(firstname);
         let inner_namespace = namespace.add(firstname.to_string());
-        let (instance_methods, class_methods) =
+        let (instance_methods, class_methods, _) =
             self.index_defs_in_class(&inner_namespace, &fullname, &typarams, defs)?;
         self.add_new_class(
             &fullname,
@@ -338,10 +460,8 @@ impl<'hir_maker> ClassDict<'hir_maker> {
         fullname: &ClassFullname,
         typarams: &[ty::TyParam],
         defs: &[shiika_ast::Definition],
-    ) -> Result<(MethodSignatures, MethodSignatures)> {
-        let (instance_methods, class_methods, _) =
-            self._index_inner_defs(namespace, fullname, typarams, defs, false)?;
-        Ok((instance_methods, class_methods))
+    ) -> Result<(MethodSignatures, MethodSignatures, Vec<MethodSignature>)> {
+        self._index_inner_defs(namespace, fullname, typarams, defs, false)
     }
 
     fn index_defs_in_module(
@@ -354,6 +474,8 @@ impl<'hir_maker> ClassDict<'hir_maker> {
         self._index_inner_defs(namespace, fullname, typarams, defs, true)
     }
 
+    /// Returns instance methods, class methods and method requirements (for
+    /// modules) or abstract methods (for classes)
     fn _index_inner_defs(
         &mut self,
         namespace: &Namespace,
@@ -410,8 +532,10 @@ impl<'hir_maker> ClassDict<'hir_maker> {
                     typarams,
                     supers,
                     defs,
+                    annotations,
                 } => {
-                    self.index_class(namespace, name, parse_typarams(typarams), supers, defs)?;
+                    let typarams = self.resolve_class_typarams(namespace, typarams)?;
+                    self.index_class(namespace, name, typarams, supers, defs, annotations)?;
                 }
                 shiika_ast::Definition::ModuleDefinition {
                     name,
@@ -431,6 +555,17 @@ impl<'hir_maker> ClassDict<'hir_maker> {
                         )));
                     }
                 }
+                shiika_ast::Definition::AbstractMethodDefinition { sig } => {
+                    if is_module {
+                        return Err(error::syntax_error(&format!(
+                            "modules cannot have abstract method (use `requirement' instead): {}#{}",
+                            fullname, sig.name
+                        )));
+                    }
+                    let hir_sig = self.create_signature(namespace, fullname, sig, typarams)?;
+                    instance_methods.insert(hir_sig.clone());
+                    requirements.push(hir_sig);
+                }
                 shiika_ast::Definition::EnumDefinition {
                     name,
                     typarams,
@@ -439,6 +574,12 @@ impl<'hir_maker> ClassDict<'hir_maker> {
                 } => {
                     self.index_enum(namespace, name, parse_typarams(typarams), cases, defs)?;
                 }
+                shiika_ast::Definition::ExtensionDefinition { name, .. } => {
+                    return Err(error::syntax_error(&format!(
+                        "extension of {} must be toplevel",
+                        name.names.join("::")
+                    )));
+                }
             }
         }
         Ok((instance_methods, class_methods, requirements))
@@ -461,6 +602,10 @@ impl<'hir_maker> ClassDict<'hir_maker> {
                 ret_ty: hir_param.ty.clone(),
                 params: Default::default(),
                 typarams: Default::default(),
+                pure: false,
+                only_for: vec![],
+                self_type: false,
+                locs: param.locs.clone(),
             };
             instance_methods.insert(sig);
         }
@@ -503,6 +648,7 @@ impl<'hir_maker> ClassDict<'hir_maker> {
             is_final,
             const_is_obj,
             wtable,
+            abstract_methods: Default::default(),
         });
 
         // Create metaclass (which is a subclass of `Class`)
@@ -526,6 +672,7 @@ impl<'hir_maker> ClassDict<'hir_maker> {
             is_final: None,
             const_is_obj: false,
             wtable: Default::default(),
+            abstract_methods: Default::default(),
         });
         Ok(())
     }
@@ -569,6 +716,7 @@ impl<'hir_maker> ClassDict<'hir_maker> {
             is_final: None,
             const_is_obj: false,
             wtable: Default::default(),
+            abstract_methods: Default::default(),
         });
     }
 
@@ -580,7 +728,16 @@ impl<'hir_maker> ClassDict<'hir_maker> {
         sig: &shiika_ast::AstMethodSignature,
         class_typarams: &[ty::TyParam],
     ) -> Result<MethodSignature> {
-        let method_typarams = parse_typarams(&sig.typarams);
+        let mut method_typarams = parse_typarams(&sig.typarams);
+        // The bounds may refer to the typarams (eg. `T: Comparable<T>`)
+        let unbounded_typarams = method_typarams.clone();
+        self._resolve_typaram_bounds(
+            namespace,
+            class_typarams,
+            &unbounded_typarams,
+            &mut method_typarams,
+            &sig.typarams,
+        )?;
         let fullname = method_fullname(class_fullname.to_type_fullname_(), &sig.name.0);
         let ret_ty = if let Some(typ) = &sig.ret_typ {
             self.resolve_typename(namespace, class_typarams, &method_typarams, typ)?
@@ -598,9 +755,61 @@ impl<'hir_maker> ClassDict<'hir_maker> {
                 &method_typarams,
             )?,
             typarams: method_typarams,
+            pure: sig.annotations.iter().any(|a| a.name == "pure"),
+            only_for: only_for_tyargs(&sig.annotations),
+            self_type: sig.annotations.iter().any(|a| a.name == "self_type"),
+            locs: sig.locs.clone(),
         })
     }
 
+    /// Convert the typarams of a class (eg. `class HashMap<K: Hashable<K>, V>`)
+    fn resolve_class_typarams(
+        &self,
+        namespace: &Namespace,
+        ast_typarams: &[shiika_ast::AstTyParam],
+    ) -> Result<Vec<ty::TyParam>> {
+        let mut typarams = parse_typarams(ast_typarams);
+        let unbounded_typarams = typarams.clone();
+        self._resolve_typaram_bounds(
+            namespace,
+            &unbounded_typarams,
+            Default::default(),
+            &mut typarams,
+            ast_typarams,
+        )?;
+        Ok(typarams)
+    }
+
+    /// Set the upper bounds written in `ast_typarams` to `typarams`
+    fn _resolve_typaram_bounds(
+        &self,
+        namespace: &Namespace,
+        class_typarams: &[ty::TyParam],
+        method_typarams: &[ty::TyParam],
+        typarams: &mut [ty::TyParam],
+        ast_typarams: &[shiika_ast::AstTyParam],
+    ) -> Result<()> {
+        for (typaram, ast_typaram) in typarams.iter_mut().zip(ast_typarams) {
+            if let Some(typ) = &ast_typaram.upper_bound {
+                let bound =
+                    self.resolve_typename(namespace, class_typarams, method_typarams, typ)?;
+                match bound.body {
+                    TyBody::TyRaw(lit_ty) if !lit_ty.is_meta => typaram.upper_bound = lit_ty,
+                    _ => {
+                        let msg = format!(
+                            "the bound of `{}' must be a class or a module",
+                            typaram.name
+                        );
+                        let report =
+                            Diagnostic::new(msg, &typ.locs, format!("this is {}", bound)).render();
+                        return Err(error::type_error(report));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Resolve the given type name to fullname
     pub fn resolve_typename(
         &self,
@@ -613,9 +822,13 @@ impl<'hir_maker> ClassDict<'hir_maker> {
         if name.args.is_empty() && name.names.len() == 1 {
             let s = name.names.first().unwrap();
             if let Some(idx) = class_typarams.iter().position(|t| *s == t.name) {
-                return Ok(ty::typaram_ref(s, TyParamKind::Class, idx).into_term_ty());
+                return Ok(class_typarams[idx]
+                    .to_ref(TyParamKind::Class, idx)
+                    .into_term_ty());
             } else if let Some(idx) = method_typarams.iter().position(|t| *s == t.name) {
-                return Ok(ty::typaram_ref(s, TyParamKind::Method, idx).into_term_ty());
+                return Ok(method_typarams[idx]
+                    .to_ref(TyParamKind::Method, idx)
+                    .into_term_ty());
             }
         }
         // Otherwise:
@@ -626,10 +839,15 @@ impl<'hir_maker> ClassDict<'hir_maker> {
         let (resolved_base, base_typarams) =
             self._resolve_simple_typename(namespace, &name.names, &name.locs)?;
         if name.args.len() != base_typarams.len() {
-            return Err(error::type_error(&format!(
-                "wrong number of type arguments: {:?}",
-                name
-            )));
+            let msg = format!("wrong number of type arguments: {:?}", name);
+            let label = format!(
+                "{} takes {} type argument(s) but got {}",
+                name.names.join("::"),
+                base_typarams.len(),
+                name.args.len()
+            );
+            let report = Diagnostic::new(msg, &name.locs, label).render();
+            return Err(error::type_error(report));
         }
         Ok(ty::nonmeta(&resolved_base, tyargs))
     }
@@ -655,9 +873,7 @@ impl<'hir_maker> ClassDict<'hir_maker> {
         }
 
         let msg = format!("unknown type {} in {:?}", names.join("::"), namespace);
-        let report = skc_error::build_report(msg, locs, |r, locs_span| {
-            r.with_label(Label::new(locs_span).with_message("unknown type"))
-        });
+        let report = Diagnostic::new(msg, locs, "unknown type").render();
         Err(error::name_error(&report))
     }
 
@@ -707,6 +923,10 @@ fn enum_case_new_sig(
         .map(|ivar| MethodParam {
             name: ivar.name.to_string(),
             ty: ivar.ty.clone(),
+            is_variadic: false,
+            has_default: false,
+            default_expr: None,
+            locs: LocationSpan::internal(),
         })
         .collect::<Vec<_>>();
     let ret_ty = if ivar_list.is_empty() {
@@ -732,6 +952,10 @@ fn enum_case_getters(case_fullname: &ClassFullname, ivars: &[SkIVar]) -> MethodS
         ret_ty: ivar.ty.clone(),
         params: Default::default(),
         typarams: Default::default(),
+        pure: false,
+        only_for: vec![],
+        self_type: false,
+        locs: LocationSpan::internal(),
     });
     MethodSignatures::from_iterator(iter)
 }
@@ -4,8 +4,18 @@ use crate::type_system;
 use anyhow::Result;
 use shiika_core::{names::*, ty, ty::*};
 use skc_hir::*;
+use std::collections::HashSet;
 
 impl<'hir_maker> ClassDict<'hir_maker> {
+    /// Returns the names of the methods added to imported classes by
+    /// `extension`
+    pub fn extension_method_names(&self) -> HashSet<MethodFullname> {
+        self.extension_methods
+            .values()
+            .flat_map(|sigs| sigs.unordered_iter().map(|(sig, _)| sig.fullname.clone()))
+            .collect()
+    }
+
     /// Find a method in a class or module. Does not lookup into superclass.
     pub fn find_method(
         &self,
@@ -22,6 +32,11 @@ impl<'hir_maker> ClassDict<'hir_maker> {
                 .base
                 .method_sigs
                 .get(method_name)
+                .or_else(|| {
+                    self.extension_methods
+                        .get(&sk_class.base.fullname())
+                        .and_then(|sigs| sigs.get(method_name))
+                })
                 .map(|(sig, _)| FoundMethod::class(sk_type, sig.clone())),
             SkType::Module(sk_module) => sk_module
                 .base
@@ -65,6 +80,12 @@ impl<'hir_maker> ClassDict<'hir_maker> {
             TyBody::TyRaw(LitTy { type_args, .. }) => {
                 (current_type.erasure(), type_args.as_slice())
             }
+            // eg. Methods of `Printable` for `T: Printable`
+            TyBody::TyPara(TyParamRef {
+                upper_bound,
+                as_class: false,
+                ..
+            }) => (upper_bound.erasure(), upper_bound.type_args.as_slice()),
             TyBody::TyPara(_) => (Erasure::nonmeta("Object"), Default::default()),
         };
         let sk_type = self.get_type(&erasure.to_type_fullname());
@@ -86,9 +107,11 @@ impl<'hir_maker> ClassDict<'hir_maker> {
                 }
                 // Look up in superclass
                 if let Some(superclass) = &sk_class.superclass {
+                    // eg. `Maybe<V>` is `Maybe<Int>` for `Some<Int>`
+                    let super_ty = superclass.ty().substitute_class(class_tyargs);
                     return self.lookup_method_(
                         receiver_type,
-                        superclass.ty(),
+                        &super_ty,
                         method_name,
                         method_tyargs,
                     );
@@ -110,6 +133,53 @@ impl<'hir_maker> ClassDict<'hir_maker> {
         )))
     }
 
+    /// Returns the names of the methods of `receiver_type` including the
+    /// inherited ones (unsorted, may contain duplicates)
+    pub fn method_names(&self, receiver_type: &TermTy) -> Vec<MethodFirstname> {
+        let mut names = vec![];
+        if !matches!(receiver_type.body, TyBody::TyRaw(_)) {
+            return names;
+        }
+        let mut current = Some(receiver_type.erasure().to_type_fullname());
+        while let Some(fullname) = current {
+            let sk_type = match self.find_type(&fullname) {
+                Some(t) => t,
+                None => break,
+            };
+            names.extend(
+                sk_type
+                    .base()
+                    .method_sigs_sorted()
+                    .map(|(name, _)| name.clone()),
+            );
+            current = match sk_type {
+                SkType::Class(c) => c
+                    .superclass
+                    .as_ref()
+                    .map(|sup| sup.base_fullname().to_type_fullname()),
+                SkType::Module(_) => None,
+            };
+        }
+        names
+    }
+
+    /// Returns the name of a method of `receiver_type` which is similar to
+    /// `method_name` (for the hint of a typo)
+    pub fn similar_method_name(
+        &self,
+        receiver_type: &TermTy,
+        method_name: &MethodFirstname,
+    ) -> Option<MethodFirstname> {
+        // Allow one typo per three chars
+        let max_distance = (method_name.0.len() / 3).max(1);
+        self.method_names(receiver_type)
+            .into_iter()
+            .map(|name| (edit_distance(&name.0, &method_name.0), name))
+            .filter(|(d, _)| *d <= max_distance)
+            .min_by(|(d1, n1), (d2, n2)| d1.cmp(d2).then_with(|| n1.0.cmp(&n2.0)))
+            .map(|(_, name)| name)
+    }
+
     /// Return the class/module of the specified name, if any
     pub fn find_type(&self, fullname: &TypeFullname) -> Option<&SkType> {
         self.sk_types
@@ -198,7 +268,7 @@ impl<'hir_maker> ClassDict<'hir_maker> {
                 .get_class(&ty.erasure().to_class_fullname())
                 .superclass
                 .as_ref()
-                .map(|scls| scls.ty().substitute(ty.tyargs(), &[])),
+                .map(|scls| scls.ty().substitute_class(ty.tyargs())),
         }
     }
 
@@ -223,6 +293,33 @@ impl<'hir_maker> ClassDict<'hir_maker> {
         class.ivars.get(ivar_name)
     }
 
+    /// Returns the param which gives the default value of the `idx`th param
+    /// of the method `type_fullname#method_name`, if any.
+    /// Unless the method has its own default value, it is inherited from the
+    /// overridden method (except for `initialize` and `new`.)
+    pub fn find_param_default(
+        &self,
+        type_fullname: &TypeFullname,
+        method_name: &MethodFirstname,
+        idx: usize,
+    ) -> Option<MethodParam> {
+        let inherits = method_name.0 != "initialize" && method_name.0 != "new";
+        let mut current = type_fullname.clone();
+        loop {
+            if let Some(sig) = self.find_method_sig(&current, method_name) {
+                let param = sig.params.get(idx)?;
+                if param.has_default {
+                    return Some(param.clone());
+                }
+                if !inherits {
+                    return None;
+                }
+            }
+            let superclass = self.find_type(&current)?.class()?.superclass.as_ref()?;
+            current = superclass.base_fullname().to_type_fullname();
+        }
+    }
+
     /// Returns instance variables of the superclass of `classname`
     pub fn superclass_ivars(&self, classname: &ClassFullname) -> Option<SkIVars> {
         self.get_class(classname).superclass.as_ref().map(|scls| {
@@ -235,6 +332,77 @@ impl<'hir_maker> ClassDict<'hir_maker> {
                 .collect()
         })
     }
+
+    /// Returns the abstract methods of `classname` and its ancestors which
+    /// are not implemented (i.e. the class cannot be instantiated if any.)
+    pub fn unimplemented_abstract_methods(&self, classname: &ClassFullname) -> Vec<MethodFullname> {
+        let mut implemented = HashSet::new();
+        let mut unimplemented = vec![];
+        let mut cls = self.lookup_class(classname);
+        while let Some(c) = cls {
+            let abstract_names = c
+                .abstract_methods
+                .iter()
+                .map(|sig| &sig.fullname.first_name)
+                .collect::<HashSet<_>>();
+            for sig in &c.abstract_methods {
+                if !implemented.contains(&sig.fullname.first_name) {
+                    unimplemented.push(sig.fullname.clone());
+                }
+            }
+            for (name, _) in c.base.method_sigs.sorted_iter() {
+                if !abstract_names.contains(name) {
+                    implemented.insert(name.clone());
+                }
+            }
+            // Abstract methods of the ancestors are not reported twice
+            implemented.extend(abstract_names.into_iter().cloned());
+            cls = c
+                .superclass
+                .as_ref()
+                .and_then(|scls| self.lookup_class(&scls.base_fullname()));
+        }
+        unimplemented
+    }
+
+    /// Returns true if `ty` is the base class of an enum (i.e. a sealed
+    /// class hierarchy.)
+    pub fn is_enum_class(&self, ty: &TermTy) -> bool {
+        if ty.is_metaclass() || ty.is_typaram_ref() {
+            return false;
+        }
+        !self.enum_cases_of(ty).is_empty()
+    }
+
+    /// Returns the names of the cases of the enum `ty`, sorted by name.
+    /// Returns an empty Vec if `ty` is not an enum.
+    pub fn enum_cases_of(&self, ty: &TermTy) -> Vec<ClassFullname> {
+        let base_name = ty.erasure().to_class_fullname();
+        let prefix = format!("{}::", base_name);
+        let mut cases = self
+            .sk_types
+            .0
+            .values()
+            .chain(self.imported_classes.0.values())
+            .filter_map(|sk_type| match sk_type {
+                SkType::Class(c) => Some(c),
+                _ => None,
+            })
+            .filter(|c| {
+                let name = c.fullname();
+                c.is_final == Some(true)
+                    && name.0.starts_with(&prefix)
+                    && !name.0[prefix.len()..].contains("::")
+                    && c.superclass
+                        .as_ref()
+                        .map(|scls| scls.ty().erasure().to_class_fullname() == base_name)
+                        .unwrap_or(false)
+            })
+            .map(|c| c.fullname())
+            .collect::<Vec<_>>();
+        cases.sort_by(|a, b| a.0.cmp(&b.0));
+        cases
+    }
 }
 
 #[cfg(test)]
@@ -383,4 +551,48 @@ mod tests {
             assert_eq!(c, None);
         })
     }
+
+    #[test]
+    fn test_enum_cases_of() -> Result<()> {
+        let src = "
+            enum E
+              case A
+              case B(x: Int)
+            end
+        ";
+        test_class_dict(src, |class_dict| {
+            assert!(class_dict.is_enum_class(&ty::raw("E")));
+            assert!(!class_dict.is_enum_class(&ty::raw("Int")));
+            assert_eq!(
+                class_dict.enum_cases_of(&ty::raw("E")),
+                vec![class_fullname("E::A"), class_fullname("E::B")]
+            );
+        })
+    }
+}
+
+/// Edit distance between `a` and `b` (a swap of adjacent chars counts as
+/// one edit)
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, x) in d[0].iter_mut().enumerate() {
+        *x = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
 }
@@ -0,0 +1,405 @@
+use crate::error;
+use crate::hir_maker::HirMaker;
+use crate::hir_maker_context::HirMakerContext;
+use crate::parse_typarams;
+use anyhow::Result;
+use shiika_ast::{AstExpression, AstExpressionBody, AstPattern, Definition, StringPart};
+use shiika_core::{names::*, ty::TyParam};
+use skc_hir::{visitor, Hir, HirExpression, HirExpressionBase};
+use skc_mir::{const_fold, LibraryExports};
+use std::collections::HashMap;
+
+/// A constant definition found in the first pass
+struct ConstDecl<'a> {
+    fullname: ConstFullname,
+    /// Classes and modules enclosing the definition (outermost first)
+    scopes: Vec<(Namespace, Vec<TyParam>)>,
+    expr: &'a AstExpression,
+    /// true if defined with `const`
+    compile_time: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mark {
+    Visiting,
+    Done,
+}
+
+impl<'hir_maker> HirMaker<'hir_maker> {
+    /// Register all the user-defined constants (both toplevel and in
+    /// classes) before converting any method.
+    ///
+    /// 1. Collect the constant definitions from the entire AST
+    /// 2. Sort them in the order of their dependency
+    /// 3. Declare the constants, i.e. convert the initializers and register
+    ///    the types so that any method can refer to them
+    /// 4. Initialize the constants, i.e. emit the assignments in the sorted
+    ///    order
+    pub(super) fn register_all_class_constants_before_methods(
+        &mut self,
+        defs: &[Definition],
+    ) -> Result<()> {
+        let mut decls = vec![];
+        for def in defs {
+            collect_const_decls(&mut decls, &[], def);
+        }
+
+        let index = decls
+            .iter()
+            .enumerate()
+            .map(|(i, decl)| (decl.fullname.clone(), i))
+            .collect::<HashMap<_, _>>();
+        let mut marks = vec![None; decls.len()];
+        let mut order = vec![];
+        for i in 0..decls.len() {
+            self._sort_const_decls(&decls, &index, &mut marks, &mut order, &mut vec![], i)?;
+        }
+
+        let mut inits = vec![];
+        for i in order {
+            let hir_expr = self._declare_const(&decls[i])?;
+            inits.push((decls[i].fullname.clone(), hir_expr));
+        }
+
+        for (fullname, hir_expr) in inits {
+            self.initialize_const(fullname, hir_expr);
+        }
+        Ok(())
+    }
+
+    /// Topological sort of the constant definitions (depth-first)
+    fn _sort_const_decls(
+        &self,
+        decls: &[ConstDecl],
+        index: &HashMap<ConstFullname, usize>,
+        marks: &mut Vec<Option<Mark>>,
+        order: &mut Vec<usize>,
+        path: &mut Vec<usize>,
+        i: usize,
+    ) -> Result<()> {
+        match marks[i] {
+            Some(Mark::Done) => return Ok(()),
+            Some(Mark::Visiting) => {
+                let start = path.iter().position(|j| *j == i).unwrap();
+                let names = path[start..]
+                    .iter()
+                    .chain(std::iter::once(&i))
+                    .map(|j| decls[*j].fullname.0.clone())
+                    .collect::<Vec<_>>();
+                return Err(error::program_error(&format!(
+                    "circular reference of constants: {}",
+                    names.join(" -> ")
+                )));
+            }
+            None => (),
+        }
+        marks[i] = Some(Mark::Visiting);
+        path.push(i);
+        let mut refs = vec![];
+        collect_const_refs(&mut refs, decls[i].expr);
+        for names in refs {
+            if let Some(j) = self._resolve_const_decl(&decls[i], index, names) {
+                self._sort_const_decls(decls, index, marks, order, path, j)?;
+            }
+        }
+        path.pop();
+        marks[i] = Some(Mark::Done);
+        order.push(i);
+        Ok(())
+    }
+
+    /// Find the constant definition referred by `names` in the initializer
+    /// of `decl`. Returns None if it refers to an already registered
+    /// constant (or an unknown one, which will be reported later.)
+    fn _resolve_const_decl(
+        &self,
+        decl: &ConstDecl,
+        index: &HashMap<ConstFullname, usize>,
+        names: &[String],
+    ) -> Option<usize> {
+        // Same order as `ctx_stack.const_scopes()`
+        let namespaces = decl
+            .scopes
+            .iter()
+            .rev()
+            .map(|(namespace, _)| namespace.clone())
+            .chain(std::iter::once(Namespace::root()));
+        for namespace in namespaces {
+            let full = resolved_const_name(namespace, names.to_vec()).to_const_fullname();
+            if let Some(j) = index.get(&full) {
+                return Some(*j);
+            }
+            if self.constants.contains_key(&full) || self.imported_constants.contains_key(&full) {
+                return None;
+            }
+        }
+        None
+    }
+
+    /// Convert the initializer and register the type of the constant.
+    /// Returns the initializer
+    fn _declare_const(&mut self, decl: &ConstDecl) -> Result<HirExpression> {
+        for (namespace, typarams) in &decl.scopes {
+            self.ctx_stack
+                .push(HirMakerContext::class(namespace.clone(), typarams.clone()));
+        }
+        let mut hir_expr = self.convert_expr(decl.expr)?;
+        for _ in &decl.scopes {
+            self.ctx_stack.pop_class_ctx();
+        }
+        if decl.compile_time {
+            const_fold::fold_expr(&mut hir_expr);
+            if !is_literal(&hir_expr) {
+                return Err(error::program_error(&format!(
+                    "the value of constant `{}' cannot be computed at compile time",
+                    decl.fullname
+                )));
+            }
+            self.compile_time_constants
+                .insert(decl.fullname.clone(), hir_expr.clone());
+        }
+        self.declare_const(decl.fullname.clone(), hir_expr.ty.clone());
+        Ok(hir_expr)
+    }
+}
+
+/// Returns warnings for constant initializers which call methods
+/// not annotated with `@pure`
+pub(crate) fn validate_const_expressions(hir: &Hir, imports: &LibraryExports) -> Vec<String> {
+    let mut warnings = vec![];
+    for const_init in &hir.const_inits {
+        let fullname = match &const_init.node {
+            HirExpressionBase::HirConstAssign { fullname, .. } => fullname,
+            _ => panic!("[BUG] unexpected const_init: {:?}", const_init),
+        };
+        // Skip constants like `::Int` (created by define_class_constants)
+        let type_name = fullname.to_type_fullname();
+        if hir.sk_types.0.contains_key(&type_name) || imports.sk_types.0.contains_key(&type_name) {
+            continue;
+        }
+        visitor::walk_expr(const_init, &mut |expr| {
+            if let HirExpressionBase::HirMethodCall {
+                method_fullname, ..
+            } = &expr.node
+            {
+                if !is_pure(hir, imports, method_fullname) {
+                    warnings.push(format!(
+                        "initializer of constant {} calls a method not annotated with @pure ({})",
+                        fullname, method_fullname
+                    ));
+                }
+            }
+        });
+    }
+    warnings
+}
+
+/// Returns true if the method is annotated with `@pure`. The annotations of
+/// the imported methods are not in `hir`, so their signatures are checked
+fn is_pure(hir: &Hir, imports: &LibraryExports, name: &MethodFullname) -> bool {
+    if hir.has_annotation(name, "pure") {
+        return true;
+    }
+    imports
+        .sk_types
+        .0
+        .get(&name.type_name)
+        .and_then(|t| t.base().method_sigs.get(&name.first_name))
+        .map(|(sig, _)| sig.pure)
+        .unwrap_or(false)
+}
+
+/// Collect constant definitions in `def` (recursively)
+fn collect_const_decls<'a>(
+    decls: &mut Vec<ConstDecl<'a>>,
+    scopes: &[(Namespace, Vec<TyParam>)],
+    def: &'a Definition,
+) {
+    let current = scopes
+        .last()
+        .map(|(namespace, _)| namespace.clone())
+        .unwrap_or_else(Namespace::root);
+    let (name, typarams, defs) = match def {
+        Definition::ConstDefinition {
+            name,
+            expr,
+            compile_time,
+        } => {
+            let fullname = if scopes.is_empty() {
+                toplevel_const(name)
+            } else {
+                current.const_fullname(name)
+            };
+            decls.push(ConstDecl {
+                fullname,
+                scopes: scopes.to_vec(),
+                expr,
+                compile_time: *compile_time,
+            });
+            return;
+        }
+        Definition::ClassDefinition {
+            name,
+            typarams,
+            defs,
+            ..
+        } => (name.to_string(), typarams, defs),
+        Definition::ModuleDefinition {
+            name,
+            typarams,
+            defs,
+            ..
+        } => (name.to_string(), typarams, defs),
+        Definition::EnumDefinition {
+            name,
+            typarams,
+            defs,
+            ..
+        } => (name.to_string(), typarams, defs),
+        _ => return,
+    };
+    let mut inner_scopes = scopes.to_vec();
+    inner_scopes.push((current.add(name), parse_typarams(typarams)));
+    for d in defs {
+        collect_const_decls(decls, &inner_scopes, d);
+    }
+}
+
+/// Returns true if `expr` is allowed as the value of `const`
+fn is_literal(expr: &HirExpression) -> bool {
+    matches!(
+        expr.node,
+        HirExpressionBase::HirDecimalLiteral { .. }
+            | HirExpressionBase::HirFloatLiteral { .. }
+            | HirExpressionBase::HirStringLiteral { .. }
+            | HirExpressionBase::HirBooleanLiteral { .. }
+    )
+}
+
+/// Collect constant names referred in `expr` (recursively)
+fn collect_const_refs<'a>(refs: &mut Vec<&'a [String]>, expr: &'a AstExpression) {
+    match &expr.body {
+        AstExpressionBody::CapitalizedName(name) => refs.push(&name.0),
+        AstExpressionBody::SpecializeExpression { base_name, args } => {
+            refs.push(&base_name.0);
+            args.iter().for_each(|e| collect_const_refs(refs, e));
+        }
+        AstExpressionBody::LogicalNot { expr } => collect_const_refs(refs, expr),
+        AstExpressionBody::LogicalAnd { left, right }
+        | AstExpressionBody::LogicalOr { left, right } => {
+            collect_const_refs(refs, left);
+            collect_const_refs(refs, right);
+        }
+        AstExpressionBody::If {
+            cond_expr,
+            then_exprs,
+            else_exprs,
+        } => {
+            collect_const_refs(refs, cond_expr);
+            then_exprs.iter().for_each(|e| collect_const_refs(refs, e));
+            if let Some(exprs) = else_exprs {
+                exprs.iter().for_each(|e| collect_const_refs(refs, e));
+            }
+        }
+        AstExpressionBody::Match { cond_expr, clauses } => {
+            collect_const_refs(refs, cond_expr);
+            for (pattern, _, guard, exprs) in clauses {
+                collect_pattern_const_refs(refs, pattern);
+                if let Some(guard) = guard {
+                    collect_const_refs(refs, guard);
+                }
+                exprs.iter().for_each(|e| collect_const_refs(refs, e));
+            }
+        }
+        AstExpressionBody::While {
+            cond_expr,
+            body_exprs,
+        } => {
+            collect_const_refs(refs, cond_expr);
+            body_exprs.iter().for_each(|e| collect_const_refs(refs, e));
+        }
+        AstExpressionBody::GuardLet {
+            pattern,
+            expr,
+            else_exprs,
+        } => {
+            collect_pattern_const_refs(refs, pattern);
+            collect_const_refs(refs, expr);
+            else_exprs.iter().for_each(|e| collect_const_refs(refs, e));
+        }
+        AstExpressionBody::ForIn {
+            collection, body, ..
+        } => {
+            collect_const_refs(refs, collection);
+            body.iter().for_each(|e| collect_const_refs(refs, e));
+        }
+        AstExpressionBody::Propagate(expr) => collect_const_refs(refs, expr),
+        AstExpressionBody::Break { arg } | AstExpressionBody::Return { arg } => {
+            if let Some(e) = arg {
+                collect_const_refs(refs, e);
+            }
+        }
+        AstExpressionBody::PatternDecl { pattern, rhs, .. } => {
+            collect_pattern_const_refs(refs, pattern);
+            collect_const_refs(refs, rhs);
+        }
+        AstExpressionBody::LVarDecl { rhs, .. }
+        | AstExpressionBody::LVarAssign { rhs, .. }
+        | AstExpressionBody::IVarDecl { rhs, .. }
+        | AstExpressionBody::IVarAssign { rhs, .. }
+        | AstExpressionBody::ConstAssign { rhs, .. } => collect_const_refs(refs, rhs),
+        AstExpressionBody::RangeLiteral { start, end, .. } => {
+            collect_const_refs(refs, start);
+            collect_const_refs(refs, end);
+        }
+        AstExpressionBody::KeywordArg { expr, .. }
+        | AstExpressionBody::Splat { expr }
+        | AstExpressionBody::AssertType { expr, .. } => collect_const_refs(refs, expr),
+        AstExpressionBody::MethodCall(call) => {
+            if let Some(e) = &call.receiver_expr {
+                collect_const_refs(refs, e);
+            }
+            call.arg_exprs
+                .iter()
+                .chain(call.type_args.iter())
+                .for_each(|e| collect_const_refs(refs, e));
+        }
+        AstExpressionBody::LambdaExpr { exprs, .. }
+        | AstExpressionBody::Super { args: exprs }
+        | AstExpressionBody::ArrayLiteral(exprs)
+        | AstExpressionBody::SetLiteral(exprs)
+        | AstExpressionBody::TupleLiteral(exprs) => {
+            exprs.iter().for_each(|e| collect_const_refs(refs, e));
+        }
+        AstExpressionBody::InterpolatedString { parts } => {
+            for part in parts {
+                if let StringPart::Expr { expr, .. } = part {
+                    collect_const_refs(refs, expr);
+                }
+            }
+        }
+        AstExpressionBody::BareName(_)
+        | AstExpressionBody::IVarRef(_)
+        | AstExpressionBody::PseudoVariable(_)
+        | AstExpressionBody::FloatLiteral { .. }
+        | AstExpressionBody::DecimalLiteral { .. }
+        | AstExpressionBody::StringLiteral { .. }
+        | AstExpressionBody::CharLiteral { .. }
+        | AstExpressionBody::Continue => (),
+    }
+}
+
+fn collect_pattern_const_refs<'a>(refs: &mut Vec<&'a [String]>, pattern: &'a AstPattern) {
+    match pattern {
+        AstPattern::ExtractorPattern { names, params } => {
+            refs.push(names);
+            params
+                .iter()
+                .for_each(|p| collect_pattern_const_refs(refs, p));
+        }
+        AstPattern::TuplePattern(params) | AstPattern::OrPattern(params) => params
+            .iter()
+            .for_each(|p| collect_pattern_const_refs(refs, p)),
+        _ => (),
+    }
+}
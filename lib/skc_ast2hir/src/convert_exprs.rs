@@ -1,5 +1,5 @@
 pub mod block;
-mod method_call;
+pub mod method_call;
 pub mod params;
 use crate::class_expr;
 use crate::error;
@@ -65,6 +65,13 @@ impl<'hir_maker> HirMaker<'hir_maker> {
             .map(|expr| self.convert_expr(expr))
             .collect::<Result<Vec<_>, _>>()?;
 
+        // Warn the expressions after `break`, `continue`, `return`, etc.
+        if let Some(i) = hir_exprs.iter().position(|e| e.ty.is_never_type()) {
+            if let Some(next) = hir_exprs.get(i + 1) {
+                self.warnings.push(error::unreachable_code(&next.locs));
+            }
+        }
+
         Ok(HirExpressions::new(hir_exprs))
     }
 
@@ -94,7 +101,23 @@ impl<'hir_maker> HirMaker<'hir_maker> {
                 body_exprs,
             } => self.convert_while_expr(cond_expr, body_exprs, &expr.locs),
 
-            AstExpressionBody::Break => self.convert_break_expr(&expr.locs),
+            AstExpressionBody::ForIn {
+                var,
+                collection,
+                body,
+            } => self.convert_for_in_expr(var, collection, body, &expr.locs),
+
+            AstExpressionBody::GuardLet {
+                pattern,
+                expr: value_expr,
+                else_exprs,
+            } => self.convert_guard_let(pattern, value_expr, else_exprs, &expr.locs),
+
+            AstExpressionBody::Break { arg } => self.convert_break_expr(arg, &expr.locs),
+            AstExpressionBody::Continue => self.convert_continue_expr(&expr.locs),
+            AstExpressionBody::Propagate(value_expr) => {
+                self.convert_propagate(value_expr, &expr.locs)
+            }
 
             AstExpressionBody::Return { arg } => self.convert_return_expr(arg, &expr.locs),
 
@@ -104,6 +127,12 @@ impl<'hir_maker> HirMaker<'hir_maker> {
                 readonly,
             } => self.convert_lvar_decl(name, &*rhs, readonly, &expr.locs),
 
+            AstExpressionBody::PatternDecl {
+                pattern,
+                rhs,
+                readonly,
+            } => self.convert_pattern_decl(pattern, rhs, *readonly, &expr.locs),
+
             AstExpressionBody::LVarAssign { name, rhs } => {
                 self.convert_lvar_assign(name, &*rhs, &expr.locs)
             }
@@ -129,15 +158,29 @@ impl<'hir_maker> HirMaker<'hir_maker> {
                 type_args,
                 has_block,
                 ..
-            }) => method_call::convert_method_call(
-                self,
-                receiver_expr,
-                method_name,
-                arg_exprs,
-                has_block,
-                type_args,
-                &expr.locs,
-            ),
+            }) => {
+                let mut hir = method_call::convert_method_call(
+                    self,
+                    receiver_expr,
+                    method_name,
+                    arg_exprs,
+                    has_block,
+                    type_args,
+                    &expr.locs,
+                )?;
+                // `expr.locs` does not include the receiver (eg. `.foo(1)`)
+                hir.locs = LocationSpan::merge(&hir.locs, &expr.locs);
+                Ok(hir)
+            }
+
+            AstExpressionBody::KeywordArg { name, .. } => Err(error::syntax_error(&format!(
+                "unexpected keyword argument `{}'",
+                name
+            ))),
+
+            AstExpressionBody::Splat { .. } => {
+                Err(error::syntax_error("unexpected splat argument"))
+            }
 
             AstExpressionBody::LambdaExpr {
                 params,
@@ -161,8 +204,23 @@ impl<'hir_maker> HirMaker<'hir_maker> {
                 self.convert_pseudo_variable(token, &expr.locs)
             }
 
+            AstExpressionBody::Super { args } => {
+                method_call::convert_super_call(self, args, &expr.locs)
+            }
+
+            AstExpressionBody::AssertType { expr, typ } => self.convert_assert_type(expr, typ),
+
             AstExpressionBody::ArrayLiteral(exprs) => self.convert_array_literal(exprs, &expr.locs),
 
+            AstExpressionBody::TupleLiteral(exprs) => self.convert_tuple_literal(exprs, &expr.locs),
+            AstExpressionBody::SetLiteral(exprs) => self.convert_set_literal(exprs, &expr.locs),
+
+            AstExpressionBody::RangeLiteral {
+                start,
+                end,
+                inclusive,
+            } => self.convert_range_literal(start, end, *inclusive, &expr.locs),
+
             AstExpressionBody::FloatLiteral { value } => {
                 Ok(Hir::float_literal(*value, expr.locs.clone()))
             }
@@ -171,8 +229,20 @@ impl<'hir_maker> HirMaker<'hir_maker> {
                 Ok(Hir::decimal_literal(*value, expr.locs.clone()))
             }
 
-            AstExpressionBody::StringLiteral { content } => {
-                Ok(self.convert_string_literal(content, &expr.locs))
+            AstExpressionBody::StringLiteral { content, frozen } => {
+                if *frozen {
+                    Ok(self.convert_frozen_string_literal(content, &expr.locs))
+                } else {
+                    Ok(self.convert_string_literal(content, &expr.locs))
+                }
+            }
+
+            AstExpressionBody::CharLiteral { value } => {
+                Ok(Hir::char_literal(*value, expr.locs.clone()))
+            }
+
+            AstExpressionBody::InterpolatedString { parts } => {
+                self.convert_interpolated_string(parts, &expr.locs)
             } //x => panic!("TODO: {:?}", x)
         }
     }
@@ -183,7 +253,7 @@ impl<'hir_maker> HirMaker<'hir_maker> {
         locs: &LocationSpan,
     ) -> Result<HirExpression> {
         let expr_hir = self.convert_expr(expr)?;
-        type_checking::check_logical_operator_ty(&expr_hir.ty, "argument of logical not")?;
+        type_checking::check_logical_operator_ty(&expr_hir, "argument of logical not")?;
         Ok(Hir::logical_not(expr_hir, locs.clone()))
     }
 
@@ -195,8 +265,8 @@ impl<'hir_maker> HirMaker<'hir_maker> {
     ) -> Result<HirExpression> {
         let left_hir = self.convert_expr(left)?;
         let right_hir = self.convert_expr(right)?;
-        type_checking::check_logical_operator_ty(&left_hir.ty, "lhs of logical and")?;
-        type_checking::check_logical_operator_ty(&right_hir.ty, "rhs of logical and")?;
+        type_checking::check_logical_operator_ty(&left_hir, "lhs of logical and")?;
+        type_checking::check_logical_operator_ty(&right_hir, "rhs of logical and")?;
         Ok(Hir::logical_and(left_hir, right_hir, locs.clone()))
     }
 
@@ -208,8 +278,8 @@ impl<'hir_maker> HirMaker<'hir_maker> {
     ) -> Result<HirExpression> {
         let left_hir = self.convert_expr(left)?;
         let right_hir = self.convert_expr(right)?;
-        type_checking::check_logical_operator_ty(&left_hir.ty, "lhs of logical or")?;
-        type_checking::check_logical_operator_ty(&right_hir.ty, "rhs of logical or")?;
+        type_checking::check_logical_operator_ty(&left_hir, "lhs of logical or")?;
+        type_checking::check_logical_operator_ty(&right_hir, "rhs of logical or")?;
         Ok(Hir::logical_or(left_hir, right_hir, locs.clone()))
     }
 
@@ -221,7 +291,7 @@ impl<'hir_maker> HirMaker<'hir_maker> {
         locs: &LocationSpan,
     ) -> Result<HirExpression> {
         let cond_hir = self.convert_expr(cond_expr)?;
-        type_checking::check_condition_ty(&cond_hir.ty, "if")?;
+        type_checking::check_condition_ty(&cond_hir, "if")?;
 
         let mut then_hirs = self.convert_exprs(then_exprs)?;
         let mut else_hirs = match else_exprs {
@@ -229,25 +299,25 @@ impl<'hir_maker> HirMaker<'hir_maker> {
             None => HirExpressions::new(vec![]),
         };
 
-        let if_ty = if then_hirs.ty.is_never_type() {
-            else_hirs.ty.clone()
-        } else if else_hirs.ty.is_never_type() {
-            then_hirs.ty.clone()
-        } else if then_hirs.ty.is_void_type() {
+        let if_ty = if then_hirs.ty().is_never_type() {
+            else_hirs.ty().clone()
+        } else if else_hirs.ty().is_never_type() {
+            then_hirs.ty().clone()
+        } else if then_hirs.ty().is_void_type() {
             else_hirs.voidify();
             ty::raw("Void")
-        } else if else_hirs.ty.is_void_type() {
+        } else if else_hirs.ty().is_void_type() {
             then_hirs.voidify();
             ty::raw("Void")
         } else {
             let opt_ty = self
                 .class_dict
-                .nearest_common_ancestor(&then_hirs.ty, &else_hirs.ty);
-            let ty = type_checking::check_if_body_ty(opt_ty)?;
-            if !then_hirs.ty.equals_to(&ty) {
+                .nearest_common_ancestor(then_hirs.ty(), else_hirs.ty());
+            let ty = type_checking::check_if_body_ty(opt_ty, &then_hirs, &else_hirs, locs)?;
+            if !then_hirs.ty().equals_to(&ty) {
                 then_hirs = then_hirs.bitcast_to(ty.clone());
             }
-            if !else_hirs.ty.equals_to(&ty) {
+            if !else_hirs.ty().equals_to(&ty) {
                 else_hirs = else_hirs.bitcast_to(ty.clone());
             }
             ty
@@ -266,16 +336,32 @@ impl<'hir_maker> HirMaker<'hir_maker> {
         &mut self,
         cond_expr: &AstExpression,
         clauses: &[AstMatchClause],
-        _locs: &LocationSpan,
+        locs: &LocationSpan,
     ) -> Result<HirExpression> {
-        let (match_expr, lvars) = pattern_match::convert_match_expr(self, cond_expr, clauses)?;
-        for (name, ty) in lvars {
+        self.detect_dead_match_clauses(clauses);
+        let (match_expr, lvars) =
+            pattern_match::convert_match_expr(self, cond_expr, clauses, locs)?;
+        for lvar in lvars {
             let readonly = true;
-            self.ctx_stack.declare_lvar(&name, ty, readonly);
+            self.ctx_stack.declare_lvar(&lvar.name, lvar.ty, readonly);
         }
         Ok(match_expr)
     }
 
+    /// Warn clauses whose pattern is a literal already covered by a
+    /// preceding clause
+    fn detect_dead_match_clauses(&mut self, clauses: &[AstMatchClause]) {
+        for (i, (pat, pat_locs, _, _)) in clauses.iter().enumerate() {
+            // A clause with a guard does not cover the later ones
+            let dead = clauses[..i].iter().any(|(prev, _, guard, _)| {
+                guard.is_none() && pattern_match::is_same_literal_pattern(prev, pat)
+            });
+            if dead {
+                self.warnings.push(error::dead_match_clause(pat_locs));
+            }
+        }
+    }
+
     fn convert_while_expr(
         &mut self,
         cond_expr: &AstExpression,
@@ -283,21 +369,171 @@ impl<'hir_maker> HirMaker<'hir_maker> {
         locs: &LocationSpan,
     ) -> Result<HirExpression> {
         let cond_hir = self.convert_expr(cond_expr)?;
-        type_checking::check_condition_ty(&cond_hir.ty, "while")?;
+        type_checking::check_condition_ty(&cond_hir, "while")?;
 
         self.ctx_stack.push(HirMakerContext::while_ctx());
         let body_hirs = self.convert_exprs(body_exprs)?;
-        self.ctx_stack.pop_while_ctx();
+        let while_ctx = self.ctx_stack.pop_while_ctx();
+
+        let while_ty = self.while_expr_ty(&cond_hir, &while_ctx.break_tys)?;
+        Ok(Hir::while_expression(
+            while_ty,
+            cond_hir,
+            body_hirs,
+            locs.clone(),
+        ))
+    }
+
+    /// Type of a while expression is the nearest common ancestor of the
+    /// `break` values. It is `Void` if the loop may end without a value.
+    fn while_expr_ty(&self, cond_hir: &HirExpression, break_tys: &[TermTy]) -> Result<TermTy> {
+        let always_true = matches!(
+            cond_hir.node,
+            HirExpressionBase::HirBooleanLiteral { value: true }
+        );
+        let tys = break_tys
+            .iter()
+            .filter(|t| !t.is_never_type())
+            .collect::<Vec<_>>();
+        if !always_true || tys.is_empty() || tys.iter().any(|t| t.is_void_type()) {
+            return Ok(ty::raw("Void"));
+        }
+        let mut while_ty = tys[0].clone();
+        for t in &tys[1..] {
+            while_ty = self
+                .class_dict
+                .nearest_common_ancestor(&while_ty, t)
+                .ok_or_else(|| {
+                    error::type_error(format!(
+                        "values of `break' have no common ancestor: {}, {}",
+                        while_ty, t
+                    ))
+                })?;
+        }
+        Ok(while_ty)
+    }
+
+    /// Convert `for x in coll do ... end` into `coll.each{|x| ... }`
+    fn convert_for_in_expr(
+        &mut self,
+        var: &str,
+        collection: &AstExpression,
+        body: &[AstExpression],
+        locs: &LocationSpan,
+    ) -> Result<HirExpression> {
+        let block = AstExpression {
+            body: AstExpressionBody::LambdaExpr {
+                params: vec![BlockParam {
+                    name: var.to_string(),
+                    opt_typ: None,
+                }],
+                exprs: body.to_vec(),
+                is_fn: false,
+            },
+            primary: true,
+            locs: locs.clone(),
+        };
+        let each_call = AstExpression {
+            body: AstExpressionBody::MethodCall(AstMethodCall {
+                receiver_expr: Some(Box::new(collection.clone())),
+                method_name: method_firstname("each"),
+                arg_exprs: vec![block],
+                type_args: vec![],
+                has_block: true,
+                may_have_paren_wo_args: false,
+            }),
+            primary: false,
+            locs: locs.clone(),
+        };
+        self.convert_expr(&each_call)
+    }
+
+    /// Convert `guard let PATTERN = EXPR else ... end`. The variables in
+    /// the pattern are declared in the current scope.
+    fn convert_guard_let(
+        &mut self,
+        pattern: &AstPattern,
+        value_expr: &AstExpression,
+        else_exprs: &[AstExpression],
+        locs: &LocationSpan,
+    ) -> Result<HirExpression> {
+        let value = self.convert_expr(value_expr)?;
+        let tmp_name = self.generate_lvar_name("guard");
+        let readonly = true;
+        self.ctx_stack
+            .declare_lvar(&tmp_name, value.ty.clone(), readonly);
+        let tmp_ref = Hir::lvar_ref(value.ty.clone(), tmp_name.clone(), locs.clone());
+        let (test, binds) = pattern_match::convert_pattern_test(self, &tmp_ref, pattern)?;
+
+        let mut else_hirs = self.convert_exprs(else_exprs)?;
+        if !else_hirs.ty().is_never_type() {
+            // Return the value of the else clause
+            let from = self._validate_return()?;
+            let arg = Hir::parenthesized_expression(else_hirs, locs.clone());
+            self._validate_return_type(&arg)?;
+            else_hirs =
+                Hir::expressions_from_body(vec![Hir::return_expression(from, arg, locs.clone())]);
+        }
 
-        Ok(Hir::while_expression(cond_hir, body_hirs, locs.clone()))
+        let mut exprs = vec![
+            Hir::lvar_assign(tmp_name, value, locs.clone()),
+            Hir::if_expression(
+                ty::raw("Void"),
+                Hir::logical_not(test, locs.clone()),
+                else_hirs,
+                HirExpressions::new(vec![]),
+                locs.clone(),
+            ),
+        ];
+        self.declare_pattern_lvars(binds, readonly, locs, &mut exprs)?;
+        exprs.push(Hir::const_ref(
+            ty::raw("Void"),
+            toplevel_const("Void"),
+            locs.clone(),
+        ));
+        Ok(Hir::parenthesized_expression(
+            Hir::expressions_from_body(exprs),
+            locs.clone(),
+        ))
     }
 
-    fn convert_break_expr(&mut self, locs: &LocationSpan) -> Result<HirExpression> {
+    /// Declare the variables bound by a pattern in the current scope and
+    /// push the assignments to `exprs`
+    fn declare_pattern_lvars(
+        &mut self,
+        binds: Vec<(String, HirExpression)>,
+        readonly: bool,
+        locs: &LocationSpan,
+        exprs: &mut Vec<HirExpression>,
+    ) -> Result<()> {
+        for (name, hir) in binds {
+            if self._lookup_var(&name, locs.clone()).is_some() {
+                return Err(error::lvar_redeclaration(&name, locs));
+            }
+            self.ctx_stack.declare_lvar(&name, hir.ty.clone(), readonly);
+            exprs.push(Hir::lvar_assign(name, hir, locs.clone()));
+        }
+        Ok(())
+    }
+
+    fn convert_break_expr(
+        &mut self,
+        arg: &Option<Box<AstExpression>>,
+        locs: &LocationSpan,
+    ) -> Result<HirExpression> {
+        let value = match arg {
+            Some(expr) => Some(self.convert_expr(expr)?),
+            None => None,
+        };
         let from;
         match self.ctx_stack.loop_ctx_mut() {
             Some(HirMakerContext::Lambda(lambda_ctx)) => {
                 if lambda_ctx.is_fn {
                     return Err(error::program_error("`break' inside a fn"));
+                } else if value.is_some() {
+                    return Err(error::program_error(
+                        "`break' with a value is only allowed in a while",
+                    ));
                 } else {
                     // OK for now. This `break` still may be invalid
                     // (eg. `ary.map{ break }`) but it cannot be checked here
@@ -305,14 +541,106 @@ impl<'hir_maker> HirMaker<'hir_maker> {
                     from = HirBreakFrom::Block;
                 }
             }
-            Some(HirMakerContext::While(_)) => {
+            Some(HirMakerContext::While(while_ctx)) => {
+                let ty = match &value {
+                    Some(v) => v.ty.clone(),
+                    None => ty::raw("Void"),
+                };
+                while_ctx.break_tys.push(ty);
                 from = HirBreakFrom::While;
             }
             _ => {
                 return Err(error::program_error("`break' outside a loop"));
             }
         }
-        Ok(Hir::break_expression(from, locs.clone()))
+        Ok(Hir::break_expression(from, value, locs.clone()))
+    }
+
+    fn convert_continue_expr(&mut self, locs: &LocationSpan) -> Result<HirExpression> {
+        let from = match self.ctx_stack.loop_ctx_mut() {
+            Some(HirMakerContext::Lambda(lambda_ctx)) => {
+                if lambda_ctx.is_fn {
+                    return Err(error::program_error("`continue' inside a fn"));
+                }
+                // Checked later in the same way as `break`
+                lambda_ctx.has_continue = true;
+                HirBreakFrom::Block
+            }
+            Some(HirMakerContext::While(_)) => HirBreakFrom::While,
+            _ => {
+                return Err(error::program_error("`continue' outside a loop"));
+            }
+        };
+        Ok(Hir::continue_expression(from, locs.clone()))
+    }
+
+    /// Convert `expr?` into
+    /// ```sk
+    /// tmp = expr
+    /// return tmp unless tmp is an Ok
+    /// tmp.value
+    /// ```
+    fn convert_propagate(
+        &mut self,
+        value_expr: &AstExpression,
+        locs: &LocationSpan,
+    ) -> Result<HirExpression> {
+        let ret_ty = match (self.ctx_stack.lambda_ctx(), self.ctx_stack.method_ctx()) {
+            (None, Some(method_ctx)) => method_ctx.signature.ret_ty.clone(),
+            (Some(_), _) => {
+                return Err(error::program_error(
+                    "`?' cannot be used in a fn or a block",
+                ));
+            }
+            (None, None) => return Err(error::program_error("`?' outside a method")),
+        };
+        let value = self.convert_expr(value_expr)?;
+        let err_ty = result_err_ty(&value.ty).ok_or_else(|| {
+            error::type_error(format!(
+                "`?' can only be applied to a Result but got {}",
+                value.ty
+            ))
+        })?;
+        if result_err_ty(&ret_ty) != Some(err_ty) {
+            return Err(error::type_error(format!(
+                "`?' on {} needs the method to return Result<_, {}> but it returns {}",
+                value.ty, err_ty, ret_ty
+            )));
+        }
+
+        let tmp_name = self.generate_lvar_name("propagate");
+        let readonly = true;
+        self.ctx_stack
+            .declare_lvar(&tmp_name, value.ty.clone(), readonly);
+        let tmp_ref = Hir::lvar_ref(value.ty.clone(), tmp_name.clone(), locs.clone());
+        let pattern = AstPattern::ExtractorPattern {
+            names: vec!["Result".to_string(), "Ok".to_string()],
+            params: vec![AstPattern::VariablePattern("value".to_string())],
+        };
+        let (test, mut binds) = pattern_match::convert_pattern_test(self, &tmp_ref, &pattern)?;
+        let (_, ok_value) = binds.pop().expect("[BUG] no binding for Ok");
+        // The `Fail` can be returned as is because the type arguments are
+        // erased at runtime (`Fail<V, E>` is the same as `Fail<U, E>`)
+        let ret = Hir::return_expression(
+            HirReturnFrom::Method,
+            Hir::bit_cast(ret_ty, tmp_ref),
+            locs.clone(),
+        );
+        let exprs = vec![
+            Hir::lvar_assign(tmp_name, value, locs.clone()),
+            Hir::if_expression(
+                ty::raw("Void"),
+                Hir::logical_not(test, locs.clone()),
+                Hir::expressions_from_body(vec![ret]),
+                HirExpressions::new(vec![]),
+                locs.clone(),
+            ),
+            ok_value,
+        ];
+        Ok(Hir::parenthesized_expression(
+            Hir::expressions_from_body(exprs),
+            locs.clone(),
+        ))
     }
 
     fn convert_return_expr(
@@ -324,13 +652,9 @@ impl<'hir_maker> HirMaker<'hir_maker> {
         let arg_expr = if let Some(x) = arg {
             self.convert_expr(x)?
         } else {
-            Hir::const_ref(
-                ty::raw("Void"),
-                toplevel_const("Void"),
-                LocationSpan::todo(),
-            )
+            Hir::const_ref(ty::raw("Void"), toplevel_const("Void"), locs.clone())
         };
-        self._validate_return_type(&arg_expr.ty)?;
+        self._validate_return_type(&arg_expr)?;
         Ok(Hir::return_expression(from, arg_expr, locs.clone()))
     }
 
@@ -355,11 +679,15 @@ impl<'hir_maker> HirMaker<'hir_maker> {
     }
 
     /// Check if the argument of `return' is valid
-    fn _validate_return_type(&self, arg_ty: &TermTy) -> Result<()> {
+    fn _validate_return_type(&self, arg_expr: &HirExpression) -> Result<()> {
         if self.ctx_stack.lambda_ctx().is_some() {
             // TODO: check arg_ty matches to fn's return type
         } else if let Some(method_ctx) = &self.ctx_stack.method_ctx() {
-            type_checking::check_return_arg_type(&self.class_dict, arg_ty, &method_ctx.signature)?;
+            type_checking::check_return_arg_type(
+                &self.class_dict,
+                arg_expr,
+                &method_ctx.signature,
+            )?;
         }
         Ok(())
     }
@@ -382,6 +710,55 @@ impl<'hir_maker> HirMaker<'hir_maker> {
         Ok(Hir::lvar_assign(name.to_string(), expr, locs.clone()))
     }
 
+    /// Local variable declaration with a pattern
+    /// `let (a, b) = ...` or `let Pair(x, y) = ...`
+    /// Panics at runtime if the value does not match the pattern.
+    fn convert_pattern_decl(
+        &mut self,
+        pattern: &AstPattern,
+        rhs: &AstExpression,
+        readonly: bool,
+        locs: &LocationSpan,
+    ) -> Result<HirExpression> {
+        let value = self.convert_expr(rhs)?;
+        let tmp_name = self.generate_lvar_name("let");
+        self.ctx_stack
+            .declare_lvar(&tmp_name, value.ty.clone(), true);
+        let tmp_ref = Hir::lvar_ref(value.ty.clone(), tmp_name.clone(), locs.clone());
+        let (test, binds) = pattern_match::convert_pattern_test(self, &tmp_ref, pattern)?;
+
+        let panic_msg = Hir::string_literal(
+            self.register_string_literal("let: the value does not match the pattern"),
+            locs.clone(),
+        );
+        let panic_expr = Hir::method_call(
+            ty::raw("Never"),
+            Hir::decimal_literal(0, locs.clone()), // whatever.
+            method_fullname_raw("Object", "panic"),
+            vec![panic_msg],
+        );
+        let mut exprs = vec![
+            Hir::lvar_assign(tmp_name, value, locs.clone()),
+            Hir::if_expression(
+                ty::raw("Void"),
+                Hir::logical_not(test, locs.clone()),
+                Hir::expressions_from_body(vec![panic_expr]),
+                HirExpressions::new(vec![]),
+                locs.clone(),
+            ),
+        ];
+        self.declare_pattern_lvars(binds, readonly, locs, &mut exprs)?;
+        exprs.push(Hir::const_ref(
+            ty::raw("Void"),
+            toplevel_const("Void"),
+            locs.clone(),
+        ));
+        Ok(Hir::parenthesized_expression(
+            Hir::expressions_from_body(exprs),
+            locs.clone(),
+        ))
+    }
+
     /// Local variable reassignment (`a = ...`)
     fn convert_lvar_assign(
         &mut self,
@@ -403,6 +780,7 @@ impl<'hir_maker> HirMaker<'hir_maker> {
                         &lvar_info.ty,
                         &expr.ty,
                         name,
+                        locs,
                     ));
                 }
             }
@@ -453,17 +831,22 @@ impl<'hir_maker> HirMaker<'hir_maker> {
                     name
                 )));
             }
-            if !ivar.ty.equals_to(&expr.ty) {
-                // TODO: Subtype (@obj = 1, etc.)
+            if !self.class_dict.conforms(&expr.ty, &ivar.ty) {
                 return Err(error::type_error(&format!(
                     "instance variable `{}' has type {:?} but tried to assign a {:?}",
                     name, ivar.ty, expr.ty
                 )));
             }
+            // Upcast so that the stored value matches the slot type (eg. `@obj = 1`)
+            let value = if expr.ty.equals_to(&ivar.ty) || expr.ty.is_never_type() {
+                expr
+            } else {
+                Hir::bit_cast(ivar.ty.clone(), expr)
+            };
             Ok(Hir::ivar_assign(
                 name,
                 ivar.idx,
-                expr,
+                value,
                 false,
                 base_ty,
                 locs.clone(),
@@ -545,13 +928,14 @@ impl<'hir_maker> HirMaker<'hir_maker> {
         let hir_exprs = self.convert_exprs(exprs)?;
         let mut lambda_ctx = self.ctx_stack.pop_lambda_ctx();
         Ok(Hir::lambda_expr(
-            block::lambda_ty(&hir_params, &hir_exprs.ty),
+            block::lambda_ty(&hir_params, hir_exprs.ty()),
             self.create_lambda_name(),
             hir_params,
             hir_exprs,
             self._resolve_lambda_captures(lambda_ctx.captures), // hir_captures
             extract_lvars(&mut lambda_ctx.lvars),               // lvars
             lambda_ctx.has_break,
+            lambda_ctx.has_continue,
             locs.clone(),
         ))
     }
@@ -574,17 +958,15 @@ impl<'hir_maker> HirMaker<'hir_maker> {
         lambda_captures: Vec<LambdaCapture>,
     ) -> Vec<HirLambdaCapture> {
         let mut ret = vec![];
+        // The lambda (if any) which encloses the lambda being created
+        let current_owner = self.ctx_stack.current_scope_owner();
         for cap in lambda_captures {
-            let captured_here = if let HirMakerContext::Lambda(_) = self.ctx_stack.top() {
-                matches!(cap.ctx_depth, Some(idx) if idx == self.ctx_stack.len() - 1)
-            } else {
-                cap.ctx_depth.is_none()
-            };
-            if captured_here {
+            if cap.ctx_depth == current_owner {
                 // The variable is in this scope
                 match cap.detail {
                     LambdaCaptureDetail::CapLVar { name } => {
-                        ret.push(HirLambdaCapture::CaptureLVar { name });
+                        let cell = !cap.readonly;
+                        ret.push(HirLambdaCapture::CaptureLVar { name, cell });
                     }
                     LambdaCaptureDetail::CapFnArg { idx } => {
                         ret.push(HirLambdaCapture::CaptureArg { idx });
@@ -608,7 +990,7 @@ impl<'hir_maker> HirMaker<'hir_maker> {
         }
 
         // Search method
-        let self_expr = self.convert_self_expr(&LocationSpan::todo());
+        let self_expr = self.convert_self_expr(locs);
         let result = self
             .class_dict
             .lookup_method(&self_expr.ty, &method_firstname(name), &[]);
@@ -635,29 +1017,26 @@ impl<'hir_maker> HirMaker<'hir_maker> {
         locs: LocationSpan,
         updating: bool,
     ) -> Result<Option<LVarInfo>> {
-        let (in_lambda, cidx) = if let Some(lambda_ctx) = self.ctx_stack.lambda_ctx() {
-            (true, lambda_ctx.captures.len())
-        } else {
-            (false, 0)
-        };
-        let (found, opt_cap) = self.__find_var(in_lambda, cidx, name, locs, updating)?;
+        let (found, opt_cap) = self.__find_var(name, locs, updating)?;
         if let Some(cap) = opt_cap {
+            if let LambdaCaptureDetail::CapLVar { name } = &cap.detail {
+                self.ctx_stack.set_lvar_captured(name);
+            }
             self.ctx_stack.push_lambda_capture(cap);
         }
         Ok(found)
     }
 
     fn __find_var(
-        &mut self,
-        in_lambda: bool,
-        cidx: usize,
+        &self,
         name: &str,
         locs: LocationSpan,
         updating: bool,
     ) -> Result<(Option<LVarInfo>, Option<LambdaCapture>)> {
-        let mut lambda_seen = false;
-        for (lvars, params, opt_depth) in self.ctx_stack.lvar_scopes() {
-            let is_lambda_capture = in_lambda && lambda_seen;
+        let current_owner = self.ctx_stack.current_scope_owner();
+        for (lvars, params, owner) in self.ctx_stack.lvar_scopes() {
+            // Variables in another llvm function should be captured
+            let is_lambda_capture = owner != current_owner;
             if let Some(lvar) = lvars.get(name) {
                 if updating && lvar.readonly {
                     return Err(error::program_error(&format!(
@@ -667,8 +1046,9 @@ impl<'hir_maker> HirMaker<'hir_maker> {
                 }
                 if is_lambda_capture {
                     let cap = LambdaCapture {
-                        ctx_depth: opt_depth,
+                        ctx_depth: owner,
                         ty: lvar.ty.clone(),
+                        readonly: lvar.readonly,
                         detail: LambdaCaptureDetail::CapLVar {
                             name: name.to_string(),
                         },
@@ -676,8 +1056,8 @@ impl<'hir_maker> HirMaker<'hir_maker> {
                     let lvar_info = LVarInfo {
                         ty: lvar.ty.clone(),
                         detail: LVarDetail::OuterScope {
-                            cidx,
-                            readonly: false,
+                            cidx: self.ctx_stack.lambda_capture_idx(&cap),
+                            readonly: lvar.readonly,
                         },
                         locs,
                     };
@@ -702,14 +1082,15 @@ impl<'hir_maker> HirMaker<'hir_maker> {
                 }
                 if is_lambda_capture {
                     let cap = LambdaCapture {
-                        ctx_depth: opt_depth,
+                        ctx_depth: owner,
                         ty: param.ty.clone(),
+                        readonly: true,
                         detail: LambdaCaptureDetail::CapFnArg { idx },
                     };
                     let lvar_info = LVarInfo {
                         ty: param.ty.clone(),
                         detail: LVarDetail::OuterScope {
-                            cidx,
+                            cidx: self.ctx_stack.lambda_capture_idx(&cap),
                             readonly: true,
                         },
                         locs,
@@ -724,10 +1105,6 @@ impl<'hir_maker> HirMaker<'hir_maker> {
                     return Ok((Some(lvar_info), None));
                 }
             }
-            let is_lambda_scope = opt_depth.is_some();
-            if is_lambda_scope {
-                lambda_seen = true;
-            }
         }
         Ok((None, None))
     }
@@ -780,6 +1157,13 @@ impl<'hir_maker> HirMaker<'hir_maker> {
         for namespace in self.ctx_stack.const_scopes() {
             let resolved = resolved_const_name(namespace, name.0.to_vec());
             let full = resolved.to_const_fullname();
+            if let Some(value) = self.compile_time_constants.get(&full) {
+                // Inline the value of `const`
+                return Ok(HirExpression {
+                    locs: locs.clone(),
+                    ..value.clone()
+                });
+            }
             if let Some(ty) = self._lookup_const(&full) {
                 return Ok(Hir::const_ref(ty, full, locs.clone()));
             }
@@ -812,7 +1196,7 @@ impl<'hir_maker> HirMaker<'hir_maker> {
         let mut type_args = vec![];
         for arg in args {
             let cls_expr = match &arg.body {
-                AstExpressionBody::CapitalizedName(n) => self.resolve_class_expr(n, &arg.locs)?,
+                AstExpressionBody::CapitalizedName(n) => self.resolve_tyarg_expr(n, &arg.locs)?,
                 AstExpressionBody::SpecializeExpression {
                     base_name: n,
                     args: a,
@@ -822,12 +1206,24 @@ impl<'hir_maker> HirMaker<'hir_maker> {
             type_args.push(cls_expr.ty.as_type_argument());
             arg_exprs.push(cls_expr);
         }
+        if let TyBody::TyRaw(LitTy { is_meta: true, .. }) = &base_expr.ty.body {
+            let class_ty = base_expr.ty.instance_ty();
+            let sk_type = self
+                .class_dict
+                .get_type(&class_ty.erasure().to_type_fullname());
+            type_checking::check_class_tyargs(
+                &self.class_dict,
+                &class_ty,
+                &sk_type.base().typarams,
+                &type_args,
+            )?;
+        }
         let meta_spe_ty = base_expr.ty.specialized_ty(type_args);
         Ok(Hir::method_call(
             meta_spe_ty,
             base_expr,
             method_fullname_raw("Class", "<>"),
-            vec![self.create_array_instance_(arg_exprs, ty::raw("Class"), LocationSpan::todo())],
+            vec![self.create_array_instance_(arg_exprs, ty::raw("Class"), locs.clone())],
         ))
     }
 
@@ -841,6 +1237,27 @@ impl<'hir_maker> HirMaker<'hir_maker> {
         Ok(e)
     }
 
+    /// Like `resolve_class_expr` but also accepts `Void`, whose constant
+    /// holds the instance rather than the class (eg. `Ok<Void, String>`)
+    fn resolve_tyarg_expr(
+        &self,
+        name: &UnresolvedConstName,
+        locs: &LocationSpan,
+    ) -> Result<HirExpression> {
+        let e = self.convert_capitalized_name(name, locs)?;
+        if e.ty.is_void_type() {
+            let cls = Hir::method_call(
+                ty::raw("Class"),
+                Hir::bit_cast(ty::raw("Object"), e),
+                method_fullname_raw("Object", "class"),
+                vec![],
+            );
+            return Ok(Hir::bit_cast(ty::meta("Void"), cls));
+        }
+        self.assert_class_expr(&e)?;
+        Ok(e)
+    }
+
     /// Check if `e` evaluates to a class object.
     fn assert_class_expr(&self, e: &HirExpression) -> Result<()> {
         if e.ty.is_metaclass() || e.ty.is_typaram_ref() {
@@ -888,14 +1305,11 @@ impl<'hir_maker> HirMaker<'hir_maker> {
         if item_exprs.is_empty() {
             return ty::raw("Object");
         }
-        let mut item_ty = item_exprs[0].ty.clone();
-        if item_exprs.len() == 1 {
-            return item_ty;
-        }
-        for expr in item_exprs {
+        let mut item_ty = class_as_instance(&item_exprs[0].ty);
+        for expr in &item_exprs[1..] {
             item_ty = self
                 .class_dict
-                .nearest_common_ancestor(&item_ty, &expr.ty)
+                .nearest_common_ancestor(&item_ty, &class_as_instance(&expr.ty))
                 .expect("array literal elements type mismatch");
         }
         item_ty
@@ -919,7 +1333,7 @@ impl<'hir_maker> HirMaker<'hir_maker> {
         // `Array<X>.new`
         let call_new = Hir::method_call(
             ary_ty.clone(),
-            class_expr(self, &ary_ty),
+            class_expr(self, &ary_ty, &locs),
             method_fullname_raw("Array", "new"),
             vec![],
         );
@@ -936,7 +1350,142 @@ impl<'hir_maker> HirMaker<'hir_maker> {
         }
 
         exprs.push(Hir::lvar_ref(ary_ty, tmp_name, locs.clone()));
-        Hir::parenthesized_expression(Hir::expressions(exprs), locs)
+        Hir::parenthesized_expression(Hir::expressions_from_body(exprs), locs)
+    }
+
+    /// Expand `a..b` into `Range<X>.new(a, b, true)`
+    fn convert_range_literal(
+        &mut self,
+        start: &AstExpression,
+        end: &AstExpression,
+        inclusive: bool,
+        locs: &LocationSpan,
+    ) -> Result<HirExpression> {
+        let start_hir = self.convert_expr(start)?;
+        let end_hir = self.convert_expr(end)?;
+        let item_ty = match self
+            .class_dict
+            .nearest_common_ancestor(&start_hir.ty, &end_hir.ty)
+        {
+            Some(t) => t,
+            None => {
+                return Err(error::type_error(format!(
+                    "range endpoints type mismatch ({} vs {})",
+                    &start_hir.ty, &end_hir.ty
+                )))
+            }
+        };
+        let range_ty = ty::spe("Range", vec![item_ty]);
+        Ok(Hir::method_call(
+            range_ty.clone(),
+            class_expr(self, &range_ty, locs),
+            method_fullname_raw("Meta:Range", "new"),
+            vec![
+                Hir::bit_cast(ty::raw("Object"), start_hir),
+                Hir::bit_cast(ty::raw("Object"), end_hir),
+                Hir::boolean_literal(inclusive, locs.clone()),
+            ],
+        ))
+    }
+
+    /// Check the type of `expr` at compile time. Nothing is done at runtime
+    fn convert_assert_type(
+        &mut self,
+        expr: &AstExpression,
+        typ: &UnresolvedTypeName,
+    ) -> Result<HirExpression> {
+        let hir_expr = self.convert_expr(expr)?;
+        let namespace = self.ctx_stack.const_scopes().next().unwrap();
+        let class_typarams = self.ctx_stack.current_class_typarams();
+        let method_typarams = self.ctx_stack.current_method_typarams();
+        let expected =
+            self.class_dict
+                .resolve_typename(&namespace, &class_typarams, &method_typarams, typ)?;
+        if hir_expr.ty != expected {
+            return Err(error::assert_type_failed(
+                &expected,
+                &hir_expr.ty,
+                &expr.locs,
+            ));
+        }
+        Ok(hir_expr)
+    }
+
+    /// Convert `(a, b)` into `Tuple2<A, B>.new(a, b)`
+    fn convert_tuple_literal(
+        &mut self,
+        item_exprs: &[AstExpression],
+        locs: &LocationSpan,
+    ) -> Result<HirExpression> {
+        let item_hirs = item_exprs
+            .iter()
+            .map(|expr| self.convert_expr(expr))
+            .collect::<Result<Vec<_>, _>>()?;
+        let base_name = format!("Tuple{}", item_hirs.len());
+        let tuple_ty = ty::spe(&base_name, item_hirs.iter().map(|x| x.ty.clone()).collect());
+        let mut hir = Hir::method_call(
+            tuple_ty.clone(),
+            class_expr(self, &tuple_ty, locs),
+            method_fullname_raw(&format!("Meta:{}", base_name), "new"),
+            item_hirs
+                .into_iter()
+                .map(|x| Hir::bit_cast(ty::raw("Object"), x))
+                .collect(),
+        );
+        hir.locs = locs.clone();
+        Ok(hir)
+    }
+
+    /// Expand `{1, 2}` into `tmp=Set<Int>.new; tmp.add(1); tmp.add(2); tmp`
+    fn convert_set_literal(
+        &mut self,
+        item_exprs: &[AstExpression],
+        locs: &LocationSpan,
+    ) -> Result<HirExpression> {
+        let item_hirs = item_exprs
+            .iter()
+            .map(|expr| self.convert_expr(expr))
+            .collect::<Result<Vec<_>, _>>()?;
+        let item_ty = self.array_item_ty(&item_hirs);
+        let set_ty = ty::spe("Set", vec![item_ty.clone()]);
+        let sk_type = self.class_dict.get_type(&type_fullname("Set"));
+        type_checking::check_class_tyargs(
+            &self.class_dict,
+            &set_ty,
+            &sk_type.base().typarams,
+            &[item_ty],
+        )?;
+        let mut exprs = vec![];
+
+        let tmp_name = self.generate_lvar_name("set");
+        let readonly = true;
+        self.ctx_stack
+            .declare_lvar(&tmp_name, set_ty.clone(), readonly);
+
+        // `Set<X>.new`
+        let call_new = Hir::method_call(
+            set_ty.clone(),
+            class_expr(self, &set_ty, locs),
+            method_fullname_raw("Meta:Set", "new"),
+            vec![],
+        );
+        exprs.push(Hir::lvar_assign(tmp_name.clone(), call_new, locs.clone()));
+
+        // `tmp.add(item)`
+        for item_hir in item_hirs {
+            exprs.push(Hir::method_call(
+                ty::raw("Void"),
+                Hir::lvar_ref(set_ty.clone(), tmp_name.clone(), locs.clone()),
+                method_fullname_raw("Set", "add"),
+                vec![Hir::bit_cast(ty::raw("Object"), item_hir)],
+            ));
+        }
+
+        exprs.push(Hir::lvar_ref(set_ty, tmp_name, locs.clone()));
+        Ok(Hir::parenthesized_expression(
+            Hir::expressions_from_body(exprs),
+            locs.clone(),
+        ))
     }
 
     fn convert_self_expr(&self, locs: &LocationSpan) -> HirExpression {
@@ -952,9 +1501,96 @@ impl<'hir_maker> HirMaker<'hir_maker> {
         Hir::string_literal(idx, locs.clone())
     }
 
+    /// Literals with the same content in the files with
+    /// `# frozen_string_literal: true` share one frozen String object
+    fn convert_frozen_string_literal(
+        &mut self,
+        content: &str,
+        locs: &LocationSpan,
+    ) -> HirExpression {
+        let idx = match self.frozen_str_literals.get(content) {
+            Some(idx) => *idx,
+            None => {
+                let idx = self.register_string_literal(content);
+                self.frozen_str_literals.insert(content.to_string(), idx);
+                idx
+            }
+        };
+        Hir::frozen_string_literal(idx, locs.clone())
+    }
+
+    /// Convert `"a#{b}c"` as `"a" + b.to_s + "c"`
+    fn convert_interpolated_string(
+        &mut self,
+        parts: &[StringPart],
+        locs: &LocationSpan,
+    ) -> Result<HirExpression> {
+        let str_literal = |content: String| AstExpression {
+            body: AstExpressionBody::StringLiteral {
+                content,
+                frozen: false,
+            },
+            primary: true,
+            locs: locs.clone(),
+        };
+        let method_call =
+            |receiver: AstExpression, name: &str, args: Vec<AstExpression>| AstExpression {
+                body: AstExpressionBody::MethodCall(AstMethodCall {
+                    receiver_expr: Some(Box::new(receiver)),
+                    method_name: method_firstname(name),
+                    arg_exprs: args,
+                    type_args: vec![],
+                    has_block: false,
+                    may_have_paren_wo_args: false,
+                }),
+                primary: false,
+                locs: locs.clone(),
+            };
+        // Always start with a string literal so that the result is a new
+        // String object
+        let (mut expr, rest) = match parts.first() {
+            Some(StringPart::Literal(s)) => (str_literal(s.clone()), &parts[1..]),
+            _ => (str_literal("".to_string()), parts),
+        };
+        for part in rest {
+            let arg = match part {
+                StringPart::Literal(s) => str_literal(s.clone()),
+                StringPart::Expr { expr, inspect } => method_call(
+                    (**expr).clone(),
+                    if *inspect { "inspect" } else { "to_s" },
+                    vec![],
+                ),
+            };
+            expr = method_call(expr, "+", vec![arg]);
+        }
+        self.convert_expr(&expr)
+    }
+
     pub(super) fn register_string_literal(&mut self, content: &str) -> usize {
         let idx = self.str_literals.len();
         self.str_literals.push(content.to_string());
         idx
     }
 }
+
+/// Returns `E` if `ty` is `Result<V, E>`
+fn result_err_ty(ty: &TermTy) -> Option<&TermTy> {
+    match &ty.body {
+        TyBody::TyRaw(LitTy {
+            base_name,
+            type_args,
+            is_meta: false,
+        }) if base_name == "Result" => type_args.get(1),
+        _ => None,
+    }
+}
+
+/// Returns `Class` if `ty` is a metaclass (eg. `[Int, Float]` is an
+/// `Array<Class>`)
+fn class_as_instance(ty: &TermTy) -> TermTy {
+    if ty.is_metaclass() {
+        ty::raw("Class")
+    } else {
+        ty.clone()
+    }
+}
@@ -56,12 +56,37 @@ pub fn convert_block(
             is_fn,
         } => {
             debug_assert!(!is_fn);
-            _convert_block(mk, block_taker, inf, params, exprs)
+            _convert_block(mk, block_taker, inf, params, exprs, &arg_expr.locs)
         }
         _ => panic!("expected LambdaExpr but got {:?}", arg_expr),
     }
 }
 
+/// Returns the types of the block parameters which have type annotations.
+/// `arg_expr` must be a LambdaExpr.
+pub fn annotated_param_tys(
+    mk: &mut HirMaker,
+    arg_expr: &AstExpression,
+) -> Result<Vec<Option<TermTy>>> {
+    let params = match &arg_expr.body {
+        AstExpressionBody::LambdaExpr { params, .. } => params,
+        _ => panic!("expected LambdaExpr but got {:?}", arg_expr),
+    };
+    let namespace = mk.ctx_stack.const_scopes().next().unwrap();
+    let class_typarams = mk.ctx_stack.current_class_typarams();
+    let method_typarams = mk.ctx_stack.current_method_typarams();
+    params
+        .iter()
+        .map(|param| match &param.opt_typ {
+            Some(typ) => mk
+                .class_dict
+                .resolve_typename(&namespace, &class_typarams, &method_typarams, typ)
+                .map(Some),
+            None => Ok(None),
+        })
+        .collect()
+}
+
 /// Convert a block to HirLambdaExpr
 /// Types of block parameters are inferred from `block_ty` (arg_ty1, arg_ty2, ..., ret_ty) if not
 /// specified.
@@ -71,6 +96,7 @@ fn _convert_block(
     inf: &method_call_inf::MethodCallInf2,
     params: &[shiika_ast::BlockParam],
     body_exprs: &[AstExpression],
+    locs: &LocationSpan,
 ) -> Result<HirExpression> {
     type_checking::check_block_arity(block_taker, inf, params)?;
 
@@ -90,14 +116,15 @@ fn _convert_block(
     let hir_exprs = mk.convert_exprs(body_exprs)?;
     let mut lambda_ctx = mk.ctx_stack.pop_lambda_ctx();
     Ok(Hir::lambda_expr(
-        lambda_ty(&hir_params, &hir_exprs.ty),
+        lambda_ty(&hir_params, hir_exprs.ty()),
         mk.create_lambda_name(),
         hir_params,
         hir_exprs,
         mk._resolve_lambda_captures(lambda_ctx.captures), // hir_captures
         extract_lvars(&mut lambda_ctx.lvars),             // lvars
         lambda_ctx.has_break,
-        LocationSpan::todo(),
+        lambda_ctx.has_continue,
+        locs.clone(),
     ))
 }
 
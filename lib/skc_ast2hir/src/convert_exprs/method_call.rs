@@ -5,8 +5,12 @@ use crate::hir_maker::HirMaker;
 use crate::type_inference::method_call_inf;
 use crate::type_system::type_checking;
 use anyhow::{Context, Result};
-use shiika_ast::{AstExpression, LocationSpan};
-use shiika_core::{names::MethodFirstname, ty, ty::TermTy};
+use shiika_ast::{AstExpression, AstExpressionBody, LocationSpan};
+use shiika_core::{
+    names::{method_firstname, MethodFirstname, TypeFullname},
+    ty,
+    ty::{LitTy, TermTy, TyBody},
+};
 use skc_hir::*;
 
 pub fn convert_method_call(
@@ -18,10 +22,22 @@ pub fn convert_method_call(
     type_args: &[AstExpression],
     locs: &LocationSpan,
 ) -> Result<HirExpression> {
+    let (mut arg_exprs, keyword_args) = split_keyword_args(arg_exprs);
+    let splat_expr = take_splat_arg(&mut arg_exprs, *has_block)?;
+
     // Check if this is a lambda invocation
     if receiver_expr.is_none() {
         if let Some(lvar) = mk._lookup_var(&method_name.0, locs.clone()) {
-            if let Some(hir) = convert_lambda_invocation(mk, arg_exprs, has_block, locs, lvar)? {
+            if let Some(hir) = convert_lambda_invocation(mk, &arg_exprs, has_block, locs, lvar)? {
+                if let Some((name, _)) = keyword_args.first() {
+                    return Err(error::type_error(format!(
+                        "keyword argument `{}' is given to a Fn",
+                        name
+                    )));
+                }
+                if splat_expr.is_some() {
+                    return Err(error::type_error("splat argument is given to a Fn"));
+                }
                 return Ok(hir);
             }
         }
@@ -30,7 +46,7 @@ pub fn convert_method_call(
     let receiver_hir = match receiver_expr {
         Some(expr) => mk.convert_expr(expr)?,
         // Implicit self
-        _ => mk.convert_self_expr(&LocationSpan::todo()),
+        _ => mk.convert_self_expr(locs),
     };
 
     let mut method_tyargs = vec![];
@@ -38,10 +54,50 @@ pub fn convert_method_call(
         method_tyargs.push(resolve_method_tyarg(mk, tyarg)?);
     }
 
-    let found = mk
-        .class_dict
-        .lookup_method(&receiver_hir.ty, method_name, method_tyargs.as_slice())?
-        .clone();
+    let lookup =
+        mk.class_dict
+            .lookup_method(&receiver_hir.ty, method_name, method_tyargs.as_slice());
+    let mut found = match lookup {
+        Ok(found) => found,
+        Err(_) => {
+            let simple_call = type_args.is_empty()
+                && !*has_block
+                && keyword_args.is_empty()
+                && splat_expr.is_none();
+            match lookup_method_missing(mk, &receiver_hir.ty) {
+                Some(mm) if simple_call => {
+                    let args = &arg_exprs;
+                    return convert_method_missing(mk, mm, receiver_hir, method_name, args, locs);
+                }
+                _ if is_operator(method_name) => {
+                    return Err(error::undefined_operator(
+                        &method_name.0,
+                        &receiver_hir.ty,
+                        locs,
+                    ))
+                }
+                _ => {
+                    let receiver_locs = receiver_expr.as_ref().map(|_| &receiver_hir.locs);
+                    let similar = mk
+                        .class_dict
+                        .similar_method_name(&receiver_hir.ty, method_name);
+                    return Err(error::undefined_method(
+                        method_name,
+                        &receiver_hir.ty,
+                        locs,
+                        receiver_locs,
+                        similar,
+                    ));
+                }
+            }
+        }
+    };
+    if found.sig.self_type {
+        // eg. The block of `Object#tap` takes the receiver as is
+        for param in found.sig.params.iter_mut() {
+            param.ty = replace_ty(&param.ty, &found.owner, &receiver_hir.ty);
+        }
+    }
     if type_args.len() > 0 && type_args.len() != found.sig.typarams.len() {
         return Err(error::type_error(format!(
             "wrong number of method-wise type arguments ({} for {:?}",
@@ -58,24 +114,282 @@ pub fn convert_method_call(
         None
     };
     let msg = format!("Type inferrence failed: {:?}", inf1);
-    let (arg_hirs, inf3) = convert_method_args(
+    let (mut arg_hirs, inf3) = convert_method_args(
         mk,
         inf1,
         &BlockTaker::Method {
             sig: found.sig.clone(),
             locs,
         },
-        arg_exprs,
+        &arg_exprs,
         has_block,
     )
     .context(msg)?;
-    build(mk, found, receiver_hir, arg_hirs, inf3)
+    if let Some(inf) = &inf3 {
+        if !inf.solved_method_tyargs.is_empty() {
+            // Now the call is the same as `foo<Int>(...)`
+            method_tyargs = inf.solved_method_tyargs.clone();
+            found.specialize(Default::default(), &method_tyargs);
+        }
+    }
+    type_checking::check_method_tyargs(&mk.class_dict, &found.sig, &method_tyargs)?;
+    let splat_hir = match splat_expr {
+        Some(expr) => Some(mk.convert_expr(expr)?),
+        None => None,
+    };
+    let mut keyword_hirs = vec![];
+    for (name, expr) in keyword_args {
+        keyword_hirs.push((name, mk.convert_expr(expr)?));
+    }
+
+    // Put the arguments to the position of the corresponding parameter
+    let n_params = found.sig.params.len();
+    let mut given = vec![];
+    let mut block_hir = None;
+    let variadic_idx = found.sig.params.iter().position(|p| p.is_variadic);
+    // The block is passed to the last param (not packed into the variadic one)
+    if *has_block && variadic_idx.map(|v| v + 1) != Some(n_params) {
+        block_hir = arg_hirs.pop();
+    }
+    if let Some(v) = variadic_idx {
+        let extras = if arg_hirs.len() > v {
+            arg_hirs.split_off(v)
+        } else {
+            vec![]
+        };
+        let ary = match splat_hir {
+            Some(hir) => {
+                if arg_hirs.len() != v || !extras.is_empty() {
+                    return Err(error::type_error(format!(
+                        "splat argument should be passed to `*{}' of {}",
+                        found.sig.params[v].name, found.sig.fullname
+                    )));
+                }
+                hir
+            }
+            None => variadic_array(mk, &found.sig, &found.sig.params[v], extras, locs)?,
+        };
+        given.extend(arg_hirs.into_iter().enumerate());
+        given.push((v, ary));
+    } else {
+        if splat_hir.is_some() {
+            return Err(error::type_error(format!(
+                "{} does not have a variadic parameter",
+                found.sig.fullname
+            )));
+        }
+        if arg_hirs.len() > n_params {
+            // Let `build` report the wrong number of arguments
+            arg_hirs.extend(block_hir);
+            return build(mk, found, receiver_hir, arg_hirs, inf3);
+        }
+        given.extend(arg_hirs.into_iter().enumerate());
+    }
+    for (name, hir) in keyword_hirs {
+        // Note: the name of an iparam (eg. `@a`) is prefixed with `@`
+        let idx = match found
+            .sig
+            .params
+            .iter()
+            .position(|p| p.name.trim_start_matches('@') == name)
+        {
+            Some(idx) => idx,
+            None => {
+                return Err(error::type_error(format!(
+                    "{} does not have a parameter named `{}'",
+                    found.sig.fullname, name
+                )))
+            }
+        };
+        if given.iter().any(|(i, _)| *i == idx) {
+            return Err(error::type_error(format!(
+                "argument `{}' of {} is given more than once",
+                name, found.sig.fullname
+            )));
+        }
+        given.push((idx, hir));
+    }
+    if let Some(hir) = block_hir {
+        // The block is always the last argument
+        given.push((n_params - 1, hir));
+    }
+
+    if given.len() == n_params && given.iter().enumerate().all(|(i, (j, _))| i == *j) {
+        let arg_hirs = given.into_iter().map(|(_, hir)| hir).collect();
+        return build(mk, found, receiver_hir, arg_hirs, inf3);
+    }
+    build_with_arg_slots(mk, found, receiver_hir, given, inf3, locs)
+}
+
+/// Returns true if `name` is an operator like `+` or `[]=`
+fn is_operator(name: &MethodFirstname) -> bool {
+    match name.0.chars().next() {
+        Some(c) => !c.is_ascii_alphabetic() && c != '_',
+        None => false,
+    }
+}
+
+/// Returns `#method_missing` of the receiver, if any
+fn lookup_method_missing(mk: &HirMaker, receiver_ty: &TermTy) -> Option<FoundMethod> {
+    mk.class_dict
+        .lookup_method(receiver_ty, &method_firstname("method_missing"), &[])
+        .ok()
+}
+
+/// Convert a call of an undefined method `foo(a, b)` into
+/// `method_missing("foo", [a, b])` (the arguments are passed as
+/// `Array<Object>`.)
+fn convert_method_missing(
+    mk: &mut HirMaker,
+    found: FoundMethod,
+    receiver_hir: HirExpression,
+    method_name: &MethodFirstname,
+    arg_exprs: &[&AstExpression],
+    locs: &LocationSpan,
+) -> Result<HirExpression> {
+    let mut arg_hirs = vec![];
+    for expr in arg_exprs {
+        arg_hirs.push(mk.convert_expr(expr)?);
+    }
+    let name_hir = mk.convert_string_literal(&method_name.0, locs);
+    let args_hir = mk.create_array_instance_(arg_hirs, ty::raw("Object"), locs.clone());
+    build(mk, found, receiver_hir, vec![name_hir, args_hir], None)
+}
+
+/// Separate keyword arguments (eg. `x: 1`) from `arg_exprs`
+fn split_keyword_args(
+    arg_exprs: &[AstExpression],
+) -> (Vec<&AstExpression>, Vec<(&String, &AstExpression)>) {
+    let mut positional = vec![];
+    let mut keyword = vec![];
+    for arg in arg_exprs {
+        match &arg.body {
+            AstExpressionBody::KeywordArg { name, expr } => keyword.push((name, &**expr)),
+            _ => positional.push(arg),
+        }
+    }
+    (positional, keyword)
+}
+
+/// Take the splat argument (eg. `*x`) out of `arg_exprs`, if any
+fn take_splat_arg<'a>(
+    arg_exprs: &mut Vec<&'a AstExpression>,
+    has_block: bool,
+) -> Result<Option<&'a AstExpression>> {
+    let i = match arg_exprs
+        .iter()
+        .position(|arg| matches!(arg.body, AstExpressionBody::Splat { .. }))
+    {
+        Some(i) => i,
+        None => return Ok(None),
+    };
+    // Must be the last one (except the block)
+    let last = if has_block { 2 } else { 1 };
+    if i + last != arg_exprs.len() {
+        return Err(error::syntax_error("splat argument must be the last one"));
+    }
+    match &arg_exprs.remove(i).body {
+        AstExpressionBody::Splat { expr } => Ok(Some(expr)),
+        _ => panic!("must not happen"),
+    }
+}
+
+/// Create an array of the arguments for the variadic parameter `param`
+fn variadic_array(
+    mk: &mut HirMaker,
+    sig: &MethodSignature,
+    param: &MethodParam,
+    item_hirs: Vec<HirExpression>,
+    locs: &LocationSpan,
+) -> Result<HirExpression> {
+    let item_ty = param.ty.tyargs()[0].clone();
+    for item_hir in &item_hirs {
+        if !mk.class_dict.conforms(&item_hir.ty, &item_ty) {
+            return Err(error::type_error(format!(
+                "the argument for `*{}' of {} should be {} but got {}",
+                param.name, sig.fullname, item_ty, item_hir.ty
+            )));
+        }
+    }
+    Ok(mk.create_array_instance_(item_hirs, item_ty, locs.clone()))
+}
+
+/// Build a method call from the arguments given as `(param_idx, expr)`.
+/// Omitted arguments are passed as `HirOmittedArg` and the callee computes
+/// their default values (see `default_params`.)
+///
+/// eg. `a.foo(y: b)` => `(tmp1 = a; tmp2 = b; tmp1.foo(<omitted>, tmp2))`
+fn build_with_arg_slots(
+    mk: &mut HirMaker,
+    found: FoundMethod,
+    receiver_hir: HirExpression,
+    given: Vec<(usize, HirExpression)>,
+    inf: Option<method_call_inf::MethodCallInf3>,
+    locs: &LocationSpan,
+) -> Result<HirExpression> {
+    let n_params = found.sig.params.len();
+    let mut omitted = vec![];
+    for (i, param) in found.sig.params.iter().enumerate() {
+        if given.iter().any(|(j, _)| *j == i) {
+            continue;
+        }
+        let method_name = &found.sig.fullname.first_name;
+        if mk
+            .class_dict
+            .find_param_default(&found.owner, method_name, i)
+            .is_none()
+        {
+            return Err(error::type_error(format!(
+                "missing argument `{}' of {}",
+                param.name, found.sig.fullname
+            )));
+        }
+        omitted.push((i, Hir::omitted_arg(param.ty.clone(), locs.clone())));
+    }
+
+    // Store the receiver and the arguments to temporary variables so that
+    // they are evaluated only once and in the order of appearance
+    let mut exprs = vec![];
+    let mut slots = vec![None; n_params];
+    let receiver_ref = bind_tmp(mk, &mut exprs, receiver_hir, locs);
+    for (i, hir) in given {
+        slots[i] = Some(bind_tmp(mk, &mut exprs, hir, locs));
+    }
+    for (i, hir) in omitted {
+        slots[i] = Some(hir);
+    }
+    let arg_hirs = slots.into_iter().map(|x| x.unwrap()).collect();
+    exprs.push(build(mk, found, receiver_ref, arg_hirs, inf)?);
+    Ok(Hir::parenthesized_expression(
+        Hir::expressions_from_body(exprs),
+        locs.clone(),
+    ))
+}
+
+/// Push `tmp = expr` to `exprs` and returns `tmp`
+/// (Blocks are returned as is because `check_break_in_block` needs them)
+fn bind_tmp(
+    mk: &mut HirMaker,
+    exprs: &mut Vec<HirExpression>,
+    expr: HirExpression,
+    locs: &LocationSpan,
+) -> HirExpression {
+    if matches!(expr.node, HirExpressionBase::HirLambdaExpr { .. }) {
+        return expr;
+    }
+    let tmp_name = mk.generate_lvar_name("arg");
+    let readonly = true;
+    mk.ctx_stack
+        .declare_lvar(&tmp_name, expr.ty.clone(), readonly);
+    let tmp_ref = Hir::lvar_ref(expr.ty.clone(), tmp_name.clone(), locs.clone());
+    exprs.push(Hir::lvar_assign(tmp_name, expr, locs.clone()));
+    tmp_ref
 }
 
 /// Returns `Some` if the method call is a lambda invocation.
 fn convert_lambda_invocation(
     mk: &mut HirMaker,
-    arg_exprs: &[AstExpression],
+    arg_exprs: &[&AstExpression],
     has_block: &bool,
     locs: &LocationSpan,
     lvar: LVarInfo,
@@ -121,7 +435,7 @@ fn convert_method_args(
     mk: &mut HirMaker,
     inf: Option<method_call_inf::MethodCallInf1>,
     block_taker: &BlockTaker,
-    arg_exprs: &[AstExpression],
+    arg_exprs: &[&AstExpression],
     has_block: &bool,
 ) -> Result<(Vec<HirExpression>, Option<method_call_inf::MethodCallInf3>)> {
     let n = arg_exprs.len();
@@ -129,24 +443,92 @@ fn convert_method_args(
     if *has_block && inf.is_some() {
         if n > 1 {
             for i in 0..n - 1 {
-                arg_hirs.push(mk.convert_expr(&arg_exprs[i])?);
+                arg_hirs.push(mk.convert_expr(arg_exprs[i])?);
             }
         }
-        let last_arg = &arg_exprs.last().unwrap();
+        let last_arg = arg_exprs.last().unwrap();
 
         let arg_tys = arg_hirs.iter().map(|x| &x.ty).collect::<Vec<_>>();
-        let inf2 = method_call_inf::infer_block_param(inf.unwrap(), &arg_tys)?;
-        let block_hir = block::convert_block(mk, block_taker, &inf2, &last_arg)?;
+        let annotated = block::annotated_param_tys(mk, last_arg)?;
+        let inf2 = method_call_inf::infer_block_param(inf.unwrap(), &arg_tys, &annotated)?;
+        let block_hir = block::convert_block(mk, block_taker, &inf2, last_arg)?;
         let inf3 = method_call_inf::infer_result_ty_with_block(inf2, &block_hir.ty)?;
 
         arg_hirs.push(block_hir);
         Ok((arg_hirs, Some(inf3)))
     } else {
         for expr in arg_exprs {
-            arg_hirs.push(mk.convert_expr(&expr)?);
+            arg_hirs.push(mk.convert_expr(expr)?);
+        }
+        let inf3 = match inf {
+            Some(inf1) => {
+                let arg_tys = arg_hirs.iter().map(|x| &x.ty).collect::<Vec<_>>();
+                Some(method_call_inf::infer_method_tyargs(inf1, &arg_tys)?)
+            }
+            None => None,
+        };
+        Ok((arg_hirs, inf3))
+    }
+}
+
+/// Convert `super(args)` into a call of the method of the same name in the
+/// superclass. The method is called directly (not via the vtable.)
+pub fn convert_super_call(
+    mk: &mut HirMaker,
+    arg_exprs: &[AstExpression],
+    locs: &LocationSpan,
+) -> Result<HirExpression> {
+    let sig = match mk.ctx_stack.method_ctx() {
+        Some(method_ctx) if !method_ctx.signature.is_class_method() => method_ctx.signature.clone(),
+        _ => {
+            return Err(error::program_error(
+                "`super' can only be used in instance methods",
+            ))
+        }
+    };
+    let method_name = &sig.fullname.first_name;
+    let self_ty = mk.ctx_stack.self_ty();
+    let (super_ty, found) = match mk.class_dict.supertype(&self_ty).and_then(|super_ty| {
+        let found = mk
+            .class_dict
+            .lookup_method(&super_ty, method_name, &[])
+            .ok()?;
+        Some((super_ty, found))
+    }) {
+        Some(x) => x,
+        None => {
+            return Err(error::program_error(format!(
+                "no method to call with `super' in {} (the superclass does not have `{}')",
+                sig.fullname, method_name
+            )))
+        }
+    };
+    match mk.class_dict.get_type(&found.owner) {
+        SkType::Module(_) => {
+            return Err(error::program_error(format!(
+                "`super' in {} cannot call {} (a method of a module)",
+                sig.fullname, found.sig.fullname
+            )))
+        }
+        SkType::Class(c) => {
+            if c.abstract_methods
+                .iter()
+                .any(|s| s.fullname == found.sig.fullname)
+            {
+                return Err(error::program_error(format!(
+                    "`super' in {} cannot call the abstract method {}",
+                    sig.fullname, found.sig.fullname
+                )));
+            }
         }
-        Ok((arg_hirs, None))
     }
+
+    let arg_hirs = arg_exprs
+        .iter()
+        .map(|expr| mk.convert_expr(expr))
+        .collect::<Result<Vec<_>>>()?;
+    let receiver_hir = Hir::bit_cast(super_ty, mk.convert_self_expr(locs));
+    build_(mk, found, receiver_hir, arg_hirs, None, false)
 }
 
 /// For method calls without any arguments.
@@ -166,15 +548,30 @@ pub fn build_simple(
 
 /// Check the arguments and create HirMethodCall or HirModuleMethodCall
 pub fn build(
+    mk: &HirMaker,
+    found: FoundMethod,
+    receiver_hir: HirExpression,
+    arg_hirs: Vec<HirExpression>,
+    inf: Option<method_call_inf::MethodCallInf3>,
+) -> Result<HirExpression> {
+    build_(mk, found, receiver_hir, arg_hirs, inf, true)
+}
+
+/// Create HirSuperMethodCall if `dispatch` is false
+fn build_(
     mk: &HirMaker,
     found: FoundMethod,
     receiver_hir: HirExpression,
     mut arg_hirs: Vec<HirExpression>,
     inf: Option<method_call_inf::MethodCallInf3>,
+    dispatch: bool,
 ) -> Result<HirExpression> {
     check_argument_types(mk, &found.sig, &receiver_hir, &mut arg_hirs, inf)?;
+    check_sortable(mk, &found.sig, &receiver_hir, &arg_hirs)?;
+    check_only_for(mk, &found, &receiver_hir.ty)?;
     let specialized = receiver_hir.ty.is_specialized();
     let first_arg_ty = arg_hirs.get(0).map(|x| x.ty.clone());
+    let receiver_ty = receiver_hir.ty.clone();
 
     let owner = mk.class_dict.get_type(&found.owner);
     let receiver = Hir::bit_cast(owner.erasure().to_term_ty(), receiver_hir);
@@ -187,9 +584,24 @@ pub fn build(
         arg_hirs
     };
 
-    let hir = build_hir(&found, &owner, receiver, args);
+    let hir = if dispatch {
+        build_hir(&found, &owner, receiver, args)
+    } else {
+        Hir::super_method_call(
+            found.sig.ret_ty.clone(),
+            receiver,
+            found.sig.fullname.clone(),
+            args,
+        )
+    };
     if found.sig.fullname.full_name == "Object#unsafe_cast" {
         Ok(Hir::bit_cast(first_arg_ty.unwrap().instance_ty(), hir))
+    } else if found.sig.self_type {
+        // Returns an object of the same class as the receiver
+        Ok(Hir::bit_cast(
+            replace_ty(&found.sig.ret_ty, &found.owner, &receiver_ty),
+            hir,
+        ))
     } else if specialized {
         Ok(Hir::bit_cast(found.sig.ret_ty, hir))
     } else {
@@ -197,6 +609,101 @@ pub fn build(
     }
 }
 
+/// Replace the type of `owner` in `t` (including the type arguments) with
+/// `to`. The owner may be generic (eg. `Array<T>`)
+fn replace_ty(t: &TermTy, owner: &TypeFullname, to: &TermTy) -> TermTy {
+    match &t.body {
+        TyBody::TyRaw(LitTy {
+            base_name, is_meta, ..
+        }) if !is_meta && base_name == &owner.0 => to.clone(),
+        TyBody::TyRaw(LitTy {
+            base_name,
+            type_args,
+            is_meta,
+        }) if !type_args.is_empty() => {
+            let args = type_args.iter().map(|x| replace_ty(x, owner, to)).collect();
+            ty::new(base_name, args, *is_meta)
+        }
+        _ => t.clone(),
+    }
+}
+
+/// `Array#sort`, etc. need `<` of the elements (or the sort keys.)
+/// This is checked here because bounded type parameters are not supported
+/// yet (`Object#<` exists but panics.)
+fn check_sortable(
+    mk: &HirMaker,
+    sig: &MethodSignature,
+    receiver_hir: &HirExpression,
+    arg_hirs: &[HirExpression],
+) -> Result<()> {
+    let key_ty = match sig.fullname.full_name.as_str() {
+        "Array#sort" | "Array#sort_inplace" => receiver_hir.ty.tyargs().first(),
+        // The return type of the block
+        "Array#sort_by" => arg_hirs.first().and_then(|f| f.ty.tyargs().last()),
+        _ => return Ok(()),
+    };
+    let key_ty = match key_ty {
+        Some(t) if !t.is_typaram_ref() => t,
+        _ => return Ok(()),
+    };
+    let found = mk
+        .class_dict
+        .lookup_method(key_ty, &method_firstname("<"), &[])?;
+    if found.owner.0 == "Object" {
+        return Err(error::type_error(format!(
+            "{} needs `<' but {} does not define it",
+            sig.fullname, key_ty
+        )));
+    }
+    Ok(())
+}
+
+/// Check the receiver has the type arguments required by `@only_for`
+/// (eg. `Range#each` is only available for `Range<Int>`.) A generic class
+/// without type arguments (eg. `@only_for(Array)`) accepts any of them.
+/// The methods of `Enumerable` are also checked because they call `#each`.
+fn check_only_for(mk: &HirMaker, found: &FoundMethod, receiver_ty: &TermTy) -> Result<()> {
+    let restricted = if found.owner.0 == "Enumerable" {
+        match mk
+            .class_dict
+            .lookup_method(receiver_ty, &method_firstname("each"), &[])
+        {
+            Ok(each) if !each.sig.only_for.is_empty() => each.sig,
+            _ => return Ok(()),
+        }
+    } else if !found.sig.only_for.is_empty() {
+        found.sig.clone()
+    } else {
+        return Ok(());
+    };
+    let tyargs = receiver_ty.tyargs();
+    // Cannot be checked in generic code
+    if tyargs.iter().any(|t| t.contains_typaram_ref()) {
+        return Ok(());
+    }
+    let matches = tyargs.len() == restricted.only_for.len()
+        && tyargs
+            .iter()
+            .zip(&restricted.only_for)
+            .all(|(t, r)| t == r || (r.tyargs().is_empty() && t.same_base(r)));
+    if !matches {
+        let names = restricted
+            .only_for
+            .iter()
+            .map(|t| t.to_string())
+            .collect::<Vec<_>>();
+        return Err(error::type_error(format!(
+            "{} is only available for {}<{}> (receiver: {})",
+            restricted.fullname,
+            restricted.fullname.type_name,
+            names.join(", "),
+            receiver_ty
+        )));
+    }
+    Ok(())
+}
+
 fn check_argument_types(
     mk: &HirMaker,
     sig: &MethodSignature,
@@ -206,27 +713,38 @@ fn check_argument_types(
 ) -> Result<()> {
     type_checking::check_method_args(&mk.class_dict, sig, receiver_hir, arg_hirs, inf)?;
     if let Some(last_arg) = arg_hirs.last_mut() {
-        check_break_in_block(sig, last_arg)?;
+        check_exit_in_block(sig, last_arg)?;
     }
     Ok(())
 }
 
-/// Check if `break` in block is valid
-fn check_break_in_block(sig: &MethodSignature, last_arg: &mut HirExpression) -> Result<()> {
-    if let HirExpressionBase::HirLambdaExpr { has_break, .. } = last_arg.node {
-        if has_break {
-            if sig.ret_ty == ty::raw("Void") {
-                match &mut last_arg.node {
-                    HirExpressionBase::HirLambdaExpr { ret_ty, .. } => {
-                        std::mem::swap(ret_ty, &mut ty::raw("Void"));
-                    }
-                    _ => panic!("[BUG] unexpected type"),
+/// Check if `break` or `continue` in block is valid
+fn check_exit_in_block(sig: &MethodSignature, last_arg: &mut HirExpression) -> Result<()> {
+    if let HirExpressionBase::HirLambdaExpr {
+        has_break,
+        has_continue,
+        ..
+    } = last_arg.node
+    {
+        let keyword = if has_break {
+            "break"
+        } else if has_continue {
+            "continue"
+        } else {
+            return Ok(());
+        };
+        if sig.ret_ty == ty::raw("Void") {
+            match &mut last_arg.node {
+                HirExpressionBase::HirLambdaExpr { ret_ty, .. } => {
+                    std::mem::swap(ret_ty, &mut ty::raw("Void"));
                 }
-            } else {
-                return Err(error::program_error(
-                    "`break' not allowed because this block is expected to return a value",
-                ));
+                _ => panic!("[BUG] unexpected type"),
             }
+        } else {
+            return Err(error::program_error(format!(
+                "`{}' not allowed because this block is expected to return a value",
+                keyword
+            )));
         }
     }
     Ok(())
@@ -13,6 +13,7 @@ use crate::class_dict::ClassDict;
 use crate::convert_exprs::MethodParam;
 use crate::type_inference::method_call_inf;
 use anyhow::Result;
+use shiika_ast::LocationSpan;
 use shiika_core::names::Namespace;
 use shiika_core::ty::{self};
 
@@ -30,7 +31,11 @@ pub fn convert_params(
             class_dict.resolve_typename(namespace, class_typarams, method_typarams, &param.typ)?;
         hir_params.push(MethodParam {
             name: param.name.to_string(),
-            ty: ty.clone(),
+            ty: if param.is_variadic { ty::ary(ty) } else { ty },
+            is_variadic: param.is_variadic,
+            has_default: param.default_expr.is_some(),
+            default_expr: param.default_expr.clone(),
+            locs: param.locs.clone(),
         });
     }
     Ok(hir_params)
@@ -59,6 +64,10 @@ pub fn convert_block_params(
             MethodParam {
                 name: param.name.to_string(),
                 ty: ty.clone(),
+                is_variadic: false,
+                has_default: false,
+                default_expr: None,
+                locs: typ.locs.clone(),
             }
         } else {
             // Infer from hint
@@ -70,6 +79,10 @@ pub fn convert_block_params(
             MethodParam {
                 name: param.name.to_string(),
                 ty: ty.clone(),
+                is_variadic: false,
+                has_default: false,
+                default_expr: None,
+                locs: LocationSpan::internal(),
             }
         };
         hir_params.push(hir_param);
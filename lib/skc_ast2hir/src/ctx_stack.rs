@@ -31,6 +31,11 @@ impl CtxStack {
         self.vec.push(c);
     }
 
+    /// Remove the ctxs above the first `len` ones
+    pub fn truncate(&mut self, len: usize) {
+        self.vec.truncate(len);
+    }
+
     /// Pop a ctx
     fn pop(&mut self) -> HirMakerContext {
         let c = self.vec.pop().expect("[BUG] no ctx to pop");
@@ -197,14 +202,28 @@ impl CtxStack {
     pub fn declare_lvar(&mut self, name: &str, ty: TermTy, readonly: bool) {
         let lvars = self.current_lvars_mut();
         let k = name.to_string();
+        // Redeclaration must not forget that the variable is captured
+        let captured = lvars.get(name).map_or(false, |v| v.captured);
         let v = CtxLVar {
             name: name.to_string(),
             ty,
             readonly,
+            captured,
         };
         lvars.insert(k, v);
     }
 
+    /// Mark the lvar `name` as captured by a lambda. The innermost one is
+    /// marked if there are more than one
+    pub fn set_lvar_captured(&mut self, name: &str) {
+        for ctx in self.vec.iter_mut().rev() {
+            if let Some(lvar) = ctx.opt_lvars().and_then(|lvars| lvars.get_mut(name)) {
+                lvar.captured = true;
+                return;
+            }
+        }
+    }
+
     /// Returns if we're in an `#initialize`
     pub fn in_initializer(&self) -> bool {
         if let Some(method_ctx) = self.method_ctx() {
@@ -214,9 +233,43 @@ impl CtxStack {
         }
     }
 
-    /// Push a LambdaCapture to captures
+    /// Returns the index of the lambda ctx which owns the scope at `idx`
+    /// (i.e. the llvm function where the lvars of the scope live.)
+    /// None if the scope does not belong to a lambda (method, toplevel, etc.)
+    pub fn scope_owner(&self, idx: usize) -> Option<usize> {
+        for i in (0..=idx).rev() {
+            match self.get(i) {
+                HirMakerContext::Lambda(_) => return Some(i),
+                HirMakerContext::MatchClause(_) | HirMakerContext::While(_) => (),
+                HirMakerContext::Toplevel(_)
+                | HirMakerContext::Class(_)
+                | HirMakerContext::Method(_) => return None,
+            }
+        }
+        None
+    }
+
+    /// `scope_owner` of the current scope
+    pub fn current_scope_owner(&self) -> Option<usize> {
+        self.scope_owner(self.len() - 1)
+    }
+
+    /// Returns the index of `cap` in the captures of the current lambda
+    /// (or the index it will get if not captured yet)
+    pub fn lambda_capture_idx(&self, cap: &LambdaCapture) -> usize {
+        let lambda_ctx = self.lambda_ctx().expect("not in lambda");
+        lambda_ctx
+            .captured_var(cap)
+            .unwrap_or(lambda_ctx.captures.len())
+    }
+
+    /// Push a LambdaCapture to captures unless the same variable is already
+    /// captured. Returns the index in the captures
     pub fn push_lambda_capture(&mut self, cap: LambdaCapture) -> usize {
         let lambda_ctx = self.lambda_ctx_mut().expect("not in lambda");
+        if let Some(idx) = lambda_ctx.captured_var(&cap) {
+            return idx;
+        }
         lambda_ctx.captures.push(cap);
         lambda_ctx.captures.len() - 1
     }
@@ -247,7 +300,7 @@ impl CtxStack {
         if let Some(method_ctx) = self.method_ctx() {
             let typarams = &method_ctx.signature.typarams;
             if let Some(i) = typarams.iter().position(|t| *name == *t.name) {
-                return Some(ty::typaram_ref(name, ty::TyParamKind::Method, i));
+                return Some(typarams[i].to_ref(ty::TyParamKind::Method, i));
             }
             if let Some(class_ctx) = self.class_ctx() {
                 if method_ctx.signature.fullname.is_class_method() {
@@ -255,7 +308,7 @@ impl CtxStack {
                 }
                 let typarams = &class_ctx.typarams;
                 if let Some(i) = typarams.iter().position(|t| *name == *t.name) {
-                    return Some(ty::typaram_ref(name, ty::TyParamKind::Class, i));
+                    return Some(typarams[i].to_ref(ty::TyParamKind::Class, i));
                 }
             }
         }
@@ -318,8 +371,21 @@ impl<'hir_maker> LVarIter<'hir_maker> {
     }
 }
 
+impl<'a> LVarIter<'a> {
+    /// Move to the next outer scope (skipping `While`s)
+    fn advance(&mut self) {
+        loop {
+            self.cur -= 1;
+            if !matches!(self.ctx_stack.get(self.cur), HirMakerContext::While(_)) {
+                break;
+            }
+        }
+    }
+}
+
 impl<'a> Iterator for LVarIter<'a> {
-    /// Yields `(lvars, params, depth)`
+    /// Yields `(lvars, params, owner)` where `owner` is the index of
+    /// the lambda ctx which the scope belongs to (see `scope_owner`)
     type Item = (
         &'a HashMap<String, CtxLVar>,
         &'a [MethodParam],
@@ -348,14 +414,15 @@ impl<'a> Iterator for LVarIter<'a> {
             }
             HirMakerContext::Lambda(lambda_ctx) => {
                 let idx = self.cur;
-                self.cur -= 1;
+                self.advance();
                 Some((&lambda_ctx.lvars, &lambda_ctx.params, Some(idx)))
             }
             HirMakerContext::MatchClause(match_clause_ctx) => {
-                self.cur -= 1;
-                Some((&match_clause_ctx.lvars, &[], None))
+                let owner = self.ctx_stack.scope_owner(self.cur);
+                self.advance();
+                Some((&match_clause_ctx.lvars, &[], owner))
             }
-            // ::new() never sets `While` to .cur
+            // `advance` never sets `While` to .cur
             HirMakerContext::While(_) => panic!("must not happen"),
         }
     }
@@ -0,0 +1,192 @@
+//! Default values of method parameters.
+//!
+//! The default value is stored in `MethodParam` and computed in the callee.
+//! The caller passes a placeholder (`HirOmittedArg`) for an omitted argument
+//! and the method begins with binding the params which have a default value.
+//!
+//! ```sk
+//! class A
+//!   def greet(name: String, greeting: String = "Hello, #{name}")
+//!     ...
+//! # is compiled like
+//! class A
+//!   def greet(name: String, greeting: String)
+//!     let greeting = (the argument or "Hello, #{name}" if omitted)
+//!     ...
+//! ```
+//!
+//! The default value may refer to the preceding params. A method which
+//! overrides another one inherits the default values unless it has its own.
+use crate::error;
+use crate::hir_maker::HirMaker;
+use anyhow::Result;
+use shiika_ast::{AstMethodSignature, Definition, TopLevelItem};
+use shiika_core::names::TypeFullname;
+use skc_hir::*;
+
+/// Check the default values in `items` are placed properly
+pub fn validate(items: &[TopLevelItem]) -> Result<()> {
+    for item in items {
+        if let TopLevelItem::Def(def) = item {
+            validate_def(def)?;
+        }
+    }
+    Ok(())
+}
+
+fn validate_def(def: &Definition) -> Result<()> {
+    match def {
+        Definition::ClassDefinition { defs, .. }
+        | Definition::ModuleDefinition { defs, .. }
+        | Definition::ExtensionDefinition { defs, .. } => validate_defs(defs),
+        Definition::EnumDefinition { cases, defs, .. } => {
+            for case in cases.iter() {
+                if case.params.iter().any(|p| p.default_expr.is_some()) {
+                    return Err(error::program_error(format!(
+                        "enum case {} cannot have default values",
+                        case.name
+                    )));
+                }
+            }
+            validate_defs(defs)
+        }
+        _ => Ok(()),
+    }
+}
+
+fn validate_defs(defs: &[Definition]) -> Result<()> {
+    for def in defs {
+        match def {
+            Definition::InstanceMethodDefinition { sig, .. }
+            | Definition::ClassMethodDefinition { sig, .. } => check_order(sig)?,
+            Definition::InitializerDefinition(d) => check_order(&d.sig)?,
+            Definition::ClassInitializerDefinition(d) => check_no_defaults(&d.sig)?,
+            Definition::MethodRequirementDefinition { sig }
+            | Definition::AbstractMethodDefinition { sig } => check_no_defaults(sig)?,
+            _ => validate_def(def)?,
+        }
+    }
+    Ok(())
+}
+
+/// Check the params after one with a default value also have default values
+fn check_order(sig: &AstMethodSignature) -> Result<()> {
+    let mut seen_default = false;
+    for param in &sig.params {
+        if param.default_expr.is_some() {
+            seen_default = true;
+        } else if seen_default && !param.is_variadic {
+            return Err(error::program_error(format!(
+                "param `{}' of {} must have a default value because the preceding one has",
+                param.name, sig.name
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn check_no_defaults(sig: &AstMethodSignature) -> Result<()> {
+    if sig.params.iter().any(|p| p.default_expr.is_some()) {
+        return Err(error::program_error(format!(
+            "method {} cannot have default values",
+            sig.name
+        )));
+    }
+    Ok(())
+}
+
+impl<'hir_maker> HirMaker<'hir_maker> {
+    /// Returns the expressions to bind the params which have a default value
+    /// (must be called in the method context.)
+    /// An iparam `@a` is referred as `a` in the default values.
+    pub(super) fn convert_default_args(
+        &mut self,
+        type_fullname: &TypeFullname,
+        signature: &MethodSignature,
+    ) -> Result<Vec<HirExpression>> {
+        let method_name = &signature.fullname.first_name;
+        let defaults = (0..signature.params.len())
+            .map(|i| {
+                self.class_dict
+                    .find_param_default(type_fullname, method_name, i)
+            })
+            .collect::<Vec<_>>();
+        if defaults.iter().all(|d| d.is_none()) {
+            return Ok(vec![]);
+        }
+
+        let mut exprs = vec![];
+        for (idx, (param, default)) in signature.params.iter().zip(defaults).enumerate() {
+            let locs = param.locs.clone();
+            let value = match default {
+                Some(default_param) => {
+                    let ast_expr = default_param.default_expr.as_ref().ok_or_else(|| {
+                        error::program_error(format!(
+                            "{} cannot inherit the default value of `{}' from an imported method",
+                            signature.fullname, param.name
+                        ))
+                    })?;
+                    let default_hir = self.convert_expr(ast_expr)?;
+                    self._check_default_arg(signature, idx, &default_hir)?;
+                    let default_hir = if default_hir.ty.equals_to(&param.ty) {
+                        default_hir
+                    } else {
+                        Hir::bit_cast(param.ty.clone(), default_hir)
+                    };
+                    let value =
+                        Hir::arg_or_default(param.ty.clone(), idx, default_hir, locs.clone());
+                    // Shadow the param
+                    let readonly = true;
+                    self.ctx_stack
+                        .declare_lvar(&param.name, param.ty.clone(), readonly);
+                    exprs.push(Hir::lvar_assign(param.name.clone(), value, locs.clone()));
+                    Hir::lvar_ref(param.ty.clone(), param.name.clone(), locs.clone())
+                }
+                None => Hir::arg_ref(param.ty.clone(), idx, locs.clone()),
+            };
+            if let Some(name) = param.name.strip_prefix('@') {
+                let readonly = true;
+                self.ctx_stack
+                    .declare_lvar(name, param.ty.clone(), readonly);
+                exprs.push(Hir::lvar_assign(name.to_string(), value, locs));
+            }
+        }
+        Ok(exprs)
+    }
+
+    /// Check the default value of the `idx`th param
+    fn _check_default_arg(
+        &self,
+        signature: &MethodSignature,
+        idx: usize,
+        default_hir: &HirExpression,
+    ) -> Result<()> {
+        let param = &signature.params[idx];
+        if !self.class_dict.conforms(&default_hir.ty, &param.ty) {
+            return Err(error::type_error(format!(
+                "the default value of `{}' of {} should be {} but got {}",
+                param.name, signature.fullname, param.ty, default_hir.ty
+            )));
+        }
+        // The arguments after this one may be omitted
+        let mut refers_later = false;
+        visitor::walk_expr(default_hir, &mut |expr| match &expr.node {
+            HirExpressionBase::HirArgRef { idx: i } => refers_later |= *i >= idx,
+            HirExpressionBase::HirLambdaExpr { captures, .. } => {
+                for cap in captures {
+                    if let HirLambdaCapture::CaptureArg { idx: i } = cap {
+                        refers_later |= *i >= idx;
+                    }
+                }
+            }
+            _ => (),
+        });
+        if refers_later {
+            return Err(error::program_error(format!(
+                "the default value of `{}' of {} can only refer to the preceding params",
+                param.name, signature.fullname
+            )));
+        }
+        Ok(())
+    }
+}
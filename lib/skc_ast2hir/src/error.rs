@@ -1,5 +1,7 @@
 use shiika_ast::LocationSpan;
-use skc_error::Label;
+use shiika_core::names::MethodFirstname;
+use shiika_core::ty::TermTy;
+use skc_error::{Diagnostic, Label};
 
 #[derive(thiserror::Error, Debug)]
 #[allow(clippy::enum_variant_names)]
@@ -39,6 +41,19 @@ pub fn program_error(msg: impl Into<String>) -> anyhow::Error {
     Error::ProgramError { msg: msg.into() }.into()
 }
 
+/// Combine the errors into one. The messages are separated by blank lines
+pub fn multiple_errors(mut errors: Vec<anyhow::Error>) -> anyhow::Error {
+    if errors.len() == 1 {
+        return errors.pop().unwrap();
+    }
+    let n = errors.len();
+    let msgs = errors
+        .into_iter()
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>();
+    program_error(format!("{}\n\n{} errors found", msgs.join("\n\n"), n))
+}
+
 pub fn lvar_redeclaration(name: &str, locs: &LocationSpan) -> anyhow::Error {
     let msg = format!(
         "variable `{}' already exists (shadowing is not allowed in Shiika)",
@@ -83,3 +98,64 @@ pub fn assign_to_undeclared_ivar(name: &str, locs: &LocationSpan) -> anyhow::Err
     program_error(report)
 }
 
+pub fn assert_type_failed(
+    expected: &TermTy,
+    actual: &TermTy,
+    locs: &LocationSpan,
+) -> anyhow::Error {
+    let msg = format!(
+        "assert_type failed: expected {} but got {}",
+        expected, actual
+    );
+    let report = skc_error::build_report(msg.clone(), locs, |r, locs_span| {
+        r.with_label(Label::new(locs_span).with_message(format!("this is {}", actual)))
+    });
+    type_error(report)
+}
+
+pub fn undefined_operator(op: &str, receiver_ty: &TermTy, locs: &LocationSpan) -> anyhow::Error {
+    let msg = format!("operator `{}' is not defined for {}", op, receiver_ty);
+    let report = skc_error::build_report(msg.clone(), locs, |r, locs_span| {
+        r.with_label(
+            Label::new(locs_span).with_message(format!("hint: define `{}' in {}", op, receiver_ty)),
+        )
+    });
+    type_error(report)
+}
+
+/// Error for a call of a method which the receiver does not have.
+/// `receiver_locs` is the location of the explicit receiver, if any
+pub fn undefined_method(
+    name: &MethodFirstname,
+    receiver_ty: &TermTy,
+    locs: &LocationSpan,
+    receiver_locs: Option<&LocationSpan>,
+    similar: Option<MethodFirstname>,
+) -> anyhow::Error {
+    let msg = format!("method {:?} not found on {:?}", name, receiver_ty.fullname);
+    let label = format!("{} does not have `{}'", receiver_ty, name);
+    let mut diag = Diagnostic::new(msg, locs, label);
+    if let Some(l) = receiver_locs {
+        diag = diag.label(l, format!("this is {}", receiver_ty));
+    }
+    if let Some(s) = similar {
+        diag = diag.note(format!("did you mean `{}'?", s));
+    }
+    program_error(diag.render())
+}
+
+/// Warning for an expression which is never evaluated
+pub fn unreachable_code(locs: &LocationSpan) -> String {
+    let msg = "unreachable code".to_string();
+    skc_error::build_warning(msg.clone(), locs, |r, locs_span| {
+        r.with_label(Label::new(locs_span).with_message(msg))
+    })
+}
+
+/// Warning for a match clause which never matches
+pub fn dead_match_clause(locs: &LocationSpan) -> String {
+    let msg = "this clause is never reached (the pattern is already covered)".to_string();
+    skc_error::build_warning(msg.clone(), locs, |r, locs_span| {
+        r.with_label(Label::new(locs_span).with_message(msg))
+    })
+}
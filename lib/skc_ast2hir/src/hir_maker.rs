@@ -23,14 +23,27 @@ pub struct HirMaker<'hir_maker> {
     pub(super) imported_constants: &'hir_maker HashMap<ConstFullname, TermTy>,
     /// Expressions that initialize constants
     pub(super) const_inits: Vec<HirExpression>,
+    /// Values of the constants defined with `const`
+    pub(super) compile_time_constants: HashMap<ConstFullname, HirExpression>,
     /// List of string literals found so far
     pub(super) str_literals: Vec<String>,
+    /// Index of the frozen string literal of each content
+    pub(super) frozen_str_literals: HashMap<String, usize>,
     /// Contextual information
     pub(super) ctx_stack: CtxStack,
     /// Counter to give unique name for lambdas
     pub(super) lambda_ct: usize,
     /// Counter for unique name
     pub(super) gensym_ct: usize,
+    /// Annotations on the methods
+    pub(super) annotations: HashMap<MethodFullname, Vec<Annotation>>,
+    /// True if compiling the core library (builtin/*.sk)
+    pub(super) is_corelib: bool,
+    /// Warnings found so far
+    pub(super) warnings: Vec<String>,
+    /// Errors in the method bodies found so far. The conversion goes on to
+    /// the next method so that all of them are reported at once
+    pub(super) errors: Vec<anyhow::Error>,
 }
 
 impl<'hir_maker> HirMaker<'hir_maker> {
@@ -44,10 +57,16 @@ impl<'hir_maker> HirMaker<'hir_maker> {
             constants: HashMap::new(),
             imported_constants,
             const_inits: vec![],
+            compile_time_constants: HashMap::new(),
             str_literals: vec![],
+            frozen_str_literals: HashMap::new(),
             ctx_stack: CtxStack::new(vec![HirMakerContext::toplevel()]),
             lambda_ct: 0,
             gensym_ct: 0,
+            annotations: HashMap::new(),
+            is_corelib: false,
+            warnings: vec![],
+            errors: vec![],
         }
     }
 
@@ -62,6 +81,8 @@ impl<'hir_maker> HirMaker<'hir_maker> {
         std::mem::swap(&mut str_literals, &mut self.str_literals);
         let mut const_inits = vec![];
         std::mem::swap(&mut const_inits, &mut self.const_inits);
+        let annotations = std::mem::take(&mut self.annotations);
+        let extension_methods = self.class_dict.extension_method_names();
 
         Hir {
             sk_types,
@@ -71,6 +92,8 @@ impl<'hir_maker> HirMaker<'hir_maker> {
             const_inits,
             main_exprs,
             main_lvars,
+            annotations,
+            extension_methods,
         }
     }
 
@@ -84,8 +107,7 @@ impl<'hir_maker> HirMaker<'hir_maker> {
         let nonmeta = self
             .class_dict
             .sk_types
-            .0
-            .iter()
+            .sorted_iter()
             .filter(|(_, sk_type)| !sk_type.fullname().is_meta());
         let v = nonmeta
             .map(|(name, sk_type)| {
@@ -153,6 +175,7 @@ impl<'hir_maker> HirMaker<'hir_maker> {
                 }
             }
         }
+        self.register_all_class_constants_before_methods(&defs)?;
         self.process_defs(&Namespace::root(), None, &defs)?;
 
         let mut main_exprs = vec![];
@@ -180,13 +203,12 @@ impl<'hir_maker> HirMaker<'hir_maker> {
                 shiika_ast::Definition::InstanceMethodDefinition { sig, body_exprs } => {
                     if let Some(fullname) = opt_fullname {
                         log::trace!("method {}#{}", &fullname, &sig.name);
-                        let method = self.convert_method_def(
-                            &fullname.to_type_fullname(),
-                            &sig.name,
-                            body_exprs,
-                        )?;
-                        self.method_dict
-                            .add_method(fullname.to_type_fullname(), method);
+                        let type_fullname = fullname.to_type_fullname();
+                        if let Some(method) =
+                            self.convert_method_def(&type_fullname, sig, body_exprs)
+                        {
+                            self.method_dict.add_method(type_fullname, method);
+                        }
                     } else {
                         return Err(error::program_error(
                             "you cannot define methods at toplevel",
@@ -200,15 +222,11 @@ impl<'hir_maker> HirMaker<'hir_maker> {
                     sig, body_exprs, ..
                 } => {
                     if let Some(fullname) = opt_fullname {
-                        let meta_name = fullname.meta_name();
+                        let meta_name = fullname.meta_name().to_type_fullname();
                         log::trace!("method {}.{}", &fullname, &sig.name);
-                        let method = self.convert_method_def(
-                            &meta_name.to_type_fullname(),
-                            &sig.name,
-                            body_exprs,
-                        )?;
-                        self.method_dict
-                            .add_method(meta_name.to_type_fullname(), method);
+                        if let Some(method) = self.convert_method_def(&meta_name, sig, body_exprs) {
+                            self.method_dict.add_method(meta_name, method);
+                        }
                     } else {
                         return Err(error::program_error(
                             "you cannot define methods at toplevel",
@@ -218,19 +236,15 @@ impl<'hir_maker> HirMaker<'hir_maker> {
                 shiika_ast::Definition::ClassInitializerDefinition { .. } => {
                     // Already processed in process_class_def
                 }
-                shiika_ast::Definition::ConstDefinition { name, expr } => {
-                    if opt_fullname.is_some() {
-                        // Already processed
-                    } else {
-                        self.register_toplevel_const(name, expr)?;
-                    }
+                shiika_ast::Definition::ConstDefinition { .. } => {
+                    // Already processed in register_all_class_constants_before_methods
                 }
                 shiika_ast::Definition::ClassDefinition {
                     name,
                     defs,
-                    typarams,
+                    annotations,
                     ..
-                } => self.process_class_def(namespace, name, parse_typarams(typarams), defs)?,
+                } => self.process_class_def(namespace, name, defs, annotations)?,
                 shiika_ast::Definition::ModuleDefinition {
                     name,
                     typarams,
@@ -247,9 +261,25 @@ impl<'hir_maker> HirMaker<'hir_maker> {
                 } => {
                     self.process_enum_def(namespace, name, parse_typarams(typarams), cases, defs)?
                 }
+                shiika_ast::Definition::ExtensionDefinition {
+                    name,
+                    typarams,
+                    defs,
+                } => self.process_extension_def(name, parse_typarams(typarams), defs)?,
                 shiika_ast::Definition::MethodRequirementDefinition { .. } => {
                     // Already processed in class_dict/indexing.rs
                 }
+                shiika_ast::Definition::AbstractMethodDefinition { sig } => {
+                    if let Some(fullname) = opt_fullname {
+                        let method = self.create_abstract_method(fullname, sig)?;
+                        self.method_dict
+                            .add_method(fullname.to_type_fullname(), method);
+                    } else {
+                        return Err(error::program_error(
+                            "you cannot define methods at toplevel",
+                        ));
+                    }
+                }
             }
         }
         Ok(())
@@ -260,18 +290,17 @@ impl<'hir_maker> HirMaker<'hir_maker> {
         &mut self,
         namespace: &Namespace,
         firstname: &ClassFirstname,
-        typarams: Vec<TyParam>,
         defs: &[shiika_ast::Definition],
+        annotations: &[shiika_ast::Annotation],
     ) -> Result<()> {
         let fullname = namespace.class_fullname(firstname);
+        // The bounds of the typarams are resolved in class_dict
+        let typarams = self.class_dict.get_class(&fullname).base.typarams.clone();
         let meta_name = fullname.meta_name();
         let inner_namespace = namespace.add(firstname.to_string());
         self.ctx_stack
             .push(HirMakerContext::class(inner_namespace.clone(), typarams));
 
-        // Register constants before processing #initialize
-        self._process_const_defs_in_class(&inner_namespace, defs)?;
-
         // Register #initialize and ivars
         let own_ivars = self._process_initialize(&fullname, shiika_ast::find_initializer(defs))?;
         if !own_ivars.is_empty() {
@@ -297,6 +326,11 @@ impl<'hir_maker> HirMaker<'hir_maker> {
             self.define_accessors(&meta_name, cls_ivars, defs);
         }
 
+        // Process annotations
+        for annotation in annotations {
+            self._process_class_annotation(&fullname, annotation, defs)?;
+        }
+
         // Process inner defs
         self.process_defs(&inner_namespace, Some(&fullname), defs)?;
         self.ctx_stack.pop_class_ctx();
@@ -316,15 +350,29 @@ impl<'hir_maker> HirMaker<'hir_maker> {
         self.ctx_stack
             .push(HirMakerContext::class(inner_namespace.clone(), typarams));
 
-        // Register constants before processing the methods
-        self._process_const_defs_in_class(&inner_namespace, defs)?;
-
         // Process inner defs
         self.process_defs(&inner_namespace, Some(&fullname), defs)?;
         self.ctx_stack.pop_class_ctx();
         Ok(())
     }
 
+    /// Process the methods in an extension (the name is already checked in
+    /// class_dict/indexing.rs)
+    fn process_extension_def(
+        &mut self,
+        name: &shiika_ast::UnresolvedTypeName,
+        typarams: Vec<TyParam>,
+        defs: &[shiika_ast::Definition],
+    ) -> Result<()> {
+        let fullname = class_fullname(name.names.join("::"));
+        let inner_namespace = Namespace::new(name.names.clone());
+        self.ctx_stack
+            .push(HirMakerContext::class(inner_namespace.clone(), typarams));
+        self.process_defs(&inner_namespace, Some(&fullname), defs)?;
+        self.ctx_stack.pop_class_ctx();
+        Ok(())
+    }
+
     /// Add `#initialize` and return defined ivars
     fn _process_initialize(
         &mut self,
@@ -335,7 +383,7 @@ impl<'hir_maker> HirMaker<'hir_maker> {
         if let Some(d) = initializer {
             log::trace!("method {}#initialize", &fullname);
             let (sk_method, found_ivars) =
-                self.create_initialize(fullname, &d.sig.name, &d.body_exprs)?;
+                self.create_initialize(fullname, &d.sig, &d.body_exprs)?;
             self.method_dict
                 .add_method(fullname.to_type_fullname(), sk_method);
             own_ivars = found_ivars;
@@ -343,34 +391,18 @@ impl<'hir_maker> HirMaker<'hir_maker> {
         Ok(own_ivars)
     }
 
-    /// Register constants defined in a class
-    fn _process_const_defs_in_class(
-        &mut self,
-        namespace: &Namespace,
-        defs: &[shiika_ast::Definition],
-    ) -> Result<()> {
-        for def in defs {
-            if let shiika_ast::Definition::ConstDefinition { name, expr } = def {
-                let full = namespace.const_fullname(name);
-                let hir_expr = self.convert_expr(expr)?;
-                self.register_const_full(full, hir_expr);
-            }
-        }
-        Ok(())
-    }
-
     /// Create the `initialize` method
     /// Also, define ivars
     fn create_initialize(
         &mut self,
         class_fullname: &ClassFullname,
-        name: &MethodFirstname,
+        sig: &AstMethodSignature,
         body_exprs: &[AstExpression],
     ) -> Result<(SkMethod, SkIVars)> {
         let super_ivars = self.class_dict.superclass_ivars(class_fullname);
         self.convert_method_def_(
             &class_fullname.to_type_fullname(),
-            name,
+            sig,
             body_exprs,
             super_ivars,
         )
@@ -398,6 +430,42 @@ impl<'hir_maker> HirMaker<'hir_maker> {
         })
     }
 
+    /// Create the body of an abstract method. It is only called when a
+    /// subclass is instantiated without implementing it, which is rejected
+    /// unless the class is instantiated via `self.new`
+    fn create_abstract_method(
+        &mut self,
+        fullname: &ClassFullname,
+        sig: &AstMethodSignature,
+    ) -> Result<SkMethod> {
+        let msg = format!(
+            "[abstract method {}#{} is not implemented]",
+            fullname, sig.name
+        );
+        let body = AstExpression {
+            body: AstExpressionBody::MethodCall(AstMethodCall {
+                receiver_expr: None,
+                method_name: method_firstname("panic"),
+                arg_exprs: vec![AstExpression {
+                    body: AstExpressionBody::StringLiteral {
+                        content: msg,
+                        frozen: false,
+                    },
+                    primary: true,
+                    locs: LocationSpan::internal(),
+                }],
+                type_args: vec![],
+                has_block: false,
+                may_have_paren_wo_args: false,
+            }),
+            primary: false,
+            locs: LocationSpan::internal(),
+        };
+        let (method, _ivars) =
+            self.convert_method_def_(&fullname.to_type_fullname(), sig, &[body], None)?;
+        Ok(method)
+    }
+
     /// Find actual `initialize` func to call from `.new`
     fn _find_initialize(&self, class: &TermTy) -> Result<(MethodFullname, ClassFullname)> {
         let found = self.class_dict.lookup_method(
@@ -412,63 +480,90 @@ impl<'hir_maker> HirMaker<'hir_maker> {
         ))
     }
 
-    /// Register a constant defined in the toplevel
-    pub(super) fn register_toplevel_const(
-        &mut self,
-        name: &str,
-        expr: &AstExpression,
-    ) -> Result<()> {
-        let hir_expr = self.convert_expr(expr)?;
-        self.constants
-            .insert(toplevel_const(name), hir_expr.ty.clone());
-        let op = Hir::const_assign(toplevel_const(name), hir_expr, LocationSpan::todo());
-        self.const_inits.push(op);
-        Ok(())
-    }
-
     /// Register a constant
     pub(super) fn register_const_full(&mut self, fullname: ConstFullname, hir_expr: HirExpression) {
+        self.declare_const(fullname.clone(), hir_expr.ty.clone());
+        self.initialize_const(fullname, hir_expr);
+    }
+
+    /// Register the type of a constant
+    pub(super) fn declare_const(&mut self, fullname: ConstFullname, ty: TermTy) {
         debug_assert!(!self.constants.contains_key(&fullname));
-        self.constants.insert(fullname.clone(), hir_expr.ty.clone());
-        let op = Hir::const_assign(fullname, hir_expr, LocationSpan::todo());
+        self.constants.insert(fullname, ty);
+    }
+
+    /// Register the initialization of a declared constant
+    pub(super) fn initialize_const(&mut self, fullname: ConstFullname, hir_expr: HirExpression) {
+        debug_assert!(self.constants.contains_key(&fullname));
+        let locs = hir_expr.locs.clone();
+        let op = Hir::const_assign(fullname, hir_expr, locs);
         self.const_inits.push(op);
     }
 
+    /// Convert a method definition. Returns None (and saves the error to
+    /// `self.errors`) if the method has an error
     fn convert_method_def(
         &mut self,
         type_fullname: &TypeFullname,
-        name: &MethodFirstname,
+        sig: &AstMethodSignature,
         body_exprs: &[AstExpression],
-    ) -> Result<SkMethod> {
-        let (sk_method, _ivars) =
-            self.convert_method_def_(type_fullname, name, body_exprs, None)?;
-        Ok(sk_method)
+    ) -> Option<SkMethod> {
+        let depth = self.ctx_stack.len();
+        match self.convert_method_def_(type_fullname, sig, body_exprs, None) {
+            Ok((sk_method, _ivars)) => Some(sk_method),
+            Err(e) => {
+                // Discard the ctxs left by the failed conversion
+                self.ctx_stack.truncate(depth);
+                self.errors.push(e);
+                None
+            }
+        }
+    }
+
+    /// Returns `result` if no error is saved in `self.errors`. Otherwise
+    /// returns an error which contains all of them (and the one in `result`)
+    pub fn check_errors<T>(&mut self, result: Result<T>) -> Result<T> {
+        let mut errors = std::mem::take(&mut self.errors);
+        match result {
+            Ok(x) if errors.is_empty() => return Ok(x),
+            Ok(_) => (),
+            Err(e) => errors.push(e),
+        }
+        Err(error::multiple_errors(errors))
     }
 
     /// Create a SkMethod and return it with ctx.iivars
     fn convert_method_def_(
         &mut self,
         type_fullname: &TypeFullname,
-        name: &MethodFirstname,
+        sig: &AstMethodSignature,
         body_exprs: &[AstExpression],
         super_ivars: Option<SkIVars>,
     ) -> Result<(SkMethod, HashMap<String, SkIVar>)> {
+        let name = &sig.name;
         // MethodSignature is built beforehand by class_dict::new
         let signature = self
             .class_dict
             .find_method_sig(type_fullname, name)
             .unwrap_or_else(|| panic!("[BUG] signature not found ({}/{})", type_fullname, name));
+        if !sig.annotations.is_empty() {
+            self._validate_method_annotations(&signature.fullname, &sig.annotations)?;
+            self.annotations
+                .insert(signature.fullname.clone(), sig.annotations.clone());
+        }
 
         self.ctx_stack
             .push(HirMakerContext::method(signature.clone(), super_ivars));
+        let default_args = self.convert_default_args(type_fullname, &signature)?;
         let mut hir_exprs = self.convert_exprs(body_exprs)?;
+        hir_exprs.prepend(default_args);
         // Insert ::Void so that last expr always matches to ret_ty
         if signature.ret_ty.is_void_type() {
             hir_exprs.voidify();
         }
         let mut method_ctx = self.ctx_stack.pop_method_ctx();
         let lvars = extract_lvars(&mut method_ctx.lvars);
-        type_checking::check_return_value(&self.class_dict, &signature, &hir_exprs.ty)?;
+        type_checking::check_return_value(&self.class_dict, &signature, &hir_exprs)?;
 
         let method = SkMethod {
             signature,
@@ -518,14 +613,14 @@ impl<'hir_maker> HirMaker<'hir_maker> {
             .iter()
             .enumerate()
             .map(|(idx, param)| {
-                let argref = Hir::arg_ref(param.ty.clone(), idx, LocationSpan::todo());
+                let argref = Hir::arg_ref(param.ty.clone(), idx, LocationSpan::internal());
                 Hir::ivar_assign(
                     &param.name,
                     idx,
                     argref,
                     false,
                     self_ty.clone(),
-                    LocationSpan::todo(),
+                    LocationSpan::internal(),
                 )
             })
             .collect();
@@ -569,6 +664,10 @@ impl<'hir_maker> HirMaker<'hir_maker> {
 pub fn extract_lvars(lvars: &mut HashMap<String, CtxLVar>) -> HirLVars {
     std::mem::take(lvars)
         .into_iter()
-        .map(|(name, ctx_lvar)| (name, ctx_lvar.ty))
+        .map(|(name, ctx_lvar)| HirLVar {
+            name,
+            ty: ctx_lvar.ty,
+            cell: ctx_lvar.captured && !ctx_lvar.readonly,
+        })
         .collect::<Vec<_>>()
 }
@@ -56,12 +56,15 @@ impl HirMakerContext {
             lvars: Default::default(),
             captures: Default::default(),
             has_break: false,
+            has_continue: false,
         })
     }
 
     // `while' is Rust's keyword
     pub fn while_ctx() -> HirMakerContext {
-        HirMakerContext::While(WhileCtx {})
+        HirMakerContext::While(WhileCtx {
+            break_tys: Default::default(),
+        })
     }
 
     pub fn match_clause() -> HirMakerContext {
@@ -113,11 +116,26 @@ pub struct LambdaCtx {
     pub captures: Vec<LambdaCapture>,
     /// true if this lambda has `break`
     pub has_break: bool,
+    /// true if this lambda has `continue`
+    pub has_continue: bool,
+}
+
+impl LambdaCtx {
+    /// Returns the index in `captures` if the variable of `cap` is already
+    /// captured
+    pub fn captured_var(&self, cap: &LambdaCapture) -> Option<usize> {
+        self.captures
+            .iter()
+            .position(|c| c.ctx_depth == cap.ctx_depth && c.detail == cap.detail)
+    }
 }
 
 /// Indicates we're in a while expr
 #[derive(Debug)]
-pub struct WhileCtx;
+pub struct WhileCtx {
+    /// Types of the `break` values (`Void` for a bare `break`)
+    pub break_tys: Vec<TermTy>,
+}
 
 /// Each clause of match expression has its own lvars
 #[derive(Debug)]
@@ -132,20 +150,24 @@ pub struct CtxLVar {
     pub name: String,
     pub ty: TermTy,
     pub readonly: bool,
+    /// true if this variable is captured by a lambda
+    pub captured: bool,
 }
 
 pub type CtxLVars = HashMap<String, CtxLVar>;
 
 #[derive(Debug)]
 pub struct LambdaCapture {
-    /// The index of ctx stack where this lvar is captured.
+    /// The index of the lambda ctx which the captured lvar belongs to.
     /// None if the lvar does not belong to a lambda (method argument, etc.)
     pub ctx_depth: Option<usize>,
     pub ty: TermTy,
+    /// true if the captured variable cannot be reassigned
+    pub readonly: bool,
     pub detail: LambdaCaptureDetail,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum LambdaCaptureDetail {
     CapLVar { name: String },
     CapFnArg { idx: usize },
@@ -1,15 +1,20 @@
 mod accessors;
+mod annotations;
 pub mod class_dict;
+mod constants;
 mod convert_exprs;
 mod ctx_stack;
+mod default_params;
 mod error;
 mod hir_maker;
 mod hir_maker_context;
 mod method_dict;
 mod pattern_match;
+mod serializable;
 mod type_inference;
 mod type_system;
 use crate::class_dict::type_index;
+use crate::class_dict::ClassDict;
 use crate::hir_maker::HirMaker;
 use anyhow::Result;
 use shiika_ast::LocationSpan;
@@ -19,24 +24,79 @@ use skc_hir::{Hir, HirExpression};
 use skc_mir::LibraryExports;
 mod rustlib_methods;
 
-pub fn make_hir(ast: shiika_ast::Program, imports: &LibraryExports) -> Result<Hir> {
+/// Convert AST into HIR.
+/// If `strict_constants` is true, warn constant initializers which may have
+/// side effects.
+pub fn make_hir(
+    mut ast: shiika_ast::Program,
+    imports: &LibraryExports,
+    strict_constants: bool,
+) -> Result<Hir> {
+    default_params::validate(&ast.toplevel_items)?;
+    serializable::expand(&mut ast.toplevel_items);
     let defs = ast.defs();
     let type_index = type_index::create(&defs, &Default::default(), &imports.sk_types);
     let class_dict = class_dict::create(&defs, type_index, &imports.sk_types)?;
 
     let mut hir_maker = HirMaker::new(class_dict, &imports.constants);
     hir_maker.define_class_constants()?;
-    let (main_exprs, main_lvars) = hir_maker.convert_toplevel_items(ast.toplevel_items)?;
+    let result = hir_maker.convert_toplevel_items(ast.toplevel_items);
+    let (main_exprs, main_lvars) = hir_maker.check_errors(result)?;
+    for warning in &hir_maker.warnings {
+        eprintln!("{}", warning);
+    }
     let hir = hir_maker.extract_hir(main_exprs, main_lvars);
+    if strict_constants {
+        for msg in constants::validate_const_expressions(&hir, imports) {
+            eprintln!("warning: {}", msg);
+        }
+    }
 
     Ok(hir)
 }
 
+/// Convert AST into HIR for tools like the language server.
+/// Unlike `make_hir`, the errors in the program are returned with the HIR of
+/// the rest of it. `f` is called with the HIR and the class dictionary (to
+/// look up methods.)
+pub fn analyze<T, F>(
+    mut ast: shiika_ast::Program,
+    imports: &LibraryExports,
+    f: F,
+) -> Result<(Vec<anyhow::Error>, T)>
+where
+    F: FnOnce(&Hir, &ClassDict) -> T,
+{
+    default_params::validate(&ast.toplevel_items)?;
+    serializable::expand(&mut ast.toplevel_items);
+    let defs = ast.defs();
+    let type_index = type_index::create(&defs, &Default::default(), &imports.sk_types);
+    let class_dict = class_dict::create(&defs, type_index, &imports.sk_types)?;
+
+    let mut hir_maker = HirMaker::new(class_dict, &imports.constants);
+    hir_maker.define_class_constants()?;
+    let (main_exprs, main_lvars) = match hir_maker.convert_toplevel_items(ast.toplevel_items) {
+        Ok(x) => x,
+        Err(e) => {
+            hir_maker.errors.push(e);
+            (Hir::expressions_from_body(vec![]), vec![])
+        }
+    };
+    let errors = std::mem::take(&mut hir_maker.errors);
+    let hir = hir_maker.extract_hir(main_exprs, main_lvars);
+    // `extract_hir` moves the types out of the class dictionary
+    hir_maker.class_dict.sk_types = hir.sk_types.clone();
+    let x = f(&hir, &hir_maker.class_dict);
+    Ok((errors, x))
+}
+
 pub fn make_corelib_hir(
     // ast of builtin/*.sk
-    ast: shiika_ast::Program,
+    mut ast: shiika_ast::Program,
     corelib: Corelib,
 ) -> Result<Hir> {
+    default_params::validate(&ast.toplevel_items)?;
+    serializable::expand(&mut ast.toplevel_items);
     let defs = ast.defs();
     // TODO: Remove this. (`imports` is a reference because it is used for building
     // mir too. But I think we can put `imports` into hir)
@@ -55,8 +115,10 @@ pub fn make_corelib_hir(
     )?;
 
     let mut hir_maker = HirMaker::new(class_dict, &dummy_constants);
+    hir_maker.is_corelib = true;
     hir_maker.define_class_constants()?;
-    let (main_exprs, main_lvars) = hir_maker.convert_toplevel_items(ast.toplevel_items)?;
+    let result = hir_maker.convert_toplevel_items(ast.toplevel_items);
+    let (main_exprs, main_lvars) = hir_maker.check_errors(result)?;
     let mut hir = hir_maker.extract_hir(main_exprs, main_lvars);
     hir.add_methods(rustlib_methods::make_sk_methods(rust_method_sigs));
 
@@ -73,9 +135,11 @@ fn parse_typarams(typarams: &[shiika_ast::AstTyParam]) -> Vec<ty::TyParam> {
                 shiika_ast::AstVariance::Covariant => ty::Variance::Covariant,
                 shiika_ast::AstVariance::Contravariant => ty::Variance::Contravariant,
             };
+            // Bounds are resolved in `ClassDict` (see `_resolve_typaram_bounds`)
             ty::TyParam {
                 name: param.name.clone(),
                 variance: v,
+                upper_bound: ty::LitTy::raw("Object"),
             }
         })
         .collect::<Vec<_>>()
@@ -83,7 +147,8 @@ fn parse_typarams(typarams: &[shiika_ast::AstTyParam]) -> Vec<ty::TyParam> {
 
 /// Build a HirExpression which evaluates to `ty`
 /// eg. `Foo.<>([Bool, Int])` if `ty` is `TermTy(Foo<Bool, Int>)`
-pub fn class_expr(mk: &mut HirMaker, ty: &TermTy) -> HirExpression {
+/// `locs` is the location of the expression which needs the class object
+pub fn class_expr(mk: &mut HirMaker, ty: &TermTy, locs: &LocationSpan) -> HirExpression {
     match &ty.body {
         TyBody::TyRaw(LitTy {
             base_name,
@@ -91,19 +156,15 @@ pub fn class_expr(mk: &mut HirMaker, ty: &TermTy) -> HirExpression {
             is_meta,
         }) => {
             debug_assert!(!is_meta);
-            let base = Hir::const_ref(
-                ty::meta(base_name),
-                toplevel_const(base_name),
-                LocationSpan::todo(),
-            );
+            let base = Hir::const_ref(ty::meta(base_name), toplevel_const(base_name), locs.clone());
             if type_args.is_empty() {
                 base
             } else {
                 let tyargs = type_args
                     .iter()
-                    .map(|t| Hir::bit_cast(ty::raw("Class"), class_expr(mk, t)))
+                    .map(|t| Hir::bit_cast(ty::raw("Class"), class_expr(mk, t, locs)))
                     .collect();
-                call_class_specialize(mk, tyargs, base_name, base)
+                call_class_specialize(mk, tyargs, base_name, base, locs)
             }
         }
         TyBody::TyPara(typaram_ref) => {
@@ -112,7 +173,7 @@ pub fn class_expr(mk: &mut HirMaker, ty: &TermTy) -> HirExpression {
                 ref2.to_term_ty(),
                 ref2,
                 mk.ctx_stack.self_ty(),
-                LocationSpan::todo(),
+                locs.clone(),
             )
         }
     }
@@ -124,6 +185,7 @@ fn call_class_specialize(
     mut tyargs: Vec<HirExpression>,
     base_name: &str,
     base: HirExpression,
+    locs: &LocationSpan,
 ) -> HirExpression {
     if tyargs.len() == 1 {
         // Workaround for bootstrap problem of arrays.
@@ -140,7 +202,7 @@ fn call_class_specialize(
             ty::meta(base_name),
             base,
             method_fullname_raw("Class", "<>"),
-            vec![mk.create_array_instance(tyargs, LocationSpan::todo())],
+            vec![mk.create_array_instance(tyargs, locs.clone())],
         )
     }
 }
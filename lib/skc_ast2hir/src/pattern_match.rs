@@ -3,9 +3,11 @@ use crate::error;
 use crate::hir_maker::extract_lvars;
 use crate::hir_maker::HirMaker;
 use crate::hir_maker_context::HirMakerContext;
+use crate::type_system::type_checking;
 use anyhow::Result;
 use shiika_ast::*;
 use shiika_core::{names::*, ty, ty::*};
+use skc_error::Diagnostic;
 use skc_hir::pattern_match::{Component, MatchClause};
 use skc_hir::*;
 
@@ -14,46 +16,93 @@ pub fn convert_match_expr(
     mk: &mut HirMaker,
     cond: &AstExpression,
     ast_clauses: &[AstMatchClause],
+    locs: &LocationSpan,
 ) -> Result<(HirExpression, HirLVars)> {
     let cond_expr = mk.convert_expr(cond)?;
     let tmp_name = mk.generate_lvar_name("expr");
-    let tmp_ref = Hir::lvar_ref(cond_expr.ty.clone(), tmp_name.clone(), LocationSpan::todo());
+    let tmp_ref = Hir::lvar_ref(
+        cond_expr.ty.clone(),
+        tmp_name.clone(),
+        cond_expr.locs.clone(),
+    );
     let mut clauses = ast_clauses
         .iter()
         .map(|clause| convert_match_clause(mk, &tmp_ref, clause))
         .collect::<Result<Vec<MatchClause>>>()?;
-    let result_ty = calc_result_ty(mk, &mut clauses)?;
+    let result_ty = calc_result_ty(mk, &mut clauses, locs)?;
     let panic_msg = Hir::string_literal(
         mk.register_string_literal("no matching clause found"),
-        LocationSpan::todo(),
+        locs.clone(),
     );
     clauses.push(MatchClause {
         components: vec![],
-        body_hir: Hir::expressions(vec![Hir::method_call(
+        body_hir: Hir::expressions_from_body(vec![Hir::method_call(
             ty::raw("Never"),
-            Hir::decimal_literal(0, LocationSpan::todo()), // whatever.
+            Hir::decimal_literal(0, locs.clone()), // whatever.
             method_fullname_raw("Object", "panic"),
             vec![panic_msg],
         )]),
         lvars: Default::default(),
     });
 
-    let lvars = vec![(tmp_name.clone(), cond_expr.ty.clone())];
-    let tmp_assign = Hir::lvar_assign(tmp_name, cond_expr, LocationSpan::todo());
+    let lvars = vec![HirLVar {
+        name: tmp_name.clone(),
+        ty: cond_expr.ty.clone(),
+        cell: false,
+    }];
+    let tmp_assign = Hir::lvar_assign(tmp_name, cond_expr, locs.clone());
     Ok((
-        Hir::match_expression(result_ty, tmp_assign, clauses, LocationSpan::todo()),
+        Hir::match_expression(result_ty, tmp_assign, clauses, locs.clone()),
         lvars,
     ))
 }
 
+/// Returns the condition for `value` to match `pat` and the variables bound
+/// by the match (used for `guard let`)
+pub fn convert_pattern_test(
+    mk: &mut HirMaker,
+    value: &HirExpression,
+    pat: &AstPattern,
+) -> Result<(HirExpression, Vec<(String, HirExpression)>)> {
+    let mut test = None;
+    let mut binds = vec![];
+    for component in convert_match(mk, value, pat)? {
+        match component {
+            Component::Test(t) => {
+                test = Some(match test {
+                    Some(prev) => Hir::logical_and(prev, t, value.locs.clone()),
+                    None => t,
+                })
+            }
+            Component::Bind(name, expr) => binds.push((name, expr)),
+        }
+    }
+    let test = test.unwrap_or_else(|| Hir::boolean_literal(true, value.locs.clone()));
+    Ok((test, binds))
+}
+
+/// Returns true if both of the patterns are the same literal
+pub fn is_same_literal_pattern(a: &AstPattern, b: &AstPattern) -> bool {
+    match (a, b) {
+        (AstPattern::BooleanLiteralPattern(x), AstPattern::BooleanLiteralPattern(y)) => x == y,
+        (AstPattern::IntegerLiteralPattern(x), AstPattern::IntegerLiteralPattern(y)) => x == y,
+        (AstPattern::FloatLiteralPattern(x), AstPattern::FloatLiteralPattern(y)) => x == y,
+        (AstPattern::StringLiteralPattern(x), AstPattern::StringLiteralPattern(y)) => x == y,
+        _ => false,
+    }
+}
+
 /// Convert a match clause into a big `if` expression
 fn convert_match_clause(
     mk: &mut HirMaker,
     value: &HirExpression,
-    (pat, body): &(AstPattern, Vec<AstExpression>),
+    (pat, _, guard, body): &AstMatchClause,
 ) -> Result<MatchClause> {
-    let components = convert_match(mk, value, pat)?;
-    let (body_hir, lvars) = compile_body(mk, &components, body)?;
+    let mut components = convert_match(mk, value, pat)?;
+    let (guard_hir, body_hir, lvars) = compile_body(mk, &components, guard, body)?;
+    if let Some(g) = guard_hir {
+        components.push(Component::Test(g));
+    }
     Ok(MatchClause {
         components,
         body_hir,
@@ -61,12 +110,13 @@ fn convert_match_clause(
     })
 }
 
-/// Compile clause body into HIR
+/// Compile clause guard and body into HIR
 fn compile_body(
     mk: &mut HirMaker,
     components: &[Component],
+    guard: &Option<AstExpression>,
     body: &[AstExpression],
-) -> Result<(HirExpressions, HirLVars)> {
+) -> Result<(Option<HirExpression>, HirExpressions, HirLVars)> {
     mk.ctx_stack.push(HirMakerContext::match_clause());
     // Declare lvars introduced by matching
     for component in components {
@@ -75,40 +125,81 @@ fn compile_body(
             mk.ctx_stack.declare_lvar(name, expr.ty.clone(), readonly);
         }
     }
+    // The guard is evaluated after all the binds are done
+    let guard_hir = match guard {
+        Some(expr) => {
+            let hir = mk.convert_expr(expr)?;
+            type_checking::check_condition_ty(&hir, "match guard")?;
+            Some(hir)
+        }
+        None => None,
+    };
     let hir_exprs = mk.convert_exprs(body)?;
     let mut clause_ctx = mk.ctx_stack.pop_match_clause_ctx();
-    Ok((hir_exprs, extract_lvars(&mut clause_ctx.lvars)))
+    Ok((guard_hir, hir_exprs, extract_lvars(&mut clause_ctx.lvars)))
+}
+
+/// Returns the location of the last expression of the clause body
+fn body_locs(clause: &MatchClause, default: &LocationSpan) -> LocationSpan {
+    match clause.body_hir.exprs().last() {
+        Some(expr) => expr.locs.clone(),
+        None => default.clone(),
+    }
+}
+
+/// Type error about the patterns against `value`
+fn match_type_error(msg: String, value: &HirExpression) -> anyhow::Error {
+    let label = format!("this is {}", value.ty);
+    error::type_error(Diagnostic::new(msg, &value.locs, label).render())
 }
 
 /// Calculate the type of the match expression from clauses
-fn calc_result_ty(mk: &HirMaker, clauses_: &mut [MatchClause]) -> Result<TermTy> {
+fn calc_result_ty(
+    mk: &HirMaker,
+    clauses_: &mut [MatchClause],
+    locs: &LocationSpan,
+) -> Result<TermTy> {
     debug_assert!(!clauses_.is_empty());
     let mut clauses = clauses_
         .iter_mut()
-        .filter(|c| !c.body_hir.ty.is_never_type())
+        .filter(|c| !c.body_hir.ty().is_never_type())
         .collect::<Vec<_>>();
     if clauses.is_empty() {
         // All clauses are type `Never`.
         Ok(ty::raw("Never"))
-    } else if clauses.iter().any(|c| c.body_hir.ty.is_void_type()) {
+    } else if clauses.iter().any(|c| c.body_hir.ty().is_void_type()) {
         for c in clauses.iter_mut() {
-            if !c.body_hir.ty.is_void_type() {
+            if !c.body_hir.ty().is_void_type() {
                 c.body_hir.voidify();
             }
         }
         Ok(ty::raw("Void"))
     } else {
-        let mut ty = clauses[0].body_hir.ty.clone();
+        let mut ty = clauses[0].body_hir.ty().clone();
         for c in &clauses {
-            if let Some(t) = mk.class_dict.nearest_common_ancestor(&ty, &c.body_hir.ty) {
+            if let Some(t) = mk.class_dict.nearest_common_ancestor(&ty, c.body_hir.ty()) {
                 ty = t;
             } else {
-                let msg = format!("match clause type mismatch ({} vs {})", &ty, &c.body_hir.ty);
-                return Err(error::type_error(msg));
+                let msg = format!(
+                    "match clause type mismatch ({} vs {})",
+                    &ty,
+                    c.body_hir.ty()
+                );
+                let report = Diagnostic::new(
+                    msg,
+                    &body_locs(c, locs),
+                    format!("this is {}", c.body_hir.ty()),
+                )
+                .label(
+                    &body_locs(&clauses[0], locs),
+                    format!("this is {}", clauses[0].body_hir.ty()),
+                )
+                .render();
+                return Err(error::type_error(report));
             }
         }
         for c in clauses.iter_mut() {
-            if !c.body_hir.ty.equals_to(&ty) {
+            if !c.body_hir.ty().equals_to(&ty) {
                 bitcast_match_clause_body(c, ty.clone());
             }
         }
@@ -118,7 +209,7 @@ fn calc_result_ty(mk: &HirMaker, clauses_: &mut [MatchClause]) -> Result<TermTy>
 
 /// Destructively bitcast body_hir
 fn bitcast_match_clause_body(c: &mut MatchClause, ty: TermTy) {
-    let mut tmp = Hir::expressions(Default::default());
+    let mut tmp = Hir::expressions_from_body(Default::default());
     std::mem::swap(&mut tmp, &mut c.body_hir);
     tmp = tmp.bitcast_to(ty);
     std::mem::swap(&mut tmp, &mut c.body_hir);
@@ -143,49 +234,180 @@ fn convert_match(
         }
         AstPattern::BooleanLiteralPattern(b) => {
             check_ty_raw(value, "Bool")?;
-            let hir_bool = Hir::boolean_literal(*b, LocationSpan::todo());
+            let hir_bool = Hir::boolean_literal(*b, value.locs.clone());
             Ok(vec![make_eq_test(value, "Bool", hir_bool)])
         }
         AstPattern::IntegerLiteralPattern(i) => {
             check_ty_raw(value, "Int")?;
-            let hir_int = Hir::decimal_literal(*i, LocationSpan::todo());
+            let hir_int = Hir::decimal_literal(*i, value.locs.clone());
             Ok(vec![make_eq_test(value, "Int", hir_int)])
         }
         AstPattern::FloatLiteralPattern(f) => {
             check_ty_raw(value, "Float")?;
-            let hir_int = Hir::float_literal(*f, LocationSpan::todo());
+            let hir_int = Hir::float_literal(*f, value.locs.clone());
             Ok(vec![make_eq_test(value, "Float", hir_int)])
         }
         AstPattern::StringLiteralPattern(s) => {
             check_ty_raw(value, "String")?;
-            let hir_str = mk.convert_string_literal(s, &LocationSpan::todo());
+            let hir_str = mk.convert_string_literal(s, &value.locs);
             Ok(vec![make_eq_test(value, "String", hir_str)])
         }
+        AstPattern::RangePattern {
+            start,
+            end,
+            inclusive,
+        } => convert_range_pattern(value, start, end, *inclusive),
+        AstPattern::TuplePattern(patterns) => convert_tuple_pattern(mk, value, patterns),
+        AstPattern::OrPattern(patterns) => convert_or_pattern(mk, value, patterns),
+    }
+}
+
+/// Create components for match against or-pattern. Each alternative must
+/// bind the same set of variables with the same types.
+fn convert_or_pattern(
+    mk: &mut HirMaker,
+    value: &HirExpression,
+    patterns: &[AstPattern],
+) -> Result<Vec<Component>> {
+    let alts = patterns
+        .iter()
+        .map(|pat| convert_pattern_test(mk, value, pat))
+        .collect::<Result<Vec<_>>>()?;
+    let (_, first_binds) = &alts[0];
+    for (_, binds) in &alts[1..] {
+        let mut names = binds.iter().map(|(name, _)| name).collect::<Vec<_>>();
+        let mut expected = first_binds.iter().map(|(name, _)| name).collect::<Vec<_>>();
+        names.sort();
+        expected.sort();
+        if names != expected {
+            let msg = format!(
+                "all alternatives of an or-pattern must bind the same variables ({:?} vs {:?})",
+                expected, names
+            );
+            let report = Diagnostic::new(msg, &value.locs, "the value to match")
+                .note(format!("bind {:?} in every alternative", expected))
+                .render();
+            return Err(error::program_error(report));
+        }
+        for (name, expr) in binds {
+            let (_, first) = first_binds.iter().find(|(n, _)| n == name).unwrap();
+            if expr.ty != first.ty {
+                let msg = format!(
+                    "variable `{}' of an or-pattern has different types ({} vs {})",
+                    name, first.ty, expr.ty
+                );
+                return Err(match_type_error(msg, value));
+            }
+        }
+    }
+
+    // The value of a variable is taken from the alternative which matched
+    // eg. `x = if test1 then value1 else value2 end`
+    let mut components = vec![];
+    for (name, first) in first_binds {
+        let (_, last_binds) = alts.last().unwrap();
+        let mut expr = find_bind(last_binds, name);
+        for (test, binds) in alts[..alts.len() - 1].iter().rev() {
+            expr = Hir::if_expression(
+                first.ty.clone(),
+                test.clone(),
+                Hir::expressions_from_body(vec![find_bind(binds, name)]),
+                Hir::expressions_from_body(vec![expr]),
+                value.locs.clone(),
+            );
+        }
+        components.push(Component::Bind(name.to_string(), expr));
     }
+    let test = alts
+        .into_iter()
+        .map(|(test, _)| test)
+        .reduce(|a, b| Hir::logical_or(a, b, value.locs.clone()))
+        .unwrap();
+    components.insert(0, Component::Test(test));
+    Ok(components)
+}
+
+fn find_bind(binds: &[(String, HirExpression)], name: &str) -> HirExpression {
+    let (_, expr) = binds.iter().find(|(n, _)| n == name).unwrap();
+    expr.clone()
+}
+
+/// Create components for match against range pattern (`value >= start`
+/// and `value <= end` or `value < end`)
+fn convert_range_pattern(
+    value: &HirExpression,
+    start: &AstPattern,
+    end: &AstPattern,
+    inclusive: bool,
+) -> Result<Vec<Component>> {
+    let (name, hir_start, hir_end) = match (start, end) {
+        (AstPattern::IntegerLiteralPattern(a), AstPattern::IntegerLiteralPattern(b)) => (
+            "Int",
+            Hir::decimal_literal(*a, value.locs.clone()),
+            Hir::decimal_literal(*b, value.locs.clone()),
+        ),
+        (AstPattern::FloatLiteralPattern(a), AstPattern::FloatLiteralPattern(b)) => (
+            "Float",
+            Hir::float_literal(*a, value.locs.clone()),
+            Hir::float_literal(*b, value.locs.clone()),
+        ),
+        _ => {
+            let msg = "both ends of a range pattern must be Int or Float".to_string();
+            return Err(match_type_error(msg, value));
+        }
+    };
+    check_ty_raw(value, name)?;
+    let end_op = if inclusive { "<=" } else { "<" };
+    Ok(vec![
+        make_cmp_test(value, name, ">=", hir_start),
+        make_cmp_test(value, name, end_op, hir_end),
+    ])
 }
 
 /// Check the type of `value` is `ty::raw(name)`
 fn check_ty_raw(value: &HirExpression, name: &str) -> Result<()> {
     if value.ty != ty::raw(name) {
-        return Err(error::type_error(&format!(
-            "expr of `{}' never matches to `{}'",
-            value.ty, name
-        )));
+        let msg = format!("expr of `{}' never matches to `{}'", value.ty, name);
+        return Err(match_type_error(msg, value));
     }
     Ok(())
 }
 
 /// Make `lhs == rhs`
 fn make_eq_test(value: &HirExpression, name: &str, rhs: HirExpression) -> Component {
+    make_cmp_test(value, name, "==", rhs)
+}
+
+/// Make `lhs (op) rhs`
+fn make_cmp_test(value: &HirExpression, name: &str, op: &str, rhs: HirExpression) -> Component {
     let test = Hir::method_call(
         ty::raw("Bool"),
         value.clone(),
-        method_fullname_raw(name, "=="),
+        method_fullname_raw(name, op),
         vec![rhs],
     );
     Component::Test(test)
 }
 
+/// Create components for match against tuple pattern. Unlike extractor
+/// patterns, the type of `value` must be a tuple type of the same size.
+fn convert_tuple_pattern(
+    mk: &mut HirMaker,
+    value: &HirExpression,
+    patterns: &[AstPattern],
+) -> Result<Vec<Component>> {
+    let tuple_name = format!("Tuple{}", patterns.len());
+    if value.ty.erasure().base_name != tuple_name {
+        let msg = format!(
+            "expr of `{}' never matches to a tuple of {} items",
+            value.ty,
+            patterns.len()
+        );
+        return Err(match_type_error(msg, value));
+    }
+    extract_props(mk, value, &value.ty, patterns)
+}
+
 /// Create components for match against extractor pattern
 fn convert_extractor(
     mk: &mut HirMaker,
@@ -194,13 +416,11 @@ fn convert_extractor(
     param_patterns: &[AstPattern],
 ) -> Result<Vec<Component>> {
     // eg. `ty::raw("Maybe::Some")`
-    let pat_base_ty = get_base_ty(mk, names)?;
+    let pat_base_ty = get_base_ty(mk, names, &value.locs)?;
     let pat_ty = infer_pat_ty(mk, &pat_base_ty, &value.ty);
     if !mk.class_dict.conforms(&pat_ty, &value.ty) {
-        return Err(error::type_error(&format!(
-            "expr of `{}' never matches to `{}'",
-            &value.ty, pat_ty
-        )));
+        let msg = format!("expr of `{}' never matches to `{}'", &value.ty, pat_ty);
+        return Err(match_type_error(msg, value));
     }
     let cast_value = Hir::bit_cast(pat_ty.clone(), value.clone());
     let mut components = extract_props(mk, &cast_value, &pat_ty, param_patterns)?;
@@ -210,9 +430,8 @@ fn convert_extractor(
     Ok(components)
 }
 
-fn get_base_ty(mk: &mut HirMaker, names: &[String]) -> Result<Erasure> {
-    let expr =
-        mk.convert_capitalized_name(&UnresolvedConstName(names.to_vec()), &LocationSpan::todo())?;
+fn get_base_ty(mk: &mut HirMaker, names: &[String], locs: &LocationSpan) -> Result<Erasure> {
+    let expr = mk.convert_capitalized_name(&UnresolvedConstName(names.to_vec()), locs)?;
     if expr.ty.is_metaclass() || expr.ty.is_typaram_ref() {
         return Ok(expr.ty.instance_ty().erasure());
     }
@@ -221,10 +440,10 @@ fn get_base_ty(mk: &mut HirMaker, names: &[String]) -> Result<Erasure> {
             return Ok(expr.ty.erasure()); // eg. Void, None, etc.
         }
     }
-    Err(error::type_error(&format!(
-        "a class expected but got {:?}",
-        &expr.ty
-    )))
+    let msg = format!("a class expected but got {:?}", &expr.ty);
+    let report =
+        Diagnostic::new(msg, locs, format!("{} is not a class", names.join("::"))).render();
+    Err(error::type_error(report))
 }
 
 // Infer pattern type. eg. for `when Pair(a, b)`, infer the types of
@@ -233,7 +452,7 @@ fn infer_pat_ty(mk: &mut HirMaker, pat_base_ty: &Erasure, value_ty: &TermTy) ->
     match &value_ty.body {
         TyBody::TyRaw(LitTy { type_args, .. }) => {
             let sk_type = mk.class_dict.get_type(&pat_base_ty.to_type_fullname());
-            sk_type.term_ty().substitute(type_args, &[])
+            sk_type.term_ty().substitute_class(type_args)
         }
         _ => pat_base_ty.to_term_ty(),
     }
@@ -260,11 +479,24 @@ fn extract_props(
 ) -> Result<Vec<Component>> {
     let ivars = class_props(mk, pat_ty)?; // eg. ("value", ty::spe("Maybe", "Int"))
     if ivars.len() != patterns.len() {
-        return Err(error::program_error(&format!(
+        let msg = format!(
             "this match needs {} patterns but {} there",
             ivars.len(),
             patterns.len()
-        )));
+        );
+        let names = ivars
+            .iter()
+            .map(|(name, _)| format!("`{}'", name.replace('@', "")))
+            .collect::<Vec<_>>();
+        let fields = if names.is_empty() {
+            "no fields".to_string()
+        } else {
+            format!("the fields {}", names.join(", "))
+        };
+        let report = Diagnostic::new(msg, &value.locs, "the value to match")
+            .note(format!("{} has {}", pat_ty, fields))
+            .render();
+        return Err(error::program_error(report));
     }
     let mut components = vec![];
     for i in 0..ivars.len() {
@@ -292,7 +524,7 @@ fn test_class(mk: &mut HirMaker, value: &HirExpression, pat_ty: &TermTy) -> HirE
         let const_ref = Hir::const_ref(
             pat_ty.clone(),
             pat_ty.fullname.to_const_fullname(),
-            LocationSpan::todo(),
+            value.locs.clone(),
         );
         Hir::method_call(
             ty::raw("Bool"),
@@ -301,7 +533,7 @@ fn test_class(mk: &mut HirMaker, value: &HirExpression, pat_ty: &TermTy) -> HirE
             vec![value.clone()],
         )
     } else {
-        let cls_ref = class_expr(mk, &pat_erasure.to_term_ty());
+        let cls_ref = class_expr(mk, &pat_erasure.to_term_ty(), &value.locs);
         // value.class.erasure_class == Foo
         Hir::method_call(
             ty::raw("Bool"),
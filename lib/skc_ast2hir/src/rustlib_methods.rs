@@ -1,5 +1,6 @@
+use crate::annotations::only_for_tyargs;
 use crate::type_index::TypeIndex;
-use shiika_ast::{AstMethodSignature, UnresolvedTypeName};
+use shiika_ast::{AstMethodSignature, LocationSpan, UnresolvedTypeName};
 use shiika_core::names::ClassFullname;
 use shiika_core::{names::method_fullname, ty, ty::TermTy};
 use skc_corelib::{self};
@@ -62,6 +63,10 @@ fn make_hir_sig(
         params,
         // TODO: Fix this when a rustlib method has method typaram
         typarams: Default::default(),
+        pure: ast_sig.annotations.iter().any(|a| a.name == "pure"),
+        only_for: only_for_tyargs(&ast_sig.annotations),
+        self_type: ast_sig.annotations.iter().any(|a| a.name == "self_type"),
+        locs: LocationSpan::internal(),
     }
 }
 
@@ -75,9 +80,14 @@ fn convert_params(params: &[shiika_ast::Param], class_typarams: &[&String]) -> V
 
 // Make hir param from ast param
 fn convert_param(param: &shiika_ast::Param, class_typarams: &[&String]) -> MethodParam {
+    let ty = convert_typ(&param.typ, class_typarams);
     MethodParam {
         name: param.name.to_string(),
-        ty: convert_typ(&param.typ, class_typarams),
+        ty: if param.is_variadic { ty::ary(ty) } else { ty },
+        is_variadic: param.is_variadic,
+        has_default: false,
+        default_expr: None,
+        locs: LocationSpan::internal(),
     }
 }
 
@@ -0,0 +1,278 @@
+//! Methods of the classes which include `Serializable`.
+//!
+//! `#to_json`, `.from_json` and `._from_json` are generated unless defined
+//! explicitly. The keys of the JSON object are the ivar params of
+//! `initialize`.
+//!
+//! ```sk
+//! class Point : Serializable
+//!   def initialize(@x: Int, @y: Int); end
+//! end
+//! # is expanded into
+//! class Point : Serializable
+//!   def initialize(@x: Int, @y: Int); end
+//!   def to_json -> String
+//!     "{\"x\":#{@x.to_json},\"y\":#{@y.to_json}}"
+//!   end
+//!   def self.from_json(s: String) -> Result<Point, String>
+//!     Point._from_json(JSON.parse(s)?)
+//!   end
+//!   def self._from_json(json: JSON) -> Result<Point, String>
+//!     Ok<Point, String>.new(Point.new(
+//!       Int._from_json(json.get("x")?)?,
+//!       Int._from_json(json.get("y")?)?))
+//!   end
+//! end
+//! ```
+//!
+//! This is done on the AST so that the generated
+//! methods are indexed before the witness tables are built.
+//! Generic classes and the classes whose `initialize` has a non-ivar param
+//! need to define them by hand.
+use shiika_ast::*;
+use shiika_core::names::{method_firstname, ClassFirstname, UnresolvedConstName};
+
+/// Add the methods to the classes in `items` which include `Serializable`
+pub fn expand(items: &mut [TopLevelItem]) {
+    for item in items {
+        if let TopLevelItem::Def(def) = item {
+            expand_def(def);
+        }
+    }
+}
+
+fn expand_def(def: &mut Definition) {
+    match def {
+        Definition::ClassDefinition {
+            name,
+            typarams,
+            supers,
+            defs,
+            ..
+        } => {
+            defs.iter_mut().for_each(expand_def);
+            if typarams.is_empty() && supers.iter().any(is_serializable) {
+                let mut new_defs = serializable_methods(name, defs);
+                defs.append(&mut new_defs);
+            }
+        }
+        Definition::ModuleDefinition { defs, .. } | Definition::EnumDefinition { defs, .. } => {
+            defs.iter_mut().for_each(expand_def)
+        }
+        _ => (),
+    }
+}
+
+fn is_serializable(name: &UnresolvedTypeName) -> bool {
+    name.names == ["Serializable"] && name.args.is_empty()
+}
+
+/// Returns the methods which are not defined in `defs`
+fn serializable_methods(name: &ClassFirstname, defs: &[Definition]) -> Vec<Definition> {
+    let params = find_initializer(defs)
+        .map(|d| d.sig.params.clone())
+        .unwrap_or_default();
+    let mut new_defs = vec![];
+    if !defines_method(defs, "to_json", false) {
+        new_defs.push(to_json(&params));
+    }
+    if params.iter().all(|p| p.is_iparam && !p.is_variadic) {
+        if !defines_method(defs, "from_json", true) {
+            new_defs.push(from_json(name));
+        }
+        if !defines_method(defs, "_from_json", true) {
+            new_defs.push(_from_json(name, &params));
+        }
+    }
+    new_defs
+}
+
+fn defines_method(defs: &[Definition], name: &str, class_method: bool) -> bool {
+    defs.iter().any(|def| match def {
+        Definition::InstanceMethodDefinition { sig, .. } => !class_method && sig.name.0 == name,
+        Definition::ClassMethodDefinition { sig, .. } => class_method && sig.name.0 == name,
+        _ => false,
+    })
+}
+
+/// `def to_json -> String`
+fn to_json(params: &[Param]) -> Definition {
+    let mut parts = vec![];
+    let mut literal = "{".to_string();
+    for (i, param) in params.iter().filter(|p| p.is_iparam).enumerate() {
+        if i > 0 {
+            literal.push(',');
+        }
+        literal.push_str(&format!("\"{}\":", key_of(param)));
+        parts.push(StringPart::Literal(literal));
+        literal = String::new();
+        // `@x.to_json`
+        let ivar = expr(AstExpressionBody::IVarRef(param.name.clone()));
+        parts.push(StringPart::Expr {
+            expr: Box::new(method_call(Some(ivar), "to_json", vec![])),
+            inspect: false,
+        });
+    }
+    literal.push('}');
+    parts.push(StringPart::Literal(literal));
+    Definition::InstanceMethodDefinition {
+        sig: signature("to_json", vec![], type_name(&["String"], vec![])),
+        body_exprs: vec![expr(AstExpressionBody::InterpolatedString { parts })],
+    }
+}
+
+/// `def self.from_json(s: String) -> Result<C, String>`
+fn from_json(name: &ClassFirstname) -> Definition {
+    let parse = method_call(
+        Some(const_expr(&["JSON"])),
+        "parse",
+        vec![expr(AstExpressionBody::BareName("s".to_string()))],
+    );
+    let body = method_call(
+        Some(const_expr(&[&name.0])),
+        "_from_json",
+        vec![expr(AstExpressionBody::Propagate(Box::new(parse)))],
+    );
+    Definition::ClassMethodDefinition {
+        sig: signature(
+            "from_json",
+            vec![param("s", type_name(&["String"], vec![]))],
+            result_type(name),
+        ),
+        body_exprs: vec![body],
+    }
+}
+
+/// `def self._from_json(json: JSON) -> Result<C, String>`
+fn _from_json(name: &ClassFirstname, params: &[Param]) -> Definition {
+    let args = params
+        .iter()
+        .map(|param| {
+            // `T._from_json(json.get("x")?)?`
+            let get = method_call(
+                Some(expr(AstExpressionBody::BareName("json".to_string()))),
+                "get",
+                vec![expr(AstExpressionBody::StringLiteral {
+                    content: key_of(param),
+                    frozen: false,
+                })],
+            );
+            let value = method_call(
+                Some(type_expr(&param.typ)),
+                "_from_json",
+                vec![expr(AstExpressionBody::Propagate(Box::new(get)))],
+            );
+            expr(AstExpressionBody::Propagate(Box::new(value)))
+        })
+        .collect();
+    let obj = method_call(Some(const_expr(&[&name.0])), "new", args);
+    let ok = expr(AstExpressionBody::SpecializeExpression {
+        base_name: UnresolvedConstName(vec!["Ok".to_string()]),
+        args: vec![const_expr(&[&name.0]), const_expr(&["String"])],
+    });
+    let body = method_call(Some(ok), "new", vec![obj]);
+    Definition::ClassMethodDefinition {
+        sig: signature(
+            "_from_json",
+            vec![param("json", type_name(&["JSON"], vec![]))],
+            result_type(name),
+        ),
+        body_exprs: vec![body],
+    }
+}
+
+/// Key of the JSON object for the ivar param `param`
+fn key_of(param: &Param) -> String {
+    param.name.trim_start_matches('@').to_string()
+}
+
+fn expr(body: AstExpressionBody) -> AstExpression {
+    AstExpression {
+        body,
+        primary: true,
+        locs: LocationSpan::internal(),
+    }
+}
+
+fn method_call(
+    receiver: Option<AstExpression>,
+    name: &str,
+    args: Vec<AstExpression>,
+) -> AstExpression {
+    AstExpression {
+        body: AstExpressionBody::MethodCall(AstMethodCall {
+            receiver_expr: receiver.map(Box::new),
+            method_name: method_firstname(name),
+            arg_exprs: args,
+            type_args: vec![],
+            has_block: false,
+            may_have_paren_wo_args: false,
+        }),
+        primary: false,
+        locs: LocationSpan::internal(),
+    }
+}
+
+fn const_expr(names: &[&str]) -> AstExpression {
+    expr(AstExpressionBody::CapitalizedName(UnresolvedConstName(
+        names.iter().map(|s| s.to_string()).collect(),
+    )))
+}
+
+/// The class object of the type `name` (eg. `Array<Int>`)
+fn type_expr(name: &UnresolvedTypeName) -> AstExpression {
+    let body = if name.args.is_empty() {
+        AstExpressionBody::CapitalizedName(UnresolvedConstName(name.names.clone()))
+    } else {
+        AstExpressionBody::SpecializeExpression {
+            base_name: UnresolvedConstName(name.names.clone()),
+            args: name.args.iter().map(type_expr).collect(),
+        }
+    };
+    AstExpression {
+        body,
+        primary: true,
+        locs: name.locs.clone(),
+    }
+}
+
+fn type_name(names: &[&str], args: Vec<UnresolvedTypeName>) -> UnresolvedTypeName {
+    UnresolvedTypeName {
+        names: names.iter().map(|s| s.to_string()).collect(),
+        args,
+        locs: LocationSpan::internal(),
+    }
+}
+
+/// `Result<C, String>`
+fn result_type(name: &ClassFirstname) -> UnresolvedTypeName {
+    type_name(
+        &["Result"],
+        vec![
+            type_name(&[&name.0], vec![]),
+            type_name(&["String"], vec![]),
+        ],
+    )
+}
+
+fn param(name: &str, typ: UnresolvedTypeName) -> Param {
+    Param {
+        name: name.to_string(),
+        typ,
+        is_iparam: false,
+        is_variadic: false,
+        default_expr: None,
+        locs: LocationSpan::internal(),
+    }
+}
+
+fn signature(name: &str, params: Vec<Param>, ret_typ: UnresolvedTypeName) -> AstMethodSignature {
+    AstMethodSignature {
+        name: method_firstname(name),
+        typarams: vec![],
+        params,
+        ret_typ: Some(ret_typ),
+        annotations: vec![],
+        locs: LocationSpan::internal(),
+    }
+}
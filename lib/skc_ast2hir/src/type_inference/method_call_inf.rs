@@ -1,13 +1,16 @@
+use crate::error::type_error;
 use crate::type_inference::{unify, Answer, Equation, TmpTy};
 use anyhow::Result;
 use shiika_core::ty;
-use shiika_core::ty::{TermTy, TyParamKind};
+use shiika_core::ty::{TermTy, TyParamKind, TyParamRef};
 use skc_hir::MethodSignature;
 
 /// Phase 1
 #[derive(Debug)]
 pub struct MethodCallInf1 {
     has_block: bool,
+    /// Method-wise type parameters to infer (`Unknown(i)` is `typarams[i]`)
+    typarams: Vec<TyParamRef>,
     pub method_arg_tys: Vec<TmpTy>,
     pub method_ret_ty: TmpTy,
     pub answer: Answer,
@@ -15,8 +18,8 @@ pub struct MethodCallInf1 {
 
 impl MethodCallInf1 {
     pub fn new(sig: &MethodSignature, has_block: bool) -> MethodCallInf1 {
-        let tprefs = ty::typarams_to_typaram_refs(&sig.typarams, TyParamKind::Method);
-        let vars = tprefs.into_iter().enumerate().collect::<Vec<_>>();
+        let typarams = ty::typarams_to_typaram_refs(&sig.typarams, TyParamKind::Method);
+        let vars = typarams.iter().cloned().enumerate().collect::<Vec<_>>();
         let method_arg_tys = sig
             .params
             .iter()
@@ -26,6 +29,7 @@ impl MethodCallInf1 {
 
         MethodCallInf1 {
             has_block,
+            typarams,
             method_arg_tys,
             method_ret_ty,
             answer: Default::default(),
@@ -43,6 +47,7 @@ impl MethodCallInf1 {
 
         MethodCallInf1 {
             has_block: true,
+            typarams: vec![],
             method_arg_tys,
             method_ret_ty,
             answer: Default::default(),
@@ -75,6 +80,7 @@ impl MethodCallInf1 {
 /// Only used when block exists.
 #[derive(Debug)]
 pub struct MethodCallInf2 {
+    typarams: Vec<TyParamRef>,
     pub solved_pre_block_arg_tys: Vec<TermTy>,
     pub block_ret_ty: TmpTy,
     pub method_ret_ty: TmpTy,
@@ -90,8 +96,9 @@ impl MethodCallInf2 {
     ) -> MethodCallInf2 {
         debug_assert!(&inf.has_block);
         MethodCallInf2 {
-            solved_pre_block_arg_tys,
             block_ret_ty: inf.block_ret_ty().clone(),
+            typarams: inf.typarams,
+            solved_pre_block_arg_tys,
             method_ret_ty: inf.method_ret_ty,
             solved_block_param_tys,
             answer: inf.answer,
@@ -100,33 +107,84 @@ impl MethodCallInf2 {
 }
 
 /// Phase 3 (All solved)
+#[derive(Debug)]
 pub struct MethodCallInf3 {
     pub solved_method_arg_tys: Vec<TermTy>,
     // Not used in current implementation
     //solved_method_ret_ty: TermTy,
+    /// Inferred method-wise type arguments (empty if the method does not
+    /// have type parameters or they are given explicitly)
+    pub solved_method_tyargs: Vec<TermTy>,
 }
 
 impl MethodCallInf3 {
-    fn with_block(inf: MethodCallInf2, solved_block_ret_ty: TermTy) -> MethodCallInf3 {
+    fn with_block(inf: MethodCallInf2, solved_block_ret_ty: TermTy) -> Result<MethodCallInf3> {
+        let solved_method_tyargs = solve_tyargs(&inf.typarams, &inf.answer)?;
         let solved_block_ty = ty::fn_ty(inf.solved_block_param_tys, solved_block_ret_ty);
         let mut solved_method_arg_tys = inf.solved_pre_block_arg_tys;
         solved_method_arg_tys.push(solved_block_ty);
-        MethodCallInf3 {
+        Ok(MethodCallInf3 {
             solved_method_arg_tys,
-        }
+            solved_method_tyargs,
+        })
     }
 }
 
+/// Returns the type arguments in `answer`. Returns `Err` if some of them
+/// could not be inferred.
+fn solve_tyargs(typarams: &[TyParamRef], answer: &Answer) -> Result<Vec<TermTy>> {
+    typarams
+        .iter()
+        .enumerate()
+        .map(|(id, typaram)| {
+            if answer.0.contains_key(&id) {
+                answer.apply_to(&TmpTy::Unknown(id))
+            } else {
+                Err(type_error(format!(
+                    "could not infer the type parameter `{}'; please give the type arguments explicitly",
+                    typaram.name
+                )))
+            }
+        })
+        .collect()
+}
+
+/// Infer the method-wise type arguments from the argument types (for
+/// method calls without a block)
+pub fn infer_method_tyargs(mut inf: MethodCallInf1, arg_tys: &[&TermTy]) -> Result<MethodCallInf3> {
+    debug_assert!(!inf.has_block);
+    let equations = inf
+        .method_arg_tys
+        .iter()
+        .zip(arg_tys.iter())
+        .map(|(l, r)| Equation(l.clone(), TmpTy::from(r)))
+        .collect::<Vec<_>>();
+    unify(equations, &mut inf.answer)?;
+    let solved_method_tyargs = solve_tyargs(&inf.typarams, &inf.answer)?;
+    Ok(MethodCallInf3 {
+        solved_method_arg_tys: inf.answer.apply_to_vec(&inf.method_arg_tys)?,
+        solved_method_tyargs,
+    })
+}
+
+/// Infer the block parameter types from the arguments before the block and
+/// the type annotations of the block parameters (if any)
 pub fn infer_block_param(
     mut inf: MethodCallInf1,
     pre_block_arg_tys: &[&TermTy],
+    annotated_block_param_tys: &[Option<TermTy>],
 ) -> Result<MethodCallInf2> {
-    let equations = inf
+    let mut equations = inf
         .method_arg_tys
         .iter()
         .zip(pre_block_arg_tys.iter())
         .map(|(l, r)| Equation(l.clone(), TmpTy::from(r)))
         .collect::<Vec<_>>();
+    for (l, r) in inf.block_param_tys().iter().zip(annotated_block_param_tys) {
+        if let Some(t) = r {
+            equations.push(Equation(l.clone(), TmpTy::from(t)));
+        }
+    }
     unify(equations, &mut inf.answer)?;
     let solved_pre_block_arg_tys = inf.answer.apply_to_vec(&inf.pre_block_arg_tys())?;
     let solved_block_param_tys = inf.answer.apply_to_vec(&inf.block_param_tys())?;
@@ -147,5 +205,5 @@ pub fn infer_result_ty_with_block(
     )];
     unify(equations, &mut inf.answer)?;
     let solved_block_ret_ty = inf.answer.apply_to(&inf.block_ret_ty)?;
-    Ok(MethodCallInf3::with_block(inf, solved_block_ret_ty))
+    MethodCallInf3::with_block(inf, solved_block_ret_ty)
 }
@@ -12,7 +12,8 @@ pub fn conforms(c: &ClassDict, ty1: &TermTy, ty2: &TermTy) -> bool {
         true
     } else if let TyBody::TyPara(ref1) = &ty1.body {
         if let TyBody::TyPara(ref2) = &ty2.body {
-            ref1.upper_bound == ref2.upper_bound && ref1.lower_bound == ref2.lower_bound
+            same_typaram(ref1, ref2)
+                || (ref1.upper_bound == ref2.upper_bound && ref1.lower_bound == ref2.lower_bound)
         } else {
             let u1 = ref1.upper_bound.to_term_ty();
             conforms(c, &u1, ty2)
@@ -28,7 +29,7 @@ pub fn conforms(c: &ClassDict, ty1: &TermTy, ty2: &TermTy) -> bool {
         let mod1 = c.get_type(&ty1.erasure().to_type_fullname()).is_module();
         let mod2 = c.get_type(&ty2.erasure().to_type_fullname()).is_module();
         match (mod1, mod2) {
-            (true, true) => false,
+            (true, true) => same_module(ty1, ty2),
             (true, false) => module_conforms_to_class(c, ty1, ty2),
             (false, true) => class_conforms_to_module(c, ty1, ty2),
             (false, false) => class_conforms_to_class(c, ty1, ty2),
@@ -36,6 +37,27 @@ pub fn conforms(c: &ClassDict, ty1: &TermTy, ty2: &TermTy) -> bool {
     }
 }
 
+// Return true if both refer to the same typaram. The bounds may differ
+// when the typaram is referred in its own bound (eg. `T: Comparable<T>`)
+fn same_typaram(ref1: &TyParamRef, ref2: &TyParamRef) -> bool {
+    ref1.kind == ref2.kind && ref1.idx == ref2.idx && ref1.name == ref2.name
+}
+
+// Return true if `ty1` and `ty2` are the same module type. Typarams in the
+// type arguments are compared by `same_typaram` because the upper bound of
+// eg. `T: Hashable<T>` is `Hashable<T>` whose `T` does not have the bound
+fn same_module(ty1: &TermTy, ty2: &TermTy) -> bool {
+    ty1.same_base(ty2)
+        && ty1
+            .tyargs()
+            .iter()
+            .zip(ty2.tyargs())
+            .all(|(t1, t2)| match (&t1.body, &t2.body) {
+                (TyBody::TyPara(ref1), TyBody::TyPara(ref2)) => same_typaram(ref1, ref2),
+                _ => t1.equals_to(t2),
+            })
+}
+
 // Return true only if `ty2` is the top type
 fn module_conforms_to_class(_c: &ClassDict, _ty1: &TermTy, ty2: &TermTy) -> bool {
     ty2.fullname.0 == "Object"
@@ -51,7 +73,7 @@ fn includes(c: &ClassDict, class: &TermTy, module: &TermTy) -> bool {
     let sk_class = c.get_class(&class.erasure().to_class_fullname());
     sk_class.includes.iter().any(|m| {
         // eg. Make `Enumerable<Int>` from `Enumerable<T>` and `Array<Int>`
-        let ms = m.ty().substitute(class.tyargs(), Default::default());
+        let ms = m.ty().substitute_class(class.tyargs());
         ms == *module
     })
 }
@@ -3,8 +3,9 @@ use crate::convert_exprs::block::BlockTaker;
 use crate::error::type_error;
 use crate::type_inference::method_call_inf;
 use anyhow::Result;
+use shiika_ast::LocationSpan;
 use shiika_core::{ty, ty::*};
-use skc_error::{self, Label};
+use skc_error::Diagnostic;
 use skc_hir::*;
 
 macro_rules! type_error {
@@ -13,11 +14,17 @@ macro_rules! type_error {
     })
 }
 
+/// Build a type error which shows the source at `locs` with `label`
+fn type_error_at(msg: String, label: String, locs: &LocationSpan) -> anyhow::Error {
+    type_error(Diagnostic::new(msg, locs, label).render())
+}
+
 pub fn check_return_value(
     class_dict: &ClassDict,
     sig: &MethodSignature,
-    ty: &TermTy,
+    exprs: &HirExpressions,
 ) -> Result<()> {
+    let ty = exprs.ty();
     if sig.ret_ty.is_void_type() {
         return Ok(());
     }
@@ -36,67 +43,111 @@ pub fn check_return_value(
     if class_dict.conforms(ty, &want) {
         Ok(())
     } else {
-        Err(type_error!(
+        let msg = format!(
             "{} should return {:?} but returns {:?}",
-            sig.fullname,
-            sig.ret_ty,
-            ty
+            sig.fullname, sig.ret_ty, ty
+        );
+        let last_expr = exprs.exprs().last().unwrap();
+        Err(type_error_at(
+            msg,
+            format!("this is {}", ty),
+            &last_expr.locs,
         ))
     }
 }
 
-pub fn check_logical_operator_ty(ty: &TermTy, on: &str) -> Result<()> {
-    if *ty == ty::raw("Bool") {
+pub fn check_logical_operator_ty(expr: &HirExpression, on: &str) -> Result<()> {
+    if expr.ty == ty::raw("Bool") {
         Ok(())
     } else {
-        Err(type_error!("{} must be bool but got {:?}", on, ty.fullname))
+        let msg = format!("{} must be bool but got {:?}", on, expr.ty.fullname);
+        Err(type_error_at(
+            msg,
+            format!("this is {}", expr.ty),
+            &expr.locs,
+        ))
     }
 }
 
-pub fn check_condition_ty(ty: &TermTy, on: &str) -> Result<()> {
-    if *ty == ty::raw("Bool") {
+pub fn check_condition_ty(cond: &HirExpression, on: &str) -> Result<()> {
+    if cond.ty == ty::raw("Bool") {
         Ok(())
     } else {
-        Err(type_error!(
+        let msg = format!(
             "{} condition must be bool but got {:?}",
-            on,
-            ty.fullname
+            on, cond.ty.fullname
+        );
+        Err(type_error_at(
+            msg,
+            format!("this is {}", cond.ty),
+            &cond.locs,
         ))
     }
 }
 
-pub fn check_if_body_ty(opt_ty: Option<TermTy>) -> Result<TermTy> {
+pub fn check_if_body_ty(
+    opt_ty: Option<TermTy>,
+    then_hirs: &HirExpressions,
+    else_hirs: &HirExpressions,
+    locs: &LocationSpan,
+) -> Result<TermTy> {
     match opt_ty {
         Some(ty) => Ok(ty),
-        None => Err(type_error!("if clauses type mismatch")),
+        None => {
+            let msg = format!(
+                "if clauses type mismatch ({} vs {})",
+                then_hirs.ty(),
+                else_hirs.ty()
+            );
+            let mut diag = Diagnostic::new(msg, locs, "if clauses type mismatch");
+            for (name, hirs) in [("then", then_hirs), ("else", else_hirs)] {
+                if let Some(last) = hirs.exprs().last() {
+                    diag = diag.label(&last.locs, format!("{} clause is {}", name, hirs.ty()));
+                }
+            }
+            Err(type_error(diag.render()))
+        }
     }
 }
 
 /// Check the type of the argument of `return`
 pub fn check_return_arg_type(
     class_dict: &ClassDict,
-    return_arg_ty: &TermTy,
+    return_arg: &HirExpression,
     method_sig: &MethodSignature,
 ) -> Result<()> {
-    if class_dict.conforms(return_arg_ty, &method_sig.ret_ty) {
+    if class_dict.conforms(&return_arg.ty, &method_sig.ret_ty) {
         Ok(())
     } else {
-        Err(type_error!(
+        let msg = format!(
             "method {} should return {} but returns {}",
-            &method_sig.fullname,
-            &method_sig.ret_ty,
-            &return_arg_ty
+            &method_sig.fullname, &method_sig.ret_ty, &return_arg.ty
+        );
+        Err(type_error_at(
+            msg,
+            format!("this is {}", return_arg.ty),
+            &return_arg.locs,
         ))
     }
 }
 
-pub fn invalid_reassign_error(orig_ty: &TermTy, new_ty: &TermTy, name: &str) -> anyhow::Error {
-    type_error!(
+pub fn invalid_reassign_error(
+    orig_ty: &TermTy,
+    new_ty: &TermTy,
+    name: &str,
+    locs: &LocationSpan,
+) -> anyhow::Error {
+    let msg = format!(
         "variable {} is {:?} but tried to assign a {:?}",
-        name,
-        orig_ty,
-        new_ty
-    )
+        name, orig_ty, new_ty
+    );
+    let report = Diagnostic::new(msg, locs, format!("this is {}", new_ty))
+        .note(format!(
+            "`{}' is {}; use another variable for {}",
+            name, orig_ty, new_ty
+        ))
+        .render();
+    type_error(report)
 }
 
 /// Check argument types of a method call
@@ -107,29 +158,121 @@ pub fn check_method_args(
     arg_hirs: &[HirExpression],
     inf: Option<method_call_inf::MethodCallInf3>,
 ) -> Result<()> {
-    let mut result = check_method_arity(sig, arg_hirs);
-    if result.is_ok() {
-        result = check_arg_types(class_dict, sig, arg_hirs, inf);
+    check_method_arity(sig, receiver_hir, arg_hirs)?;
+    check_arg_types(class_dict, sig, arg_hirs, inf)?;
+    if sig.fullname.first_name.0 == "new" {
+        check_instantiable(class_dict, receiver_hir)?;
+    }
+    Ok(())
+}
+
+/// Check method-wise type arguments conform to the bounds of the type
+/// parameters
+pub fn check_method_tyargs(
+    class_dict: &ClassDict,
+    sig: &MethodSignature,
+    tyargs: &[TermTy],
+) -> Result<()> {
+    for (typaram, tyarg) in sig.typarams.iter().zip(tyargs) {
+        let bound = typaram.upper_bound.to_term_ty().substitute_method(tyargs);
+        if !class_dict.conforms(tyarg, &bound) {
+            return Err(type_error!(
+                "the type argument `{}' of {} does not conform to the bound `{}: {}'",
+                tyarg,
+                sig.fullname,
+                typaram.name,
+                bound
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Check the type arguments of a generic class conform to the bounds of
+/// its typarams (eg. `K: Hashable<K>` of `HashMap`)
+pub fn check_class_tyargs(
+    class_dict: &ClassDict,
+    class_ty: &TermTy,
+    typarams: &[TyParam],
+    tyargs: &[TermTy],
+) -> Result<()> {
+    for (typaram, tyarg) in typarams.iter().zip(tyargs) {
+        let bound = typaram.upper_bound.to_term_ty().substitute_class(tyargs);
+        if !class_dict.conforms(tyarg, &bound) {
+            return Err(type_error!(
+                "the type argument `{}' of {} does not conform to the bound `{}: {}'",
+                tyarg,
+                class_ty,
+                typaram.name,
+                bound
+            ));
+        }
     }
+    Ok(())
+}
 
-    if result.is_err() {
-        // Remove this when shiika can show the location in the .sk
-        dbg!(&receiver_hir);
-        dbg!(&sig.fullname);
-        dbg!(&arg_hirs);
+/// Check the class of `receiver_hir.new` does not have unimplemented
+/// abstract methods
+fn check_instantiable(class_dict: &ClassDict, receiver_hir: &HirExpression) -> Result<()> {
+    // `self.new` in a class method may create an instance of a subclass
+    if !receiver_hir.ty.is_metaclass() || is_self_expr(receiver_hir) {
+        return Ok(());
+    }
+    let classname = receiver_hir.ty.instance_ty().erasure().to_class_fullname();
+    let unimplemented = class_dict.unimplemented_abstract_methods(&classname);
+    match unimplemented.first() {
+        Some(name) => {
+            let msg = format!(
+                "cannot instantiate abstract class {} ({} is not implemented)",
+                classname, name
+            );
+            let report = Diagnostic::new(
+                msg,
+                &receiver_hir.locs,
+                format!("{} is abstract", classname),
+            )
+            .note(format!("define `{}' in {}", name.first_name, classname))
+            .render();
+            Err(type_error(report))
+        }
+        None => Ok(()),
+    }
+}
+
+fn is_self_expr(expr: &HirExpression) -> bool {
+    match &expr.node {
+        HirExpressionBase::HirSelfExpression => true,
+        HirExpressionBase::HirBitCast { expr } => is_self_expr(expr),
+        _ => false,
     }
-    result
 }
 
 /// Check number of method call args
-fn check_method_arity(sig: &MethodSignature, arg_hirs: &[HirExpression]) -> Result<()> {
+fn check_method_arity(
+    sig: &MethodSignature,
+    receiver_hir: &HirExpression,
+    arg_hirs: &[HirExpression],
+) -> Result<()> {
     if sig.params.len() != arg_hirs.len() {
-        return Err(type_error!(
+        let msg = format!(
             "{} takes {} args but got {}",
             sig.fullname,
             sig.params.len(),
             arg_hirs.len()
-        ));
+        );
+        let locs = match arg_hirs.last() {
+            Some(last) => LocationSpan::merge(&receiver_hir.locs, &last.locs),
+            None => receiver_hir.locs.clone(),
+        };
+        let mut diag = Diagnostic::new(msg.clone(), &locs, msg);
+        for param in &sig.params {
+            diag = diag.label(
+                &param.locs,
+                format!("parameter `{}' is declared here", param.name),
+            );
+        }
+        let report = diag.note(format!("the signature is `{}'", sig)).render();
+        return Err(type_error(report));
     }
     Ok(())
 }
@@ -175,10 +318,12 @@ fn check_arg_type(
             param.name, sig.fullname, param.ty, arg_ty
         )
     };
-    let locs = &arg_hir.locs;
-    let report = skc_error::build_report(msg, locs, |r, locs_span| {
-        r.with_label(Label::new(locs_span).with_message(&arg_hir.ty))
-    });
+    let report = Diagnostic::new(msg, &arg_hir.locs, format!("this is {}", arg_ty))
+        .label(
+            &param.locs,
+            format!("parameter `{}' is declared here", param.name),
+        )
+        .render();
     Err(type_error(report))
 }
 
@@ -199,9 +344,6 @@ pub fn check_block_arity(
         expected,
         params.len()
     );
-    let locs = &block_taker.locs();
-    let report = skc_error::build_report(msg.clone(), locs, |r, locs_span| {
-        r.with_label(Label::new(locs_span).with_message(msg))
-    });
+    let report = Diagnostic::new(msg.clone(), block_taker.locs(), msg).render();
     Err(type_error(report))
 }
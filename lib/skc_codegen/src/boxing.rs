@@ -32,6 +32,14 @@ impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
             .f64_type
             .fn_type(&[self.llvm_type(&ty::raw("Float")).into()], false);
         self.module.add_function("unbox_float", fn_type, None);
+        let fn_type = self
+            .llvm_type(&ty::raw("Char"))
+            .fn_type(&[self.i32_type.into()], false);
+        self.module.add_function("box_char", fn_type, None);
+        let fn_type = self
+            .i32_type
+            .fn_type(&[self.llvm_type(&ty::raw("Char")).into()], false);
+        self.module.add_function("unbox_char", fn_type, None);
         let fn_type = self
             .llvm_type(&ty::raw("Shiika::Internal::Ptr"))
             .fn_type(&[self.i8ptr_type.into()], false);
@@ -107,6 +115,25 @@ impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
         let f64_val = self.build_ivar_load(sk_float, 0, "@llvm_float");
         self.build_return(&f64_val);
 
+        // box_char
+        let function = self.module.get_function("box_char").unwrap();
+        let basic_block = self.context.append_basic_block(function, "");
+        self.builder.position_at_end(basic_block);
+
+        let i32_val = SkObj(function.get_params()[0]);
+        let sk_char = self.allocate_sk_obj(&class_fullname("Char"), "sk_char");
+        self.build_ivar_store(&sk_char, 0, i32_val, "@llvm_char");
+        self.build_return(&sk_char);
+
+        // unbox_char
+        let function = self.module.get_function("unbox_char").unwrap();
+        let basic_block = self.context.append_basic_block(function, "");
+        self.builder.position_at_end(basic_block);
+
+        let sk_char = SkObj(function.get_params()[0]);
+        let i32_val = self.build_ivar_load(sk_char, 0, "@llvm_char");
+        self.build_return(&i32_val);
+
         // box_i8ptr
         let function = self.module.get_function("box_i8ptr").unwrap();
         let basic_block = self.context.append_basic_block(function, "");
@@ -201,6 +228,15 @@ impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
         .into_float_value()
     }
 
+    /// Convert LLVM i32 into Shiika Char
+    pub fn box_char(&self, c: &inkwell::values::IntValue<'run>) -> SkObj<'run> {
+        SkObj(self.call_llvm_func(
+            &llvm_func_name("box_char"),
+            &[c.as_basic_value_enum().into()],
+            "sk_char",
+        ))
+    }
+
     /// Convert LLVM i8* into Shiika::Internal::Ptr
     pub fn box_i8ptr(&self, p: inkwell::values::BasicValueEnum<'run>) -> SkObj<'run> {
         SkObj(self.call_llvm_func(&llvm_func_name("box_i8ptr"), &[p.into()], "sk_ptr"))
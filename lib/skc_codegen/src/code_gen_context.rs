@@ -1,4 +1,5 @@
 use crate::values::SkObj;
+use shiika_core::ty::TermTy;
 use skc_hir::*;
 use std::collections::HashMap;
 use std::rc::Rc;
@@ -14,12 +15,30 @@ pub struct CodeGenContext<'hir: 'run, 'run> {
     pub function_params: Option<&'hir [MethodParam]>,
     /// Ptr of local variables
     pub lvars: HashMap<String, inkwell::values::PointerValue<'run>>,
+    /// Beginning of `while` (where the condition is evaluated), if any
+    pub current_loop_begin: Option<Rc<inkwell::basic_block::BasicBlock<'run>>>,
     /// End of `while`, if any
     pub current_loop_end: Option<Rc<inkwell::basic_block::BasicBlock<'run>>>,
+    /// Type of the current `while`, if any
+    pub current_loop_ty: Option<&'hir TermTy>,
+    /// Values of `break` found in the current `while`
+    pub loop_breaks: Vec<(SkObj<'run>, inkwell::basic_block::BasicBlock<'run>)>,
     /// End of the current llvm function. Only used for lambdas
     pub current_func_end: Rc<inkwell::basic_block::BasicBlock<'run>>,
     /// Arguments of `return` found in this context
     pub returns: Vec<(SkObj<'run>, inkwell::basic_block::BasicBlock<'run>)>,
+    /// Set if the method ends with a self-recursive call (see tail_call.rs)
+    pub tail_call: Option<TailCall<'hir, 'run>>,
+}
+
+#[derive(Debug)]
+pub struct TailCall<'hir: 'run, 'run> {
+    /// The self-recursive call to be replaced with a jump
+    pub expr: &'hir HirExpression,
+    /// Beginning of the method body
+    pub loop_start: inkwell::basic_block::BasicBlock<'run>,
+    /// Ptr of the arguments
+    pub arg_ptrs: Vec<inkwell::values::PointerValue<'run>>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -42,9 +61,21 @@ impl<'hir, 'run> CodeGenContext<'hir, 'run> {
             function_origin,
             function_params,
             lvars,
+            current_loop_begin: None,
             current_loop_end: None,
+            current_loop_ty: None,
+            loop_breaks: Default::default(),
             current_func_end: function_end,
             returns: Default::default(),
+            tail_call: None,
+        }
+    }
+
+    /// Returns true if `expr` is the self-recursive call to be eliminated
+    pub fn is_tail_call(&self, expr: &HirExpression) -> bool {
+        match &self.tail_call {
+            Some(tail_call) => std::ptr::eq(tail_call.expr, expr),
+            None => false,
         }
     }
 
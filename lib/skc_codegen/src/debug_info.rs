@@ -0,0 +1,208 @@
+//! DWARF debug information for `--debug-info`
+use crate::CodeGen;
+use inkwell::basic_block::BasicBlock;
+use inkwell::debug_info::{
+    AsDIScope, DICompileUnit, DIFile, DIFlags, DIFlagsConstants, DIScope, DIType,
+    DWARFEmissionKind, DWARFSourceLanguage, DebugInfoBuilder,
+};
+use inkwell::module::FlagBehavior;
+use inkwell::values::{FunctionValue, PointerValue};
+use shiika_ast::LocationSpan;
+use std::cell::Cell;
+use std::path::Path;
+
+/// `DW_ATE_address`
+const DW_ATE_ADDRESS: u32 = 0x01;
+
+/// Builder of the debug information of a module
+pub struct DebugInfo<'ictx> {
+    builder: DebugInfoBuilder<'ictx>,
+    compile_unit: DICompileUnit<'ictx>,
+    /// Type of Shiika values (they are all pointers)
+    obj_type: DIType<'ictx>,
+    /// Scope of the function being generated, if it has debug information
+    current_scope: Cell<Option<DIScope<'ictx>>>,
+}
+
+impl<'ictx> DebugInfo<'ictx> {
+    /// Create the compile unit for `src_path`
+    pub fn new(
+        context: &'ictx inkwell::context::Context,
+        module: &inkwell::module::Module<'ictx>,
+        src_path: &str,
+    ) -> DebugInfo<'ictx> {
+        let version = context.i32_type().const_int(3, false);
+        module.add_basic_value_flag("Debug Info Version", FlagBehavior::Warning, version);
+        let (filename, directory) = split_path(Path::new(src_path));
+        let (builder, compile_unit) = module.create_debug_info_builder(
+            true,
+            DWARFSourceLanguage::C,
+            &filename,
+            &directory,
+            "shiika",
+            false,
+            "",
+            0,
+            "",
+            DWARFEmissionKind::Full,
+            0,
+            false,
+            false,
+            "",
+            "",
+        );
+        let obj_type = builder
+            .create_basic_type("Object", 64, DW_ATE_ADDRESS, DIFlags::PUBLIC)
+            .expect("failed to create debug info type")
+            .as_type();
+        DebugInfo {
+            builder,
+            compile_unit,
+            obj_type,
+            current_scope: Cell::new(None),
+        }
+    }
+
+    /// Resolve the debug information. Must be called before writing the module
+    pub fn finalize(&self) {
+        self.builder.finalize();
+    }
+
+    /// Returns the DIFile of `locs`
+    fn file(&self, locs: &LocationSpan) -> DIFile<'ictx> {
+        match locs {
+            LocationSpan::Just { filepath, .. } => {
+                let (filename, directory) = split_path(filepath);
+                self.builder.create_file(&filename, &directory)
+            }
+            LocationSpan::Empty => self.compile_unit.get_file(),
+        }
+    }
+}
+
+impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
+    /// Attach a DISubprogram to `function` and set the current debug location
+    /// to its beginning. Returns the scope for the contents of the function
+    /// (or None if `--debug-info` is not given.)
+    pub(super) fn gen_subprogram(
+        &self,
+        function: FunctionValue<'run>,
+        name: &str,
+        locs: &LocationSpan,
+    ) -> Option<DIScope<'ictx>> {
+        let dbg = self.debug_info.as_ref()?;
+        let file = dbg.file(locs);
+        let line = line_of(locs);
+        let param_types = vec![dbg.obj_type; function.count_params() as usize];
+        let subroutine_type = dbg.builder.create_subroutine_type(
+            file,
+            Some(dbg.obj_type),
+            &param_types,
+            DIFlags::PUBLIC,
+        );
+        let linkage_name = function.get_name().to_string_lossy().to_string();
+        let subprogram = dbg.builder.create_function(
+            dbg.compile_unit.as_debug_info_scope(),
+            name,
+            Some(&linkage_name),
+            file,
+            line,
+            subroutine_type,
+            false,
+            true,
+            line,
+            DIFlags::PUBLIC,
+            false,
+        );
+        function.set_subprogram(subprogram);
+        let scope = subprogram.as_debug_info_scope();
+        dbg.current_scope.set(Some(scope));
+        self.set_debug_location(locs);
+        Some(scope)
+    }
+
+    /// Declare a local variable stored in `ptr`
+    pub(super) fn gen_lvar_debug_info(
+        &self,
+        scope: DIScope<'ictx>,
+        name: &str,
+        ptr: PointerValue<'run>,
+        block: BasicBlock<'run>,
+    ) {
+        let dbg = match &self.debug_info {
+            Some(x) => x,
+            None => return,
+        };
+        let var = dbg.builder.create_auto_variable(
+            scope,
+            name,
+            dbg.compile_unit.get_file(),
+            0,
+            dbg.obj_type,
+            true,
+            DIFlags::ZERO,
+            0,
+        );
+        let loc = dbg
+            .builder
+            .create_debug_location(self.context, 0, 0, scope, None);
+        dbg.builder
+            .insert_declare_at_end(ptr, Some(var), None, loc, block);
+    }
+
+    /// Set the current debug location to `locs` (if generating a function
+    /// with debug information)
+    pub(super) fn set_debug_location(&self, locs: &LocationSpan) {
+        let dbg = match &self.debug_info {
+            Some(x) => x,
+            None => return,
+        };
+        if let (Some(scope), LocationSpan::Just { .. }) = (dbg.current_scope.get(), locs) {
+            let loc = dbg.builder.create_debug_location(
+                self.context,
+                line_of(locs),
+                col_of(locs),
+                scope,
+                None,
+            );
+            self.builder.set_current_debug_location(self.context, loc);
+        }
+    }
+
+    /// Clear the current debug location (on leaving a function)
+    pub(super) fn unset_debug_location(&self) {
+        if let Some(dbg) = &self.debug_info {
+            dbg.current_scope.set(None);
+            self.builder.unset_current_debug_location();
+        }
+    }
+}
+
+/// Returns the file name and the directory name of `path`
+fn split_path(path: &Path) -> (String, String) {
+    let filename = path
+        .file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let directory = path
+        .parent()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    (filename, directory)
+}
+
+/// Line number in DWARF (1-origin)
+fn line_of(locs: &LocationSpan) -> u32 {
+    match locs {
+        LocationSpan::Just { begin, .. } => begin.line as u32,
+        LocationSpan::Empty => 0,
+    }
+}
+
+/// Column number in DWARF (1-origin)
+fn col_of(locs: &LocationSpan) -> u32 {
+    match locs {
+        LocationSpan::Just { begin, .. } => begin.col as u32 + 1,
+        LocationSpan::Empty => 0,
+    }
+}
@@ -34,9 +34,9 @@ impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
         ctx: &mut CodeGenContext<'hir, 'run>,
         exprs: &'hir HirExpressions,
     ) -> Result<Option<SkObj<'run>>> {
-        debug_assert!(!exprs.exprs.is_empty());
+        debug_assert!(!exprs.exprs().is_empty());
         let mut last_value = None;
-        for expr in &exprs.exprs {
+        for expr in exprs.exprs() {
             let value = self.gen_expr(ctx, expr)?;
             if value.is_none() {
                 log::warn!("detected unreachable code");
@@ -53,6 +53,7 @@ impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
         ctx: &mut CodeGenContext<'hir, 'run>,
         expr: &'hir HirExpression,
     ) -> Result<Option<SkObj<'run>>> {
+        self.set_debug_location(&expr.locs);
         match &expr.node {
             HirLogicalNot { expr } => self.gen_logical_not(ctx, expr),
             HirLogicalAnd { left, right } => self.gen_logical_and(ctx, left, right),
@@ -69,8 +70,9 @@ impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
             HirWhileExpression {
                 cond_expr,
                 body_exprs,
-            } => self.gen_while_expr(ctx, cond_expr, body_exprs),
-            HirBreakExpression { from } => self.gen_break_expr(ctx, from),
+            } => self.gen_while_expr(ctx, &expr.ty, cond_expr, body_exprs),
+            HirBreakExpression { from, value } => self.gen_break_expr(ctx, from, value),
+            HirContinueExpression { from } => self.gen_continue_expr(ctx, from),
             HirReturnExpression { arg, .. } => self.gen_return_expr(ctx, arg),
             HirLVarAssign { name, rhs } => self.gen_lvar_assign(ctx, name, rhs),
             HirIVarAssign {
@@ -81,11 +83,33 @@ impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
                 ..
             } => self.gen_ivar_assign(ctx, name, idx, rhs, self_ty),
             HirConstAssign { fullname, rhs } => self.gen_const_assign(ctx, fullname, rhs),
+            HirMethodCall { arg_exprs, .. } if ctx.is_tail_call(expr) => {
+                self.gen_tail_call(ctx, arg_exprs)
+            }
             HirMethodCall {
                 receiver_expr,
                 method_fullname,
                 arg_exprs,
-            } => self.gen_method_call(ctx, method_fullname, receiver_expr, arg_exprs, &expr.ty),
+            } => self.gen_method_call(
+                ctx,
+                method_fullname,
+                receiver_expr,
+                arg_exprs,
+                &expr.ty,
+                true,
+            ),
+            HirSuperMethodCall {
+                receiver_expr,
+                method_fullname,
+                arg_exprs,
+            } => self.gen_method_call(
+                ctx,
+                method_fullname,
+                receiver_expr,
+                arg_exprs,
+                &expr.ty,
+                false,
+            ),
             HirModuleMethodCall {
                 receiver_expr,
                 module_fullname,
@@ -106,6 +130,10 @@ impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
                 arg_exprs,
             } => self.gen_lambda_invocation(ctx, lambda_expr, arg_exprs, &expr.ty),
             HirArgRef { idx } => Ok(Some(self.gen_arg_ref(ctx, idx))),
+            HirOmittedArg => Ok(Some(self.gen_omitted_arg(&expr.ty))),
+            HirArgOrDefault { idx, default_expr } => {
+                self.gen_arg_or_default(ctx, &expr.ty, idx, default_expr)
+            }
             HirLVarRef { name } => Ok(Some(self.gen_lvar_ref(ctx, name))),
             HirIVarRef { name, idx, self_ty } => {
                 Ok(Some(self.gen_ivar_ref(ctx, name, idx, self_ty)))
@@ -131,7 +159,14 @@ impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
             HirSelfExpression => Ok(Some(self.gen_self_expression(ctx, &expr.ty))),
             HirFloatLiteral { value } => Ok(Some(self.gen_float_literal(*value))),
             HirDecimalLiteral { value } => Ok(Some(self.gen_decimal_literal(*value))),
-            HirStringLiteral { idx } => Ok(Some(self.gen_string_literal(idx))),
+            HirStringLiteral { idx, frozen } => {
+                if *frozen {
+                    Ok(Some(self.gen_frozen_string_literal(ctx, idx)))
+                } else {
+                    Ok(Some(self.gen_string_literal(idx)))
+                }
+            }
+            HirCharLiteral { value } => Ok(Some(self.gen_char_literal(*value))),
             HirBooleanLiteral { value } => Ok(Some(self.gen_boolean_literal(*value))),
 
             HirLambdaCaptureRef { idx, readonly } => Ok(Some(
@@ -361,7 +396,7 @@ impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
         skip_block: inkwell::basic_block::BasicBlock,
         result_ty: &TermTy,
     ) -> Result<Option<SkObj<'run>>> {
-        let lvar_ptrs = self.gen_alloca_lvars(ctx.function, &clause.lvars);
+        let lvar_ptrs = self.gen_alloca_lvars(ctx.function, &clause.lvars, None);
         let orig_lvars = ctx.inject_lvars(lvar_ptrs);
         for component in &clause.components {
             match component {
@@ -387,6 +422,7 @@ impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
     fn gen_while_expr(
         &self,
         ctx: &mut CodeGenContext<'hir, 'run>,
+        while_ty: &'hir TermTy,
         cond_expr: &'hir HirExpression,
         body_exprs: &'hir HirExpressions,
     ) -> Result<Option<SkObj<'run>>> {
@@ -395,6 +431,7 @@ impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
         // WhileBegin:
         self.builder.position_at_end(begin_block);
         let cond_value = self.gen_expr(ctx, cond_expr)?.unwrap();
+        let cond_block_end = self.builder.get_insert_block().unwrap();
         let body_block = self.context.append_basic_block(ctx.function, "WhileBody");
         let end_block = self.context.append_basic_block(ctx.function, "WhileEnd");
         self.gen_conditional_branch(cond_value, body_block, end_block);
@@ -402,30 +439,68 @@ impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
         self.builder.position_at_end(body_block);
         let rc1 = Rc::new(end_block);
         let rc2 = Rc::clone(&rc1);
+        let orig_loop_begin = ctx.current_loop_begin.replace(Rc::new(begin_block));
         let orig_loop_end = ctx.current_loop_end.as_ref().map(Rc::clone);
+        let orig_loop_ty = ctx.current_loop_ty.replace(while_ty);
+        let orig_loop_breaks = std::mem::take(&mut ctx.loop_breaks);
         ctx.current_loop_end = Some(rc1);
-        self.gen_exprs(ctx, body_exprs)?;
+        let body_value = self.gen_exprs(ctx, body_exprs)?;
+        ctx.current_loop_begin = orig_loop_begin;
         ctx.current_loop_end = orig_loop_end;
-        self.builder.build_unconditional_branch(begin_block);
+        ctx.current_loop_ty = orig_loop_ty;
+        let breaks = std::mem::replace(&mut ctx.loop_breaks, orig_loop_breaks);
+        // The body may end with `break` or `continue`
+        if body_value.is_some() {
+            self.builder.build_unconditional_branch(begin_block);
+        }
 
         // WhileEnd:
         self.builder.position_at_end(*rc2);
-        Ok(Some(self.gen_const_ref(&toplevel_const("Void"))))
+        if while_ty.is_void_type() {
+            return Ok(Some(self.gen_const_ref(&toplevel_const("Void"))));
+        }
+        let phi_node = self
+            .builder
+            .build_phi(self.llvm_type(while_ty), "whileResult");
+        // The condition is `true` here so this edge is never taken
+        let null = self.null_ptr(while_ty);
+        phi_node.add_incoming(&[(&null.0, cond_block_end)]);
+        for (value, block) in &breaks {
+            phi_node.add_incoming(&[(&value.0, *block)]);
+        }
+        Ok(Some(SkObj(phi_node.as_basic_value())))
     }
 
     fn gen_break_expr(
         &self,
         ctx: &mut CodeGenContext<'hir, 'run>,
         from: &HirBreakFrom,
+        value: &'hir Option<Box<HirExpression>>,
     ) -> Result<Option<SkObj<'run>>> {
         match from {
-            HirBreakFrom::While => match &ctx.current_loop_end {
-                Some(b) => {
-                    self.builder.build_unconditional_branch(*Rc::clone(b));
-                    Ok(None)
+            HirBreakFrom::While => {
+                if let Some(expr) = value {
+                    let v = match self.gen_expr(ctx, expr)? {
+                        Some(v) => v,
+                        // `expr` never returns
+                        None => return Ok(None),
+                    };
+                    let while_ty = ctx.current_loop_ty.expect("[BUG] break outside of a loop");
+                    // The value is discarded if the `while` is Void
+                    if !while_ty.is_void_type() {
+                        let v = self.bitcast(v, while_ty, "as");
+                        let block_end = self.builder.get_insert_block().unwrap();
+                        ctx.loop_breaks.push((v, block_end));
+                    }
                 }
-                None => panic!("[BUG] break outside of a loop"),
-            },
+                match &ctx.current_loop_end {
+                    Some(b) => {
+                        self.builder.build_unconditional_branch(*Rc::clone(b));
+                        Ok(None)
+                    }
+                    None => panic!("[BUG] break outside of a loop"),
+                }
+            }
             HirBreakFrom::Block => {
                 debug_assert!(ctx.function_origin == FunctionOrigin::Lambda);
                 // Set @exit_status
@@ -441,6 +516,30 @@ impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
         }
     }
 
+    fn gen_continue_expr(
+        &self,
+        ctx: &mut CodeGenContext<'hir, 'run>,
+        from: &HirBreakFrom,
+    ) -> Result<Option<SkObj<'run>>> {
+        match from {
+            HirBreakFrom::While => match &ctx.current_loop_begin {
+                Some(b) => {
+                    self.builder.build_unconditional_branch(*Rc::clone(b));
+                    Ok(None)
+                }
+                None => panic!("[BUG] continue outside of a loop"),
+            },
+            HirBreakFrom::Block => {
+                debug_assert!(ctx.function_origin == FunctionOrigin::Lambda);
+                // Finish this call of the block. The return value is not
+                // used because the block is `Void` (checked in ast2hir)
+                self.builder
+                    .build_unconditional_branch(*Rc::clone(&ctx.current_func_end));
+                Ok(None)
+            }
+        }
+    }
+
     fn gen_return_expr(
         &self,
         ctx: &mut CodeGenContext<'hir, 'run>,
@@ -479,11 +578,43 @@ impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
         self_ty: &TermTy,
     ) -> Result<Option<SkObj<'run>>> {
         let object = self.gen_self_expression(ctx, self_ty);
-        let value = self.gen_expr(ctx, rhs)?.unwrap();
+        let value = match self.gen_expr(ctx, rhs)? {
+            Some(v) => v,
+            // `rhs` never returns
+            None => return Ok(None),
+        };
+        self.gen_frozen_check(ctx, &object, name);
         self.build_ivar_store(&object, *idx, value.clone(), name);
         Ok(Some(value))
     }
 
+    /// Build IR to panic if `object` is frozen
+    fn gen_frozen_check(
+        &self,
+        ctx: &mut CodeGenContext<'hir, 'run>,
+        object: &SkObj<'run>,
+        name: &str,
+    ) {
+        let frozen_block = self.context.append_basic_block(ctx.function, "IVarFrozen");
+        let store_block = self.context.append_basic_block(ctx.function, "IVarStore");
+        let is_frozen = self.build_is_frozen(object.clone());
+        self.builder
+            .build_conditional_branch(is_frozen, frozen_block, store_block);
+        // IVarFrozen:
+        self.builder.position_at_end(frozen_block);
+        let name_ptr = self
+            .builder
+            .build_global_string_ptr(name, "ivar_name")
+            .as_pointer_value();
+        let obj = self.builder.build_bitcast(object.0, self.i8ptr_type, "obj");
+        let f = self.get_llvm_func(&llvm_func_name("shiika_frozen_error"));
+        self.builder
+            .build_call(f, &[obj.into(), name_ptr.into()], "");
+        self.builder.build_unreachable();
+        // IVarStore:
+        self.builder.position_at_end(store_block);
+    }
+
     fn gen_const_assign(
         &self,
         ctx: &mut CodeGenContext<'hir, 'run>,
@@ -502,6 +633,8 @@ impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
     }
 
     /// Generate method call
+    /// Generate a method call. If `dispatch` is false, the method is called
+    /// directly without looking up the vtable (for `super`)
     fn gen_method_call(
         &self,
         ctx: &mut CodeGenContext<'hir, 'run>,
@@ -509,6 +642,7 @@ impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
         receiver_expr: &'hir HirExpression,
         arg_exprs: &'hir [HirExpression],
         ret_ty: &TermTy,
+        dispatch: bool,
     ) -> Result<Option<SkObj<'run>>> {
         // Prepare arguments
         let receiver_value = self.gen_expr(ctx, receiver_expr)?.unwrap();
@@ -531,10 +665,11 @@ impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
             ret_ty,
         );
         let func = self._get_method_func(
-            &method_fullname.first_name,
+            method_fullname,
             &receiver_expr.ty,
             receiver_value.clone(),
             func_type,
+            dispatch,
         );
 
         let result = self.gen_llvm_function_call(
@@ -558,14 +693,24 @@ impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
     /// Retrieve the llvm func
     fn _get_method_func(
         &self,
-        method_name: &MethodFirstname,
+        method_fullname: &MethodFullname,
         receiver_ty: &TermTy,
         receiver_value: SkObj<'run>,
         func_type: inkwell::types::FunctionType<'ictx>,
+        dispatch: bool,
     ) -> inkwell::values::PointerValue<'run> {
-        let vtable = self.get_vtable_of_obj(receiver_value);
-        let (idx, size) = self.__lookup_vtable(receiver_ty, method_name);
-        let func_raw = self.build_vtable_ref(vtable, *idx, size);
+        // Methods added to an imported class by `extension` are not in the
+        // vtable. They are called directly (as well as `super`)
+        let func_raw = if dispatch && !self.extension_methods.contains(method_fullname) {
+            let vtable = self.get_vtable_of_obj(receiver_value);
+            let (idx, size) = self.__lookup_vtable(receiver_ty, &method_fullname.first_name);
+            self.build_vtable_ref(vtable, *idx, size)
+        } else {
+            self.get_llvm_func(&method_func_name(method_fullname))
+                .as_global_value()
+                .as_pointer_value()
+                .as_basic_value_enum()
+        };
         self.builder
             .build_bitcast(func_raw, func_type.ptr_type(AddressSpace::Generic), "func")
             .into_pointer_value()
@@ -777,7 +922,12 @@ impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
     fn gen_arg_ref(&self, ctx: &mut CodeGenContext<'hir, 'run>, idx: &usize) -> SkObj<'run> {
         match ctx.function_origin {
             FunctionOrigin::Method => {
-                SkObj(ctx.function.get_nth_param((*idx as u32) + 1).unwrap()) // +1 for the first %self
+                if let Some(tail_call) = &ctx.tail_call {
+                    SkObj(self.builder.build_load(tail_call.arg_ptrs[*idx], "arg"))
+                } else {
+                    // +1 for the first %self
+                    SkObj(ctx.function.get_nth_param((*idx as u32) + 1).unwrap())
+                }
             }
             FunctionOrigin::Lambda => {
                 // +1 for the first %self
@@ -789,6 +939,52 @@ impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
         }
     }
 
+    /// Generate IR for HirOmittedArg (a null pointer)
+    fn gen_omitted_arg(&self, ty: &TermTy) -> SkObj<'run> {
+        let null = self.llvm_type(ty).into_pointer_type().const_null();
+        SkObj(null.as_basic_value_enum())
+    }
+
+    /// Generate IR for HirArgOrDefault i.e. returns the argument, or
+    /// computes the default value if it is omitted (null)
+    fn gen_arg_or_default(
+        &self,
+        ctx: &mut CodeGenContext<'hir, 'run>,
+        ty: &TermTy,
+        idx: &usize,
+        default_expr: &'hir HirExpression,
+    ) -> Result<Option<SkObj<'run>>> {
+        let arg = self.gen_arg_ref(ctx, idx);
+        let arg_block = self.builder.get_insert_block().unwrap();
+        let default_block = self.context.append_basic_block(ctx.function, "DefaultArg");
+        let merge_block = self
+            .context
+            .append_basic_block(ctx.function, "DefaultArgEnd");
+        let omitted = self
+            .builder
+            .build_is_null(arg.0.into_pointer_value(), "omitted");
+        self.builder
+            .build_conditional_branch(omitted, default_block, merge_block);
+        // DefaultArg:
+        self.builder.position_at_end(default_block);
+        let default_value = match self.gen_expr(ctx, default_expr)? {
+            Some(v) => Some(self.bitcast(v, ty, "default")),
+            None => None,
+        };
+        let default_block_end = self.builder.get_insert_block().unwrap();
+        if default_value.is_some() {
+            self.builder.build_unconditional_branch(merge_block);
+        }
+        // DefaultArgEnd:
+        self.builder.position_at_end(merge_block);
+        let phi_node = self.builder.build_phi(self.llvm_type(ty), "argOrDefault");
+        phi_node.add_incoming(&[(&arg.0, arg_block)]);
+        if let Some(v) = &default_value {
+            phi_node.add_incoming(&[(&v.0, default_block_end)]);
+        }
+        Ok(Some(SkObj(phi_node.as_basic_value())))
+    }
+
     fn gen_lvar_ref(&self, ctx: &mut CodeGenContext<'hir, 'run>, name: &str) -> SkObj<'run> {
         let ptr = ctx
             .lvars
@@ -857,7 +1053,13 @@ impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
         let mut arg_types = (1..=params.len()).map(|_| &obj_type).collect::<Vec<_>>();
         arg_types.insert(0, fn_x_type);
         let func_type = self.llvm_func_type(None, &arg_types, ret_ty);
-        self.module.add_function(&func_name.0, func_type, None);
+        // Internal because the names may conflict with the ones in another
+        // module (see `ModuleKind::Library`)
+        self.module.add_function(
+            &func_name.0,
+            func_type,
+            Some(inkwell::module::Linkage::Internal),
+        );
 
         // eg. Fn1.new(fnptr, the_self, captures)
         let cls_name = format!("Fn{}", params.len());
@@ -889,9 +1091,15 @@ impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
         );
         for cap in captures {
             let item = match cap {
-                HirLambdaCapture::CaptureLVar { name } => {
-                    // Local vars are captured by pointer
-                    SkObj(ctx.lvars.get(name).unwrap().as_basic_value_enum())
+                HirLambdaCapture::CaptureLVar { name, cell } => {
+                    let ptr = *ctx.lvars.get(name).unwrap();
+                    if *cell {
+                        // Reassignable vars are captured by pointer to the cell
+                        SkObj(ptr.as_basic_value_enum())
+                    } else {
+                        // Others are captured by value
+                        SkObj(self.builder.build_load(ptr, name))
+                    }
                 }
                 HirLambdaCapture::CaptureArg { idx } => {
                     // Args are captured by value
@@ -936,6 +1144,10 @@ impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
         self.box_float(&self.f64_type.const_float(value))
     }
 
+    fn gen_char_literal(&self, value: char) -> SkObj<'run> {
+        self.box_char(&self.i32_type.const_int(value as u64, false))
+    }
+
     fn gen_decimal_literal(&self, value: i64) -> SkObj<'run> {
         self.box_int(&self.i64_type.const_int(value as u64, false))
     }
@@ -960,6 +1172,47 @@ impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
         ))
     }
 
+    /// Get the frozen string object for the literal. It is created on the
+    /// first evaluation and kept in the global `frozen_str_N`
+    fn gen_frozen_string_literal(
+        &self,
+        ctx: &mut CodeGenContext<'hir, 'run>,
+        idx: &usize,
+    ) -> SkObj<'run> {
+        let str_type = self.llvm_type(&ty::raw("String")).into_pointer_type();
+        let name = format!("frozen_str_{}", idx);
+        let global = self.module.get_global(&name).unwrap_or_else(|| {
+            let global = self.module.add_global(str_type, None, &name);
+            global.set_linkage(inkwell::module::Linkage::Internal);
+            global.set_initializer(&str_type.const_null());
+            global
+        });
+        let ptr = global.as_pointer_value();
+        let cached = self.builder.build_load(ptr, "cached").into_pointer_value();
+        let is_null = self.builder.build_is_null(cached, "is_null");
+        let begin_block = self.builder.get_insert_block().unwrap();
+        let create_block = self
+            .context
+            .append_basic_block(ctx.function, "FrozenStrCreate");
+        let merge_block = self
+            .context
+            .append_basic_block(ctx.function, "FrozenStrEnd");
+        self.builder
+            .build_conditional_branch(is_null, create_block, merge_block);
+        // FrozenStrCreate:
+        self.builder.position_at_end(create_block);
+        let sk_str = self.gen_string_literal(idx);
+        let obj = self.bitcast(sk_str.clone(), &ty::raw("Object"), "obj");
+        self.call_method_func(&method_fullname_raw("Object", "freeze"), obj, &[], "_");
+        self.builder.build_store(ptr, sk_str.0);
+        self.builder.build_unconditional_branch(merge_block);
+        // FrozenStrEnd:
+        self.builder.position_at_end(merge_block);
+        let phi_node = self.builder.build_phi(str_type, "frozen_str");
+        phi_node.add_incoming(&[(&cached, begin_block), (&sk_str.0, create_block)]);
+        SkObj(phi_node.as_basic_value())
+    }
+
     fn gen_boolean_literal(&self, value: bool) -> SkObj<'run> {
         let n = if value { 1 } else { 0 };
         let i = self.i1_type.const_int(n, false);
@@ -1133,6 +1386,7 @@ impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
                     self.null_ptr(&ty::raw("Class")),
                 ],
             );
+            self.store_class_info(&cls, &fullname.clone().to_class_fullname());
             if *includes_modules {
                 let fname = wtable::insert_wtable_func_name(&fullname.clone().to_class_fullname());
                 self.call_void_llvm_func(&llvm_func_name(fname), &[cls.0.into()], "_");
@@ -1143,6 +1397,23 @@ impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
         }
     }
 
+    /// Set `@class_info` of the class object `cls`
+    fn store_class_info(&self, cls: &SkObj<'run>, classname: &ClassFullname) {
+        if let Some(global) = self.module.get_global(&llvm_class_info_name(classname)) {
+            let info = SkObj(self.builder.build_bitcast(
+                global.as_pointer_value(),
+                self.llvm_type(&ty::raw("Object")),
+                "class_info",
+            ));
+            self.build_ivar_store(
+                cls,
+                skc_corelib::class::IVAR_CLASS_INFO_IDX,
+                info,
+                "@class_info",
+            );
+        }
+    }
+
     fn call_class_level_initialize(
         &self,
         receiver: &SkObj,
@@ -1,5 +1,5 @@
 use crate::utils::{llvm_func_name, LlvmFuncName};
-use crate::CodeGen;
+use crate::{sorted_methods, CodeGen};
 use anyhow::Result;
 use either::Either::*;
 use shiika_core::ty::*;
@@ -11,10 +11,10 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
     /// PERF: Ideally they should be created during gen_methods but I couldn't
     /// avoid borrow checker errors.
     pub(super) fn gen_lambda_funcs(&self, hir: &'hir Hir) -> Result<()> {
-        for methods in hir.sk_methods.values() {
+        for (_, methods) in sorted_methods(&hir.sk_methods) {
             for method in methods {
                 if let SkMethodBody::Normal { exprs } = &method.body {
-                    self.gen_lambda_funcs_in_exprs(&exprs.exprs)?;
+                    self.gen_lambda_funcs_in_exprs(exprs.exprs())?;
                 }
             }
         }
@@ -23,7 +23,7 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
             self.gen_lambda_funcs_in_expr(expr)?;
         }
 
-        self.gen_lambda_funcs_in_exprs(&hir.main_exprs.exprs)?;
+        self.gen_lambda_funcs_in_exprs(hir.main_exprs.exprs())?;
         Ok(())
     }
 
@@ -51,8 +51,8 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
                 else_exprs,
             } => {
                 self.gen_lambda_funcs_in_expr(cond_expr)?;
-                self.gen_lambda_funcs_in_exprs(&then_exprs.exprs)?;
-                self.gen_lambda_funcs_in_exprs(&else_exprs.exprs)?;
+                self.gen_lambda_funcs_in_exprs(then_exprs.exprs())?;
+                self.gen_lambda_funcs_in_exprs(else_exprs.exprs())?;
             }
             HirMatchExpression {
                 cond_assign_expr,
@@ -60,7 +60,7 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
             } => {
                 self.gen_lambda_funcs_in_expr(cond_assign_expr)?;
                 for clause in clauses {
-                    self.gen_lambda_funcs_in_exprs(&clause.body_hir.exprs)?;
+                    self.gen_lambda_funcs_in_exprs(clause.body_hir.exprs())?;
                 }
             }
             HirWhileExpression {
@@ -68,9 +68,14 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
                 body_exprs,
             } => {
                 self.gen_lambda_funcs_in_expr(cond_expr)?;
-                self.gen_lambda_funcs_in_exprs(&body_exprs.exprs)?;
+                self.gen_lambda_funcs_in_exprs(body_exprs.exprs())?;
             }
-            HirBreakExpression { .. } => (),
+            HirBreakExpression { value, .. } => {
+                if let Some(v) = value {
+                    self.gen_lambda_funcs_in_expr(v)?;
+                }
+            }
+            HirContinueExpression { .. } => (),
             HirReturnExpression { arg, .. } => self.gen_lambda_funcs_in_expr(arg)?,
             HirLVarAssign { rhs, .. } => self.gen_lambda_funcs_in_expr(rhs)?,
             HirIVarAssign { rhs, .. } => self.gen_lambda_funcs_in_expr(rhs)?,
@@ -79,6 +84,11 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
                 receiver_expr,
                 arg_exprs,
                 ..
+            }
+            | HirSuperMethodCall {
+                receiver_expr,
+                arg_exprs,
+                ..
             } => {
                 self.gen_lambda_funcs_in_expr(receiver_expr)?;
                 for expr in arg_exprs {
@@ -105,6 +115,8 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
                 }
             }
             HirArgRef { .. } => (),
+            HirOmittedArg => (),
+            HirArgOrDefault { default_expr, .. } => self.gen_lambda_funcs_in_expr(default_expr)?,
             HirLVarRef { .. } => (),
             HirIVarRef { .. } => (),
             HirTVarRef { .. } => (),
@@ -118,19 +130,20 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
                 ..
             } => {
                 self.gen_lambda_func(&llvm_func_name(name), params, exprs, ret_ty, lvars)?;
-                self.gen_lambda_funcs_in_exprs(&exprs.exprs)?;
+                self.gen_lambda_funcs_in_exprs(exprs.exprs())?;
             }
             HirSelfExpression => (),
             HirFloatLiteral { .. } => (),
             HirDecimalLiteral { .. } => (),
             HirStringLiteral { .. } => (),
+            HirCharLiteral { .. } => (),
             HirBooleanLiteral { .. } => (),
 
             HirLambdaCaptureRef { .. } => (),
             HirLambdaCaptureWrite { rhs, .. } => self.gen_lambda_funcs_in_expr(rhs)?,
             HirBitCast { expr } => self.gen_lambda_funcs_in_expr(expr)?,
             HirClassLiteral { .. } => (),
-            HirParenthesizedExpr { exprs } => self.gen_lambda_funcs_in_exprs(&exprs.exprs)?,
+            HirParenthesizedExpr { exprs } => self.gen_lambda_funcs_in_exprs(exprs.exprs())?,
         }
         Ok(())
     }
@@ -141,8 +154,8 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
         params: &'hir [MethodParam],
         exprs: &'hir HirExpressions,
         ret_ty: &TermTy,
-        lvars: &[(String, TermTy)],
+        lvars: &[HirLVar],
     ) -> Result<()> {
-        self.gen_llvm_func_body(func_name, params, Right(exprs), lvars, ret_ty, true)
+        self.gen_llvm_func_body(func_name, params, Right(exprs), lvars, ret_ty, true, None)
     }
 }
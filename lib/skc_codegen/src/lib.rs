@@ -1,7 +1,12 @@
 mod boxing;
 mod code_gen_context;
+mod debug_info;
 mod gen_exprs;
 mod lambda;
+mod optimize;
+mod stacktrace;
+mod tail_call;
+mod trace;
 mod utils;
 pub mod values;
 mod wtable;
@@ -10,16 +15,56 @@ use crate::utils::*;
 use crate::values::*;
 use anyhow::{anyhow, Result};
 use either::*;
+use inkwell::attributes::{Attribute, AttributeLoc};
 use inkwell::types::*;
 use inkwell::values::*;
 use inkwell::AddressSpace;
+use shiika_ast::LocationSpan;
 use shiika_core::{names::*, ty, ty::*};
 use skc_hir::*;
 use skc_mir::{LibraryExports, Mir, VTables};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 use std::rc::Rc;
 
+pub use crate::optimize::OptLevel;
+
+/// What kind of module to generate
+#[derive(Debug)]
+pub enum ModuleKind {
+    /// The core library (builtin/builtin.bc)
+    Builtin,
+    /// A library file compiled separately. Its constants are initialized by
+    /// `<name>_init_constants`
+    Library { name: String },
+    /// A program which has `main`. The libraries are linked into it and
+    /// initialized in this order
+    Program { libraries: Vec<LibraryModule> },
+}
+
+/// A library compiled with `ModuleKind::Library`
+#[derive(Debug)]
+pub struct LibraryModule {
+    pub name: String,
+    pub bc_path: String,
+}
+
+/// Classes whose instances hold a pointer to the data managed by skc_rustlib
+const OPAQUE_CLASSES: [&str; 12] = [
+    "Shiika::Internal::Ptr",
+    "File",
+    "HashMap",
+    "JSON",
+    "Lazy",
+    "Mutex",
+    "Queue",
+    "Regex",
+    "SortedMap",
+    "Stack",
+    "Thread",
+    "WeakRef",
+];
+
 /// CodeGen
 ///
 /// 'hir > 'ictx > 'run
@@ -30,7 +75,11 @@ use std::rc::Rc;
 ///
 /// Basically inkwell types has 'ictx and inkwell values has 'run.
 pub struct CodeGen<'hir: 'ictx, 'run, 'ictx: 'run> {
-    pub generate_main: bool,
+    pub kind: ModuleKind,
+    /// Emit calls of `shiika_trace_call`/`shiika_trace_return` (`--trace-calls`)
+    pub trace_calls: bool,
+    /// Make `Int` arithmetic panic on overflow (unless `--opt` is given)
+    pub overflow_checks: bool,
     pub context: &'ictx inkwell::context::Context,
     pub module: &'run inkwell::module::Module<'ictx>,
     pub builder: &'run inkwell::builder::Builder<'ictx>,
@@ -45,8 +94,12 @@ pub struct CodeGen<'hir: 'ictx, 'run, 'ictx: 'run> {
     str_literals: &'hir Vec<String>,
     vtables: &'hir VTables,
     imported_vtables: &'hir VTables,
+    /// Methods which are not in the vtables (see `Hir::extension_methods`)
+    extension_methods: &'hir HashSet<MethodFullname>,
     /// Toplevel `self`
     the_main: Option<SkObj<'run>>,
+    /// Set if `--debug-info` is given
+    debug_info: Option<debug_info::DebugInfo<'ictx>>,
 }
 
 /// Compile hir and dump it to `outpath`
@@ -54,7 +107,10 @@ pub fn run(
     mir: &Mir,
     bc_path: &str,
     opt_ll_path: Option<&str>,
-    generate_main: bool,
+    kind: ModuleKind,
+    trace_calls: bool,
+    debug_info: bool,
+    opt_level: OptLevel,
     opt_target_triple: Option<&inkwell::targets::TargetTriple>,
 ) -> Result<()> {
     let context = inkwell::context::Context::create();
@@ -63,8 +119,29 @@ pub fn run(
         module.set_triple(triple);
     }
     let builder = context.create_builder();
-    let mut code_gen = CodeGen::new(mir, &context, &module, &builder, &generate_main);
+    let mut code_gen = CodeGen::new(mir, &context, &module, &builder, kind);
+    code_gen.trace_calls = trace_calls;
+    code_gen.overflow_checks = opt_level == OptLevel::None;
+    if debug_info {
+        // bc_path is `foo.sk.bc`
+        let src_path = bc_path.trim_end_matches(".bc");
+        code_gen.debug_info = Some(debug_info::DebugInfo::new(&context, &module, src_path));
+    }
     code_gen.gen_program(&mir.hir, &mir.imports)?;
+    if let Some(dbg) = &code_gen.debug_info {
+        dbg.finalize();
+    }
+    if let ModuleKind::Program { libraries } = &code_gen.kind {
+        for lib in libraries {
+            let lib_module =
+                inkwell::module::Module::parse_bitcode_from_path(&lib.bc_path, &context)
+                    .map_err(|llvm_str| anyhow!("{}: {}", lib.bc_path, llvm_str.to_string()))?;
+            module
+                .link_in_module(lib_module)
+                .map_err(|llvm_str| anyhow!("{}: {}", lib.bc_path, llvm_str.to_string()))?;
+        }
+    }
+    optimize::run_passes(code_gen.module, opt_level);
     code_gen.module.write_bitcode_to_path(Path::new(bc_path));
     if let Some(ll_path) = opt_ll_path {
         code_gen
@@ -81,10 +158,12 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
         context: &'ictx inkwell::context::Context,
         module: &'run inkwell::module::Module<'ictx>,
         builder: &'run inkwell::builder::Builder<'ictx>,
-        generate_main: &bool,
+        kind: ModuleKind,
     ) -> CodeGen<'hir, 'run, 'ictx> {
         CodeGen {
-            generate_main: *generate_main,
+            kind,
+            trace_calls: false,
+            overflow_checks: true,
             context,
             module,
             builder,
@@ -99,7 +178,9 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
             str_literals: &mir.hir.str_literals,
             vtables: &mir.vtables,
             imported_vtables: &mir.imports.vtables,
+            extension_methods: &mir.hir.extension_methods,
             the_main: None,
+            debug_info: None,
         }
     }
 
@@ -112,33 +193,65 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
         self.gen_constant_ptrs(&hir.constants);
         self.gen_boxing_funcs();
         self.gen_method_funcs(&hir.sk_methods);
-        self.gen_vtables();
+        self.set_method_attributes(hir);
+        self.gen_vtables(&hir.sk_types, &imports.sk_types);
+        self.gen_class_infos(&hir.sk_types, &imports.sk_types);
         self.gen_wtables(&hir.sk_types);
         self.gen_insert_wtables(&hir.sk_types);
-        self.gen_methods(&hir.sk_methods)?;
+        self.gen_methods(&hir.sk_methods, &hir.sk_types)?;
         self.gen_const_inits(&hir.const_inits)?;
-        if self.generate_main {
-            self.gen_init_constants(&hir.const_inits, true);
-            self.gen_user_main(&hir.main_exprs, &hir.main_lvars)?;
-            self.gen_main();
-        } else {
-            // generating builtin
-            self.gen_init_constants(&hir.const_inits, false);
-            self.impl_boxing_funcs();
+        match &self.kind {
+            ModuleKind::Program { libraries } => {
+                let mut imports = vec!["builtin".to_string()];
+                imports.extend(libraries.iter().map(|lib| lib.name.clone()));
+                self.gen_init_constants(&hir.const_inits, "main", &imports);
+                self.gen_user_main(&hir.main_exprs, &hir.main_lvars)?;
+                self.gen_main();
+                self.gen_overflow_checks_flag();
+            }
+            ModuleKind::Library { name } => {
+                self.gen_init_constants(&hir.const_inits, name, &[]);
+            }
+            ModuleKind::Builtin => {
+                self.gen_init_constants(&hir.const_inits, "builtin", &[]);
+                self.impl_boxing_funcs();
+            }
         }
         self.gen_lambda_funcs(hir)?;
         Ok(())
     }
 
+    /// Define `shiika_overflow_checks` which is referred from skc_rustlib
+    fn gen_overflow_checks_flag(&self) {
+        let global = self
+            .module
+            .add_global(self.i8_type, None, "shiika_overflow_checks");
+        global.set_initializer(&self.i8_type.const_int(self.overflow_checks as u64, false));
+        global.set_constant(true);
+    }
+
     fn gen_declares(&self) {
         let fn_type = self.void_type.fn_type(&[], false);
         self.module.add_function("GC_init", fn_type, None);
+        let fn_type = self.void_type.fn_type(
+            &[
+                self.i32_type.into(),
+                self.i8ptr_type.ptr_type(AddressSpace::Generic).into(),
+            ],
+            false,
+        );
+        self.module.add_function("shiika_init_args", fn_type, None);
         let fn_type = self.i8ptr_type.fn_type(&[self.i64_type.into()], false);
         self.module.add_function("shiika_malloc", fn_type, None);
         let fn_type = self
             .i8ptr_type
             .fn_type(&[self.i8ptr_type.into(), self.i64_type.into()], false);
         self.module.add_function("shiika_realloc", fn_type, None);
+        let fn_type = self
+            .void_type
+            .fn_type(&[self.i8ptr_type.into(), self.i8ptr_type.into()], false);
+        self.module
+            .add_function("shiika_frozen_error", fn_type, None);
 
         let fn_type = self.i8ptr_type.fn_type(
             &[
@@ -163,6 +276,11 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
         self.module
             .add_function("shiika_insert_wtable", fn_type, None);
 
+        self.gen_stacktrace_declares();
+        if self.trace_calls {
+            self.gen_trace_declares();
+        }
+
         let str_type = self.i8_type.array_type(4);
         let global = self.module.add_global(str_type, None, "putd_tmpl");
         global.set_linkage(inkwell::module::Linkage::Internal);
@@ -203,15 +321,15 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
     /// Generate LLVM types and `declare`s for imported class/modules
     fn gen_import_classes(&mut self, imported_types: &SkTypes) {
         // LLVM type
-        for name in imported_types.0.keys() {
+        for (name, _) in imported_types.sorted_iter() {
             self.llvm_struct_types
                 .insert(name.clone(), self.context.opaque_struct_type(&name.0));
         }
         self.define_type_struct_fields(imported_types);
 
         // Methods
-        for (typename, sk_type) in &imported_types.0 {
-            for (sig, _) in sk_type.base().method_sigs.unordered_iter() {
+        for (typename, sk_type) in imported_types.sorted_iter() {
+            for (_, sig) in sk_type.base().method_sigs_sorted() {
                 let func_type = self.method_llvm_func_type(&sk_type.erasure().to_term_ty(), sig);
                 let func_name = typename.method_fullname(&sig.fullname.first_name);
                 self.module
@@ -222,7 +340,7 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
 
     /// Declare `external global` for vtable of each class
     fn gen_import_vtables(&self, vtables: &VTables) {
-        for (fullname, vtable) in vtables.iter() {
+        for (fullname, vtable) in vtables.sorted_iter() {
             let name = llvm_vtable_const_name(fullname);
             let ary_type = self.i8ptr_type.array_type(vtable.size() as u32);
             let _global = self.module.add_global(ary_type, None, &name);
@@ -231,7 +349,7 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
 
     /// Declare `external global` for each imported constant
     fn gen_import_constants(&self, imported_constants: &HashMap<ConstFullname, TermTy>) {
-        for (fullname, ty) in imported_constants {
+        for (fullname, ty) in sorted_constants(imported_constants) {
             let name = llvm_const_name(fullname);
             let global = self.module.add_global(self.llvm_type(ty), None, &name);
             global.set_linkage(inkwell::module::Linkage::External);
@@ -243,10 +361,22 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
     }
 
     /// Generate vtable constants
-    fn gen_vtables(&self) {
-        for (class_fullname, vtable) in self.vtables.iter() {
+    fn gen_vtables(&self, sk_types: &SkTypes, imported_types: &SkTypes) {
+        let find_class = |name: &ClassFullname| {
+            let type_name = name.to_type_fullname();
+            sk_types
+                .0
+                .get(&type_name)
+                .or_else(|| imported_types.0.get(&type_name))
+                .and_then(|t| t.class())
+        };
+        for (class_fullname, vtable) in self.vtables.sorted_iter() {
+            let method_count = find_class(class_fullname)
+                .expect("[BUG] class of vtable not found")
+                .method_count(find_class);
             let method_names = vtable.to_vec();
-            let ary_type = self.i8ptr_type.array_type(method_names.len() as u32);
+            debug_assert_eq!(method_count, method_names.len());
+            let ary_type = self.i8ptr_type.array_type(method_count as u32);
             let tmp = llvm_vtable_const_name(class_fullname);
             let global = self.module.add_global(ary_type, None, &tmp);
             global.set_constant(true);
@@ -266,16 +396,155 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
         }
     }
 
+    /// Generate `shiika_class_info_XX` constants, which are stored in the
+    /// class objects. Each of them is a struct of
+    /// - pointer to the constant of the superclass (or null)
+    /// - number of the instance methods
+    /// - pointer to the array of the method names
+    /// - number of the ivars (-1 if the instances have other fields)
+    /// - pointer to the array of the ivar names (or null)
+    fn gen_class_infos(&self, sk_types: &SkTypes, imported_types: &SkTypes) {
+        let find_class = |name: &ClassFullname| {
+            let type_name = name.to_type_fullname();
+            sk_types
+                .0
+                .get(&type_name)
+                .or_else(|| imported_types.0.get(&type_name))
+                .and_then(|t| t.class())
+        };
+        let info_type = self.context.struct_type(
+            &[
+                self.i8ptr_type.into(),
+                self.i64_type.into(),
+                self.i8ptr_type.into(),
+                self.i64_type.into(),
+                self.i8ptr_type.into(),
+            ],
+            false,
+        );
+        for (class_fullname, vtable) in self.vtables.sorted_iter() {
+            if class_fullname.is_meta() {
+                continue;
+            }
+            let sk_class = find_class(class_fullname).expect("[BUG] class of vtable not found");
+            let superclass = sk_class
+                .superclass
+                .as_ref()
+                .map(|scls| scls.base_fullname())
+                // eg. the constant `::Void` is not a class object
+                .filter(|name| find_class(name).map_or(false, |c| !c.const_is_obj));
+            let superclass_ptr = match superclass {
+                Some(name) => {
+                    let global = self
+                        .module
+                        .get_global(&llvm_const_name(&toplevel_const(&name.0)))
+                        .unwrap_or_else(|| panic!("[BUG] constant of {} not found", name));
+                    global.as_pointer_value().const_cast(self.i8ptr_type)
+                }
+                None => self.i8ptr_type.const_null(),
+            };
+
+            let names = vtable
+                .to_vec()
+                .iter()
+                .map(|name| self.gen_method_name_const(&name.first_name))
+                .collect::<Vec<_>>();
+            let ary_type = self.i8ptr_type.array_type(names.len() as u32);
+            let names_global = self.module.add_global(
+                ary_type,
+                None,
+                &format!("shiika_method_names_{}", class_fullname.0),
+            );
+            names_global.set_linkage(inkwell::module::Linkage::Internal);
+            names_global.set_constant(true);
+            names_global.set_initializer(&self.i8ptr_type.const_array(&names));
+            let names_ptr = names_global.as_pointer_value().const_cast(self.i8ptr_type);
+
+            // -1 if the fields are not (only) the ivars
+            let (n_ivars, ivar_names_ptr) = if has_ivar_layout(&class_fullname.0) {
+                let mut ivars = sk_class.ivars.values().collect::<Vec<_>>();
+                ivars.sort_by_key(|ivar| ivar.idx);
+                let ivar_names = ivars
+                    .iter()
+                    .map(|ivar| self.gen_ivar_name_const(&ivar.name))
+                    .collect::<Vec<_>>();
+                let ary_type = self.i8ptr_type.array_type(ivar_names.len() as u32);
+                let ivar_names_global = self.module.add_global(
+                    ary_type,
+                    None,
+                    &format!("shiika_ivar_names_{}", class_fullname.0),
+                );
+                ivar_names_global.set_linkage(inkwell::module::Linkage::Internal);
+                ivar_names_global.set_constant(true);
+                ivar_names_global.set_initializer(&self.i8ptr_type.const_array(&ivar_names));
+                (
+                    ivars.len() as u64,
+                    ivar_names_global
+                        .as_pointer_value()
+                        .const_cast(self.i8ptr_type),
+                )
+            } else {
+                (u64::MAX, self.i8ptr_type.const_null())
+            };
+
+            let global =
+                self.module
+                    .add_global(info_type, None, &llvm_class_info_name(class_fullname));
+            global.set_linkage(inkwell::module::Linkage::Internal);
+            global.set_constant(true);
+            global.set_initializer(&info_type.const_named_struct(&[
+                superclass_ptr.into(),
+                self.i64_type.const_int(names.len() as u64, false).into(),
+                names_ptr.into(),
+                self.i64_type.const_int(n_ivars, true).into(),
+                ivar_names_ptr.into(),
+            ]));
+        }
+    }
+
+    /// Returns a null-terminated string constant of a method name as i8*
+    fn gen_method_name_const(&self, name: &MethodFirstname) -> PointerValue<'ictx> {
+        self.gen_name_const(&format!("shiika_method_name_{}", name.0), &name.0)
+    }
+
+    /// Returns a null-terminated string constant of an ivar name (eg.
+    /// `@foo`) as i8*
+    fn gen_ivar_name_const(&self, name: &str) -> PointerValue<'ictx> {
+        self.gen_name_const(
+            &format!("shiika_ivar_name_{}", name.trim_start_matches('@')),
+            name,
+        )
+    }
+
+    /// Returns the global constant `const_name` which holds `s` (created
+    /// if not yet)
+    fn gen_name_const(&self, const_name: &str, s: &str) -> PointerValue<'ictx> {
+        let global = self.module.get_global(const_name).unwrap_or_else(|| {
+            let bytes = format!("{}\0", s).into_bytes();
+            let str_type = self.i8_type.array_type(bytes.len() as u32);
+            let global = self.module.add_global(str_type, None, const_name);
+            global.set_linkage(inkwell::module::Linkage::Internal);
+            global.set_constant(true);
+            let content = bytes
+                .iter()
+                .map(|byte| self.i8_type.const_int((*byte).into(), false))
+                .collect::<Vec<_>>();
+            global.set_initializer(&self.i8_type.const_array(&content));
+            global
+        });
+        global.as_pointer_value().const_cast(self.i8ptr_type)
+    }
+
     /// Generate wtable constants
     fn gen_wtables(&self, sk_types: &SkTypes) {
-        for sk_class in sk_types.sk_classes() {
+        for sk_class in sk_types.sorted_classes() {
             wtable::gen_wtable_constants(self, sk_class);
         }
     }
 
     /// Generate functions to insert wtables
     fn gen_insert_wtables(&self, sk_types: &SkTypes) {
-        for sk_class in sk_types.sk_classes() {
+        for sk_class in sk_types.sorted_classes() {
             if !sk_class.wtable.is_empty() {
                 wtable::gen_insert_wtable(self, sk_class);
             }
@@ -284,8 +553,12 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
 
     /// Generate `init_constants()`
     // TODO: imported_constants should be Vec (order matters)
-    fn gen_init_constants(&self, const_inits: &'hir [HirExpression], is_main: bool) {
-        let package_name = if is_main { "main" } else { "builtin" };
+    fn gen_init_constants(
+        &self,
+        const_inits: &'hir [HirExpression],
+        package_name: &str,
+        imports: &[String],
+    ) {
         // define void @xxx_init_constants()
         let fn_type = self.void_type.fn_type(&[], false);
         let function =
@@ -295,15 +568,12 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
         self.builder.position_at_end(basic_block);
 
         // Initialize imported constants
-        if is_main {
-            let imports = vec!["builtin"];
-            for s in imports {
-                let fn_type = self.void_type.fn_type(&[], false);
-                self.module
-                    .add_function(&format!("{}_init_constants", s), fn_type, None);
-                let func = self.get_llvm_func(&llvm_func_name(format!("{}_init_constants", s)));
-                self.builder.build_call(func, &[], "");
-            }
+        for s in imports {
+            let fn_type = self.void_type.fn_type(&[], false);
+            self.module
+                .add_function(&format!("{}_init_constants", s), fn_type, None);
+            let func = self.get_llvm_func(&llvm_func_name(format!("{}_init_constants", s)));
+            self.builder.build_call(func, &[], "");
         }
 
         // Initialize own constants
@@ -311,7 +581,7 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
             .into_iter()
             .map(const_fullname)
             .collect::<Vec<_>>();
-        if !is_main {
+        if matches!(self.kind, ModuleKind::Builtin) {
             // These builtin classes must be created first
             for name in &basic_classes {
                 let func = self.get_llvm_func(&llvm_func_name(const_initialize_func_name(name)));
@@ -345,9 +615,15 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
         let function = self.module.add_function("user_main", user_main_type, None);
         let block = self.context.append_basic_block(function, "");
         self.builder.position_at_end(block);
+        let locs = main_exprs
+            .exprs
+            .first()
+            .map(|e| e.locs.clone())
+            .unwrap_or_else(LocationSpan::internal);
+        let debug_scope = self.gen_subprogram(function, "user_main", &locs);
 
         // alloca
-        let lvar_ptrs = self.gen_alloca_lvars(function, main_lvars);
+        let lvar_ptrs = self.gen_alloca_lvars(function, main_lvars, debug_scope);
 
         // CreateMain:
         let create_main_block = self.context.append_basic_block(function, "CreateMain");
@@ -365,13 +641,20 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
         self.builder.build_unconditional_branch(*end_block);
         self.builder.position_at_end(*end_block);
         self.builder.build_return(None);
+        self.unset_debug_location();
 
         Ok(())
     }
 
     fn gen_main(&mut self) {
-        // define i32 @main() {
-        let main_type = self.i32_type.fn_type(&[], false);
+        // define i32 @main(i32 %argc, i8** %argv) {
+        let main_type = self.i32_type.fn_type(
+            &[
+                self.i32_type.into(),
+                self.i8ptr_type.ptr_type(AddressSpace::Generic).into(),
+            ],
+            false,
+        );
         let function = self.module.add_function("main", main_type, None);
         let basic_block = self.context.append_basic_block(function, "");
         self.builder.position_at_end(basic_block);
@@ -380,6 +663,14 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
         let func = self.get_llvm_func(&llvm_func_name("GC_init"));
         self.builder.build_call(func, &[], "");
 
+        // Save argc and argv for `Process.args`
+        let func = self.get_llvm_func(&llvm_func_name("shiika_init_args"));
+        let args = function
+            .get_param_iter()
+            .map(|p| p.into())
+            .collect::<Vec<_>>();
+        self.builder.build_call(func, &args, "");
+
         // Call init_constants, user_main
         let func = self.get_llvm_func(&llvm_func_name("main_init_constants"));
         self.builder.build_call(func, &[], "");
@@ -394,7 +685,7 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
     /// Create llvm struct types for Shiika objects
     fn gen_type_structs(&mut self, sk_types: &SkTypes) {
         // Create all the struct types in advance (because it may be used as other class's ivar)
-        for name in sk_types.0.keys() {
+        for (name, _) in sk_types.sorted_iter() {
             self.llvm_struct_types
                 .insert(name.clone(), self.context.opaque_struct_type(&name.0));
         }
@@ -406,7 +697,7 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
     fn define_type_struct_fields(&self, sk_types: &SkTypes) {
         let vt = self.llvm_vtable_ref_type().into();
         let ct = self.class_object_ref_type().into();
-        for (name, sk_type) in &sk_types.0 {
+        for (name, sk_type) in sk_types.sorted_iter() {
             let struct_type = self.llvm_struct_types.get(name).unwrap();
             match sk_type {
                 SkType::Class(class) => match name.0.as_str() {
@@ -419,7 +710,10 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
                     "Bool" => {
                         struct_type.set_body(&[vt, ct, self.i1_type.into()], false);
                     }
-                    "Shiika::Internal::Ptr" => {
+                    "Char" => {
+                        struct_type.set_body(&[vt, ct, self.i32_type.into()], false);
+                    }
+                    s if OPAQUE_CLASSES.contains(&s) => {
                         struct_type.set_body(&[vt, ct, self.i8ptr_type.into()], false);
                     }
                     _ => {
@@ -472,7 +766,7 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
 
     /// Generate llvm global that holds Shiika constants
     fn gen_constant_ptrs(&self, constants: &HashMap<ConstFullname, TermTy>) {
-        for (fullname, ty) in constants {
+        for (fullname, ty) in sorted_constants(constants) {
             let name = llvm_const_name(fullname);
             let global = self.module.add_global(self.llvm_type(ty), None, &name);
             let null = self.llvm_type(ty).into_pointer_type().const_null();
@@ -509,7 +803,7 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
 
     /// Create inkwell functions
     fn gen_method_funcs(&self, methods: &HashMap<TypeFullname, Vec<SkMethod>>) {
-        methods.iter().for_each(|(tname, sk_methods)| {
+        sorted_methods(methods).for_each(|(tname, sk_methods)| {
             sk_methods.iter().for_each(|method| {
                 let self_ty = tname.to_ty();
                 let func_type = self.method_llvm_func_type(&self_ty, &method.signature);
@@ -519,6 +813,21 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
         })
     }
 
+    /// Set llvm function attributes according to the annotations
+    /// (eg. `@inline`)
+    fn set_method_attributes(&self, hir: &Hir) {
+        let mut method_names = hir.annotations.keys().collect::<Vec<_>>();
+        method_names.sort_unstable_by(|a, b| a.full_name.cmp(&b.full_name));
+        for method_name in method_names {
+            if hir.has_annotation(method_name, "inline") {
+                let function = self.get_llvm_func(&method_func_name(method_name));
+                let kind_id = Attribute::get_named_enum_kind_id("alwaysinline");
+                let attr = self.context.create_enum_attribute(kind_id, 0);
+                function.add_attribute(AttributeLoc::Function, attr);
+            }
+        }
+    }
+
     /// Return llvm funcion type of a method
     fn method_llvm_func_type(
         &self,
@@ -553,39 +862,47 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
         }
     }
 
-    fn gen_methods(&self, methods: &'hir HashMap<TypeFullname, Vec<SkMethod>>) -> Result<()> {
-        methods.values().try_for_each(|sk_methods| {
+    fn gen_methods(
+        &self,
+        methods: &'hir HashMap<TypeFullname, Vec<SkMethod>>,
+        sk_types: &SkTypes,
+    ) -> Result<()> {
+        sorted_methods(methods).try_for_each(|(_, sk_methods)| {
             sk_methods
                 .iter()
-                .try_for_each(|method| self.gen_method(method))
+                .try_for_each(|method| self.gen_method(method, sk_types))
         })
     }
 
-    fn gen_method(&self, method: &'hir SkMethod) -> Result<()> {
+    fn gen_method(&self, method: &'hir SkMethod, sk_types: &SkTypes) -> Result<()> {
         if method.is_rustlib() {
             return Ok(());
         }
         let func_name = method_func_name(&method.signature.fullname);
+        let tail_call = self.find_tail_self_call(method, sk_types);
         self.gen_llvm_func_body(
             &func_name,
             &method.signature.params,
-            Left(&method.body),
+            Left((&method.signature, &method.body)),
             &method.lvars,
             &method.signature.ret_ty,
             false,
+            tail_call,
         )
     }
 
     /// Generate body of a llvm function
     /// Used for methods and lambdas
+    #[allow(clippy::too_many_arguments)]
     fn gen_llvm_func_body(
         &self,
         func_name: &LlvmFuncName,
         params: &'hir [MethodParam],
-        body: Either<&'hir SkMethodBody, &'hir HirExpressions>,
-        lvars: &[(String, TermTy)],
+        body: Either<(&'hir MethodSignature, &'hir SkMethodBody), &'hir HirExpressions>,
+        lvars: &[HirLVar],
         ret_ty: &TermTy,
         is_lambda: bool,
+        tail_call: Option<&'hir HirExpression>,
     ) -> Result<()> {
         // LLVM function
         // (Function for lambdas are created in gen_lambda_expr)
@@ -607,19 +924,33 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
             inkwell_set_name(param, name);
         }
 
+        // Debug information (only for functions written in Shiika)
+        let debug_scope = match body {
+            Left((sig, SkMethodBody::Normal { exprs })) => {
+                self.gen_subprogram(function, &sig.fullname.full_name, &exprs.exprs()[0].locs)
+            }
+            Right(exprs) => match exprs.exprs().first() {
+                Some(e) => self.gen_subprogram(function, &func_name.0, &e.locs),
+                None => None,
+            },
+            _ => None,
+        };
+
         // alloca
-        let lvar_ptrs = self.gen_alloca_lvars(function, lvars);
+        let lvar_ptrs = self.gen_alloca_lvars(function, lvars, debug_scope);
 
         // Method body
         match body {
-            Left(method_body) => match method_body {
+            Left((sig, method_body)) => match method_body {
                 SkMethodBody::Normal { exprs } => self.gen_shiika_function_body(
                     function,
                     None,
+                    Some(sig),
                     FunctionOrigin::Method,
                     ret_ty,
                     exprs,
                     lvar_ptrs,
+                    tail_call,
                 )?,
                 SkMethodBody::RustLib => (),
                 SkMethodBody::New {
@@ -653,10 +984,12 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
                 self.gen_shiika_function_body(
                     function,
                     Some(params),
+                    None,
                     FunctionOrigin::Lambda,
                     ret_ty,
                     exprs,
                     lvar_ptrs,
+                    None,
                 )?;
             }
         }
@@ -664,10 +997,13 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
     }
 
     /// Generate `alloca` section
+    /// Lvars captured as a cell are allocated on the heap so that they
+    /// survive after the function returns
     fn gen_alloca_lvars(
         &self,
         function: inkwell::values::FunctionValue,
-        lvars: &[(String, TermTy)],
+        lvars: &[HirLVar],
+        debug_scope: Option<inkwell::debug_info::DIScope<'ictx>>,
     ) -> HashMap<String, inkwell::values::PointerValue<'run>> {
         if lvars.is_empty() {
             return HashMap::new();
@@ -676,9 +1012,19 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
         let alloca_start = self.context.append_basic_block(function, "alloca");
         self.builder.build_unconditional_branch(alloca_start);
         self.builder.position_at_end(alloca_start);
-        for (name, ty) in lvars {
-            let ptr = self.builder.build_alloca(self.llvm_type(ty), name);
-            lvar_ptrs.insert(name.to_string(), ptr);
+        for lvar in lvars {
+            let ptr = if lvar.cell {
+                self.allocate_cell(&lvar.ty, &lvar.name)
+            } else {
+                let ptr = self
+                    .builder
+                    .build_alloca(self.llvm_type(&lvar.ty), &lvar.name);
+                if let Some(scope) = debug_scope {
+                    self.gen_lvar_debug_info(scope, &lvar.name, ptr, alloca_start);
+                }
+                ptr
+            };
+            lvar_ptrs.insert(lvar.name.to_string(), ptr);
         }
         let alloca_end = self.context.append_basic_block(function, "alloca_End");
         self.builder.build_unconditional_branch(alloca_end);
@@ -687,16 +1033,37 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
     }
 
     /// Generate body of llvm function of Shiika method or lambda
+    /// `method_sig` is given if this is a method
+    /// `tail_call` is given if the method ends with a self-recursive call
+    #[allow(clippy::too_many_arguments)]
     fn gen_shiika_function_body(
         &self,
         function: inkwell::values::FunctionValue<'run>,
         function_params: Option<&'hir [MethodParam]>,
+        method_sig: Option<&MethodSignature>,
         function_origin: FunctionOrigin,
         ret_ty: &TermTy,
         exprs: &'hir HirExpressions,
         lvars: HashMap<String, inkwell::values::PointerValue<'run>>,
+        tail_call: Option<&'hir HirExpression>,
     ) -> Result<()> {
+        let mut trace_name = None;
+        if let Some(sig) = method_sig {
+            // Methods generated by the compiler (eg. accessors) do not have
+            // the location of `def`
+            let locs = match &sig.locs {
+                LocationSpan::Empty => &exprs.exprs()[0].locs,
+                locs => locs,
+            };
+            self.gen_stacktrace_capture(&sig.fullname, locs);
+            if self.trace_calls {
+                trace_name = Some(self.gen_trace_call(&sig.fullname));
+            }
+        }
         let (end_block, mut ctx) = self.new_ctx(function_origin, function, function_params, lvars);
+        if let Some(expr) = tail_call {
+            ctx.tail_call = Some(self.gen_tail_call_loop(function, expr));
+        }
         let (last_value, last_value_block) = if let Some(v) = self.gen_exprs(&mut ctx, exprs)? {
             let b = self.context.append_basic_block(ctx.function, "Ret");
             self.builder.build_unconditional_branch(b);
@@ -709,16 +1076,16 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
         };
 
         self.builder.position_at_end(*end_block);
-
-        if ret_ty.is_never_type() {
-            // `Never` does not have an instance
-            self.builder.build_return(None);
+        // Note: the phi node must be built before the other instructions in
+        // the block
+        let ret_value = if ret_ty.is_never_type() {
+            None
         } else if last_value.is_none() && ctx.returns.is_empty() {
             // `exprs` ends with `panic` and there is no `return`
             let null = self.llvm_type(ret_ty).into_pointer_type().const_null();
-            self.builder.build_return(Some(&null));
+            Some(null.as_basic_value_enum())
         } else if ret_ty.is_void_type() {
-            self.build_return_void();
+            None
         } else {
             // Make a phi node from the `return`s
             let mut incomings = ctx
@@ -735,8 +1102,25 @@ impl<'hir: 'ictx, 'run, 'ictx: 'run> CodeGen<'hir, 'run, 'ictx> {
                 .builder
                 .build_phi(self.llvm_type(ret_ty), "methodResult");
             phi_node.add_incoming(incomings.as_slice());
-            self.builder.build_return(Some(&phi_node.as_basic_value()));
+            Some(phi_node.as_basic_value())
+        };
+
+        if method_sig.is_some() {
+            self.gen_stacktrace_pop();
+        }
+        if let Some(name_ptr) = trace_name {
+            self.gen_trace_return(name_ptr);
+        }
+
+        if let Some(v) = ret_value {
+            self.builder.build_return(Some(&v));
+        } else if ret_ty.is_never_type() {
+            // `Never` does not have an instance
+            self.builder.build_return(None);
+        } else {
+            self.build_return_void();
         }
+        self.unset_debug_location();
         Ok(())
     }
 
@@ -828,6 +1212,30 @@ fn inkwell_set_name(val: BasicValueEnum, name: &str) {
     }
 }
 
+/// Returns false if the instances of the class have fields other than the
+/// ivars (see `define_type_struct_fields`)
+fn has_ivar_layout(name: &str) -> bool {
+    // `Array` has a field for the `Vec` which is set by skc_rustlib
+    !matches!(name, "Int" | "Float" | "Bool" | "Char" | "Array") && !OPAQUE_CLASSES.contains(&name)
+}
+
 fn const_initialize_func_name(name: &ConstFullname) -> String {
     format!("init_{}", &name.0[2..])
 }
+
+/// Returns the methods sorted by the type name (to make the output
+/// reproducible)
+fn sorted_methods(methods: &SkMethods) -> impl Iterator<Item = (&TypeFullname, &Vec<SkMethod>)> {
+    let mut v = methods.iter().collect::<Vec<_>>();
+    v.sort_unstable_by(|(a, _), (b, _)| a.0.cmp(&b.0));
+    v.into_iter()
+}
+
+/// Returns the constants sorted by the name
+fn sorted_constants(
+    constants: &HashMap<ConstFullname, TermTy>,
+) -> impl Iterator<Item = (&ConstFullname, &TermTy)> {
+    let mut v = constants.iter().collect::<Vec<_>>();
+    v.sort_unstable_by(|(a, _), (b, _)| a.0.cmp(&b.0));
+    v.into_iter()
+}
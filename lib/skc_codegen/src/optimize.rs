@@ -0,0 +1,82 @@
+//! LLVM optimization passes for `--opt`
+use inkwell::passes::{PassManager, PassManagerBuilder};
+use inkwell::OptimizationLevel;
+use std::str::FromStr;
+
+/// How much the generated module is optimized
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptLevel {
+    /// No optimization (`--opt=0`)
+    None,
+    /// Cheap cleanups only (`--opt=1`)
+    Less,
+    /// Inlining, SROA and GVN (`--opt=2`)
+    Default,
+    /// Also unroll loops and run the LTO passes (`--opt=3`)
+    Aggressive,
+}
+
+impl Default for OptLevel {
+    fn default() -> Self {
+        OptLevel::None
+    }
+}
+
+impl FromStr for OptLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "0" | "none" => Ok(OptLevel::None),
+            "1" | "less" => Ok(OptLevel::Less),
+            "2" | "default" => Ok(OptLevel::Default),
+            "3" | "aggressive" => Ok(OptLevel::Aggressive),
+            _ => Err(format!("unknown optimization level: {}", s)),
+        }
+    }
+}
+
+impl OptLevel {
+    fn llvm_level(&self) -> OptimizationLevel {
+        match self {
+            OptLevel::None => OptimizationLevel::None,
+            OptLevel::Less => OptimizationLevel::Less,
+            OptLevel::Default => OptimizationLevel::Default,
+            OptLevel::Aggressive => OptimizationLevel::Aggressive,
+        }
+    }
+}
+
+/// Run the passes for `level` on `module`
+pub fn run_passes(module: &inkwell::module::Module, level: OptLevel) {
+    if level == OptLevel::None {
+        return;
+    }
+    let pm = PassManager::create(());
+    pm.add_promote_memory_to_register_pass();
+    pm.add_instruction_combining_pass();
+    pm.add_cfg_simplification_pass();
+    if level == OptLevel::Less {
+        pm.run_on(module);
+        return;
+    }
+
+    pm.add_function_inlining_pass();
+    pm.add_scalar_repl_aggregates_pass();
+    pm.add_gvn_pass();
+    pm.add_instruction_combining_pass();
+    pm.add_cfg_simplification_pass();
+    if level == OptLevel::Aggressive {
+        pm.add_loop_unroll_pass();
+    }
+
+    let pmb = PassManagerBuilder::create();
+    pmb.set_optimization_level(level.llvm_level());
+    pmb.populate_module_pass_manager(&pm);
+    if level == OptLevel::Aggressive {
+        // Symbols must not be internalized because this module is linked
+        // with builtin.bc afterwards
+        pmb.populate_lto_pass_manager(&pm, false, true);
+    }
+    pm.run_on(module);
+}
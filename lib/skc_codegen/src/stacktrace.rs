@@ -0,0 +1,56 @@
+//! Maintain the Shiika-level call stack (see skc_rustlib/src/stacktrace.rs)
+use crate::utils::llvm_func_name;
+use crate::CodeGen;
+use shiika_ast::LocationSpan;
+use shiika_core::names::*;
+
+impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
+    /// Declare runtime functions for the stack trace
+    pub(super) fn gen_stacktrace_declares(&self) {
+        let fn_type = self.void_type.fn_type(
+            &[
+                self.i8ptr_type.into(),
+                self.i8ptr_type.into(),
+                self.i32_type.into(),
+            ],
+            false,
+        );
+        self.module.add_function("shiika_push_frame", fn_type, None);
+        let fn_type = self.void_type.fn_type(&[], false);
+        self.module.add_function("shiika_pop_frame", fn_type, None);
+    }
+
+    /// Build IR to push a stack frame of the method (on method entry)
+    pub(super) fn gen_stacktrace_capture(&self, method_name: &MethodFullname, locs: &LocationSpan) {
+        let (file, line) = match locs {
+            LocationSpan::Just {
+                filepath, begin, ..
+            } => (filepath.to_string_lossy().to_string(), begin.line + 1),
+            LocationSpan::Empty => ("".to_string(), 0),
+        };
+        let name_ptr = self
+            .builder
+            .build_global_string_ptr(&method_name.full_name, "frame_name")
+            .as_pointer_value();
+        let file_ptr = self
+            .builder
+            .build_global_string_ptr(&file, "frame_file")
+            .as_pointer_value();
+        let f = self.get_llvm_func(&llvm_func_name("shiika_push_frame"));
+        self.builder.build_call(
+            f,
+            &[
+                name_ptr.into(),
+                file_ptr.into(),
+                self.i32_type.const_int(line as u64, false).into(),
+            ],
+            "",
+        );
+    }
+
+    /// Build IR to pop the stack frame (before returning from the method)
+    pub(super) fn gen_stacktrace_pop(&self) {
+        let f = self.get_llvm_func(&llvm_func_name("shiika_pop_frame"));
+        self.builder.build_call(f, &[], "");
+    }
+}
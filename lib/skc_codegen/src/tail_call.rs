@@ -0,0 +1,139 @@
+//! Tail call elimination of self-recursive methods.
+//!
+//! ```sk
+//! class A
+//!   def self.sum(n: Int, acc: Int) -> Int
+//!     if n == 0
+//!       return acc
+//!     end
+//!     sum(n - 1, acc + n)
+//!   end
+//! end
+//! ```
+//!
+//! When a method ends with a call of itself on `self`, the arguments are
+//! kept in `alloca`s and the call is compiled into storing the new
+//! arguments and jumping back to the beginning of the method. This is done
+//! only when the call is never dispatched to another method (i.e. the class
+//! cannot have a subclass which overrides it.)
+use crate::code_gen_context::*;
+use crate::values::*;
+use crate::{CodeGen, ModuleKind};
+use anyhow::Result;
+use shiika_core::names::*;
+use skc_hir::*;
+
+impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
+    /// Returns the last expression of `method` if it is a self-recursive
+    /// call which can be replaced with a jump
+    pub(super) fn find_tail_self_call(
+        &self,
+        method: &'hir SkMethod,
+        sk_types: &SkTypes,
+    ) -> Option<&'hir HirExpression> {
+        let exprs = match &method.body {
+            SkMethodBody::Normal { exprs } => exprs,
+            _ => return None,
+        };
+        let last = exprs.exprs().last()?;
+        let (receiver_expr, arg_exprs) = match &last.node {
+            HirExpressionBase::HirMethodCall {
+                receiver_expr,
+                method_fullname,
+                arg_exprs,
+            } if *method_fullname == method.signature.fullname => (receiver_expr, arg_exprs),
+            _ => return None,
+        };
+        if !is_self_expr(receiver_expr)
+            || !method.signature.typarams.is_empty()
+            || arg_exprs.len() != method.signature.params.len()
+            || self.may_be_overridden(&method.signature.fullname, sk_types)
+        {
+            return None;
+        }
+        Some(last)
+    }
+
+    /// Returns true if the method may be dispatched to a method of a
+    /// subclass
+    fn may_be_overridden(&self, method_name: &MethodFullname, sk_types: &SkTypes) -> bool {
+        // Class methods are inherited along with the instance classes
+        let type_name = &method_name.type_name.0;
+        let name = class_fullname(type_name.strip_prefix("Meta:").unwrap_or(type_name));
+        let found = sk_types.0.get(&name.to_type_fullname());
+        let cls = match found.and_then(|t| t.class()) {
+            Some(c) => c,
+            // Module methods are called via wtable
+            None => return true,
+        };
+        if cls.is_final == Some(true) {
+            return false;
+        }
+        if !matches!(self.kind, ModuleKind::Program { .. }) {
+            // Library classes may be inherited by other modules
+            return true;
+        }
+        sk_types.sk_classes().any(|c| {
+            c.superclass
+                .as_ref()
+                .map_or(false, |sup| sup.base_fullname() == name)
+        })
+    }
+
+    /// Store the arguments of the current method to `alloca`s and start the
+    /// loop for tail calls
+    pub(super) fn gen_tail_call_loop(
+        &self,
+        function: inkwell::values::FunctionValue<'run>,
+        expr: &'hir HirExpression,
+    ) -> TailCall<'hir, 'run> {
+        // Skip the first %self
+        let arg_ptrs = function
+            .get_param_iter()
+            .skip(1)
+            .map(|param| {
+                let ptr = self.builder.build_alloca(param.get_type(), "arg");
+                self.builder.build_store(ptr, param);
+                ptr
+            })
+            .collect();
+        let loop_start = self.context.append_basic_block(function, "TailCallLoop");
+        self.builder.build_unconditional_branch(loop_start);
+        self.builder.position_at_end(loop_start);
+        TailCall {
+            expr,
+            loop_start,
+            arg_ptrs,
+        }
+    }
+
+    /// Generate a self-recursive call as a jump
+    pub(super) fn gen_tail_call(
+        &self,
+        ctx: &mut CodeGenContext<'hir, 'run>,
+        arg_exprs: &'hir [HirExpression],
+    ) -> Result<Option<SkObj<'run>>> {
+        // Evaluate all the arguments before overwriting any of them
+        let mut arg_values = vec![];
+        for arg_expr in arg_exprs {
+            arg_values.push(self.gen_expr(ctx, arg_expr)?.unwrap());
+        }
+        let tail_call = ctx.tail_call.as_ref().unwrap();
+        for (ptr, value) in tail_call.arg_ptrs.iter().zip(arg_values) {
+            let param_ty = ptr.get_type().get_element_type().into_pointer_type();
+            let v = self.builder.build_bitcast(value.0, param_ty, "arg");
+            self.builder.build_store(*ptr, v);
+        }
+        self.builder
+            .build_unconditional_branch(tail_call.loop_start);
+        Ok(None)
+    }
+}
+
+fn is_self_expr(expr: &HirExpression) -> bool {
+    match &expr.node {
+        HirExpressionBase::HirSelfExpression => true,
+        HirExpressionBase::HirBitCast { expr } => is_self_expr(expr),
+        _ => false,
+    }
+}
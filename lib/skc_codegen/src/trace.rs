@@ -0,0 +1,34 @@
+//! Method call tracing for `--trace-calls` (see skc_rustlib/src/trace.rs)
+use crate::utils::llvm_func_name;
+use crate::CodeGen;
+use inkwell::values::PointerValue;
+use shiika_core::names::*;
+
+impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
+    /// Declare runtime functions for the tracing
+    pub(super) fn gen_trace_declares(&self) {
+        let fn_type = self.void_type.fn_type(&[self.i8ptr_type.into()], false);
+        self.module.add_function("shiika_trace_call", fn_type, None);
+        self.module
+            .add_function("shiika_trace_return", fn_type, None);
+    }
+
+    /// Build IR to log the method call (on method entry.)
+    /// Returns the pointer to the method name, which should be passed to
+    /// `gen_trace_return`
+    pub(super) fn gen_trace_call(&self, method_name: &MethodFullname) -> PointerValue<'run> {
+        let name_ptr = self
+            .builder
+            .build_global_string_ptr(&method_name.full_name, "trace_name")
+            .as_pointer_value();
+        let f = self.get_llvm_func(&llvm_func_name("shiika_trace_call"));
+        self.builder.build_call(f, &[name_ptr.into()], "");
+        name_ptr
+    }
+
+    /// Build IR to log the method return (before returning from the method)
+    pub(super) fn gen_trace_return(&self, name_ptr: PointerValue<'run>) {
+        let f = self.get_llvm_func(&llvm_func_name("shiika_trace_return"));
+        self.builder.build_call(f, &[name_ptr.into()], "");
+    }
+}
@@ -12,6 +12,9 @@ const OBJ_HEADER_SIZE: usize = 2;
 const OBJ_VTABLE_IDX: usize = 0;
 /// 1st: reference to the class object
 const OBJ_CLASS_IDX: usize = 1;
+/// The lowest bit of the class object reference is set when the object is
+/// frozen (see `Object#freeze` in skc_rustlib)
+const OBJ_FROZEN_BIT: u64 = 1;
 
 #[derive(Debug)]
 pub struct LlvmFuncName(pub String);
@@ -45,6 +48,18 @@ impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
         value: SkObj<'a>,
         name: &str,
     ) {
+        debug_assert_eq!(
+            object
+                .0
+                .into_pointer_value()
+                .get_type()
+                .get_element_type()
+                .into_struct_type()
+                .get_field_type_at_index((OBJ_HEADER_SIZE + idx) as u32),
+            Some(value.0.get_type()),
+            "[BUG] type mismatch on storing ivar {}",
+            name
+        );
         self.build_ivar_store_raw(object, idx, value.0, name)
     }
 
@@ -66,7 +81,36 @@ impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
 
     /// Get the class object of an object as `*Class`
     pub fn get_class_of_obj(&self, object: SkObj<'run>) -> SkClassObj<'run> {
-        SkClassObj(self.build_llvm_struct_ref(object, OBJ_CLASS_IDX, "class"))
+        let tagged = self
+            .build_llvm_struct_ref(object, OBJ_CLASS_IDX, "tagged_class")
+            .into_pointer_value();
+        let addr = self
+            .builder
+            .build_ptr_to_int(tagged, self.i64_type, "tagged_addr");
+        let mask = self.i64_type.const_int(!OBJ_FROZEN_BIT, false);
+        let masked = self.builder.build_and(addr, mask, "class_addr");
+        let class_obj = self
+            .builder
+            .build_int_to_ptr(masked, tagged.get_type(), "class");
+        SkClassObj(class_obj.into())
+    }
+
+    /// Build IR to check if `object` is frozen (returns i1)
+    pub fn build_is_frozen(&self, object: SkObj<'run>) -> IntValue<'run> {
+        let tagged = self
+            .build_llvm_struct_ref(object, OBJ_CLASS_IDX, "tagged_class")
+            .into_pointer_value();
+        let addr = self
+            .builder
+            .build_ptr_to_int(tagged, self.i64_type, "tagged_addr");
+        let bit = self.i64_type.const_int(OBJ_FROZEN_BIT, false);
+        let flag = self.builder.build_and(addr, bit, "frozen_bit");
+        self.builder.build_int_compare(
+            inkwell::IntPredicate::NE,
+            flag,
+            self.i64_type.const_zero(),
+            "frozen",
+        )
     }
 
     /// Set `class_obj` to the class object field of `object`
@@ -175,6 +219,27 @@ impl<'hir, 'run, 'ictx> CodeGen<'hir, 'run, 'ictx> {
         self._allocate_sk_obj(class_fullname, reg_name, class_obj)
     }
 
+    /// Generate call of malloc and returns a ptr to a cell which holds
+    /// a value of `ty`
+    pub fn allocate_cell(&self, ty: &TermTy, reg_name: &str) -> PointerValue<'run> {
+        let cell_type = self.llvm_type(ty);
+        let size = cell_type.size_of().expect("[BUG] cell_type has no size");
+        let func = self.get_llvm_func(&llvm_func_name("shiika_malloc"));
+        let raw_addr = self
+            .builder
+            .build_call(func, &[size.as_basic_value_enum().into()], "mem")
+            .try_as_basic_value()
+            .left()
+            .unwrap();
+        self.builder
+            .build_bitcast(
+                raw_addr,
+                cell_type.ptr_type(AddressSpace::Generic),
+                reg_name,
+            )
+            .into_pointer_value()
+    }
+
     /// Load a class object
     pub fn load_class_object(&self, class_fullname: &ClassFullname) -> SkClassObj<'run> {
         let class_const_name = llvm_const_name(&class_fullname.to_const_fullname());
@@ -334,6 +399,11 @@ pub(super) fn llvm_vtable_const_name(classname: &ClassFullname) -> String {
     format!("shiika_vtable_{}", classname.0)
 }
 
+/// Name of llvm constant of the class info (see `gen_class_infos`)
+pub(super) fn llvm_class_info_name(classname: &ClassFullname) -> String {
+    format!("shiika_class_info_{}", classname.0)
+}
+
 /// Returns llvm function name of the given method
 pub fn method_func_name(method_name: &MethodFullname) -> LlvmFuncName {
     LlvmFuncName(mangle_method(&method_name.full_name))
@@ -6,7 +6,7 @@ use skc_hir::SkClass;
 
 /// Define llvm constants like `@shiika_wtable_Array_Enumerable`
 pub fn gen_wtable_constants(code_gen: &CodeGen, sk_class: &SkClass) {
-    for (mod_name, method_names) in &sk_class.wtable.0 {
+    for (mod_name, method_names) in sk_class.wtable.sorted_iter() {
         let ary_type = code_gen.i8ptr_type.array_type(method_names.len() as u32);
         let cname = llvm_wtable_const_name(&sk_class.fullname(), mod_name);
         let global = code_gen.module.add_global(ary_type, None, &cname);
@@ -37,7 +37,7 @@ pub fn gen_insert_wtable(code_gen: &CodeGen, sk_class: &SkClass) {
     let basic_block = code_gen.context.append_basic_block(function, "");
     code_gen.builder.position_at_end(basic_block);
 
-    for mod_name in sk_class.wtable.0.keys() {
+    for (mod_name, _) in sk_class.wtable.sorted_iter() {
         let key = code_gen.get_const_addr_int(&mod_name.to_const_fullname());
         let funcs = load_wtable_const(
             code_gen,
@@ -6,6 +6,7 @@ use std::collections::HashMap;
 
 pub const N_IVARS: usize = 2;
 pub const IVAR_NAME_IDX: usize = 0;
+pub const IVAR_CLASS_INFO_IDX: usize = 5;
 
 pub fn ivars() -> HashMap<String, SkIVar> {
     let mut ivars = HashMap::new();
@@ -45,5 +46,33 @@ pub fn ivars() -> HashMap<String, SkIVar> {
             readonly: true,
         },
     );
+    ivars.insert(
+        "@erasure_cls".to_string(),
+        SkIVar {
+            name: "@erasure_cls".to_string(),
+            idx: 4,
+            ty: ty::raw("Object"),
+            readonly: true,
+        },
+    );
+    // Pointer to `shiika_class_info_XX` (null for metaclasses)
+    ivars.insert(
+        "@class_info".to_string(),
+        SkIVar {
+            name: "@class_info".to_string(),
+            idx: IVAR_CLASS_INFO_IDX,
+            ty: ty::raw("Object"),
+            readonly: true,
+        },
+    );
+    ivars.insert(
+        "@method_indices".to_string(),
+        SkIVar {
+            name: "@method_indices".to_string(),
+            idx: 6,
+            ty: ty::raw("Object"),
+            readonly: true,
+        },
+    );
     ivars
 }
@@ -58,19 +58,91 @@ fn rust_body_items() -> Vec<ClassItem> {
             HashMap::new(),
             vec![],
         ),
+        (
+            "Char".to_string(),
+            Some(Superclass::simple("Object")),
+            HashMap::new(),
+            vec![],
+        ),
         (
             "Float".to_string(),
             Some(Superclass::simple("Object")),
             HashMap::new(),
             vec![],
         ),
+        (
+            "File".to_string(),
+            Some(Superclass::simple("Object")),
+            HashMap::new(),
+            vec![],
+        ),
+        (
+            "HashMap".to_string(),
+            Some(Superclass::simple("Object")),
+            HashMap::new(),
+            vec!["K".to_string(), "V".to_string()],
+        ),
         (
             "Int".to_string(),
             Some(Superclass::simple("Object")),
             HashMap::new(),
             vec![],
         ),
+        (
+            "IO".to_string(),
+            Some(Superclass::simple("Object")),
+            HashMap::new(),
+            vec![],
+        ),
+        (
+            "JSON".to_string(),
+            Some(Superclass::simple("Object")),
+            HashMap::new(),
+            vec![],
+        ),
+        (
+            "Lazy".to_string(),
+            Some(Superclass::simple("Object")),
+            HashMap::new(),
+            vec!["T".to_string()],
+        ),
         ("Object".to_string(), None, HashMap::new(), vec![]),
+        (
+            "Mutex".to_string(),
+            Some(Superclass::simple("Object")),
+            HashMap::new(),
+            vec!["T".to_string()],
+        ),
+        (
+            "Queue".to_string(),
+            Some(Superclass::simple("Object")),
+            HashMap::new(),
+            vec!["T".to_string()],
+        ),
+        (
+            "SortedMap".to_string(),
+            Some(Superclass::simple("Object")),
+            HashMap::new(),
+            vec!["K".to_string(), "V".to_string()],
+        ),
+        (
+            "Stack".to_string(),
+            Some(Superclass::simple("Object")),
+            HashMap::new(),
+            vec!["T".to_string()],
+        ),
+        (
+            "Thread".to_string(),
+            Some(Superclass::simple("Object")),
+            HashMap::new(),
+            vec![],
+        ),
+        (
+            "WeakRef".to_string(),
+            Some(Superclass::simple("Object")),
+            HashMap::new(),
+            vec!["T".to_string()],
+        ),
         (
             "Void".to_string(),
             Some(Superclass::simple("Object")),
@@ -83,6 +155,12 @@ fn rust_body_items() -> Vec<ClassItem> {
             HashMap::new(),
             vec![],
         ),
+        (
+            "Benchmark".to_string(),
+            Some(Superclass::simple("Object")),
+            HashMap::new(),
+            vec![],
+        ),
         // Modules
         (
             "Math".to_string(),
@@ -90,6 +168,24 @@ fn rust_body_items() -> Vec<ClassItem> {
             HashMap::new(),
             vec![],
         ),
+        (
+            "Process".to_string(),
+            Some(Superclass::simple("Object")),
+            HashMap::new(),
+            vec![],
+        ),
+        (
+            "Regex".to_string(),
+            Some(Superclass::simple("Object")),
+            HashMap::new(),
+            vec![],
+        ),
+        (
+            "Shiika::FFI".to_string(),
+            Some(Superclass::simple("Object")),
+            HashMap::new(),
+            vec![],
+        ),
         (
             "Shiika::Internal::Memory".to_string(),
             Some(Superclass::simple("Object")),
@@ -1,6 +1,6 @@
 use anyhow::{Context, Result};
 use json5;
-use shiika_ast::AstMethodSignature;
+use shiika_ast::{Annotation, AstMethodSignature};
 use shiika_core::names::{class_fullname, ClassFullname};
 use shiika_parser::Parser;
 use std::fs;
@@ -25,9 +25,32 @@ fn load_methods_json() -> Result<Vec<(String, String)>> {
     json5::from_str(&contents).context("provided_methods.json5 is broken")
 }
 
-// Parse signature into AstMethodSignature
+// Parse signature into AstMethodSignature.
+// Annotations may be written before the signature (eg. `@pure abs -> Int`,
+// `@only_for(Int) size -> Int`)
 fn parse_signature(item: &(String, String)) -> (ClassFullname, AstMethodSignature) {
     let (classname, sig_str) = item;
-    let ast_sig = Parser::parse_signature(sig_str).unwrap();
+    let mut rest = sig_str.as_str();
+    let mut annotations = vec![];
+    while let Some(s) = rest.strip_prefix('@') {
+        let (annot, r) = s
+            .split_once(' ')
+            .unwrap_or_else(|| panic!("signature not found: {}", sig_str));
+        // eg. `@only_for(Int)`
+        let (name, args) = match annot.strip_suffix(')').and_then(|a| a.split_once('(')) {
+            Some((name, args)) => (
+                name,
+                args.split(',').map(|x| x.trim().to_string()).collect(),
+            ),
+            None => (annot, vec![]),
+        };
+        annotations.push(Annotation {
+            name: name.to_string(),
+            args,
+        });
+        rest = r;
+    }
+    let mut ast_sig = Parser::parse_signature(rest).unwrap();
+    ast_sig.annotations = annotations;
     (class_fullname(classname), ast_sig)
 }
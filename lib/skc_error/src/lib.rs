@@ -1,13 +1,50 @@
 pub use ariadne::Label;
-use ariadne::{Report, ReportBuilder, ReportKind, Source};
+use ariadne::{Color, Config, Report, ReportBuilder, ReportKind, Source};
 use shiika_ast::LocationSpan;
 use std::fs;
 use std::ops::Range;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
 
 type AriadneSpan<'a> = (&'a String, Range<usize>);
 
+/// Whether to colour the reports. Off by default so that the output does
+/// not depend on the environment (eg. in the tests)
+static COLOR: AtomicBool = AtomicBool::new(false);
+
+/// Enable or disable the colouring of the reports (the compiler enables it
+/// when stdout is a terminal)
+pub fn set_color(enabled: bool) {
+    COLOR.store(enabled, Ordering::Relaxed);
+}
+
+fn config() -> Config {
+    Config::default().with_color(COLOR.load(Ordering::Relaxed))
+}
+
 /// Helper for building report with ariadne crate.
 pub fn build_report<F>(main_msg: String, locs: &LocationSpan, f: F) -> String
+where
+    F: for<'b> FnOnce(
+        ReportBuilder<AriadneSpan<'b>>,
+        AriadneSpan<'b>,
+    ) -> ReportBuilder<AriadneSpan<'b>>,
+{
+    build_report_(ReportKind::Error, main_msg, locs, f)
+}
+
+/// Like `build_report` but for a warning.
+pub fn build_warning<F>(main_msg: String, locs: &LocationSpan, f: F) -> String
+where
+    F: for<'b> FnOnce(
+        ReportBuilder<AriadneSpan<'b>>,
+        AriadneSpan<'b>,
+    ) -> ReportBuilder<AriadneSpan<'b>>,
+{
+    build_report_(ReportKind::Warning, main_msg, locs, f)
+}
+
+fn build_report_<F>(kind: ReportKind, main_msg: String, locs: &LocationSpan, f: F) -> String
 where
     F: for<'b> FnOnce(
         ReportBuilder<AriadneSpan<'b>>,
@@ -28,24 +65,124 @@ where
 
         if id.is_empty() {}
         let src = Source::from(fs::read_to_string(&**filepath).unwrap_or_default());
-        let report = f(Report::build(ReportKind::Error, &id, begin.pos), locs_span)
+        let report = f(Report::build(kind, &id, begin.pos), locs_span)
             .with_message(main_msg.clone())
+            .with_config(config())
             .finish();
 
-        match std::panic::catch_unwind(|| {
-            let mut rendered = vec![];
-            report.write((&id, src), &mut rendered).unwrap();
-            String::from_utf8_lossy(&rendered).to_string()
-        }) {
-            Ok(u8str) => u8str,
-            Err(e) => {
-                println!("[BUG] ariadne crate crashed!");
-                dbg!(&e);
-                main_msg
-            }
-        }
+        render(main_msg, |rendered| report.write((&id, src), rendered))
     } else {
         // No location information available
         main_msg
     }
 }
+
+/// An error report with secondary labels and a note.
+///
+/// ```ignore
+/// let report = Diagnostic::new(msg, &arg.locs, "this is String")
+///     .label(&param.locs, "the parameter is declared here")
+///     .note("convert it with `to_i`")
+///     .render();
+/// ```
+///
+/// The secondary labels may point to other files than the primary one.
+/// Labels without location information are omitted.
+#[derive(Debug)]
+pub struct Diagnostic {
+    main_msg: String,
+    locs: LocationSpan,
+    labels: Vec<(LocationSpan, String)>,
+    note: Option<String>,
+}
+
+impl Diagnostic {
+    /// Create a report of `main_msg` with `label` on `locs`
+    pub fn new(main_msg: impl Into<String>, locs: &LocationSpan, label: impl Into<String>) -> Self {
+        Diagnostic {
+            main_msg: main_msg.into(),
+            locs: locs.clone(),
+            labels: vec![(locs.clone(), label.into())],
+            note: None,
+        }
+    }
+
+    /// Add a secondary label (eg. "declared here")
+    pub fn label(mut self, locs: &LocationSpan, label: impl Into<String>) -> Self {
+        self.labels.push((locs.clone(), label.into()));
+        self
+    }
+
+    /// Add a note shown under the source (eg. a suggestion to fix the error)
+    pub fn note(mut self, note: impl Into<String>) -> Self {
+        self.note = Some(note.into());
+        self
+    }
+
+    /// Render the report. Returns the main message (and the note) when the
+    /// primary location is not available
+    pub fn render(self) -> String {
+        let (id, pos) = match &self.locs {
+            LocationSpan::Just {
+                filepath, begin, ..
+            } => (format!("{}", filepath.display()), begin.pos),
+            _ => {
+                return match self.note {
+                    Some(note) => format!("{} ({})", self.main_msg, note),
+                    None => self.main_msg,
+                }
+            }
+        };
+        let mut files = vec![];
+        let mut builder = Report::build(ReportKind::Error, id, pos)
+            .with_message(self.main_msg.clone())
+            .with_config(config());
+        for (i, (locs, msg)) in self.labels.into_iter().enumerate() {
+            if let LocationSpan::Just {
+                filepath,
+                begin,
+                end,
+            } = locs
+            {
+                let file_id = format!("{}", filepath.display());
+                if !files.iter().any(|(f, _)| *f == file_id) {
+                    let src = fs::read_to_string(&*filepath).unwrap_or_default();
+                    files.push((file_id.clone(), src));
+                }
+                let color = if i == 0 { Color::Red } else { Color::Blue };
+                builder = builder.with_label(
+                    Label::new((file_id, begin.pos..end.pos))
+                        .with_message(msg)
+                        .with_color(color),
+                );
+            }
+        }
+        if let Some(note) = self.note {
+            builder = builder.with_note(note);
+        }
+        let report = builder.finish();
+
+        render(self.main_msg, |rendered| {
+            report.write(ariadne::sources(files), rendered)
+        })
+    }
+}
+
+/// Call `write` to render a report. Returns `main_msg` if ariadne crashed
+fn render<F>(main_msg: String, write: F) -> String
+where
+    F: FnOnce(&mut Vec<u8>) -> std::io::Result<()>,
+{
+    match panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut rendered = vec![];
+        write(&mut rendered).unwrap();
+        String::from_utf8_lossy(&rendered).to_string()
+    })) {
+        Ok(u8str) => u8str,
+        Err(e) => {
+            println!("[BUG] ariadne crate crashed!");
+            dbg!(&e);
+            main_msg
+        }
+    }
+}
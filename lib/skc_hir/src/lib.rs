@@ -4,15 +4,16 @@ mod signatures;
 mod sk_method;
 mod sk_type;
 mod superclass;
+pub mod visitor;
 pub use crate::signature::*;
 pub use crate::signatures::MethodSignatures;
 pub use crate::sk_method::{SkMethod, SkMethodBody, SkMethods};
 pub use crate::sk_type::{SkClass, SkModule, SkType, SkTypeBase, SkTypes, WTable};
 pub use crate::superclass::Superclass;
 use serde::{Deserialize, Serialize};
-use shiika_ast::LocationSpan;
+use shiika_ast::{Annotation, LocationSpan};
 use shiika_core::{names::*, ty, ty::*};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug)]
 pub struct Hir {
@@ -24,9 +25,29 @@ pub struct Hir {
     pub main_exprs: HirExpressions,
     /// Local variables in `main_exprs`
     pub main_lvars: HirLVars,
+    /// Annotations on the methods (eg. `@inline`)
+    pub annotations: HashMap<MethodFullname, Vec<Annotation>>,
+    /// Methods added to imported classes by `extension` (they are not in
+    /// the vtables)
+    pub extension_methods: HashSet<MethodFullname>,
 }
 
 impl Hir {
+    /// Returns the annotations on the method
+    pub fn annotations_on(&self, name: &MethodFullname) -> &[Annotation] {
+        self.annotations
+            .get(name)
+            .map(|v| v.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Returns true if the method has the annotation `@name`
+    pub fn has_annotation(&self, method_name: &MethodFullname, name: &str) -> bool {
+        self.annotations_on(method_name)
+            .iter()
+            .any(|a| a.name == name)
+    }
+
     pub fn add_methods(&mut self, sk_methods: SkMethods) {
         for (classname, mut new_methods) in sk_methods {
             match self.sk_methods.get_mut(&classname) {
@@ -58,19 +79,29 @@ impl SkIVar {
     /// Apply type arguments
     pub fn substitute(&self, tyargs: &[TermTy]) -> SkIVar {
         let mut ivar = self.clone();
-        ivar.ty = self.ty.substitute(tyargs, &[]);
+        ivar.ty = self.ty.substitute_class(tyargs);
         ivar
     }
 }
 
 pub type SkIVars = HashMap<String, SkIVar>;
 
-pub type HirLVars = Vec<(String, TermTy)>;
+/// A local variable
+#[derive(Debug, Clone)]
+pub struct HirLVar {
+    pub name: String,
+    pub ty: TermTy,
+    /// true if this variable is stored in a heap-allocated cell (rather than
+    /// the stack) because it is reassignable and captured by a lambda
+    pub cell: bool,
+}
+
+pub type HirLVars = Vec<HirLVar>;
 
 #[derive(Debug, Clone)]
 pub struct HirExpressions {
-    pub ty: TermTy,
-    pub exprs: Vec<HirExpression>,
+    ty: TermTy,
+    exprs: Vec<HirExpression>,
 }
 
 impl HirExpressions {
@@ -79,23 +110,49 @@ impl HirExpressions {
         if exprs.is_empty() {
             exprs.push(void_const_ref());
         }
-        let last_expr = exprs.last().unwrap();
-        let ty = last_expr.ty.clone();
-
+        let ty = Self::compute_ty(&exprs);
         HirExpressions { ty, exprs }
     }
 
+    /// Type of the sequence (the value of the last expression)
+    pub fn ty(&self) -> &TermTy {
+        &self.ty
+    }
+
+    /// The expressions in the sequence
+    pub fn exprs(&self) -> &[HirExpression] {
+        &self.exprs
+    }
+
+    /// Type of the sequence; `Never` if any of the expressions never
+    /// returns, otherwise the type of the last one
+    fn compute_ty(exprs: &[HirExpression]) -> TermTy {
+        if exprs.iter().any(|e| e.ty.is_never_type()) {
+            ty::raw("Never")
+        } else {
+            exprs.last().unwrap().ty.clone()
+        }
+    }
+
+    /// Insert `exprs` at the beginning
+    pub fn prepend(&mut self, exprs: Vec<HirExpression>) {
+        self.exprs.splice(0..0, exprs);
+        self.ty = Self::compute_ty(&self.exprs);
+    }
+
     /// Change the type of `self` to `Void`
+    /// (unless it is `Never`, which is compatible with `Void`)
     pub fn voidify(&mut self) {
         self.exprs.push(void_const_ref());
-        self.ty = ty::raw("Void");
+        self.ty = Self::compute_ty(&self.exprs);
     }
 
     /// Change the type of `self` to `ty` by bitcasting the result
+    /// (unless it is `Never`)
     pub fn bitcast_to(mut self, ty: TermTy) -> Self {
         let last_expr = self.exprs.pop().unwrap();
-        self.exprs.push(Hir::bit_cast(ty.clone(), last_expr));
-        self.ty = ty;
+        self.exprs.push(Hir::bit_cast(ty, last_expr));
+        self.ty = Self::compute_ty(&self.exprs);
         self
     }
 }
@@ -144,6 +201,11 @@ pub enum HirExpressionBase {
     },
     HirBreakExpression {
         from: HirBreakFrom,
+        /// Value of the `while` (eg. `break 1`)
+        value: Option<Box<HirExpression>>,
+    },
+    HirContinueExpression {
+        from: HirBreakFrom,
     },
     HirReturnExpression {
         from: HirReturnFrom,
@@ -169,6 +231,13 @@ pub enum HirExpressionBase {
         method_fullname: MethodFullname,
         arg_exprs: Vec<HirExpression>,
     },
+    /// `super(...)`. Unlike `HirMethodCall`, the method is called directly
+    /// (not via the vtable)
+    HirSuperMethodCall {
+        receiver_expr: Box<HirExpression>,
+        method_fullname: MethodFullname,
+        arg_exprs: Vec<HirExpression>,
+    },
     HirModuleMethodCall {
         receiver_expr: Box<HirExpression>,
         module_fullname: ModuleFullname,
@@ -183,6 +252,14 @@ pub enum HirExpressionBase {
     HirArgRef {
         idx: usize,
     },
+    /// An argument omitted at the call site (the callee computes the
+    /// default value)
+    HirOmittedArg,
+    /// The `idx`th argument, or `default_expr` if it is omitted
+    HirArgOrDefault {
+        idx: usize,
+        default_expr: Box<HirExpression>,
+    },
     HirLVarRef {
         name: String,
     },
@@ -209,6 +286,8 @@ pub enum HirExpressionBase {
         ret_ty: TermTy,
         /// true if there is a `break` in this lambda
         has_break: bool,
+        /// true if there is a `continue` in this lambda
+        has_continue: bool,
     },
     HirSelfExpression,
     HirFloatLiteral {
@@ -220,6 +299,11 @@ pub enum HirExpressionBase {
     /// A string literal. Its body is stored in str_literals
     HirStringLiteral {
         idx: usize,
+        /// True if the literal evaluates to the same frozen object every time
+        frozen: bool,
+    },
+    HirCharLiteral {
+        value: char,
     },
     HirBooleanLiteral {
         value: bool,
@@ -262,8 +346,9 @@ pub enum HirExpressionBase {
 /// Denotes which variable to include in the `captures`
 #[derive(Debug, Clone)]
 pub enum HirLambdaCapture {
-    /// Local variable
-    CaptureLVar { name: String },
+    /// Local variable. If `cell` is true, the pointer to the cell is
+    /// captured (so that updates are shared.) Otherwise its value is captured
+    CaptureLVar { name: String, cell: bool },
     /// Method/Function argument
     CaptureArg { idx: usize },
     /// Variable in the current `captures`
@@ -271,7 +356,7 @@ pub enum HirLambdaCapture {
     CaptureFwd { cidx: usize, ty: TermTy },
 }
 
-/// Denotes what a `break` escapes from
+/// Denotes what a `break` escapes from (or what a `continue` goes back to)
 #[derive(Debug, Clone)]
 pub enum HirBreakFrom {
     While,
@@ -287,7 +372,9 @@ pub enum HirReturnFrom {
 }
 
 impl Hir {
-    pub fn expressions(exprs: Vec<HirExpression>) -> HirExpressions {
+    /// Create a HirExpressions from the expressions of a body (of a method,
+    /// `if`, etc.) Its type is computed from the elements.
+    pub fn expressions_from_body(exprs: Vec<HirExpression>) -> HirExpressions {
         HirExpressions::new(exprs)
     }
 
@@ -366,12 +453,13 @@ impl Hir {
     }
 
     pub fn while_expression(
+        while_ty: TermTy,
         cond_hir: HirExpression,
         body_hirs: HirExpressions,
         locs: LocationSpan,
     ) -> HirExpression {
         HirExpression {
-            ty: ty::raw("Void"),
+            ty: while_ty,
             node: HirExpressionBase::HirWhileExpression {
                 cond_expr: Box::new(cond_hir),
                 body_exprs: Box::new(body_hirs),
@@ -380,10 +468,25 @@ impl Hir {
         }
     }
 
-    pub fn break_expression(from: HirBreakFrom, locs: LocationSpan) -> HirExpression {
+    pub fn break_expression(
+        from: HirBreakFrom,
+        value: Option<HirExpression>,
+        locs: LocationSpan,
+    ) -> HirExpression {
         HirExpression {
             ty: ty::raw("Never"),
-            node: HirExpressionBase::HirBreakExpression { from },
+            node: HirExpressionBase::HirBreakExpression {
+                from,
+                value: value.map(Box::new),
+            },
+            locs,
+        }
+    }
+
+    pub fn continue_expression(from: HirBreakFrom, locs: LocationSpan) -> HirExpression {
+        HirExpression {
+            ty: ty::raw("Never"),
+            node: HirExpressionBase::HirContinueExpression { from },
             locs,
         }
     }
@@ -475,6 +578,31 @@ impl Hir {
         }
     }
 
+    pub fn super_method_call(
+        result_ty: TermTy,
+        receiver_hir: HirExpression,
+        method_fullname: MethodFullname,
+        arg_hirs: Vec<HirExpression>,
+    ) -> HirExpression {
+        let locs = LocationSpan::merge(
+            &receiver_hir.locs,
+            if let Some(e) = arg_hirs.last() {
+                &e.locs
+            } else {
+                &receiver_hir.locs
+            },
+        );
+        HirExpression {
+            ty: result_ty,
+            node: HirExpressionBase::HirSuperMethodCall {
+                receiver_expr: Box::new(receiver_hir),
+                method_fullname,
+                arg_exprs: arg_hirs,
+            },
+            locs,
+        }
+    }
+
     pub fn module_method_call(
         result_ty: TermTy,
         receiver_hir: HirExpression,
@@ -528,6 +656,30 @@ impl Hir {
         }
     }
 
+    pub fn omitted_arg(ty: TermTy, locs: LocationSpan) -> HirExpression {
+        HirExpression {
+            ty,
+            node: HirExpressionBase::HirOmittedArg,
+            locs,
+        }
+    }
+
+    pub fn arg_or_default(
+        ty: TermTy,
+        idx: usize,
+        default_expr: HirExpression,
+        locs: LocationSpan,
+    ) -> HirExpression {
+        HirExpression {
+            ty,
+            node: HirExpressionBase::HirArgOrDefault {
+                idx,
+                default_expr: Box::new(default_expr),
+            },
+            locs,
+        }
+    }
+
     pub fn lvar_ref(ty: TermTy, name: String, locs: LocationSpan) -> HirExpression {
         HirExpression {
             ty,
@@ -574,6 +726,7 @@ impl Hir {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn lambda_expr(
         ty: TermTy,
         name: String,
@@ -582,6 +735,7 @@ impl Hir {
         captures: Vec<HirLambdaCapture>,
         lvars: HirLVars,
         has_break: bool,
+        has_continue: bool,
         locs: LocationSpan,
     ) -> HirExpression {
         let ret_ty = exprs.ty.clone();
@@ -595,6 +749,7 @@ impl Hir {
                 lvars,
                 ret_ty,
                 has_break,
+                has_continue,
             },
             locs,
         }
@@ -624,10 +779,26 @@ impl Hir {
         }
     }
 
+    pub fn char_literal(value: char, locs: LocationSpan) -> HirExpression {
+        HirExpression {
+            ty: ty::raw("Char"),
+            node: HirExpressionBase::HirCharLiteral { value },
+            locs,
+        }
+    }
+
     pub fn string_literal(idx: usize, locs: LocationSpan) -> HirExpression {
         HirExpression {
             ty: ty::raw("String"),
-            node: HirExpressionBase::HirStringLiteral { idx },
+            node: HirExpressionBase::HirStringLiteral { idx, frozen: false },
+            locs,
+        }
+    }
+
+    pub fn frozen_string_literal(idx: usize, locs: LocationSpan) -> HirExpression {
+        HirExpression {
+            ty: ty::raw("String"),
+            node: HirExpressionBase::HirStringLiteral { idx, frozen: true },
             locs,
         }
     }
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use shiika_ast::{AstExpression, LocationSpan};
 use shiika_core::{names::*, ty, ty::*};
 use std::fmt;
 
@@ -8,6 +9,21 @@ pub struct MethodSignature {
     pub ret_ty: TermTy,
     pub params: Vec<MethodParam>,
     pub typarams: Vec<TyParam>,
+    /// true if the method is annotated with `@pure` i.e. has no side effects
+    pub pure: bool,
+    /// The type arguments of the receiver class which this method is
+    /// restricted to (eg. `@only_for(Int)` on `Range#each`.) Empty if not
+    /// restricted.
+    #[serde(default)]
+    pub only_for: Vec<TermTy>,
+    /// true if the method is annotated with `@self_type` i.e. the class
+    /// itself in the signature means the type of the receiver (eg.
+    /// `Object#dup` returns a `String` when called on a `String`.)
+    #[serde(default)]
+    pub self_type: bool,
+    /// Where the method is defined
+    #[serde(skip, default = "LocationSpan::internal")]
+    pub locs: LocationSpan,
 }
 
 impl fmt::Display for MethodSignature {
@@ -34,13 +50,17 @@ impl MethodSignature {
     pub fn specialize(&self, class_tyargs: &[TermTy], method_tyargs: &[TermTy]) -> MethodSignature {
         MethodSignature {
             fullname: self.fullname.clone(),
-            ret_ty: self.ret_ty.substitute(class_tyargs, method_tyargs),
+            ret_ty: self.ret_ty.substitute_both(class_tyargs, method_tyargs),
             params: self
                 .params
                 .iter()
                 .map(|param| param.substitute(class_tyargs, method_tyargs))
                 .collect(),
             typarams: self.typarams.clone(), // eg. Array<T>#map<U>(f: Fn1<T, U>) -> Array<Int>#map<U>(f: Fn1<Int, U>)
+            pure: self.pure,
+            only_for: self.only_for.clone(),
+            self_type: self.self_type,
+            locs: self.locs.clone(),
         }
     }
 
@@ -57,7 +77,9 @@ impl MethodSignature {
             return false;
         }
         for i in 0..self.params.len() {
-            if self.params[i].ty != other.params[i].ty {
+            if self.params[i].ty != other.params[i].ty
+                || self.params[i].is_variadic != other.params[i].is_variadic
+            {
                 return false;
             }
         }
@@ -96,14 +118,31 @@ impl MethodSignature {
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct MethodParam {
     pub name: String,
+    /// For a variadic param `*a: T`, this is `Array<T>`
     pub ty: TermTy,
+    pub is_variadic: bool,
+    /// true if the argument may be omitted
+    pub has_default: bool,
+    /// The default value (only available for the methods defined in the
+    /// program being compiled.) It is computed in the callee when the
+    /// argument is omitted
+    #[serde(skip)]
+    pub default_expr: Option<AstExpression>,
+    /// Where the param is declared (only available for the methods defined
+    /// in the program being compiled)
+    #[serde(skip, default = "LocationSpan::internal")]
+    pub locs: LocationSpan,
 }
 
 impl MethodParam {
     pub fn substitute(&self, class_tyargs: &[TermTy], method_tyargs: &[TermTy]) -> MethodParam {
         MethodParam {
             name: self.name.clone(),
-            ty: self.ty.substitute(class_tyargs, method_tyargs),
+            ty: self.ty.substitute_both(class_tyargs, method_tyargs),
+            is_variadic: self.is_variadic,
+            has_default: self.has_default,
+            default_expr: self.default_expr.clone(),
+            locs: self.locs.clone(),
         }
     }
 }
@@ -127,6 +166,10 @@ pub fn signature_of_new(
         ret_ty: instance_ty.clone(),
         params: initialize_params,
         typarams: vec![],
+        pure: false,
+        only_for: vec![],
+        self_type: false,
+        locs: LocationSpan::internal(),
     }
 }
 
@@ -140,5 +183,9 @@ pub fn signature_of_initialize(
         ret_ty: ty::raw("Void"),
         params,
         typarams: vec![],
+        pure: false,
+        only_for: vec![],
+        self_type: false,
+        locs: LocationSpan::internal(),
     }
 }
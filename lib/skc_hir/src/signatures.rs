@@ -64,6 +64,17 @@ impl MethodSignatures {
         v
     }
 
+    /// Returns iterator over signatures, sorted by the method name.
+    pub fn sorted_iter(&self) -> impl Iterator<Item = (&MethodFirstname, &MethodSignature)> {
+        let mut v = self
+            .0
+            .iter()
+            .map(|(name, (sig, _))| (name, sig))
+            .collect::<Vec<_>>();
+        v.sort_unstable_by(|(a, _), (b, _)| a.0.cmp(&b.0));
+        v.into_iter()
+    }
+
     /// Returns iterator over signatures (not ordered.)
     pub fn unordered_iter(&self) -> impl Iterator<Item = &(MethodSignature, usize)> {
         self.0.values()
@@ -33,6 +33,19 @@ impl SkTypes {
         })
     }
 
+    /// Returns iterator over the types, sorted by the name
+    pub fn sorted_iter(&self) -> impl Iterator<Item = (&TypeFullname, &SkType)> {
+        let mut v = self.0.iter().collect::<Vec<_>>();
+        v.sort_unstable_by(|(a, _), (b, _)| a.0.cmp(&b.0));
+        v.into_iter()
+    }
+
+    /// Returns iterator over the classes, sorted by the name
+    pub fn sorted_classes(&self) -> impl Iterator<Item = &SkClass> + '_ {
+        self.sorted_iter()
+            .filter_map(|(_, sk_type)| sk_type.class())
+    }
+
     pub fn get_class<'hir>(&'hir self, name: &ClassFullname) -> &'hir SkClass {
         let sk_type = self.0.get(&name.to_type_fullname()).unwrap();
         if let SkType::Class(class) = sk_type {
@@ -1,10 +1,11 @@
 use super::SkTypeBase;
+use crate::signature::MethodSignature;
 use crate::sk_type::wtable::WTable;
 use crate::superclass::Superclass;
 use crate::{SkIVar, SkIVars};
 use serde::{Deserialize, Serialize};
 use shiika_core::names::ClassFullname;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct SkClass {
@@ -21,6 +22,8 @@ pub struct SkClass {
     pub const_is_obj: bool,
     /// Witness table
     pub wtable: WTable,
+    /// Methods declared with `abstract def` in this class
+    pub abstract_methods: Vec<MethodSignature>,
 }
 
 impl SkClass {
@@ -33,6 +36,7 @@ impl SkClass {
             is_final: Some(false),
             const_is_obj: false,
             wtable: Default::default(),
+            abstract_methods: Default::default(),
         }
     }
 
@@ -45,6 +49,7 @@ impl SkClass {
             is_final: Some(false),
             const_is_obj: false,
             wtable: Default::default(),
+            abstract_methods: Default::default(),
         }
     }
 
@@ -61,4 +66,77 @@ impl SkClass {
     pub fn fullname(&self) -> ClassFullname {
         self.base.erasure.to_class_fullname()
     }
+
+    /// Returns the number of the virtual methods (own and inherited ones,
+    /// de-duplicated by name) i.e. the size of the vtable.
+    /// `find_class` should return the class of the given name.
+    pub fn method_count<'a, F>(&'a self, find_class: F) -> usize
+    where
+        F: Fn(&ClassFullname) -> Option<&'a SkClass>,
+    {
+        let mut names = HashSet::new();
+        let mut cls = self;
+        loop {
+            names.extend(cls.base.method_sigs_sorted().map(|(name, _)| name));
+            match &cls.superclass {
+                Some(superclass) => {
+                    let super_name = superclass.base_fullname();
+                    cls = find_class(&super_name)
+                        .unwrap_or_else(|| panic!("[BUG] class {} not found", super_name));
+                }
+                None => break,
+            }
+        }
+        names.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signatures::MethodSignatures;
+    use shiika_ast::LocationSpan;
+    use shiika_core::names::{class_fullname, method_fullname_raw};
+    use shiika_core::ty::{self, Erasure};
+
+    fn class(name: &str, superclass: Option<&str>, methods: &[&str]) -> SkClass {
+        let sigs = methods.iter().map(|m| MethodSignature {
+            fullname: method_fullname_raw(name, *m),
+            ret_ty: ty::raw("Int"),
+            params: vec![],
+            typarams: vec![],
+            pure: false,
+            only_for: vec![],
+            self_type: false,
+            locs: LocationSpan::internal(),
+        });
+        let base = SkTypeBase {
+            erasure: Erasure::nonmeta(name),
+            typarams: vec![],
+            method_sigs: MethodSignatures::from_iterator(sigs),
+            foreign: false,
+        };
+        SkClass::nonmeta(base, superclass.map(Superclass::simple))
+    }
+
+    #[test]
+    fn test_method_count() {
+        let classes = [
+            class("Object", None, &["hash", "to_s"]),
+            // Overrides `to_s`
+            class("A", Some("Object"), &["to_s", "foo"]),
+            // Inherits all
+            class("B", Some("A"), &[]),
+            class("C", Some("B"), &["foo", "bar"]),
+        ];
+        let find_class = |name: &ClassFullname| classes.iter().find(|c| c.fullname() == *name);
+        let count = |name: &str| {
+            let c = find_class(&class_fullname(name)).unwrap();
+            c.method_count(find_class)
+        };
+        assert_eq!(count("Object"), 2);
+        assert_eq!(count("A"), 3);
+        assert_eq!(count("B"), 3);
+        assert_eq!(count("C"), 4);
+    }
 }
@@ -1,3 +1,4 @@
+use crate::signature::MethodSignature;
 use crate::signatures::MethodSignatures;
 use serde::{Deserialize, Serialize};
 use shiika_core::names::*;
@@ -21,4 +22,10 @@ impl SkTypeBase {
     pub fn fullname_(&self) -> ClassFullname {
         self.erasure.to_class_fullname()
     }
+
+    /// Returns iterator over the method signatures in lexicographic order of
+    /// the method names (useful to make the output deterministic.)
+    pub fn method_sigs_sorted(&self) -> impl Iterator<Item = (&MethodFirstname, &MethodSignature)> {
+        self.method_sigs.sorted_iter()
+    }
 }
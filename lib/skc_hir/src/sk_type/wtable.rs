@@ -26,6 +26,14 @@ impl WTable {
         self.0.is_empty()
     }
 
+    /// Returns iterator over the modules and the methods, sorted by the
+    /// module name
+    pub fn sorted_iter(&self) -> impl Iterator<Item = (&ModuleFullname, &Vec<MethodFullname>)> {
+        let mut v = self.0.iter().collect::<Vec<_>>();
+        v.sort_unstable_by(|(a, _), (b, _)| a.0.cmp(&b.0));
+        v.into_iter()
+    }
+
     pub fn get_len(&self, key: &ModuleFullname) -> usize {
         self.0.get(key).unwrap().len()
     }
@@ -48,7 +48,7 @@ impl Superclass {
 
     /// Create concrete superclass of a generic class
     pub fn substitute(&self, tyargs: &[TermTy]) -> Superclass {
-        let t = self.0.substitute(tyargs, Default::default());
+        let t = self.0.substitute_class(tyargs);
         Superclass::from_ty(t)
     }
 }
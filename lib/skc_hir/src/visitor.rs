@@ -0,0 +1,234 @@
+//! Helpers to traverse HIR expression trees
+use crate::pattern_match::{Component, MatchClause};
+use crate::{HirExpression, HirExpressionBase, HirExpressions};
+
+/// Call `f` for `expr` and each of its subexpressions (pre-order)
+pub fn walk_expr<F>(expr: &HirExpression, f: &mut F)
+where
+    F: FnMut(&HirExpression),
+{
+    f(expr);
+    match &expr.node {
+        HirExpressionBase::HirLogicalNot { expr } => walk_expr(expr, f),
+        HirExpressionBase::HirLogicalAnd { left, right }
+        | HirExpressionBase::HirLogicalOr { left, right } => {
+            walk_expr(left, f);
+            walk_expr(right, f);
+        }
+        HirExpressionBase::HirIfExpression {
+            cond_expr,
+            then_exprs,
+            else_exprs,
+        } => {
+            walk_expr(cond_expr, f);
+            walk_exprs(then_exprs, f);
+            walk_exprs(else_exprs, f);
+        }
+        HirExpressionBase::HirMatchExpression {
+            cond_assign_expr,
+            clauses,
+        } => {
+            walk_expr(cond_assign_expr, f);
+            for clause in clauses {
+                walk_match_clause(clause, f);
+            }
+        }
+        HirExpressionBase::HirWhileExpression {
+            cond_expr,
+            body_exprs,
+        } => {
+            walk_expr(cond_expr, f);
+            walk_exprs(body_exprs, f);
+        }
+        HirExpressionBase::HirBreakExpression { value, .. } => {
+            if let Some(v) = value {
+                walk_expr(v, f);
+            }
+        }
+        HirExpressionBase::HirReturnExpression { arg, .. } => walk_expr(arg, f),
+        HirExpressionBase::HirLVarAssign { rhs, .. }
+        | HirExpressionBase::HirIVarAssign { rhs, .. }
+        | HirExpressionBase::HirConstAssign { rhs, .. }
+        | HirExpressionBase::HirLambdaCaptureWrite { rhs, .. } => walk_expr(rhs, f),
+        HirExpressionBase::HirMethodCall {
+            receiver_expr,
+            arg_exprs,
+            ..
+        }
+        | HirExpressionBase::HirSuperMethodCall {
+            receiver_expr,
+            arg_exprs,
+            ..
+        }
+        | HirExpressionBase::HirModuleMethodCall {
+            receiver_expr,
+            arg_exprs,
+            ..
+        } => {
+            walk_expr(receiver_expr, f);
+            arg_exprs.iter().for_each(|e| walk_expr(e, f));
+        }
+        HirExpressionBase::HirLambdaInvocation {
+            lambda_expr,
+            arg_exprs,
+        } => {
+            walk_expr(lambda_expr, f);
+            arg_exprs.iter().for_each(|e| walk_expr(e, f));
+        }
+        HirExpressionBase::HirLambdaExpr { exprs, .. }
+        | HirExpressionBase::HirParenthesizedExpr { exprs } => walk_exprs(exprs, f),
+        HirExpressionBase::HirBitCast { expr } => walk_expr(expr, f),
+        HirExpressionBase::HirArgOrDefault { default_expr, .. } => walk_expr(default_expr, f),
+        HirExpressionBase::HirArgRef { .. }
+        | HirExpressionBase::HirOmittedArg
+        | HirExpressionBase::HirLVarRef { .. }
+        | HirExpressionBase::HirIVarRef { .. }
+        | HirExpressionBase::HirTVarRef { .. }
+        | HirExpressionBase::HirConstRef { .. }
+        | HirExpressionBase::HirSelfExpression
+        | HirExpressionBase::HirFloatLiteral { .. }
+        | HirExpressionBase::HirDecimalLiteral { .. }
+        | HirExpressionBase::HirStringLiteral { .. }
+        | HirExpressionBase::HirCharLiteral { .. }
+        | HirExpressionBase::HirBooleanLiteral { .. }
+        | HirExpressionBase::HirLambdaCaptureRef { .. }
+        | HirExpressionBase::HirContinueExpression { .. }
+        | HirExpressionBase::HirClassLiteral { .. } => (),
+    }
+}
+
+/// Call `f` for each expression in `exprs` and their subexpressions
+pub fn walk_exprs<F>(exprs: &HirExpressions, f: &mut F)
+where
+    F: FnMut(&HirExpression),
+{
+    exprs.exprs.iter().for_each(|e| walk_expr(e, f));
+}
+
+fn walk_match_clause<F>(clause: &MatchClause, f: &mut F)
+where
+    F: FnMut(&HirExpression),
+{
+    for component in &clause.components {
+        match component {
+            Component::Test(e) | Component::Bind(_, e) => walk_expr(e, f),
+        }
+    }
+    walk_exprs(&clause.body_hir, f);
+}
+
+/// Call `f` for each subexpression of `expr` and then `expr` itself
+/// (post-order). `f` may rewrite the expression
+pub fn walk_expr_mut<F>(expr: &mut HirExpression, f: &mut F)
+where
+    F: FnMut(&mut HirExpression),
+{
+    match &mut expr.node {
+        HirExpressionBase::HirLogicalNot { expr } => walk_expr_mut(expr, f),
+        HirExpressionBase::HirLogicalAnd { left, right }
+        | HirExpressionBase::HirLogicalOr { left, right } => {
+            walk_expr_mut(left, f);
+            walk_expr_mut(right, f);
+        }
+        HirExpressionBase::HirIfExpression {
+            cond_expr,
+            then_exprs,
+            else_exprs,
+        } => {
+            walk_expr_mut(cond_expr, f);
+            walk_exprs_mut(then_exprs, f);
+            walk_exprs_mut(else_exprs, f);
+        }
+        HirExpressionBase::HirMatchExpression {
+            cond_assign_expr,
+            clauses,
+        } => {
+            walk_expr_mut(cond_assign_expr, f);
+            for clause in clauses {
+                walk_match_clause_mut(clause, f);
+            }
+        }
+        HirExpressionBase::HirWhileExpression {
+            cond_expr,
+            body_exprs,
+        } => {
+            walk_expr_mut(cond_expr, f);
+            walk_exprs_mut(body_exprs, f);
+        }
+        HirExpressionBase::HirBreakExpression { value, .. } => {
+            if let Some(v) = value {
+                walk_expr_mut(v, f);
+            }
+        }
+        HirExpressionBase::HirReturnExpression { arg, .. } => walk_expr_mut(arg, f),
+        HirExpressionBase::HirLVarAssign { rhs, .. }
+        | HirExpressionBase::HirIVarAssign { rhs, .. }
+        | HirExpressionBase::HirConstAssign { rhs, .. }
+        | HirExpressionBase::HirLambdaCaptureWrite { rhs, .. } => walk_expr_mut(rhs, f),
+        HirExpressionBase::HirMethodCall {
+            receiver_expr,
+            arg_exprs,
+            ..
+        }
+        | HirExpressionBase::HirSuperMethodCall {
+            receiver_expr,
+            arg_exprs,
+            ..
+        }
+        | HirExpressionBase::HirModuleMethodCall {
+            receiver_expr,
+            arg_exprs,
+            ..
+        } => {
+            walk_expr_mut(receiver_expr, f);
+            arg_exprs.iter_mut().for_each(|e| walk_expr_mut(e, f));
+        }
+        HirExpressionBase::HirLambdaInvocation {
+            lambda_expr,
+            arg_exprs,
+        } => {
+            walk_expr_mut(lambda_expr, f);
+            arg_exprs.iter_mut().for_each(|e| walk_expr_mut(e, f));
+        }
+        HirExpressionBase::HirLambdaExpr { exprs, .. }
+        | HirExpressionBase::HirParenthesizedExpr { exprs } => walk_exprs_mut(exprs, f),
+        HirExpressionBase::HirBitCast { expr } => walk_expr_mut(expr, f),
+        HirExpressionBase::HirArgOrDefault { default_expr, .. } => walk_expr_mut(default_expr, f),
+        HirExpressionBase::HirArgRef { .. }
+        | HirExpressionBase::HirOmittedArg
+        | HirExpressionBase::HirLVarRef { .. }
+        | HirExpressionBase::HirIVarRef { .. }
+        | HirExpressionBase::HirTVarRef { .. }
+        | HirExpressionBase::HirConstRef { .. }
+        | HirExpressionBase::HirSelfExpression
+        | HirExpressionBase::HirFloatLiteral { .. }
+        | HirExpressionBase::HirDecimalLiteral { .. }
+        | HirExpressionBase::HirStringLiteral { .. }
+        | HirExpressionBase::HirCharLiteral { .. }
+        | HirExpressionBase::HirBooleanLiteral { .. }
+        | HirExpressionBase::HirLambdaCaptureRef { .. }
+        | HirExpressionBase::HirContinueExpression { .. }
+        | HirExpressionBase::HirClassLiteral { .. } => (),
+    }
+    f(expr);
+}
+
+/// Call `walk_expr_mut` for each expression in `exprs`
+pub fn walk_exprs_mut<F>(exprs: &mut HirExpressions, f: &mut F)
+where
+    F: FnMut(&mut HirExpression),
+{
+    exprs.exprs.iter_mut().for_each(|e| walk_expr_mut(e, f));
+}
+
+fn walk_match_clause_mut<F>(clause: &mut MatchClause, f: &mut F)
+where
+    F: FnMut(&mut HirExpression),
+{
+    for component in &mut clause.components {
+        match component {
+            Component::Test(e) | Component::Bind(_, e) => walk_expr_mut(e, f),
+        }
+    }
+    walk_exprs_mut(&mut clause.body_hir, f);
+}
@@ -0,0 +1,253 @@
+//! Compile-time evaluation of arithmetic on literals.
+//!
+//! ```sk
+//! let a = 2 + 3 * 4
+//! # is compiled as
+//! let a = 14
+//! ```
+//!
+//! Only the calls of the operators of `Int` and `Float` whose receiver and
+//! argument are both literals are folded. Calls which would fail at runtime
+//! (eg. overflow, division by zero) are left as is.
+use shiika_core::ty;
+use skc_hir::visitor::{walk_expr_mut, walk_exprs_mut};
+use skc_hir::*;
+
+/// Fold the constant expressions in the whole program
+pub fn fold_hir(hir: &mut Hir) {
+    for methods in hir.sk_methods.values_mut() {
+        for method in methods {
+            if let SkMethodBody::Normal { exprs } = &mut method.body {
+                fold_exprs(exprs);
+            }
+        }
+    }
+    for expr in &mut hir.const_inits {
+        fold_expr(expr);
+    }
+    fold_exprs(&mut hir.main_exprs);
+}
+
+/// Fold the constant expressions in `exprs`
+pub fn fold_exprs(exprs: &mut HirExpressions) {
+    walk_exprs_mut(exprs, &mut fold_node);
+}
+
+/// Fold the constant expressions in `expr`
+pub fn fold_expr(expr: &mut HirExpression) {
+    walk_expr_mut(expr, &mut fold_node);
+}
+
+/// A literal value known at compile time
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Value {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+}
+
+/// Replace `expr` with a literal if it is an operator call on literals.
+/// Subexpressions are already folded because this is called in post-order.
+fn fold_node(expr: &mut HirExpression) {
+    let value = match &expr.node {
+        HirExpressionBase::HirMethodCall {
+            receiver_expr,
+            method_fullname,
+            arg_exprs,
+        } => {
+            let receiver = literal_value(receiver_expr);
+            let args = arg_exprs.iter().map(literal_value).collect::<Vec<_>>();
+            match (receiver, args.as_slice()) {
+                (Some(recv), []) => eval_unary(&method_fullname.full_name, recv),
+                (Some(recv), [Some(arg)]) => eval_binary(&method_fullname.full_name, recv, *arg),
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+    if let Some(v) = value {
+        let locs = expr.locs.clone();
+        *expr = match v {
+            Value::Int(i) => Hir::decimal_literal(i, locs),
+            Value::Float(f) => Hir::float_literal(f, locs),
+            Value::Bool(b) => Hir::boolean_literal(b, locs),
+        };
+    }
+}
+
+/// Returns the value of `expr` if it is a number literal
+fn literal_value(expr: &HirExpression) -> Option<Value> {
+    match &expr.node {
+        HirExpressionBase::HirDecimalLiteral { value } => Some(Value::Int(*value)),
+        HirExpressionBase::HirFloatLiteral { value } => Some(Value::Float(*value)),
+        // The receiver of a method call is casted to the owner class
+        HirExpressionBase::HirBitCast { expr: inner } if is_number_ty(&expr.ty) => {
+            literal_value(inner)
+        }
+        _ => None,
+    }
+}
+
+fn is_number_ty(t: &ty::TermTy) -> bool {
+    *t == ty::raw("Int") || *t == ty::raw("Float")
+}
+
+fn eval_unary(name: &str, recv: Value) -> Option<Value> {
+    match (name, recv) {
+        ("Int#-@", Value::Int(a)) => a.checked_neg().map(Value::Int),
+        ("Float#-@", Value::Float(a)) => Some(Value::Float(-a)),
+        _ => None,
+    }
+}
+
+fn eval_binary(name: &str, recv: Value, arg: Value) -> Option<Value> {
+    match (recv, arg) {
+        (Value::Int(a), Value::Int(b)) => eval_int(name, a, b),
+        (Value::Float(a), Value::Float(b)) => eval_float(name, a, b),
+        _ => None,
+    }
+}
+
+fn eval_int(name: &str, a: i64, b: i64) -> Option<Value> {
+    let v = match name {
+        "Int#+" => Value::Int(a.checked_add(b)?),
+        "Int#-" => Value::Int(a.checked_sub(b)?),
+        "Int#*" => Value::Int(a.checked_mul(b)?),
+        "Int#/" if b != 0 => Value::Float(a as f64 / b as f64),
+        "Int#%" => Value::Int(a.checked_rem(b)?),
+        "Int#<" => Value::Bool(a < b),
+        "Int#<=" => Value::Bool(a <= b),
+        "Int#>" => Value::Bool(a > b),
+        "Int#>=" => Value::Bool(a >= b),
+        "Int#==" => Value::Bool(a == b),
+        _ => return None,
+    };
+    Some(v)
+}
+
+fn eval_float(name: &str, a: f64, b: f64) -> Option<Value> {
+    let v = match name {
+        "Float#+" => Value::Float(a + b),
+        "Float#-" => Value::Float(a - b),
+        "Float#*" => Value::Float(a * b),
+        "Float#/" => Value::Float(a / b),
+        "Float#<" => Value::Bool(a < b),
+        "Float#<=" => Value::Bool(a <= b),
+        "Float#>" => Value::Bool(a > b),
+        "Float#>=" => Value::Bool(a >= b),
+        "Float#==" => Value::Bool(a == b),
+        _ => return None,
+    };
+    Some(v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shiika_ast::LocationSpan;
+    use shiika_core::names::method_fullname_raw;
+
+    fn int(i: i64) -> HirExpression {
+        Hir::decimal_literal(i, LocationSpan::internal())
+    }
+
+    fn float(f: f64) -> HirExpression {
+        Hir::float_literal(f, LocationSpan::internal())
+    }
+
+    /// `recv.op(arg)` as ast2hir generates it
+    fn call(
+        cls: &str,
+        op: &str,
+        ret: &str,
+        recv: HirExpression,
+        arg: HirExpression,
+    ) -> HirExpression {
+        Hir::method_call(
+            ty::raw(ret),
+            Hir::bit_cast(ty::raw(cls), recv),
+            method_fullname_raw(cls, op),
+            vec![arg],
+        )
+    }
+
+    fn folded(mut expr: HirExpression) -> Option<Value> {
+        fold_expr(&mut expr);
+        literal_value(&expr).or(match expr.node {
+            HirExpressionBase::HirBooleanLiteral { value } => Some(Value::Bool(value)),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let e = call("Int", "+", "Int", int(2), int(3));
+        assert_eq!(folded(e), Some(Value::Int(5)));
+        let e = call("Int", "%", "Int", int(7), int(3));
+        assert_eq!(folded(e), Some(Value::Int(1)));
+        let e = call("Int", "/", "Float", int(7), int(2));
+        assert_eq!(folded(e), Some(Value::Float(3.5)));
+        let e = call("Float", "*", "Float", float(1.5), float(2.0));
+        assert_eq!(folded(e), Some(Value::Float(3.0)));
+        let e = call("Int", "<", "Bool", int(1), int(2));
+        assert_eq!(folded(e), Some(Value::Bool(true)));
+    }
+
+    #[test]
+    fn test_chained() {
+        // 2 + 3 * 4
+        let e = call(
+            "Int",
+            "+",
+            "Int",
+            int(2),
+            call("Int", "*", "Int", int(3), int(4)),
+        );
+        assert_eq!(folded(e), Some(Value::Int(14)));
+    }
+
+    #[test]
+    fn test_no_folding_of_variables() {
+        // a + (1 + 2)
+        let a = Hir::lvar_ref(ty::raw("Int"), "a".to_string(), LocationSpan::internal());
+        let mut e = call(
+            "Int",
+            "+",
+            "Int",
+            a,
+            call("Int", "+", "Int", int(1), int(2)),
+        );
+        fold_expr(&mut e);
+        match &e.node {
+            HirExpressionBase::HirMethodCall { arg_exprs, .. } => {
+                assert_eq!(literal_value(&arg_exprs[0]), Some(Value::Int(3)))
+            }
+            node => panic!("unexpectedly folded: {:?}", node),
+        }
+    }
+
+    #[test]
+    fn test_runtime_errors_are_not_folded() {
+        let e = call("Int", "%", "Int", int(1), int(0));
+        assert_eq!(folded(e), None);
+        let e = call("Int", "/", "Float", int(1), int(0));
+        assert_eq!(folded(e), None);
+        let e = call("Int", "+", "Int", int(i64::MAX), int(1));
+        assert_eq!(folded(e), None);
+    }
+
+    #[test]
+    fn test_float_edge_cases() {
+        let e = call("Float", "/", "Float", float(1.0), float(0.0));
+        assert_eq!(folded(e), Some(Value::Float(f64::INFINITY)));
+        let e = call("Float", "/", "Float", float(0.0), float(0.0));
+        match folded(e) {
+            Some(Value::Float(f)) => assert!(f.is_nan()),
+            v => panic!("not folded: {:?}", v),
+        }
+        // NaN is not equal to itself
+        let nan = call("Float", "/", "Float", float(0.0), float(0.0));
+        let e = call("Float", "==", "Bool", nan.clone(), nan);
+        assert_eq!(folded(e), Some(Value::Bool(false)));
+    }
+}
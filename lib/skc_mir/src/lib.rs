@@ -1,3 +1,4 @@
+pub mod const_fold;
 mod library;
 mod vtable;
 mod vtables;
@@ -13,7 +14,8 @@ pub struct Mir {
     pub imports: LibraryExports,
 }
 
-pub fn build(hir: Hir, imports: LibraryExports) -> Mir {
+pub fn build(mut hir: Hir, imports: LibraryExports) -> Mir {
+    const_fold::fold_hir(&mut hir);
     let vtables = VTables::build(&hir.sk_types, &imports);
     Mir {
         hir,
@@ -20,4 +20,11 @@ impl LibraryExports {
             constants: mir.hir.constants.clone(),
         }
     }
+
+    /// Add the items exported from another library
+    pub fn merge(&mut self, other: LibraryExports) {
+        self.sk_types.0.extend(other.sk_types.0);
+        self.vtables.merge(other.vtables);
+        self.constants.extend(other.constants);
+    }
 }
@@ -66,4 +66,16 @@ impl VTables {
     pub fn iter(&self) -> std::collections::hash_map::Iter<'_, ClassFullname, VTable> {
         self.vtables.iter()
     }
+
+    /// Returns iterator over each vtable, sorted by the class name
+    pub fn sorted_iter(&self) -> impl Iterator<Item = (&ClassFullname, &VTable)> {
+        let mut v = self.vtables.iter().collect::<Vec<_>>();
+        v.sort_unstable_by(|(a, _), (b, _)| a.0.cmp(&b.0));
+        v.into_iter()
+    }
+
+    /// Add the vtables of another library
+    pub fn merge(&mut self, other: VTables) {
+        self.vtables.extend(other.vtables);
+    }
 }
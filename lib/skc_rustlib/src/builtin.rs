@@ -1,22 +1,40 @@
 pub mod array;
+mod benchmark;
 pub mod bool;
+pub mod char;
 pub mod class;
+mod file;
 pub mod float;
-mod time;
-//mod fn_x;
+mod fn_x;
+mod hash_map;
 pub mod int;
+mod io;
+mod json;
+mod lazy;
 mod math;
+mod mutex;
 pub mod object;
+mod process;
+mod queue;
+mod range;
+mod regex;
+mod shiika_ffi;
 mod shiika_internal_memory;
 pub mod shiika_internal_ptr;
-//pub mod shiika_internal_ptr_typed;
+pub mod shiika_internal_ptr_typed;
+mod sorted_map;
+mod stack;
 pub mod string;
+mod thread;
+mod time;
 mod void;
+mod weak_ref;
 pub use self::array::SkAry;
 pub use self::bool::SkBool;
+pub use self::char::SkChar;
 pub use self::class::SkClass;
 pub use self::float::SkFloat;
-//pub use self::fn_x::SkFn1;
+pub use self::fn_x::{SkFn0, SkFn1, SkFn2};
 pub use self::int::SkInt;
 pub use self::object::SkObj;
 pub use self::shiika_internal_ptr::SkPtr;
@@ -1,4 +1,4 @@
-use crate::builtin::{SkInt, SkObj};
+use crate::builtin::{SkBool, SkFn2, SkInt, SkObj};
 use crate::sk_methods::meta_array_new;
 use shiika_ffi_macro::shiika_method;
 
@@ -62,7 +62,11 @@ pub extern "C" fn array_get(receiver: SkAry<SkObj>, idx: SkInt) -> SkObj {
 #[shiika_method("Array#[]=")]
 pub extern "C" fn array_set(receiver: SkAry<SkObj>, idx: SkInt, obj: SkObj) {
     let v = receiver.as_vec_mut();
-    v[idx.val() as usize] = obj;
+    let len = v.len();
+    let item = v
+        .get_mut(idx.val() as usize)
+        .unwrap_or_else(|| panic!("Array#[]=: idx too large (len: {}, idx: {})", len, idx));
+    *item = obj;
 }
 
 #[shiika_method("Array#clear")]
@@ -75,6 +79,42 @@ pub extern "C" fn array_length(receiver: SkAry<SkObj>) -> SkInt {
     receiver.as_vec().len().into()
 }
 
+/// Stable sort of the elements. `less` is usually a call of `T#<`
+#[shiika_method("Array#_merge_sort")]
+pub extern "C" fn array__merge_sort(receiver: SkAry<SkObj>, less: SkFn2<SkObj, SkObj, SkBool>) {
+    merge_sort(receiver.as_vec_mut(), |a, b| {
+        less.call(a.dup(), b.dup()).val()
+    });
+}
+
+/// Bottom-up merge sort which calls `less` O(n log n) times
+fn merge_sort(v: &mut Vec<SkObj>, less: impl Fn(&SkObj, &SkObj) -> bool) {
+    let len = v.len();
+    let mut width = 1;
+    while width < len {
+        let mut merged = Vec::with_capacity(len);
+        for start in (0..len).step_by(2 * width) {
+            let mid = (start + width).min(len);
+            let end = (start + 2 * width).min(len);
+            let (mut i, mut j) = (start, mid);
+            while i < mid && j < end {
+                // Take the right one only if it is strictly less (to be stable)
+                if less(&v[j], &v[i]) {
+                    merged.push(v[j].dup());
+                    j += 1;
+                } else {
+                    merged.push(v[i].dup());
+                    i += 1;
+                }
+            }
+            merged.extend(v[i..mid].iter().map(SkObj::dup));
+            merged.extend(v[j..end].iter().map(SkObj::dup));
+        }
+        *v = merged;
+        width *= 2;
+    }
+}
+
 #[shiika_method("Array#push")]
 pub extern "C" fn array_push(receiver: SkAry<SkObj>, item: SkObj) {
     receiver.as_vec_mut().push(item);
@@ -82,7 +122,11 @@ pub extern "C" fn array_push(receiver: SkAry<SkObj>, item: SkObj) {
 
 #[shiika_method("Array#pop")]
 pub extern "C" fn array_pop(receiver: SkAry<SkObj>) -> SkObj {
-    receiver.as_vec_mut().pop().unwrap().dup()
+    receiver
+        .as_vec_mut()
+        .pop()
+        .unwrap_or_else(|| panic!("Array#pop: array is empty"))
+        .dup()
 }
 
 #[shiika_method("Array#reserve")]
@@ -0,0 +1,39 @@
+//! Class methods of `::Benchmark`
+use crate::builtin::{SkFloat, SkFn0, SkInt, SkObj};
+use shiika_ffi_macro::shiika_method;
+use std::time::{Duration, Instant};
+
+/// Call `f` repeatedly for `budget_ns` nanoseconds and returns the number of
+/// the calls per second
+#[shiika_method("Meta:Benchmark#_ips")]
+#[allow(non_snake_case)]
+pub extern "C" fn benchmark__ips(
+    _receiver: *const u8,
+    budget_ns: SkInt,
+    f: SkFn0<SkObj>,
+) -> SkFloat {
+    let budget = Duration::from_nanos(budget_ns.val().max(0) as u64);
+    let mut n = 0u64;
+    // `Instant` uses `CLOCK_MONOTONIC` on Linux
+    let start = Instant::now();
+    let elapsed = loop {
+        f.call();
+        n += 1;
+        let elapsed = start.elapsed();
+        if elapsed >= budget {
+            break elapsed;
+        }
+    };
+    (n as f64 / elapsed.as_secs_f64()).into()
+}
+
+/// Call `f` `n` times and returns the elapsed time in nanoseconds
+#[shiika_method("Meta:Benchmark#_run")]
+#[allow(non_snake_case)]
+pub extern "C" fn benchmark__run(_receiver: *const u8, n: SkInt, f: SkFn0<SkObj>) -> SkInt {
+    let start = Instant::now();
+    for _ in 0..n.val() {
+        f.call();
+    }
+    (start.elapsed().as_nanos() as i64).into()
+}
@@ -7,6 +7,7 @@
 //! let sk_bool: SkBool = b.into();
 //! let rust_bool: bool = sk_bool.into();
 //! ```
+use shiika_ffi_macro::shiika_method;
 
 extern "C" {
     fn box_bool(b: bool) -> SkBool;
@@ -34,3 +35,30 @@ impl From<bool> for SkBool {
         unsafe { box_bool(b) }
     }
 }
+
+impl SkBool {
+    /// Convert to Rust value
+    pub fn val(&self) -> bool {
+        unsafe { (*self.0).value }
+    }
+}
+
+#[shiika_method("Bool#!")]
+pub extern "C" fn bool_not(receiver: SkBool) -> SkBool {
+    (!receiver.val()).into()
+}
+
+#[shiika_method("Bool#&")]
+pub extern "C" fn bool_and(receiver: SkBool, other: SkBool) -> SkBool {
+    (receiver.val() & other.val()).into()
+}
+
+#[shiika_method("Bool#|")]
+pub extern "C" fn bool_or(receiver: SkBool, other: SkBool) -> SkBool {
+    (receiver.val() | other.val()).into()
+}
+
+#[shiika_method("Bool#^")]
+pub extern "C" fn bool_xor(receiver: SkBool, other: SkBool) -> SkBool {
+    (receiver.val() ^ other.val()).into()
+}
@@ -0,0 +1,63 @@
+//! Instance of `::Char`
+use crate::builtin::{SkBool, SkInt, SkStr};
+use shiika_ffi_macro::shiika_method;
+
+extern "C" {
+    fn box_char(c: u32) -> SkChar;
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct SkChar(*const ShiikaChar);
+
+#[repr(C)]
+#[derive(Debug)]
+struct ShiikaChar {
+    vtable: *const u8,
+    class_obj: *const u8,
+    value: u32,
+}
+
+impl From<char> for SkChar {
+    fn from(c: char) -> Self {
+        unsafe { box_char(c as u32) }
+    }
+}
+
+impl SkChar {
+    /// Convert to Rust value
+    pub fn val(&self) -> char {
+        // Char literals and `String#get_char` only make valid scalar values
+        unsafe { char::from_u32_unchecked((*self.0).value) }
+    }
+}
+
+#[shiika_method("Char#==")]
+pub extern "C" fn char_eq(receiver: SkChar, other: SkChar) -> SkBool {
+    (receiver.val() == other.val()).into()
+}
+
+#[shiika_method("Char#<")]
+pub extern "C" fn char_lt(receiver: SkChar, other: SkChar) -> SkBool {
+    (receiver.val() < other.val()).into()
+}
+
+#[shiika_method("Char#alphabetic?")]
+pub extern "C" fn char_alphabetic_p(receiver: SkChar) -> SkBool {
+    receiver.val().is_alphabetic().into()
+}
+
+#[shiika_method("Char#digit?")]
+pub extern "C" fn char_digit_p(receiver: SkChar) -> SkBool {
+    receiver.val().is_ascii_digit().into()
+}
+
+#[shiika_method("Char#to_i")]
+pub extern "C" fn char_to_i(receiver: SkChar) -> SkInt {
+    (receiver.val() as u32).into()
+}
+
+#[shiika_method("Char#to_s")]
+pub extern "C" fn char_to_s(receiver: SkChar) -> SkStr {
+    receiver.val().to_string().into()
+}
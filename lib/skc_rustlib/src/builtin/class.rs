@@ -1,10 +1,14 @@
 /// An instance of `::Class`
 mod witness_table;
 use crate::builtin::class::witness_table::WitnessTable;
+use crate::builtin::object::FROZEN_BIT;
 use crate::builtin::{SkAry, SkInt, SkStr};
 use crate::sk_methods::meta_class_new;
 use shiika_ffi_macro::shiika_method;
 use std::collections::HashMap;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::sync::atomic::{AtomicPtr, Ordering};
 #[repr(C)]
 #[derive(Debug)]
 pub struct SkClass(*mut ShiikaClass);
@@ -23,11 +27,12 @@ impl SkClass {
     }
 
     fn metacls_obj(&self) -> SkClass {
-        let metacls_obj = unsafe { &(*self.0).metacls_obj };
-        SkClass::new(metacls_obj.0)
+        // Drop the frozen flag (see `Object#freeze`)
+        let addr = unsafe { (*self.0).metacls_obj.0 } as usize;
+        SkClass::new((addr & !FROZEN_BIT) as *mut ShiikaClass)
     }
 
-    fn name(&self) -> &SkStr {
+    pub fn name(&self) -> &SkStr {
         unsafe { &(*self.0).name }
     }
 
@@ -43,6 +48,73 @@ impl SkClass {
         unsafe { (*self.0).witness_table.as_mut().unwrap() }
     }
 
+    fn class_info(&self) -> Option<&ClassInfo> {
+        unsafe { (*self.0).class_info.as_ref() }
+    }
+
+    /// Returns the names of the instance methods in the order of the vtable
+    fn method_names(&self) -> Vec<String> {
+        let info = match self.erasure_class().class_info() {
+            Some(x) => x,
+            None => return vec![],
+        };
+        let ptrs =
+            unsafe { std::slice::from_raw_parts(info.method_names, info.n_methods as usize) };
+        ptrs.iter()
+            .map(|p| unsafe { CStr::from_ptr(*p) }.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    /// Returns the number of ivars of the instances, or None if the
+    /// instances have other fields (or this is a metaclass)
+    pub fn n_ivars(&self) -> Option<usize> {
+        let info = self.erasure_class().class_info()?;
+        usize::try_from(info.n_ivars).ok()
+    }
+
+    /// Returns the names of the ivars (including `@`) in the order of the
+    /// fields, or None if `n_ivars` is None
+    pub fn ivar_names(&self) -> Option<Vec<String>> {
+        let n_ivars = self.n_ivars()?;
+        let info = self.erasure_class().class_info()?;
+        let ptrs = unsafe { std::slice::from_raw_parts(info.ivar_names, n_ivars) };
+        Some(
+            ptrs.iter()
+                .map(|p| unsafe { CStr::from_ptr(*p) }.to_string_lossy().into_owned())
+                .collect(),
+        )
+    }
+
+    /// Returns the vtable index of the instance method `name`
+    pub fn method_index(&self, name: &str) -> Option<usize> {
+        let cls = self.erasure_class();
+        let slot = unsafe { &(*cls.0).method_indices };
+        let mut ptr = slot.load(Ordering::Acquire);
+        if ptr.is_null() {
+            let indices = cls
+                .method_names()
+                .into_iter()
+                .enumerate()
+                .map(|(i, name)| (name, i))
+                .collect::<HashMap<_, _>>();
+            let new_ptr = Box::into_raw(Box::new(indices));
+            // Another thread may have created it meanwhile
+            ptr = match slot.compare_exchange(
+                std::ptr::null_mut(),
+                new_ptr,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => new_ptr,
+                Err(existing) => {
+                    drop(unsafe { Box::from_raw(new_ptr) });
+                    existing
+                }
+            };
+        }
+        unsafe { (*ptr).get(name).copied() }
+    }
+
     fn erasure_class(&self) -> SkClass {
         let erasure_cls = unsafe { &(*self.0).erasure_cls };
         if erasure_cls.0.is_null() {
@@ -66,6 +138,25 @@ pub struct ShiikaClass {
     // `Pair<Int, Bool>` -> `Pair`
     // `Object` -> null (means that its erasure is itself)
     erasure_cls: SkClass,
+    // null for metaclasses
+    class_info: *const ClassInfo,
+    // Method name -> vtable index (created when first needed, possibly by
+    // multiple threads at the same time)
+    method_indices: AtomicPtr<HashMap<String, usize>>,
+}
+
+/// `shiika_class_info_XX` generated by skc_codegen
+#[repr(C)]
+#[derive(Debug)]
+struct ClassInfo {
+    // Pointer to the constant of the superclass (null if none)
+    superclass: *const SkClass,
+    n_methods: u64,
+    method_names: *const *const c_char,
+    // Number of ivars (-1 if the struct has fields managed by skc_rustlib)
+    n_ivars: i64,
+    // Null if `n_ivars` is -1
+    ivar_names: *const *const c_char,
 }
 
 #[shiika_method("Meta:Class#_new")]
@@ -84,6 +175,9 @@ pub extern "C" fn meta_class__new(
         (*cls_obj.0).name = name;
         (*cls_obj.0).metacls_obj = metacls_obj;
         (*cls_obj.0).erasure_cls = erasure_cls;
+        // Set by the generated code for non-meta classes
+        (*cls_obj.0).class_info = std::ptr::null();
+        (*cls_obj.0).method_indices = AtomicPtr::new(std::ptr::null_mut());
         (*cls_obj.0).specialized_classes = Box::leak(Box::new(HashMap::new()));
         if witness_table.is_null() {
             (*cls_obj.0).witness_table = Box::leak(Box::new(WitnessTable::new()));
@@ -157,6 +251,7 @@ fn class_specialize(mut receiver: SkClass, tyargs: Vec<SkClass>) -> SkClass {
             receiver.dup(),
         );
         unsafe {
+            (*c.0).class_info = (*receiver.0).class_info;
             // Q. Why not just `(*c.0).type_args = tyargs` ?
             // A. To avoid `improper_ctypes` warning of some extern funcs.
             (*c.0).type_args = Box::into_raw(Box::new(tyargs));
@@ -179,3 +274,25 @@ fn specialized_name(class: &SkClass, tyargs: &[SkClass]) -> String {
 pub extern "C" fn class_erasure_class(receiver: SkClass) -> SkClass {
     receiver.erasure_class()
 }
+
+/// Returns `[superclass]` or `[]`
+#[shiika_method("Class#_superclass")]
+pub extern "C" fn class__superclass(receiver: SkClass) -> SkAry<SkClass> {
+    let ary = SkAry::<SkClass>::new();
+    if let Some(info) = receiver.erasure_class().class_info() {
+        if !info.superclass.is_null() {
+            let cls = unsafe { (*info.superclass).dup() };
+            ary.set_vec(vec![cls]);
+        }
+    }
+    ary
+}
+
+#[shiika_method("Class#instance_method_names")]
+pub extern "C" fn class_instance_method_names(receiver: SkClass) -> SkAry<SkStr> {
+    let mut names = receiver.method_names();
+    names.sort();
+    let ary = SkAry::<SkStr>::new();
+    ary.set_vec(names.into_iter().map(|s| s.into()).collect());
+    ary
+}
@@ -0,0 +1,146 @@
+//! Instance of `::File`
+//!
+//! The file handle is allocated separately from the Shiika object, which
+//! holds the pointer to it. Failed operations store the error message so
+//! that `File` (in Shiika) can make a `Fail` with it.
+use crate::builtin::{SkAry, SkBool, SkStr};
+use shiika_ffi_macro::shiika_method;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct SkFile(*mut ShiikaFile);
+
+#[repr(C)]
+#[derive(Debug)]
+struct ShiikaFile {
+    vtable: *const u8,
+    class_obj: *const u8,
+    // Allocated with the global allocator (i.e. `GC_malloc`)
+    state: *mut FileState,
+}
+
+#[derive(Debug, Default)]
+struct FileState {
+    /// None if not opened or closed
+    reader: Option<BufReader<fs::File>>,
+    /// Message of the last error
+    error: String,
+}
+
+impl SkFile {
+    fn state(&self) -> &mut FileState {
+        unsafe {
+            if (*self.0).state.is_null() {
+                (*self.0).state = Box::leak(Box::default());
+            }
+            (*self.0).state.as_mut().unwrap()
+        }
+    }
+
+    /// Run `f` with the file handle. Returns None (and saves the error
+    /// message) if failed
+    fn with_reader<T>(
+        &self,
+        f: impl FnOnce(&mut BufReader<fs::File>) -> std::io::Result<T>,
+    ) -> Option<T> {
+        let state = self.state();
+        let result = match &mut state.reader {
+            Some(reader) => f(reader).map_err(|e| e.to_string()),
+            None => Err("file is not opened".to_string()),
+        };
+        result.map_err(|e| state.error = e).ok()
+    }
+}
+
+/// Make a Shiika `String`. Fails if `s` contains a null byte
+fn to_sk_str(s: String) -> std::io::Result<SkStr> {
+    if s.contains('\0') {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "the contents has a null byte",
+        ))
+    } else {
+        Ok(s.into())
+    }
+}
+
+/// Returns `[value]` or `[]`
+fn opt_to_ary(v: Option<SkStr>) -> SkAry<SkStr> {
+    let ary = SkAry::<SkStr>::new();
+    ary.set_vec(v.into_iter().collect());
+    ary
+}
+
+#[shiika_method("File#close")]
+pub extern "C" fn file_close(receiver: SkFile) {
+    receiver.state().reader = None;
+}
+
+#[shiika_method("File#_error")]
+#[allow(non_snake_case)]
+pub extern "C" fn file__error(receiver: SkFile) -> SkStr {
+    receiver.state().error.clone().into()
+}
+
+#[shiika_method("File#_open")]
+#[allow(non_snake_case)]
+pub extern "C" fn file__open(receiver: SkFile, path: SkStr, mode: SkStr) -> SkBool {
+    let mut options = fs::OpenOptions::new();
+    match mode.as_str() {
+        "r" => options.read(true),
+        "w" => options.write(true).create(true).truncate(true),
+        "a" => options.append(true).create(true),
+        m => {
+            receiver.state().error = format!("unknown mode: {}", m);
+            return false.into();
+        }
+    };
+    let state = receiver.state();
+    match options.open(path.as_str()) {
+        Ok(file) => {
+            state.reader = Some(BufReader::new(file));
+            true.into()
+        }
+        Err(e) => {
+            state.error = format!("{}: {}", path.as_str(), e);
+            false.into()
+        }
+    }
+}
+
+#[shiika_method("File#_read_all")]
+#[allow(non_snake_case)]
+pub extern "C" fn file__read_all(receiver: SkFile) -> SkAry<SkStr> {
+    let s = receiver.with_reader(|reader| {
+        let mut buf = String::new();
+        reader.read_to_string(&mut buf)?;
+        to_sk_str(buf)
+    });
+    opt_to_ary(s)
+}
+
+#[shiika_method("File#_read_line")]
+#[allow(non_snake_case)]
+pub extern "C" fn file__read_line(receiver: SkFile) -> SkAry<SkStr> {
+    let line = receiver.with_reader(|reader| {
+        let mut buf = String::new();
+        if reader.read_line(&mut buf)? == 0 {
+            return Ok(None);
+        }
+        let line = buf.strip_suffix('\n').unwrap_or(&buf);
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        to_sk_str(line.to_string()).map(Some)
+    });
+    opt_to_ary(line.flatten())
+}
+
+#[shiika_method("File#_write")]
+#[allow(non_snake_case)]
+pub extern "C" fn file__write(receiver: SkFile, s: SkStr) -> SkBool {
+    receiver
+        .with_reader(|reader| reader.get_mut().write_all(s.as_byteslice()))
+        .is_some()
+        .into()
+}
@@ -97,6 +97,65 @@ pub extern "C" fn float_floor(receiver: SkFloat) -> SkFloat {
     receiver.val().floor().into()
 }
 
+#[shiika_method("Float#ceil")]
+pub extern "C" fn float_ceil(receiver: SkFloat) -> SkFloat {
+    receiver.val().ceil().into()
+}
+
+/// Round half away from zero (`2.5.round == 3.0`, `-2.5.round == -3.0`)
+#[shiika_method("Float#round")]
+pub extern "C" fn float_round(receiver: SkFloat) -> SkFloat {
+    receiver.val().round().into()
+}
+
+#[shiika_method("Float#sqrt")]
+pub extern "C" fn float_sqrt(receiver: SkFloat) -> SkFloat {
+    receiver.val().sqrt().into()
+}
+
+#[shiika_method("Float#sin")]
+pub extern "C" fn float_sin(receiver: SkFloat) -> SkFloat {
+    receiver.val().sin().into()
+}
+
+#[shiika_method("Float#cos")]
+pub extern "C" fn float_cos(receiver: SkFloat) -> SkFloat {
+    receiver.val().cos().into()
+}
+
+#[shiika_method("Float#tan")]
+pub extern "C" fn float_tan(receiver: SkFloat) -> SkFloat {
+    receiver.val().tan().into()
+}
+
+#[shiika_method("Float#ln")]
+pub extern "C" fn float_ln(receiver: SkFloat) -> SkFloat {
+    receiver.val().ln().into()
+}
+
+#[shiika_method("Float#log10")]
+pub extern "C" fn float_log10(receiver: SkFloat) -> SkFloat {
+    receiver.val().log10().into()
+}
+
+#[shiika_method("Float#hash")]
+pub extern "C" fn float_hash(receiver: SkFloat) -> SkInt {
+    // `0.0 == -0.0` but they have different bits
+    let v = receiver.val();
+    let bits = if v == 0.0 { 0 } else { v.to_bits() };
+    (bits as i64).into()
+}
+
+#[shiika_method("Float#nan?")]
+pub extern "C" fn float_nan_p(receiver: SkFloat) -> SkBool {
+    receiver.val().is_nan().into()
+}
+
+#[shiika_method("Float#infinite?")]
+pub extern "C" fn float_infinite_p(receiver: SkFloat) -> SkBool {
+    receiver.val().is_infinite().into()
+}
+
 #[shiika_method("Float#to_i")]
 pub extern "C" fn float_to_i(receiver: SkFloat) -> SkInt {
     (receiver.val().trunc() as i64).into()
@@ -104,5 +163,7 @@ pub extern "C" fn float_to_i(receiver: SkFloat) -> SkInt {
 
 #[shiika_method("Float#to_s")]
 pub extern "C" fn float_to_s(receiver: SkFloat) -> SkStr {
-    format!("{}", receiver.val()).into()
+    // Shortest representation which keeps the value, with ".0" for integral
+    // values (`1.0`, `0.1`, `1e100`, `NaN`, `inf`)
+    format!("{:?}", receiver.val()).into()
 }
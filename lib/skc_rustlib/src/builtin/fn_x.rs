@@ -1,7 +1,37 @@
 use crate::builtin::shiika_internal_ptr_typed::SkPtrTyped;
 use crate::builtin::{SkAry, SkInt, SkObj};
 
-// TODO: implement SkFn0, SkFn2..SkFn9
+// TODO: implement SkFn3..SkFn9
+
+/// Value of `@exit_status` when the lambda is exited with `break`
+const EXIT_BREAK: i64 = 1;
+
+#[repr(C)]
+pub struct SkFn0<R>(*const ShiikaFn0<R>);
+
+#[repr(C)]
+struct ShiikaFn0<R> {
+    vtable: *const u8,
+    class_obj: *const u8,
+    func: SkPtrTyped<extern "C" fn(*const ShiikaFn0<R>) -> R>,
+    the_self: SkObj,
+    captures: SkAry<*const u8>,
+    exit_status: SkInt,
+}
+
+impl<R> SkFn0<R> {
+    /// Address of the lambda object
+    pub fn as_ptr(&self) -> *const u8 {
+        self.0 as *const u8
+    }
+
+    pub fn call(&self) -> R {
+        unsafe {
+            let f = (*self.0).func.get();
+            f(self.0)
+        }
+    }
+}
 
 #[repr(C)]
 pub struct SkFn1<A, R>(*const ShiikaFn1<A, R>);
@@ -23,4 +53,32 @@ impl<A, R> SkFn1<A, R> {
             f(self.0, arg)
         }
     }
+
+    /// Returns true if `break` is called in the lambda (the caller
+    /// should stop iterating)
+    pub fn is_break(&self) -> bool {
+        unsafe { (*self.0).exit_status.val() == EXIT_BREAK }
+    }
+}
+
+#[repr(C)]
+pub struct SkFn2<A, B, R>(*const ShiikaFn2<A, B, R>);
+
+#[repr(C)]
+struct ShiikaFn2<A, B, R> {
+    vtable: *const u8,
+    class_obj: *const u8,
+    func: SkPtrTyped<extern "C" fn(*const ShiikaFn2<A, B, R>, A, B) -> R>,
+    the_self: SkObj,
+    captures: SkAry<*const u8>,
+    exit_status: SkInt,
+}
+
+impl<A, B, R> SkFn2<A, B, R> {
+    pub fn call(&self, arg1: A, arg2: B) -> R {
+        unsafe {
+            let f = (*self.0).func.get();
+            f(self.0, arg1, arg2)
+        }
+    }
 }
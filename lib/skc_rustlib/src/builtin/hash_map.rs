@@ -0,0 +1,150 @@
+//! Instance of `::HashMap`
+//!
+//! The entries are grouped by the hash value of the keys (computed by
+//! `Hashable#hash` in Shiika.) Keys in a bucket are compared with `==` in
+//! `HashMap#_index_of`. The key objects are stored along with the values so
+//! that they are not collected by GC while being used as keys.
+use crate::allocator::shiika_malloc;
+use crate::builtin::object::FROZEN_BIT;
+use crate::builtin::{SkAry, SkInt, SkObj};
+use shiika_ffi_macro::shiika_method;
+use std::collections::HashMap;
+use std::mem;
+
+type Map = HashMap<i64, Vec<(SkObj, SkObj)>>;
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct SkHashMap(*mut ShiikaHashMap);
+
+#[repr(C)]
+#[derive(Debug)]
+struct ShiikaHashMap {
+    vtable: *const u8,
+    class_obj: *const u8,
+    // Allocated with the global allocator (i.e. `GC_malloc`)
+    map: *mut Map,
+}
+
+impl SkHashMap {
+    fn map(&self) -> &Map {
+        unsafe { (*self.0).map.as_ref().unwrap() }
+    }
+
+    fn map_mut(&self) -> &mut Map {
+        unsafe { (*self.0).map.as_mut().unwrap() }
+    }
+
+    fn entries(&self) -> impl Iterator<Item = &(SkObj, SkObj)> {
+        self.map().values().flatten()
+    }
+
+    /// Returns a new (not frozen) map of the same class whose keys and
+    /// values are converted with `f`. The hash values of the keys must not
+    /// be changed by `f`
+    fn copy_with(&self, f: impl Fn(&SkObj) -> SkObj) -> SkHashMap {
+        let map = self
+            .map()
+            .iter()
+            .map(|(h, bucket)| (*h, bucket.iter().map(|(k, v)| (f(k), f(v))).collect()))
+            .collect::<Map>();
+        unsafe {
+            let p = shiika_malloc(mem::size_of::<ShiikaHashMap>()) as *mut ShiikaHashMap;
+            (*p).vtable = (*self.0).vtable;
+            (*p).class_obj = ((*self.0).class_obj as usize & !FROZEN_BIT) as *const u8;
+            (*p).map = Box::leak(Box::new(map));
+            SkHashMap(p)
+        }
+    }
+}
+
+/// Called from `HashMap.new` and initializes internal fields.
+#[shiika_method("HashMap#_initialize_rustlib")]
+#[allow(non_snake_case)]
+pub extern "C" fn hash_map__initialize_rustlib(receiver: SkHashMap) {
+    unsafe {
+        (*receiver.0).map = Box::leak(Box::new(HashMap::new()));
+    }
+}
+
+#[shiika_method("HashMap#_bucket_keys")]
+#[allow(non_snake_case)]
+pub extern "C" fn hash_map__bucket_keys(receiver: SkHashMap, h: SkInt) -> SkAry<SkObj> {
+    let ary = SkAry::<SkObj>::new();
+    let keys = match receiver.map().get(&h.val()) {
+        Some(bucket) => bucket.iter().map(|(k, _)| k.dup()).collect(),
+        None => vec![],
+    };
+    ary.set_vec(keys);
+    ary
+}
+
+#[shiika_method("HashMap#_get_at")]
+#[allow(non_snake_case)]
+pub extern "C" fn hash_map__get_at(receiver: SkHashMap, h: SkInt, i: SkInt) -> SkObj {
+    receiver
+        .map()
+        .get(&h.val())
+        .and_then(|bucket| bucket.get(i.val() as usize))
+        .unwrap_or_else(|| panic!("HashMap#_get_at: key not found"))
+        .1
+        .dup()
+}
+
+#[shiika_method("HashMap#_set_at")]
+#[allow(non_snake_case)]
+pub extern "C" fn hash_map__set_at(
+    receiver: SkHashMap,
+    h: SkInt,
+    i: SkInt,
+    key: SkObj,
+    value: SkObj,
+) {
+    let bucket = receiver.map_mut().entry(h.val()).or_default();
+    if i.val() == -1 {
+        bucket.push((key, value));
+    } else {
+        bucket[i.val() as usize] = (key, value);
+    }
+}
+
+#[shiika_method("HashMap#_delete_at")]
+#[allow(non_snake_case)]
+pub extern "C" fn hash_map__delete_at(receiver: SkHashMap, h: SkInt, i: SkInt) {
+    let map = receiver.map_mut();
+    if let Some(bucket) = map.get_mut(&h.val()) {
+        bucket.remove(i.val() as usize);
+        if bucket.is_empty() {
+            map.remove(&h.val());
+        }
+    }
+}
+
+#[shiika_method("HashMap#deep_copy")]
+pub extern "C" fn hash_map_deep_copy(receiver: SkHashMap) -> SkHashMap {
+    receiver.copy_with(|obj| obj.deep_copy())
+}
+
+#[shiika_method("HashMap#keys")]
+pub extern "C" fn hash_map_keys(receiver: SkHashMap) -> SkAry<SkObj> {
+    let ary = SkAry::<SkObj>::new();
+    ary.set_vec(receiver.entries().map(|(k, _)| k.dup()).collect());
+    ary
+}
+
+#[shiika_method("HashMap#values")]
+pub extern "C" fn hash_map_values(receiver: SkHashMap) -> SkAry<SkObj> {
+    let ary = SkAry::<SkObj>::new();
+    ary.set_vec(receiver.entries().map(|(_, v)| v.dup()).collect());
+    ary
+}
+
+#[shiika_method("HashMap#shallow_copy")]
+pub extern "C" fn hash_map_shallow_copy(receiver: SkHashMap) -> SkHashMap {
+    receiver.copy_with(|obj| obj.dup())
+}
+
+#[shiika_method("HashMap#size")]
+pub extern "C" fn hash_map_size(receiver: SkHashMap) -> SkInt {
+    receiver.entries().count().into()
+}
@@ -1,11 +1,16 @@
 //! Instance of `::Int`
 //! May represent big number in the future
-use crate::builtin::{SkBool, SkFloat};
+use crate::builtin::{SkBool, SkFloat, SkStr};
+use crate::stacktrace::shiika_print_stacktrace;
 use shiika_ffi_macro::shiika_method;
+use std::convert::TryFrom;
 use std::fmt;
 
 extern "C" {
     fn box_int(i: i64) -> SkInt;
+    /// Defined in the generated program. Non-zero unless the program is
+    /// compiled with optimization (`--opt`)
+    static shiika_overflow_checks: u8;
 }
 
 #[repr(C)]
@@ -79,28 +84,63 @@ impl SkInt {
     }
 }
 
+/// Panic with the Shiika-level stack trace
+fn int_panic(msg: String) -> ! {
+    shiika_print_stacktrace();
+    panic!("{}", msg);
+}
+
+/// Returns the result of a checked operation. On overflow, panics if the
+/// program is built without optimization and returns the wrapped value
+/// otherwise.
+fn check_overflow<F>(result: Option<i64>, wrapped: i64, expr: F) -> SkInt
+where
+    F: FnOnce() -> String,
+{
+    match result {
+        Some(n) => n.into(),
+        None if unsafe { shiika_overflow_checks != 0 } => {
+            int_panic(format!("Int: overflow ({})", expr()))
+        }
+        None => wrapped.into(),
+    }
+}
+
 #[shiika_method("Int#-@")]
 pub extern "C" fn int_inv(receiver: SkInt) -> SkInt {
-    (-receiver.val()).into()
+    let a = receiver.val();
+    check_overflow(a.checked_neg(), a.wrapping_neg(), || format!("-({})", a))
 }
 
 #[shiika_method("Int#+")]
 pub extern "C" fn int_add(receiver: SkInt, other: SkInt) -> SkInt {
-    (receiver.val() + other.val()).into()
+    let (a, b) = (receiver.val(), other.val());
+    check_overflow(a.checked_add(b), a.wrapping_add(b), || {
+        format!("{} + {}", a, b)
+    })
 }
 
 #[shiika_method("Int#-")]
 pub extern "C" fn int_sub(receiver: SkInt, other: SkInt) -> SkInt {
-    (receiver.val() - other.val()).into()
+    let (a, b) = (receiver.val(), other.val());
+    check_overflow(a.checked_sub(b), a.wrapping_sub(b), || {
+        format!("{} - {}", a, b)
+    })
 }
 
 #[shiika_method("Int#*")]
 pub extern "C" fn int_mul(receiver: SkInt, other: SkInt) -> SkInt {
-    (receiver.val() * other.val()).into()
+    let (a, b) = (receiver.val(), other.val());
+    check_overflow(a.checked_mul(b), a.wrapping_mul(b), || {
+        format!("{} * {}", a, b)
+    })
 }
 
 #[shiika_method("Int#/")]
 pub extern "C" fn int_div(receiver: SkInt, other: SkInt) -> SkFloat {
+    if other.val() == 0 {
+        int_panic(format!("Int#/: division by zero ({} / 0)", receiver.val()));
+    }
     let a = receiver.val() as f64;
     let b = other.val() as f64;
     (a / b).into()
@@ -108,7 +148,36 @@ pub extern "C" fn int_div(receiver: SkInt, other: SkInt) -> SkFloat {
 
 #[shiika_method("Int#%")]
 pub extern "C" fn int_mod(receiver: SkInt, other: SkInt) -> SkInt {
-    (receiver.val() % other.val()).into()
+    let (a, b) = (receiver.val(), other.val());
+    if b == 0 {
+        int_panic(format!("Int#%: division by zero ({} % 0)", a));
+    }
+    a.wrapping_rem(b).into()
+}
+
+/// Integer exponentiation. Returns 0 for negative exponents (i.e. the
+/// fraction is truncated) unless the base is 1 or -1.
+#[shiika_method("Int#**")]
+pub extern "C" fn int_pow(receiver: SkInt, other: SkInt) -> SkInt {
+    let (a, b) = (receiver.val(), other.val());
+    if b < 0 {
+        let n = match a {
+            1 => 1,
+            -1 if b % 2 == 0 => 1,
+            -1 => -1,
+            _ => 0,
+        };
+        return n.into();
+    }
+    let result = u32::try_from(b).ok().and_then(|e| a.checked_pow(e));
+    let wrapped = a.wrapping_pow(b as u32);
+    check_overflow(result, wrapped, || format!("{} ** {}", a, b))
+}
+
+#[shiika_method("Int#abs")]
+pub extern "C" fn int_abs(receiver: SkInt) -> SkInt {
+    let a = receiver.val();
+    check_overflow(a.checked_abs(), a.wrapping_abs(), || format!("({}).abs", a))
 }
 
 #[shiika_method("Int#and")]
@@ -136,6 +205,54 @@ pub extern "C" fn int_rshift(receiver: SkInt, other: SkInt) -> SkInt {
     (receiver.val() >> other.val()).into()
 }
 
+#[shiika_method("Int#&")]
+pub extern "C" fn int_bit_and(receiver: SkInt, other: SkInt) -> SkInt {
+    (receiver.val() & other.val()).into()
+}
+
+#[shiika_method("Int#|")]
+pub extern "C" fn int_bit_or(receiver: SkInt, other: SkInt) -> SkInt {
+    (receiver.val() | other.val()).into()
+}
+
+#[shiika_method("Int#^")]
+pub extern "C" fn int_bit_xor(receiver: SkInt, other: SkInt) -> SkInt {
+    (receiver.val() ^ other.val()).into()
+}
+
+#[shiika_method("Int#~")]
+pub extern "C" fn int_bit_not(receiver: SkInt) -> SkInt {
+    (!receiver.val()).into()
+}
+
+#[shiika_method("Int#<<")]
+pub extern "C" fn int_shl(receiver: SkInt, n: SkInt) -> SkInt {
+    let a = receiver.val();
+    let n = shift_amount("<<", a, n.val());
+    // Shifting by 64 or more bits results in 0
+    a.checked_shl(n).unwrap_or(0).into()
+}
+
+#[shiika_method("Int#>>")]
+pub extern "C" fn int_shr(receiver: SkInt, n: SkInt) -> SkInt {
+    let a = receiver.val();
+    let n = shift_amount(">>", a, n.val());
+    // Sign-extending (eg. `-1 >> 100` is -1)
+    let filled = if a < 0 { -1 } else { 0 };
+    a.checked_shr(n).unwrap_or(filled).into()
+}
+
+/// Returns `n` as the number of bits to shift. Panics if it is negative
+fn shift_amount(op: &str, a: i64, n: i64) -> u32 {
+    if n < 0 {
+        int_panic(format!(
+            "Int#{}: negative shift amount ({} {} {})",
+            op, a, op, n
+        ));
+    }
+    u32::try_from(n).unwrap_or(u32::MAX)
+}
+
 #[shiika_method("Int#<")]
 pub extern "C" fn int_lt(receiver: SkInt, other: SkInt) -> SkBool {
     (receiver.val() < other.val()).into()
@@ -165,3 +282,8 @@ pub extern "C" fn int_eq(receiver: SkInt, other: SkInt) -> SkBool {
 pub extern "C" fn int_to_f(receiver: SkInt) -> SkFloat {
     (receiver.val() as f64).into()
 }
+
+#[shiika_method("Int#to_s")]
+pub extern "C" fn int_to_s(receiver: SkInt) -> SkStr {
+    receiver.val().to_string().into()
+}
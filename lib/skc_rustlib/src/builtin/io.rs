@@ -0,0 +1,53 @@
+//! Class methods of `::IO`
+//!
+//! `std::io::Stdin` has its own buffer guarded by a mutex, so the lines
+//! are read through `Stdin::lock` (reading from multiple threads is safe.)
+use crate::builtin::{SkAry, SkObj, SkStr};
+use shiika_ffi_macro::shiika_method;
+use std::io::{stdin, stdout, BufRead, Read, Write};
+
+/// Make a Shiika `String`. Panics if `s` contains a null byte
+fn to_sk_str(s: String, method_name: &str) -> SkStr {
+    if s.contains('\0') {
+        panic!("{}: the input has a null byte", method_name);
+    }
+    s.into()
+}
+
+#[shiika_method("Meta:IO#print")]
+pub extern "C" fn io_print(_receiver: *const u8, obj: SkObj) {
+    let _ = stdout().write_all(obj.to_s().as_byteslice());
+    let _ = stdout().flush();
+}
+
+#[shiika_method("Meta:IO#puts")]
+pub extern "C" fn io_puts(_receiver: *const u8, obj: SkObj) {
+    let _ = stdout().write_all(obj.to_s().as_byteslice());
+    println!();
+}
+
+#[shiika_method("Meta:IO#read_all")]
+pub extern "C" fn io_read_all(_receiver: *const u8) -> SkStr {
+    let mut buf = String::new();
+    if let Err(e) = stdin().lock().read_to_string(&mut buf) {
+        panic!("IO.read_all: {}", e);
+    }
+    to_sk_str(buf, "IO.read_all")
+}
+
+#[shiika_method("Meta:IO#_read_line")]
+#[allow(non_snake_case)]
+pub extern "C" fn io__read_line(_receiver: *const u8) -> SkAry<SkStr> {
+    let ary = SkAry::<SkStr>::new();
+    let mut buf = String::new();
+    match stdin().lock().read_line(&mut buf) {
+        Ok(0) => (),
+        Ok(_) => {
+            let line = buf.strip_suffix('\n').unwrap_or(&buf);
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            ary.set_vec(vec![to_sk_str(line.to_string(), "IO.read_line")]);
+        }
+        Err(e) => panic!("IO.read_line: {}", e),
+    }
+    ary
+}
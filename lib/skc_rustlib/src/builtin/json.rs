@@ -0,0 +1,137 @@
+//! Instance of `::JSON`
+//!
+//! Like `Regex`, the `serde_json::Value` is allocated separately and the
+//! Shiika object holds the pointer to it.
+use crate::builtin::{SkAry, SkBool, SkFloat, SkInt, SkStr};
+use serde_json::Value;
+use shiika_ffi_macro::shiika_method;
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct SkJSON(*mut ShiikaJSON);
+
+#[repr(C)]
+#[derive(Debug)]
+struct ShiikaJSON {
+    vtable: *const u8,
+    class_obj: *const u8,
+    // Allocated with the global allocator (i.e. `GC_malloc`)
+    state: *mut JSONState,
+}
+
+#[derive(Debug, Default)]
+struct JSONState {
+    /// `null` if not parsed yet
+    value: Value,
+    /// Message of the parse error
+    error: String,
+}
+
+impl SkJSON {
+    fn state(&self) -> &mut JSONState {
+        unsafe {
+            if (*self.0).state.is_null() {
+                (*self.0).state = Box::leak(Box::default());
+            }
+            (*self.0).state.as_mut().unwrap()
+        }
+    }
+
+    fn value(&self) -> &Value {
+        &self.state().value
+    }
+
+    fn set_value(&self, value: Value) {
+        self.state().value = value;
+    }
+}
+
+/// Returns `[value]` or `[]`
+fn opt_to_ary<T>(v: Option<T>) -> SkAry<T> {
+    let ary = SkAry::<T>::new();
+    ary.set_vec(v.into_iter().collect());
+    ary
+}
+
+#[shiika_method("Meta:JSON#quote")]
+pub extern "C" fn json_quote(_receiver: *const u8, s: SkStr) -> SkStr {
+    Value::from(s.as_str()).to_string().into()
+}
+
+#[shiika_method("JSON#to_json")]
+pub extern "C" fn json_to_json(receiver: SkJSON) -> SkStr {
+    receiver.value().to_string().into()
+}
+
+#[shiika_method("JSON#_at")]
+#[allow(non_snake_case)]
+pub extern "C" fn json__at(receiver: SkJSON, i: SkInt, dest: SkJSON) {
+    let value = receiver.value()[i.val() as usize].clone();
+    dest.set_value(value);
+}
+
+#[shiika_method("JSON#_error")]
+#[allow(non_snake_case)]
+pub extern "C" fn json__error(receiver: SkJSON) -> SkStr {
+    receiver.state().error.clone().into()
+}
+
+#[shiika_method("JSON#_get")]
+#[allow(non_snake_case)]
+pub extern "C" fn json__get(receiver: SkJSON, key: SkStr, dest: SkJSON) -> SkBool {
+    match receiver.value().get(key.as_str()) {
+        Some(v) => {
+            dest.set_value(v.clone());
+            true.into()
+        }
+        None => false.into(),
+    }
+}
+
+#[shiika_method("JSON#_length")]
+#[allow(non_snake_case)]
+pub extern "C" fn json__length(receiver: SkJSON) -> SkInt {
+    match receiver.value().as_array() {
+        Some(a) => (a.len() as i64).into(),
+        None => (-1).into(),
+    }
+}
+
+#[shiika_method("JSON#_parse")]
+#[allow(non_snake_case)]
+pub extern "C" fn json__parse(receiver: SkJSON, s: SkStr) -> SkBool {
+    match serde_json::from_str(s.as_str()) {
+        Ok(v) => {
+            receiver.set_value(v);
+            true.into()
+        }
+        Err(e) => {
+            receiver.state().error = e.to_string();
+            false.into()
+        }
+    }
+}
+
+#[shiika_method("JSON#_to_bool")]
+#[allow(non_snake_case)]
+pub extern "C" fn json__to_bool(receiver: SkJSON) -> SkAry<SkBool> {
+    opt_to_ary(receiver.value().as_bool().map(|b| b.into()))
+}
+
+#[shiika_method("JSON#_to_float")]
+#[allow(non_snake_case)]
+pub extern "C" fn json__to_float(receiver: SkJSON) -> SkAry<SkFloat> {
+    opt_to_ary(receiver.value().as_f64().map(|f| f.into()))
+}
+
+#[shiika_method("JSON#_to_int")]
+#[allow(non_snake_case)]
+pub extern "C" fn json__to_int(receiver: SkJSON) -> SkAry<SkInt> {
+    opt_to_ary(receiver.value().as_i64().map(|i| i.into()))
+}
+
+#[shiika_method("JSON#_to_str")]
+#[allow(non_snake_case)]
+pub extern "C" fn json__to_str(receiver: SkJSON) -> SkAry<SkStr> {
+    opt_to_ary(receiver.value().as_str().map(|s| s.to_string().into()))
+}
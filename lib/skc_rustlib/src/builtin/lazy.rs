@@ -0,0 +1,95 @@
+//! Instance of `::Lazy`
+//!
+//! Backed by `std::sync::Once`. The lambda is called while holding `lock`
+//! (not the lock of bdwgc) so that the other threads calling `#get` at the
+//! same time wait for the value without blocking GC.
+use crate::builtin::{SkFn0, SkObj};
+use shiika_ffi_macro::shiika_method;
+use std::cell::UnsafeCell;
+use std::os::raw::c_void;
+use std::sync::{Mutex, MutexGuard, Once, PoisonError};
+
+extern "C" {
+    fn GC_do_blocking(
+        f: extern "C" fn(*mut c_void) -> *mut c_void,
+        client_data: *mut c_void,
+    ) -> *mut c_void;
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct SkLazy(*mut ShiikaLazy);
+
+#[repr(C)]
+#[derive(Debug)]
+struct ShiikaLazy {
+    vtable: *const u8,
+    class_obj: *const u8,
+    // Allocated with the global allocator (i.e. `GC_malloc`)
+    cell: *mut LazyCell,
+}
+
+struct LazyCell {
+    f: SkFn0<SkObj>,
+    once: Once,
+    lock: Mutex<()>,
+    // Written only in `once.call_once`
+    value: UnsafeCell<Option<SkObj>>,
+}
+
+impl SkLazy {
+    fn cell(&self) -> &LazyCell {
+        unsafe { (*self.0).cell.as_ref().unwrap() }
+    }
+}
+
+/// Passed to `acquire` via `GC_do_blocking`
+struct LockRequest<'a> {
+    lock: &'a Mutex<()>,
+    guard: Option<MutexGuard<'a, ()>>,
+}
+
+/// Wait for the lock. This runs in the "inactive" state of bdwgc (i.e. GC
+/// may run in other threads meanwhile) so must not touch Shiika objects.
+extern "C" fn acquire(data: *mut c_void) -> *mut c_void {
+    let req = unsafe { &mut *(data as *mut LockRequest) };
+    let guard = req.lock.lock().unwrap_or_else(PoisonError::into_inner);
+    req.guard = Some(guard);
+    std::ptr::null_mut()
+}
+
+/// Called from `Lazy.new` and initializes internal fields.
+#[shiika_method("Lazy#_initialize_rustlib")]
+#[allow(non_snake_case)]
+pub extern "C" fn lazy__initialize_rustlib(receiver: SkLazy, f: SkFn0<SkObj>) {
+    let cell = LazyCell {
+        f,
+        once: Once::new(),
+        lock: Mutex::new(()),
+        value: UnsafeCell::new(None),
+    };
+    unsafe {
+        (*receiver.0).cell = Box::leak(Box::new(cell));
+    }
+}
+
+#[shiika_method("Lazy#get")]
+pub extern "C" fn lazy_get(receiver: SkLazy) -> SkObj {
+    let cell = receiver.cell();
+    if !cell.once.is_completed() {
+        let mut req = LockRequest {
+            lock: &cell.lock,
+            guard: None,
+        };
+        unsafe {
+            GC_do_blocking(acquire, &mut req as *mut LockRequest as *mut c_void);
+        }
+        let _guard = req.guard.expect("[BUG] lock not acquired");
+        cell.once.call_once(|| {
+            let value = cell.f.call();
+            unsafe { *cell.value.get() = Some(value) };
+        });
+    }
+    let value = unsafe { &*cell.value.get() };
+    value.as_ref().expect("[BUG] Lazy not evaluated").dup()
+}
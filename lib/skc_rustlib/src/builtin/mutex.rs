@@ -0,0 +1,82 @@
+//! Instance of `::Mutex`
+//!
+//! Backed by `std::sync::Mutex`. The protected value is passed to the lambda
+//! given to `#lock` so that it is accessed only while the lock is held.
+use crate::builtin::{SkBool, SkFn1, SkObj};
+use shiika_ffi_macro::shiika_method;
+use std::os::raw::c_void;
+use std::sync::{Mutex, MutexGuard, PoisonError, TryLockError};
+
+extern "C" {
+    fn GC_do_blocking(
+        f: extern "C" fn(*mut c_void) -> *mut c_void,
+        client_data: *mut c_void,
+    ) -> *mut c_void;
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct SkMutex(*mut ShiikaMutex);
+
+#[repr(C)]
+#[derive(Debug)]
+struct ShiikaMutex {
+    vtable: *const u8,
+    class_obj: *const u8,
+    // Allocated with the global allocator (i.e. `GC_malloc`)
+    mutex: *mut Mutex<SkObj>,
+}
+
+impl SkMutex {
+    fn mutex(&self) -> &Mutex<SkObj> {
+        unsafe { (*self.0).mutex.as_ref().unwrap() }
+    }
+}
+
+/// Passed to `acquire` via `GC_do_blocking`
+struct LockRequest<'a> {
+    mutex: &'a Mutex<SkObj>,
+    guard: Option<MutexGuard<'a, SkObj>>,
+}
+
+/// Wait for the lock. This runs in the "inactive" state of bdwgc (i.e. GC
+/// may run in other threads meanwhile) so must not touch Shiika objects.
+extern "C" fn acquire(data: *mut c_void) -> *mut c_void {
+    let req = unsafe { &mut *(data as *mut LockRequest) };
+    let guard = req.mutex.lock().unwrap_or_else(PoisonError::into_inner);
+    req.guard = Some(guard);
+    std::ptr::null_mut()
+}
+
+/// Called from `Mutex.new` and initializes internal fields.
+#[shiika_method("Mutex#_initialize_rustlib")]
+#[allow(non_snake_case)]
+pub extern "C" fn mutex__initialize_rustlib(receiver: SkMutex, value: SkObj) {
+    unsafe {
+        (*receiver.0).mutex = Box::leak(Box::new(Mutex::new(value)));
+    }
+}
+
+#[shiika_method("Mutex#lock")]
+pub extern "C" fn mutex_lock(receiver: SkMutex, f: SkFn1<SkObj, SkObj>) {
+    let mut req = LockRequest {
+        mutex: receiver.mutex(),
+        guard: None,
+    };
+    unsafe {
+        GC_do_blocking(acquire, &mut req as *mut LockRequest as *mut c_void);
+    }
+    let guard = req.guard.expect("[BUG] lock not acquired");
+    f.call(guard.dup());
+}
+
+#[shiika_method("Mutex#try_lock")]
+pub extern "C" fn mutex_try_lock(receiver: SkMutex, f: SkFn1<SkObj, SkObj>) -> SkBool {
+    let guard = match receiver.mutex().try_lock() {
+        Ok(guard) => guard,
+        Err(TryLockError::Poisoned(e)) => e.into_inner(),
+        Err(TryLockError::WouldBlock) => return false.into(),
+    };
+    f.call(guard.dup());
+    true.into()
+}
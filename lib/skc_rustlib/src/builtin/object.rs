@@ -1,9 +1,12 @@
-use crate::builtin::class::SkClass;
+use crate::allocator::shiika_malloc;
+use crate::builtin::class::{ShiikaClass, SkClass};
 use crate::builtin::{SkBool, SkInt, SkStr};
+use crate::stacktrace::shiika_print_stacktrace;
 use plain::Plain;
 use shiika_ffi_macro::shiika_method;
-use std::io::{stdout, Write};
-use std::mem;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::{mem, ptr, slice};
 
 #[repr(C)]
 #[derive(Debug)]
@@ -16,9 +19,14 @@ unsafe impl Plain for SkObj {}
 #[derive(Debug)]
 pub struct ShiikaObject {
     vtable: *const u8,
-    class_obj: SkClass,
+    /// The lowest bit is the frozen flag (class objects are aligned so
+    /// the bit is otherwise always 0)
+    class_obj: *mut ShiikaClass,
 }
 
+/// Must be the same as `OBJ_FROZEN_BIT` in skc_codegen
+pub const FROZEN_BIT: usize = 1;
+
 impl SkObj {
     //    pub fn new(p: *const ShiikaObject) -> SkObj {
     //        SkObj(p)
@@ -30,12 +38,109 @@ impl SkObj {
     }
 
     pub fn class(&self) -> SkClass {
-        unsafe { (*self.0).class_obj.dup() }
+        let addr = unsafe { (*self.0).class_obj } as usize;
+        SkClass::new((addr & !FROZEN_BIT) as *mut ShiikaClass)
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        let addr = unsafe { (*self.0).class_obj } as usize;
+        addr & FROZEN_BIT != 0
+    }
+
+    fn freeze(&self) {
+        unsafe {
+            let obj = self.0 as *mut ShiikaObject;
+            (*obj).class_obj = ((*obj).class_obj as usize | FROZEN_BIT) as *mut ShiikaClass;
+        }
     }
 
     pub fn same_object<T>(&self, other: *const T) -> bool {
         self.0 == (other as *const ShiikaObject)
     }
+
+    /// Call the `idx`th method in the vtable which takes no arguments
+    fn call_method0(&self, idx: usize) -> SkObj {
+        unsafe {
+            let vtable = (*self.0).vtable as *const *const u8;
+            let f = mem::transmute::<*const u8, extern "C" fn(*const ShiikaObject) -> SkObj>(
+                *vtable.add(idx),
+            );
+            f(self.0)
+        }
+    }
+
+    /// Returns a new object which has the same ivars as `self` (not frozen.)
+    /// Returns `self` as is if it has no ivars (eg. `Maybe::None`, which
+    /// is a singleton) or its fields are managed by skc_rustlib (eg. `Int`,
+    /// `File`, class objects.) `Array` and `HashMap` override the copy
+    /// methods.
+    fn copy(&self) -> SkObj {
+        let n_ivars = match self.class().n_ivars() {
+            Some(n) if n > 0 => n,
+            _ => return self.dup(),
+        };
+        let size = mem::size_of::<ShiikaObject>() + n_ivars * mem::size_of::<SkObj>();
+        unsafe {
+            let p = shiika_malloc(size) as *mut ShiikaObject;
+            ptr::copy_nonoverlapping(self.0 as *const u8, p as *mut u8, size);
+            (*p).class_obj = ((*p).class_obj as usize & !FROZEN_BIT) as *mut ShiikaClass;
+            SkObj(p)
+        }
+    }
+
+    /// Call `#deep_copy` of `self` via the vtable
+    pub fn deep_copy(&self) -> SkObj {
+        match self.class().method_index("deep_copy") {
+            Some(idx) => self.call_method0(idx),
+            // Class objects are not copied
+            None => self.dup(),
+        }
+    }
+
+    /// Call `#to_s` of `self` via the vtable
+    pub fn to_s(&self) -> SkStr {
+        self.call_str_method("to_s")
+    }
+
+    /// Call `#inspect` of `self` via the vtable
+    pub fn inspect(&self) -> SkStr {
+        self.call_str_method("inspect")
+    }
+
+    /// Call the method `name` which takes no arguments and returns a `String`
+    fn call_str_method(&self, name: &str) -> SkStr {
+        match self.class().method_index(name) {
+            // Safety: `SkStr` is also a pointer to a Shiika object
+            Some(idx) => unsafe { mem::transmute::<SkObj, SkStr>(self.call_method0(idx)) },
+            // Class objects (whose class is a metaclass) do not have the
+            // method table. Use the class name instead
+            None => {
+                let cls = SkClass::new(self.0 as *mut ShiikaClass);
+                cls.name().as_str().to_string().into()
+            }
+        }
+    }
+
+    /// Returns the ivars (`n_ivars` must be the one of the class)
+    fn ivars(&self, n_ivars: usize) -> &[SkObj] {
+        unsafe {
+            let first = self.0.add(1) as *const SkObj;
+            slice::from_raw_parts(first, n_ivars)
+        }
+    }
+
+    /// Returns the ivars (`n_ivars` must be the one of the class)
+    fn ivars_mut(&mut self, n_ivars: usize) -> &mut [SkObj] {
+        unsafe {
+            let first = (self.0 as *mut ShiikaObject).add(1) as *mut SkObj;
+            slice::from_raw_parts_mut(first, n_ivars)
+        }
+    }
+
+    /// Address of the object (same as `Object#object_id`)
+    pub fn id(&self) -> u64 {
+        self.0 as u64
+    }
 }
 
 #[shiika_method("Object#==")]
@@ -48,10 +153,49 @@ pub extern "C" fn object_class(receiver: SkObj) -> SkClass {
     receiver.class()
 }
 
-// TODO: Move to `Process.exit` or something
-#[shiika_method("Object#exit")]
-pub extern "C" fn object_exit(_receiver: SkObj, code: SkInt) {
-    std::process::exit(code.val() as i32);
+#[shiika_method("Object#deep_copy")]
+pub extern "C" fn object_deep_copy(receiver: SkObj) -> SkObj {
+    let n_ivars = match receiver.class().n_ivars() {
+        Some(n) if n > 0 => n,
+        _ => return receiver,
+    };
+    let mut copy = receiver.copy();
+    for ivar in copy.ivars_mut(n_ivars) {
+        // Uninitialized ivars are null
+        if !ivar.0.is_null() {
+            *ivar = ivar.deep_copy();
+        }
+    }
+    copy
+}
+
+#[shiika_method("Object#freeze")]
+pub extern "C" fn object_freeze(receiver: SkObj) {
+    receiver.freeze();
+}
+
+#[shiika_method("Object#frozen?")]
+pub extern "C" fn object_frozen_p(receiver: SkObj) -> SkBool {
+    receiver.is_frozen().into()
+}
+
+/// Returns the class name and the ivars like `#<Point @x=1, @y=2>`, or
+/// `#to_s` if the object has no ivars
+#[shiika_method("Object#inspect")]
+pub extern "C" fn object_inspect(receiver: SkObj) -> SkStr {
+    let cls = receiver.class();
+    let names = match cls.ivar_names() {
+        Some(names) if !names.is_empty() => names,
+        _ => return receiver.to_s(),
+    };
+    let fields = names
+        .iter()
+        .zip(receiver.ivars(names.len()))
+        // Uninitialized ivars are null
+        .filter(|(_, ivar)| !ivar.0.is_null())
+        .map(|(name, ivar)| format!("{}={}", name, ivar.inspect().as_str()))
+        .collect::<Vec<_>>();
+    format!("#<{} {}>", cls.name().as_str(), fields.join(", ")).into()
 }
 
 #[shiika_method("Object#object_id")]
@@ -62,21 +206,46 @@ pub extern "C" fn object_object_id(receiver: SkObj) -> SkInt {
     }
 }
 
+#[shiika_method("Object#shallow_copy")]
+pub extern "C" fn object_shallow_copy(receiver: SkObj) -> SkObj {
+    receiver.copy()
+}
+
+/// Returns `#<ClassName:0x...>`
+#[shiika_method("Object#to_s")]
+pub extern "C" fn object_to_s(receiver: SkObj) -> SkStr {
+    format!(
+        "#<{}:{:#x}>",
+        receiver.class().name().as_str(),
+        receiver.id()
+    )
+    .into()
+}
+
 #[shiika_method("Object#panic")]
 pub extern "C" fn object_panic(_receiver: *const u8, s: SkStr) {
+    shiika_print_stacktrace();
     panic!("{}", s.as_str());
 }
 
-#[shiika_method("Object#print")]
-pub extern "C" fn object_print(_receiver: *const u8, s: SkStr) {
-    //TODO: Return SkVoid
-    let _ = stdout().write_all(s.as_byteslice());
-    let _ = stdout().flush();
+#[shiika_method("Object#respond_to?")]
+pub extern "C" fn object_respond_to_p(receiver: SkObj, method_name: SkStr) -> SkBool {
+    receiver
+        .class()
+        .method_index(method_name.as_str())
+        .is_some()
+        .into()
 }
 
-#[shiika_method("Object#puts")]
-pub extern "C" fn object_puts(_receiver: *const u8, s: SkStr) {
-    //TODO: Return SkVoid
-    let _ = stdout().write_all(s.as_byteslice());
-    println!("");
+/// Called when an ivar of a frozen object is about to be modified
+#[no_mangle]
+pub extern "C" fn shiika_frozen_error(receiver: SkObj, ivar_name: *const c_char) {
+    // Safety: `ivar_name` is a llvm global string constant
+    let name = unsafe { CStr::from_ptr(ivar_name) }.to_string_lossy();
+    shiika_print_stacktrace();
+    panic!(
+        "can't modify frozen {}: {}",
+        receiver.class().name().as_str(),
+        name
+    );
 }
@@ -0,0 +1,50 @@
+//! Class methods of `::Process`
+use crate::builtin::{SkAry, SkInt, SkStr};
+use shiika_ffi_macro::shiika_method;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::sync::atomic::{AtomicI32, AtomicPtr, Ordering};
+
+/// `argc` and `argv` given to `main`
+static ARGC: AtomicI32 = AtomicI32::new(0);
+static ARGV: AtomicPtr<*const c_char> = AtomicPtr::new(std::ptr::null_mut());
+
+/// Called from `main` to save the command line arguments
+#[no_mangle]
+pub extern "C" fn shiika_init_args(argc: i32, argv: *mut *const c_char) {
+    ARGC.store(argc, Ordering::Relaxed);
+    ARGV.store(argv, Ordering::Relaxed);
+}
+
+#[shiika_method("Meta:Process#args")]
+pub extern "C" fn process_args(_receiver: *const u8) -> SkAry<SkStr> {
+    let argc = ARGC.load(Ordering::Relaxed);
+    let argv = ARGV.load(Ordering::Relaxed);
+    let args = if argv.is_null() {
+        vec![]
+    } else {
+        (0..argc as usize)
+            .map(|i| {
+                let s = unsafe { CStr::from_ptr(*argv.add(i)) };
+                s.to_string_lossy().into_owned().into()
+            })
+            .collect()
+    };
+    let ary = SkAry::<SkStr>::new();
+    ary.set_vec(args);
+    ary
+}
+
+#[shiika_method("Meta:Process#exit")]
+pub extern "C" fn process_exit(_receiver: *const u8, code: SkInt) {
+    std::process::exit(code.val() as i32);
+}
+
+#[shiika_method("Meta:Process#_env")]
+#[allow(non_snake_case)]
+pub extern "C" fn process__env(_receiver: *const u8, key: SkStr) -> SkAry<SkStr> {
+    let ary = SkAry::<SkStr>::new();
+    let value = std::env::var(key.as_str()).ok();
+    ary.set_vec(value.into_iter().map(|s| s.into()).collect());
+    ary
+}
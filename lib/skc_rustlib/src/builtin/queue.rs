@@ -0,0 +1,65 @@
+//! Instance of `::Queue`
+use crate::builtin::{SkAry, SkInt, SkObj};
+use shiika_ffi_macro::shiika_method;
+use std::collections::VecDeque;
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct SkQueue(*mut ShiikaQueue);
+
+#[repr(C)]
+#[derive(Debug)]
+struct ShiikaQueue {
+    vtable: *const u8,
+    class_obj: *const u8,
+    // Allocated with the global allocator (i.e. `GC_malloc`)
+    items: *mut VecDeque<SkObj>,
+}
+
+impl SkQueue {
+    fn items(&self) -> &mut VecDeque<SkObj> {
+        unsafe { (*self.0).items.as_mut().unwrap() }
+    }
+}
+
+/// Called from `Queue.new` and initializes internal fields.
+#[shiika_method("Queue#_initialize_rustlib")]
+#[allow(non_snake_case)]
+pub extern "C" fn queue__initialize_rustlib(receiver: SkQueue) {
+    unsafe {
+        (*receiver.0).items = Box::leak(Box::new(VecDeque::new()));
+    }
+}
+
+#[shiika_method("Queue#_dequeue")]
+#[allow(non_snake_case)]
+pub extern "C" fn queue__dequeue(receiver: SkQueue) -> SkAry<SkObj> {
+    let ary = SkAry::<SkObj>::new();
+    ary.set_vec(receiver.items().pop_front().into_iter().collect());
+    ary
+}
+
+#[shiika_method("Queue#_peek")]
+#[allow(non_snake_case)]
+pub extern "C" fn queue__peek(receiver: SkQueue) -> SkAry<SkObj> {
+    let ary = SkAry::<SkObj>::new();
+    ary.set_vec(
+        receiver
+            .items()
+            .front()
+            .map(SkObj::dup)
+            .into_iter()
+            .collect(),
+    );
+    ary
+}
+
+#[shiika_method("Queue#enqueue")]
+pub extern "C" fn queue_enqueue(receiver: SkQueue, v: SkObj) {
+    receiver.items().push_back(v);
+}
+
+#[shiika_method("Queue#size")]
+pub extern "C" fn queue_size(receiver: SkQueue) -> SkInt {
+    receiver.items().len().into()
+}
@@ -0,0 +1,44 @@
+//! Instance of `::Range`
+use crate::builtin::{SkBool, SkInt};
+use shiika_ffi_macro::shiika_method;
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct SkRange<T>(*mut ShiikaRange<T>);
+
+#[repr(C)]
+struct ShiikaRange<T> {
+    vtable: *const u8,
+    class_obj: *const u8,
+    start: T,
+    end: T,
+    inclusive: SkBool,
+}
+
+impl<T> SkRange<T> {
+    pub fn start(&self) -> &T {
+        unsafe { &(*self.0).start }
+    }
+
+    pub fn end(&self) -> &T {
+        unsafe { &(*self.0).end }
+    }
+
+    pub fn inclusive(&self) -> bool {
+        unsafe { (*self.0).inclusive.val() }
+    }
+}
+
+#[shiika_method("Range#size")]
+pub extern "C" fn range_size(receiver: SkRange<SkInt>) -> SkInt {
+    // Computed in i128 because `end - start` may overflow i64
+    let start = receiver.start().val() as i128;
+    let mut end = receiver.end().val() as i128;
+    if receiver.inclusive() {
+        end += 1;
+    }
+    let size = (end - start).max(0);
+    i64::try_from(size)
+        .unwrap_or_else(|_| panic!("Range#size: too many elements ({})", size))
+        .into()
+}
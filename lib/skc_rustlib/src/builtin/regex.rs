@@ -0,0 +1,123 @@
+//! Instance of `::Regex`
+//!
+//! Like `File`, the compiled `regex::Regex` is allocated separately and the
+//! Shiika object holds the pointer to it.
+use crate::builtin::{SkAry, SkBool, SkInt, SkStr};
+use shiika_ffi_macro::shiika_method;
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct SkRegex(*mut ShiikaRegex);
+
+#[repr(C)]
+#[derive(Debug)]
+struct ShiikaRegex {
+    vtable: *const u8,
+    class_obj: *const u8,
+    // Allocated with the global allocator (i.e. `GC_malloc`)
+    state: *mut RegexState,
+}
+
+#[derive(Debug, Default)]
+struct RegexState {
+    /// None if not compiled yet
+    regex: Option<regex::Regex>,
+    /// Message of the compile error
+    error: String,
+}
+
+impl SkRegex {
+    fn state(&self) -> &mut RegexState {
+        unsafe {
+            if (*self.0).state.is_null() {
+                (*self.0).state = Box::leak(Box::default());
+            }
+            (*self.0).state.as_mut().unwrap()
+        }
+    }
+
+    fn regex(&self) -> &regex::Regex {
+        self.state()
+            .regex
+            .as_ref()
+            .expect("[Regex is not compiled]")
+    }
+}
+
+/// Append the byte offsets of the groups of `caps` to `v`
+fn push_offsets(v: &mut Vec<SkInt>, caps: &regex::Captures) {
+    for group in caps.iter() {
+        let (start, end) = match group {
+            Some(m) => (m.start() as i64, m.end() as i64),
+            None => (-1, -1),
+        };
+        v.push(start.into());
+        v.push(end.into());
+    }
+}
+
+#[shiika_method("Regex#replace")]
+pub extern "C" fn regex_replace(receiver: SkRegex, s: SkStr, replacement: SkStr) -> SkStr {
+    let replaced = receiver.regex().replace(s.as_str(), replacement.as_str());
+    replaced.into_owned().into()
+}
+
+#[shiika_method("Regex#replace_all")]
+pub extern "C" fn regex_replace_all(receiver: SkRegex, s: SkStr, replacement: SkStr) -> SkStr {
+    let replaced = receiver
+        .regex()
+        .replace_all(s.as_str(), replacement.as_str());
+    replaced.into_owned().into()
+}
+
+#[shiika_method("Regex#_captures_len")]
+#[allow(non_snake_case)]
+pub extern "C" fn regex__captures_len(receiver: SkRegex) -> SkInt {
+    (receiver.regex().captures_len() as i64).into()
+}
+
+#[shiika_method("Regex#_compile")]
+#[allow(non_snake_case)]
+pub extern "C" fn regex__compile(receiver: SkRegex, pattern: SkStr) -> SkBool {
+    let state = receiver.state();
+    match regex::Regex::new(pattern.as_str()) {
+        Ok(r) => {
+            state.regex = Some(r);
+            true.into()
+        }
+        Err(e) => {
+            state.error = e.to_string();
+            false.into()
+        }
+    }
+}
+
+#[shiika_method("Regex#_error")]
+#[allow(non_snake_case)]
+pub extern "C" fn regex__error(receiver: SkRegex) -> SkStr {
+    receiver.state().error.clone().into()
+}
+
+#[shiika_method("Regex#_find")]
+#[allow(non_snake_case)]
+pub extern "C" fn regex__find(receiver: SkRegex, s: SkStr) -> SkAry<SkInt> {
+    let mut v = vec![];
+    if let Some(caps) = receiver.regex().captures(s.as_str()) {
+        push_offsets(&mut v, &caps);
+    }
+    let ary = SkAry::<SkInt>::new();
+    ary.set_vec(v);
+    ary
+}
+
+#[shiika_method("Regex#_find_all")]
+#[allow(non_snake_case)]
+pub extern "C" fn regex__find_all(receiver: SkRegex, s: SkStr) -> SkAry<SkInt> {
+    let mut v = vec![];
+    for caps in receiver.regex().captures_iter(s.as_str()) {
+        push_offsets(&mut v, &caps);
+    }
+    let ary = SkAry::<SkInt>::new();
+    ary.set_vec(v);
+    ary
+}
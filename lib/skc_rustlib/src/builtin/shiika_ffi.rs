@@ -0,0 +1,144 @@
+//! Class methods of `::Shiika::FFI`
+//!
+//! Argument types are checked in `FFI.call` (in Shiika) so values are just
+//! reinterpreted here.
+use crate::builtin::object::SkObj;
+use crate::builtin::shiika_internal_ptr::SkPtr;
+use crate::builtin::{SkAry, SkFloat, SkInt, SkStr};
+use libffi::middle::{Arg, Cif, CodePtr, Type};
+use shiika_ffi_macro::shiika_method;
+use std::ffi::CString;
+use std::mem;
+use std::os::raw::{c_char, c_void};
+
+extern "C" {
+    fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+}
+
+/// Search the symbol in all the loaded libraries
+#[cfg(target_os = "macos")]
+const RTLD_DEFAULT: *mut c_void = -2isize as *mut c_void;
+#[cfg(not(target_os = "macos"))]
+const RTLD_DEFAULT: *mut c_void = std::ptr::null_mut();
+
+/// Types which can be passed to C functions (must match
+/// `Shiika::FFI._type_code`)
+#[derive(Debug, Clone, Copy)]
+enum FFIType {
+    Int,
+    Float,
+    Ptr,
+    Void,
+}
+
+impl FFIType {
+    fn from_code(code: i64) -> FFIType {
+        match code {
+            0 => FFIType::Int,
+            1 => FFIType::Float,
+            2 => FFIType::Ptr,
+            3 => FFIType::Void,
+            n => panic!("[BUG] unknown ffi type code: {}", n),
+        }
+    }
+
+    fn libffi_type(&self) -> Type {
+        match self {
+            FFIType::Int => Type::i64(),
+            FFIType::Float => Type::f64(),
+            FFIType::Ptr => Type::pointer(),
+            FFIType::Void => Type::void(),
+        }
+    }
+}
+
+/// A value converted for C
+enum Value {
+    Int(i64),
+    Float(f64),
+    Ptr(*mut u8),
+}
+
+impl Value {
+    fn new(ty: FFIType, obj: SkObj) -> Value {
+        unsafe {
+            match ty {
+                FFIType::Int => Value::Int(mem::transmute::<SkObj, SkInt>(obj).val()),
+                FFIType::Float => Value::Float(mem::transmute::<SkObj, SkFloat>(obj).val()),
+                FFIType::Ptr => Value::Ptr(mem::transmute::<SkObj, SkPtr>(obj).unbox_mut()),
+                FFIType::Void => panic!("[BUG] void argument"),
+            }
+        }
+    }
+
+    fn as_arg(&self) -> Arg {
+        match self {
+            Value::Int(i) => Arg::new(i),
+            Value::Float(f) => Arg::new(f),
+            Value::Ptr(p) => Arg::new(p),
+        }
+    }
+}
+
+#[shiika_method("Meta:Shiika::FFI#_call")]
+#[allow(non_snake_case)]
+pub extern "C" fn shiika_ffi__call(
+    _receiver: *const u8,
+    ptr: SkPtr,
+    arg_types: SkAry<SkInt>,
+    ret_type: SkInt,
+    args: SkAry<SkObj>,
+) -> SkObj {
+    let types = arg_types
+        .as_vec()
+        .iter()
+        .map(|code| FFIType::from_code(code.val()))
+        .collect::<Vec<_>>();
+    let values = types
+        .iter()
+        .zip(args.as_vec())
+        .map(|(ty, obj)| Value::new(*ty, obj.dup()))
+        .collect::<Vec<_>>();
+    let ffi_args = values.iter().map(|v| v.as_arg()).collect::<Vec<_>>();
+    let ret_type = FFIType::from_code(ret_type.val());
+    let cif = Cif::new(
+        types.iter().map(|t| t.libffi_type()),
+        ret_type.libffi_type(),
+    );
+    let code = CodePtr(ptr.unbox_mut() as *mut c_void);
+    unsafe {
+        match ret_type {
+            FFIType::Int => {
+                let i = cif.call::<i64>(code, &ffi_args);
+                mem::transmute::<SkInt, SkObj>(i.into())
+            }
+            FFIType::Float => {
+                let f = cif.call::<f64>(code, &ffi_args);
+                mem::transmute::<SkFloat, SkObj>(f.into())
+            }
+            FFIType::Ptr => {
+                let p = cif.call::<*const u8>(code, &ffi_args);
+                mem::transmute::<SkPtr, SkObj>(SkPtr::new(p))
+            }
+            FFIType::Void => {
+                cif.call::<()>(code, &ffi_args);
+                // Replaced with `Void` by `FFI.call`
+                mem::transmute::<*const u8, SkObj>(std::ptr::null())
+            }
+        }
+    }
+}
+
+#[shiika_method("Meta:Shiika::FFI#_dlsym")]
+#[allow(non_snake_case)]
+pub extern "C" fn shiika_ffi__dlsym(_receiver: *const u8, name: SkStr) -> SkAry<SkPtr> {
+    let ary = SkAry::<SkPtr>::new();
+    let p = match CString::new(name.as_str()) {
+        Ok(s) => unsafe { dlsym(RTLD_DEFAULT, s.as_ptr()) },
+        Err(_) => std::ptr::null_mut(),
+    };
+    if !p.is_null() {
+        ary.set_vec(vec![p.into()]);
+    }
+    ary
+}
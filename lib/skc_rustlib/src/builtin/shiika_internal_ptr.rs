@@ -1,8 +1,8 @@
 //! Provides (unsafe) utilities for pointers.
 //!
 //! Should be removed once `Array`, etc. is re-implemented in skc_rustlib.
-use crate::builtin::object::ShiikaObject;
-use crate::builtin::{SkInt, SkStr};
+use crate::builtin::object::{ShiikaObject, SkObj};
+use crate::builtin::{SkFloat, SkInt, SkStr};
 use shiika_ffi_macro::shiika_method;
 use std::convert::TryInto;
 use std::os::raw::c_void;
@@ -53,6 +53,18 @@ pub extern "C" fn shiika_internal_ptr_add(receiver: SkPtr, n_bytes: SkInt) -> Sk
     unsafe { SkPtr::new(p.offset(n)) }
 }
 
+#[shiika_method("Shiika::Internal::Ptr#-")]
+pub extern "C" fn shiika_internal_ptr_sub(receiver: SkPtr, n_bytes: SkInt) -> SkPtr {
+    let p = receiver.unbox() as *const u8;
+    let n: isize = n_bytes.val().try_into().unwrap();
+    unsafe { SkPtr::new(p.offset(-n)) }
+}
+
+#[shiika_method("Meta:Shiika::Internal::Ptr#from_int")]
+pub extern "C" fn meta_shiika_internal_ptr_from_int(_receiver: SkObj, i: SkInt) -> SkPtr {
+    SkPtr::new(i.val() as *const u8)
+}
+
 #[shiika_method("Shiika::Internal::Ptr#inspect")]
 pub extern "C" fn shiika_internal_ptr_inspect(receiver: SkPtr) -> SkStr {
     let p = receiver.unbox();
@@ -75,6 +87,13 @@ pub extern "C" fn shiika_internal_ptr_store(receiver: SkPtr, object: *const Shii
     }
 }
 
+#[shiika_method("Shiika::Internal::Ptr#offset")]
+pub extern "C" fn shiika_internal_ptr_offset(receiver: SkPtr, n: SkInt, size: SkInt) -> SkPtr {
+    let p = receiver.unbox() as *const u8;
+    let n_bytes: isize = (n.val() * size.val()).try_into().unwrap();
+    unsafe { SkPtr::new(p.offset(n_bytes)) }
+}
+
 #[shiika_method("Shiika::Internal::Ptr#read")]
 pub extern "C" fn shiika_internal_ptr_read(receiver: SkPtr) -> SkInt {
     unsafe {
@@ -90,3 +109,28 @@ pub extern "C" fn shiika_internal_ptr_write(receiver: SkPtr, byte: SkInt) {
         *p = byte.val().try_into().unwrap();
     }
 }
+
+#[shiika_method("Shiika::Internal::Ptr#read_f64")]
+pub extern "C" fn shiika_internal_ptr_read_f64(receiver: SkPtr) -> SkFloat {
+    unsafe { std::ptr::read_unaligned(receiver.unbox() as *const f64).into() }
+}
+
+#[shiika_method("Shiika::Internal::Ptr#read_i64")]
+pub extern "C" fn shiika_internal_ptr_read_i64(receiver: SkPtr) -> SkInt {
+    unsafe { std::ptr::read_unaligned(receiver.unbox() as *const i64).into() }
+}
+
+#[shiika_method("Shiika::Internal::Ptr#to_int")]
+pub extern "C" fn shiika_internal_ptr_to_int(receiver: SkPtr) -> SkInt {
+    (receiver.unbox() as i64).into()
+}
+
+#[shiika_method("Shiika::Internal::Ptr#write_f64")]
+pub extern "C" fn shiika_internal_ptr_write_f64(receiver: SkPtr, value: SkFloat) {
+    unsafe { std::ptr::write_unaligned(receiver.unbox_mut() as *mut f64, value.val()) }
+}
+
+#[shiika_method("Shiika::Internal::Ptr#write_i64")]
+pub extern "C" fn shiika_internal_ptr_write_i64(receiver: SkPtr, value: SkInt) {
+    unsafe { std::ptr::write_unaligned(receiver.unbox_mut() as *mut i64, value.val()) }
+}
@@ -0,0 +1,93 @@
+//! Instance of `::SortedMap`
+//!
+//! The entries are stored in a `Vec` in ascending order of the keys.
+//! Because the keys are compared with `Comparable#<` in Shiika, the
+//! position of a key is searched in `SortedMap#_search` and this module
+//! only stores the entries.
+use crate::builtin::{SkAry, SkInt, SkObj};
+use shiika_ffi_macro::shiika_method;
+
+type Entries = Vec<(SkObj, SkObj)>;
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct SkSortedMap(*mut ShiikaSortedMap);
+
+#[repr(C)]
+#[derive(Debug)]
+struct ShiikaSortedMap {
+    vtable: *const u8,
+    class_obj: *const u8,
+    // Allocated with the global allocator (i.e. `GC_malloc`)
+    entries: *mut Entries,
+}
+
+impl SkSortedMap {
+    fn entries(&self) -> &Entries {
+        unsafe { (*self.0).entries.as_ref().unwrap() }
+    }
+
+    fn entries_mut(&self) -> &mut Entries {
+        unsafe { (*self.0).entries.as_mut().unwrap() }
+    }
+
+    fn entry(&self, i: SkInt) -> &mut (SkObj, SkObj) {
+        self.entries_mut()
+            .get_mut(i.val() as usize)
+            .unwrap_or_else(|| panic!("SortedMap: index out of range ({})", i.val()))
+    }
+}
+
+/// Called from `SortedMap.new` and initializes internal fields.
+#[shiika_method("SortedMap#_initialize_rustlib")]
+#[allow(non_snake_case)]
+pub extern "C" fn sorted_map__initialize_rustlib(receiver: SkSortedMap) {
+    unsafe {
+        (*receiver.0).entries = Box::leak(Box::new(vec![]));
+    }
+}
+
+#[shiika_method("SortedMap#_insert_at")]
+#[allow(non_snake_case)]
+pub extern "C" fn sorted_map__insert_at(receiver: SkSortedMap, i: SkInt, key: SkObj, value: SkObj) {
+    receiver
+        .entries_mut()
+        .insert(i.val() as usize, (key, value));
+}
+
+#[shiika_method("SortedMap#_key_at")]
+#[allow(non_snake_case)]
+pub extern "C" fn sorted_map__key_at(receiver: SkSortedMap, i: SkInt) -> SkObj {
+    receiver.entry(i).0.dup()
+}
+
+#[shiika_method("SortedMap#_set_value_at")]
+#[allow(non_snake_case)]
+pub extern "C" fn sorted_map__set_value_at(receiver: SkSortedMap, i: SkInt, value: SkObj) {
+    receiver.entry(i).1 = value;
+}
+
+#[shiika_method("SortedMap#_value_at")]
+#[allow(non_snake_case)]
+pub extern "C" fn sorted_map__value_at(receiver: SkSortedMap, i: SkInt) -> SkObj {
+    receiver.entry(i).1.dup()
+}
+
+#[shiika_method("SortedMap#keys")]
+pub extern "C" fn sorted_map_keys(receiver: SkSortedMap) -> SkAry<SkObj> {
+    let ary = SkAry::<SkObj>::new();
+    ary.set_vec(receiver.entries().iter().map(|(k, _)| k.dup()).collect());
+    ary
+}
+
+#[shiika_method("SortedMap#size")]
+pub extern "C" fn sorted_map_size(receiver: SkSortedMap) -> SkInt {
+    receiver.entries().len().into()
+}
+
+#[shiika_method("SortedMap#values")]
+pub extern "C" fn sorted_map_values(receiver: SkSortedMap) -> SkAry<SkObj> {
+    let ary = SkAry::<SkObj>::new();
+    ary.set_vec(receiver.entries().iter().map(|(_, v)| v.dup()).collect());
+    ary
+}
@@ -0,0 +1,64 @@
+//! Instance of `::Stack`
+use crate::builtin::{SkAry, SkInt, SkObj};
+use shiika_ffi_macro::shiika_method;
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct SkStack(*mut ShiikaStack);
+
+#[repr(C)]
+#[derive(Debug)]
+struct ShiikaStack {
+    vtable: *const u8,
+    class_obj: *const u8,
+    // Allocated with the global allocator (i.e. `GC_malloc`)
+    items: *mut Vec<SkObj>,
+}
+
+impl SkStack {
+    fn items(&self) -> &mut Vec<SkObj> {
+        unsafe { (*self.0).items.as_mut().unwrap() }
+    }
+}
+
+/// Called from `Stack.new` and initializes internal fields.
+#[shiika_method("Stack#_initialize_rustlib")]
+#[allow(non_snake_case)]
+pub extern "C" fn stack__initialize_rustlib(receiver: SkStack) {
+    unsafe {
+        (*receiver.0).items = Box::leak(Box::new(vec![]));
+    }
+}
+
+#[shiika_method("Stack#_peek")]
+#[allow(non_snake_case)]
+pub extern "C" fn stack__peek(receiver: SkStack) -> SkAry<SkObj> {
+    let ary = SkAry::<SkObj>::new();
+    ary.set_vec(
+        receiver
+            .items()
+            .last()
+            .map(SkObj::dup)
+            .into_iter()
+            .collect(),
+    );
+    ary
+}
+
+#[shiika_method("Stack#_pop")]
+#[allow(non_snake_case)]
+pub extern "C" fn stack__pop(receiver: SkStack) -> SkAry<SkObj> {
+    let ary = SkAry::<SkObj>::new();
+    ary.set_vec(receiver.items().pop().into_iter().collect());
+    ary
+}
+
+#[shiika_method("Stack#push")]
+pub extern "C" fn stack_push(receiver: SkStack, v: SkObj) {
+    receiver.items().push(v);
+}
+
+#[shiika_method("Stack#size")]
+pub extern "C" fn stack_size(receiver: SkStack) -> SkInt {
+    receiver.items().len().into()
+}
@@ -1,7 +1,10 @@
 //! Instance of `::String`
-use crate::builtin::{SkAry, SkInt, SkPtr};
+mod format;
+use crate::builtin::{SkAry, SkBool, SkChar, SkFloat, SkFn1, SkInt, SkObj, SkPtr};
 use shiika_ffi_macro::shiika_method;
+use std::collections::hash_map::DefaultHasher;
 use std::ffi::CString;
+use std::hash::{Hash, Hasher};
 use unicode_segmentation::UnicodeSegmentation;
 
 extern "C" {
@@ -65,22 +68,180 @@ impl SkStr {
     }
 }
 
-#[shiika_method("String#chars")]
-pub extern "C" fn string_chars(receiver: SkStr) -> SkAry<SkStr> {
+/// Returns `[s]` if `bytes` is a valid UTF-8 sequence, `[]` otherwise
+#[shiika_method("Meta:String#_from_utf8")]
+#[allow(non_snake_case)]
+pub extern "C" fn meta_string__from_utf8(
+    _receiver: *const u8,
+    bytes: SkAry<SkInt>,
+) -> SkAry<SkStr> {
     let ary = SkAry::<SkStr>::new();
-    let v = UnicodeSegmentation::graphemes(receiver.as_str(), true)
-        .map(|s| s.to_string().into())
-        .collect::<Vec<SkStr>>();
+    let v = bytes
+        .as_vec()
+        .iter()
+        .map(|b| u8::try_from(b.val()).ok())
+        .collect::<Option<Vec<u8>>>();
+    if let Some(s) = v.and_then(|v| String::from_utf8(v).ok()) {
+        ary.set_vec(vec![s.into()]);
+    }
+    ary
+}
+
+#[shiika_method("String#<")]
+pub extern "C" fn string_lt(receiver: SkStr, other: SkStr) -> SkBool {
+    (receiver.as_byteslice() < other.as_byteslice()).into()
+}
+
+#[shiika_method("String#<=")]
+pub extern "C" fn string_le(receiver: SkStr, other: SkStr) -> SkBool {
+    (receiver.as_byteslice() <= other.as_byteslice()).into()
+}
+
+#[shiika_method("String#>")]
+pub extern "C" fn string_gt(receiver: SkStr, other: SkStr) -> SkBool {
+    (receiver.as_byteslice() > other.as_byteslice()).into()
+}
+
+#[shiika_method("String#>=")]
+pub extern "C" fn string_ge(receiver: SkStr, other: SkStr) -> SkBool {
+    (receiver.as_byteslice() >= other.as_byteslice()).into()
+}
+
+#[shiika_method("String#chars")]
+pub extern "C" fn string_chars(receiver: SkStr) -> SkAry<SkChar> {
+    let ary = SkAry::<SkChar>::new();
+    let v = receiver
+        .as_str()
+        .chars()
+        .map(|c| c.into())
+        .collect::<Vec<SkChar>>();
     ary.set_vec(v);
     ary
 }
 
+#[shiika_method("String#contains?")]
+pub extern "C" fn string_contains(receiver: SkStr, sub: SkStr) -> SkBool {
+    receiver.as_str().contains(sub.as_str()).into()
+}
+
+#[shiika_method("String#each_char")]
+pub extern "C" fn string_each_char(receiver: SkStr, f: SkFn1<SkChar, SkObj>) {
+    for c in receiver.as_str().chars() {
+        f.call(c.into());
+        if f.is_break() {
+            break;
+        }
+    }
+}
+
+#[shiika_method("String#each_line")]
+pub extern "C" fn string_each_line(receiver: SkStr, f: SkFn1<SkStr, SkObj>) {
+    each_line(receiver.as_str().split_terminator('\n'), f);
+}
+
+#[shiika_method("String#each_line_crlf")]
+pub extern "C" fn string_each_line_crlf(receiver: SkStr, f: SkFn1<SkStr, SkObj>) {
+    // Same as `each_line` except `\r` before `\n` is also removed
+    each_line(receiver.as_str().lines(), f);
+}
+
+fn each_line<'a>(lines: impl Iterator<Item = &'a str>, f: SkFn1<SkStr, SkObj>) {
+    for line in lines {
+        f.call(line.to_string().into());
+        if f.is_break() {
+            break;
+        }
+    }
+}
+
+#[shiika_method("String#ends_with?")]
+pub extern "C" fn string_ends_with(receiver: SkStr, suffix: SkStr) -> SkBool {
+    receiver.as_str().ends_with(suffix.as_str()).into()
+}
+
+/// Returns `[n]` if `self` is an integer, `[]` otherwise
+#[shiika_method("String#get_char")]
+pub extern "C" fn string_get_char(receiver: SkStr, idx: SkInt) -> SkChar {
+    let i = idx.val();
+    let c = if i < 0 {
+        None
+    } else {
+        receiver.as_str().chars().nth(i as usize)
+    };
+    match c {
+        Some(c) => c.into(),
+        None => panic!("String#get_char: out of range (idx: {})", i),
+    }
+}
+
+#[shiika_method("String#hash")]
+pub extern "C" fn string_hash(receiver: SkStr) -> SkInt {
+    let mut hasher = DefaultHasher::new();
+    receiver.as_byteslice().hash(&mut hasher);
+    (hasher.finish() as i64).into()
+}
+
+#[shiika_method("String#_parse_int")]
+#[allow(non_snake_case)]
+pub extern "C" fn string__parse_int(receiver: SkStr) -> SkAry<SkInt> {
+    let ary = SkAry::<SkInt>::new();
+    if let Ok(n) = receiver.as_str().parse::<i64>() {
+        ary.set_vec(vec![n.into()]);
+    }
+    ary
+}
+
+/// Returns `[f]` if `self` is a float number, `[]` otherwise
+#[shiika_method("String#_parse_float")]
+#[allow(non_snake_case)]
+pub extern "C" fn string__parse_float(receiver: SkStr) -> SkAry<SkFloat> {
+    let ary = SkAry::<SkFloat>::new();
+    if let Ok(f) = receiver.as_str().parse::<f64>() {
+        ary.set_vec(vec![f.into()]);
+    }
+    ary
+}
+
+#[shiika_method("String#size")]
+pub extern "C" fn string_size(receiver: SkStr) -> SkInt {
+    UnicodeSegmentation::graphemes(receiver.as_str(), true)
+        .count()
+        .into()
+}
+
+#[shiika_method("String#slice")]
+pub extern "C" fn string_slice(receiver: SkStr, start: SkInt, len: SkInt) -> SkStr {
+    let chars = UnicodeSegmentation::graphemes(receiver.as_str(), true).collect::<Vec<_>>();
+    let (from, n) = (start.val(), len.val());
+    if from < 0 || n < 0 || (from + n) as usize > chars.len() {
+        panic!(
+            "String#slice: out of range (start: {}, len: {}, size: {})",
+            from,
+            n,
+            chars.len()
+        );
+    }
+    chars[from as usize..(from + n) as usize].concat().into()
+}
+
+#[shiika_method("String#starts_with?")]
+pub extern "C" fn string_starts_with(receiver: SkStr, prefix: SkStr) -> SkBool {
+    receiver.as_str().starts_with(prefix.as_str()).into()
+}
+
+#[shiika_method("String#to_lower")]
+pub extern "C" fn string_to_lower(receiver: SkStr) -> SkStr {
+    receiver.as_str().to_lowercase().into()
+}
+
+#[shiika_method("String#to_upper")]
+pub extern "C" fn string_to_upper(receiver: SkStr) -> SkStr {
+    receiver.as_str().to_uppercase().into()
+}
+
+#[shiika_method("String#trim")]
+pub extern "C" fn string_trim(receiver: SkStr) -> SkStr {
+    receiver.as_str().trim().to_string().into()
+}
+
 // TODO: How to support `break`
-//#[shiika_method("String#each_char")]
-//pub extern "C" fn string_each_char(receiver: SkStr, block: SkFn1<SkStr, SkVoid>) {
-//    UnicodeSegmentation::graphemes(receiver.as_str(), true)
-//        .map(|s| s.to_string().into())
-//        .for_each(|sk_str| {
-//            block.call(sk_str);
-//        });
-//}
@@ -0,0 +1,164 @@
+//! `String.format`
+//!
+//! A directive is `%[flags][width][.precision]conversion` where
+//! - flags: `-` (left-justify) and `0` (pad numbers with zeros)
+//! - conversion: `d` (Int), `x` (Int in hexadecimal), `f` (Float or Int)
+//!   and `s` (any object; converted with `#to_s`)
+//!
+//! `%%` is a literal `%`.
+use crate::builtin::object::SkObj;
+use crate::builtin::{SkAry, SkFloat, SkInt, SkStr};
+use shiika_ffi_macro::shiika_method;
+use std::iter::Peekable;
+use std::mem;
+use std::str::Chars;
+
+#[shiika_method("Meta:String#_format")]
+pub extern "C" fn meta_string__format(
+    _receiver: *const u8,
+    template: SkStr,
+    args: SkAry<SkObj>,
+) -> SkStr {
+    format(template.as_str(), args.as_vec()).into()
+}
+
+fn format(template: &str, args: &[SkObj]) -> String {
+    let mut out = String::new();
+    let mut n_used = 0;
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        if chars.peek() == Some(&'%') {
+            chars.next();
+            out.push('%');
+            continue;
+        }
+        let directive = Directive::parse(&mut chars);
+        let arg = args.get(n_used).unwrap_or_else(|| {
+            panic!(
+                "String.format: too few arguments (given {}, template: {:?})",
+                args.len(),
+                template
+            )
+        });
+        n_used += 1;
+        out.push_str(&directive.format(arg));
+    }
+    if n_used < args.len() {
+        panic!(
+            "String.format: too many arguments (given {}, expected {})",
+            args.len(),
+            n_used
+        );
+    }
+    out
+}
+
+/// A value given to `String.format`
+enum Value {
+    Int(i64),
+    Float(f64),
+    Other,
+}
+
+impl Value {
+    fn new(obj: &SkObj) -> Value {
+        match obj.class().name().as_str() {
+            "Int" => Value::Int(unsafe { mem::transmute::<SkObj, SkInt>(obj.dup()) }.val()),
+            "Float" => Value::Float(unsafe { mem::transmute::<SkObj, SkFloat>(obj.dup()) }.val()),
+            _ => Value::Other,
+        }
+    }
+}
+
+struct Directive {
+    left_justify: bool,
+    zero_pad: bool,
+    width: usize,
+    precision: Option<usize>,
+    conversion: char,
+}
+
+impl Directive {
+    /// Parse the characters after `%`
+    fn parse(chars: &mut Peekable<Chars>) -> Directive {
+        let mut left_justify = false;
+        let mut zero_pad = false;
+        loop {
+            match chars.peek() {
+                Some('-') => left_justify = true,
+                Some('0') => zero_pad = true,
+                _ => break,
+            }
+            chars.next();
+        }
+        let width = read_digits(chars).unwrap_or(0);
+        let precision = if chars.peek() == Some(&'.') {
+            chars.next();
+            Some(read_digits(chars).unwrap_or(0))
+        } else {
+            None
+        };
+        let conversion = match chars.next() {
+            Some(c @ ('d' | 'x' | 'f' | 's')) => c,
+            Some(c) => panic!("String.format: unknown directive `%{}'", c),
+            None => panic!("String.format: incomplete directive at the end of the template"),
+        };
+        Directive {
+            left_justify,
+            zero_pad,
+            width,
+            precision,
+            conversion,
+        }
+    }
+
+    fn format(&self, arg: &SkObj) -> String {
+        let s = match (self.conversion, Value::new(arg)) {
+            ('d', Value::Int(i)) => i.to_string(),
+            ('x', Value::Int(i)) if i < 0 => format!("-{:x}", (i as i128).abs()),
+            ('x', Value::Int(i)) => format!("{:x}", i),
+            ('f', Value::Int(i)) => format!("{:.*}", self.precision.unwrap_or(6), i as f64),
+            ('f', Value::Float(f)) => format!("{:.*}", self.precision.unwrap_or(6), f),
+            ('s', _) => arg.to_s().as_str().to_string(),
+            (c, _) => panic!(
+                "String.format: `%{}' needs {} but got {}",
+                c,
+                if c == 'f' { "a Float" } else { "an Int" },
+                arg.class().name().as_str()
+            ),
+        };
+        self.pad(s)
+    }
+
+    /// Pad `s` to `width` characters
+    fn pad(&self, s: String) -> String {
+        let len = s.chars().count();
+        if len >= self.width {
+            return s;
+        }
+        let n = self.width - len;
+        if self.left_justify {
+            s + &" ".repeat(n)
+        } else if self.zero_pad && self.conversion != 's' {
+            match s.strip_prefix('-') {
+                Some(digits) => format!("-{}{}", "0".repeat(n), digits),
+                None => "0".repeat(n) + &s,
+            }
+        } else {
+            " ".repeat(n) + &s
+        }
+    }
+}
+
+fn read_digits(chars: &mut Peekable<Chars>) -> Option<usize> {
+    let mut n = None;
+    while let Some(d) = chars.peek().and_then(|c| c.to_digit(10)) {
+        n = Some(n.unwrap_or(0) * 10 + d as usize);
+        chars.next();
+    }
+    n
+}
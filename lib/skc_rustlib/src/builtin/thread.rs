@@ -0,0 +1,102 @@
+//! Instance of `::Thread`
+//!
+//! Each thread is backed by `std::thread`. Threads are registered to bdwgc
+//! so that the objects referred only from their stacks are not collected.
+//! Until the new thread is registered, its lambda is kept in `PENDING` so
+//! that it is not collected meanwhile.
+use crate::builtin::{SkFn0, SkObj};
+use shiika_ffi_macro::shiika_method;
+use std::os::raw::{c_int, c_void};
+use std::sync::{Mutex, Once};
+use std::thread::JoinHandle;
+
+/// `struct GC_stack_base` of bdwgc
+#[repr(C)]
+struct GcStackBase {
+    mem_base: *mut c_void,
+}
+
+extern "C" {
+    fn GC_allow_register_threads();
+    fn GC_get_stack_base(sb: *mut GcStackBase) -> c_int;
+    fn GC_register_my_thread(sb: *const GcStackBase) -> c_int;
+    fn GC_unregister_my_thread() -> c_int;
+}
+
+/// `GC_allow_register_threads` must be called (in the main thread) before
+/// creating the first thread
+static ALLOW_REGISTER_THREADS: Once = Once::new();
+
+/// Addresses of the lambdas passed to the threads which are not registered
+/// to bdwgc yet. This is a GC root because bdwgc scans the static data (and
+/// the buffer of the Vec is allocated with `GC_malloc`)
+static PENDING: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct SkThread(*mut ShiikaThread);
+
+#[repr(C)]
+#[derive(Debug)]
+struct ShiikaThread {
+    vtable: *const u8,
+    class_obj: *const u8,
+    // Allocated with the global allocator (i.e. `GC_malloc`)
+    handle: *mut Option<JoinHandle<()>>,
+}
+
+/// The lambda to run in the new thread.
+/// Shiika objects are not `Send` but the lambda is only used by the new
+/// thread after it is passed.
+struct ThreadBody(SkFn0<SkObj>);
+
+unsafe impl Send for ThreadBody {}
+
+impl ThreadBody {
+    fn addr(&self) -> usize {
+        self.0.as_ptr() as usize
+    }
+
+    /// Called in the new thread after it is registered to bdwgc
+    fn run(self) {
+        // `self` is on the stack, which is scanned now
+        let mut pending = PENDING.lock().unwrap();
+        if let Some(i) = pending.iter().position(|x| *x == self.addr()) {
+            pending.swap_remove(i);
+        }
+        drop(pending);
+        self.0.call();
+    }
+}
+
+#[shiika_method("Thread#_start")]
+#[allow(non_snake_case)]
+pub extern "C" fn thread__start(receiver: SkThread, f: SkFn0<SkObj>) {
+    ALLOW_REGISTER_THREADS.call_once(|| unsafe { GC_allow_register_threads() });
+    let body = ThreadBody(f);
+    PENDING.lock().unwrap().push(body.addr());
+    let handle = std::thread::spawn(move || {
+        let mut sb = GcStackBase {
+            mem_base: std::ptr::null_mut(),
+        };
+        unsafe {
+            GC_get_stack_base(&mut sb);
+            GC_register_my_thread(&sb);
+        }
+        body.run();
+        unsafe {
+            GC_unregister_my_thread();
+        }
+    });
+    unsafe {
+        (*receiver.0).handle = Box::leak(Box::new(Some(handle)));
+    }
+}
+
+#[shiika_method("Thread#join")]
+pub extern "C" fn thread_join(receiver: SkThread) {
+    let handle = unsafe { (*receiver.0).handle.as_mut() };
+    if let Some(h) = handle.and_then(|h| h.take()) {
+        h.join().expect("Thread#join: the thread panicked");
+    }
+}
@@ -0,0 +1,60 @@
+//! Instance of `::WeakRef`
+//!
+//! The pointer to the object is stored in a memory allocated with
+//! `GC_malloc_atomic` (which is not scanned by GC) and registered as a
+//! "disappearing link", so that bdwgc sets it to null when the object is
+//! collected.
+use crate::builtin::SkAry;
+use shiika_ffi_macro::shiika_method;
+use std::os::raw::{c_int, c_void};
+
+extern "C" {
+    fn GC_malloc_atomic(size: usize) -> *mut c_void;
+    fn GC_general_register_disappearing_link(link: *mut *mut c_void, obj: *const c_void) -> c_int;
+    fn GC_call_with_alloc_lock(
+        f: extern "C" fn(*mut c_void) -> *mut c_void,
+        client_data: *mut c_void,
+    ) -> *mut c_void;
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct SkWeakRef(*mut ShiikaWeakRef);
+
+#[repr(C)]
+#[derive(Debug)]
+struct ShiikaWeakRef {
+    vtable: *const u8,
+    class_obj: *const u8,
+    // The disappearing link
+    link: *mut *mut c_void,
+}
+
+/// Read the link. Called with the allocation lock of bdwgc held so that the
+/// object is not collected while reading.
+extern "C" fn read_link(link: *mut c_void) -> *mut c_void {
+    unsafe { *(link as *mut *mut c_void) }
+}
+
+/// Called from `WeakRef.new` and initializes internal fields.
+#[shiika_method("WeakRef#_initialize_rustlib")]
+#[allow(non_snake_case)]
+pub extern "C" fn weak_ref__initialize_rustlib(receiver: SkWeakRef, obj: *const u8) {
+    unsafe {
+        let link = GC_malloc_atomic(std::mem::size_of::<*mut c_void>()) as *mut *mut c_void;
+        *link = obj as *mut c_void;
+        GC_general_register_disappearing_link(link, obj as *const c_void);
+        (*receiver.0).link = link;
+    }
+}
+
+#[shiika_method("WeakRef#_get")]
+#[allow(non_snake_case)]
+pub extern "C" fn weak_ref__get(receiver: SkWeakRef) -> SkAry<*const u8> {
+    let obj = unsafe { GC_call_with_alloc_lock(read_link, (*receiver.0).link as *mut c_void) };
+    let ary = SkAry::<*const u8>::new();
+    if !obj.is_null() {
+        ary.set_vec(vec![obj as *const u8]);
+    }
+    ary
+}
@@ -1,3 +1,5 @@
 mod allocator;
 mod builtin;
 mod sk_methods;
+mod stacktrace;
+mod trace;
@@ -0,0 +1,49 @@
+//! Shiika-level call stack, used to show a stack trace on panic.
+//! Frames are pushed/popped by the generated code on each method call.
+use std::cell::RefCell;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+struct Frame {
+    /// Fullname of the method (eg. `Foo#bar`)
+    name: *const c_char,
+    /// Path of the source file (may be empty)
+    file: *const c_char,
+    /// 1-origin line number (0 if unknown)
+    line: u32,
+}
+
+thread_local! {
+    static FRAMES: RefCell<Vec<Frame>> = RefCell::new(vec![]);
+}
+
+/// Called on method entry
+#[no_mangle]
+pub extern "C" fn shiika_push_frame(name: *const c_char, file: *const c_char, line: u32) {
+    FRAMES.with(|frames| frames.borrow_mut().push(Frame { name, file, line }));
+}
+
+/// Called on method exit
+#[no_mangle]
+pub extern "C" fn shiika_pop_frame() {
+    FRAMES.with(|frames| {
+        frames.borrow_mut().pop();
+    });
+}
+
+/// Print the current call stack to stderr (the innermost first)
+#[no_mangle]
+pub extern "C" fn shiika_print_stacktrace() {
+    FRAMES.with(|frames| {
+        for frame in frames.borrow().iter().rev() {
+            // Safety: `name` and `file` are llvm global string constants
+            let name = unsafe { CStr::from_ptr(frame.name) }.to_string_lossy();
+            let file = unsafe { CStr::from_ptr(frame.file) }.to_string_lossy();
+            if file.is_empty() {
+                eprintln!("  from {}", name);
+            } else {
+                eprintln!("  from {} ({}:{})", name, file, frame.line);
+            }
+        }
+    });
+}
@@ -0,0 +1,36 @@
+//! Method call tracing (enabled by compiling with `--trace-calls` and
+//! running with the environment variable `SHIIKA_TRACE` set.)
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Current depth of the method calls
+static DEPTH: AtomicUsize = AtomicUsize::new(0);
+
+fn enabled() -> bool {
+    std::env::var_os("SHIIKA_TRACE").is_some()
+}
+
+/// Called on method entry
+#[no_mangle]
+pub extern "C" fn shiika_trace_call(name: *const c_char) {
+    if !enabled() {
+        return;
+    }
+    let depth = DEPTH.fetch_add(1, Ordering::SeqCst);
+    // Safety: `name` is a llvm global string constant
+    let name = unsafe { CStr::from_ptr(name) }.to_string_lossy();
+    eprintln!("{}-> {}", "  ".repeat(depth), name);
+}
+
+/// Called on method exit
+#[no_mangle]
+pub extern "C" fn shiika_trace_return(name: *const c_char) {
+    if !enabled() {
+        return;
+    }
+    let depth = DEPTH.fetch_sub(1, Ordering::SeqCst).saturating_sub(1);
+    // Safety: `name` is a llvm global string constant
+    let name = unsafe { CStr::from_ptr(name) }.to_string_lossy();
+    eprintln!("{}<- {}", "  ".repeat(depth), name);
+}
@@ -0,0 +1,252 @@
+//! Skip compiling a program whose sources are not changed since the last
+//! compilation.
+//!
+//! For each compiled program, the hashes of its source files (the entry
+//! file and the ones `require`d from it) and the dependency graph among
+//! them are saved in `.shiika_cache/`. The program is compiled again only
+//! when its .bc is missing or any of the files has changed. The type
+//! information of the core library comes from builtin/exports.json, so its
+//! hash is saved too, along with the hash of the compiler executable.
+//!
+//! The `require`d files are compiled one by one (`LibraryUnit`) into
+//! `.shiika_cache/lib/` with their exports, so a change in a file rebuilds
+//! only the files which depend on it. A file which has toplevel expressions
+//! or `extension`s cannot be compiled separately; the whole program is
+//! compiled at once in that case.
+//!
+//! The cache is used only when `CompileOptions::incremental` is set.
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use shiika_parser::{loader, SourceFile};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+const CACHE_DIR: &str = ".shiika_cache";
+
+/// What a program is compiled from
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct BuildRecord {
+    /// Hash of the compiler executable
+    compiler_hash: String,
+    /// Compile options (which may change the generated code)
+    options: String,
+    /// Hash of builtin/exports.json
+    exports_hash: String,
+    /// The source files in the order of loading
+    files: Vec<FileRecord>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct FileRecord {
+    path: PathBuf,
+    hash: String,
+    /// Files `require`d from this file
+    requires: Vec<PathBuf>,
+}
+
+impl BuildRecord {
+    pub fn new<'a>(
+        files: impl IntoIterator<Item = &'a SourceFile>,
+        options: &str,
+        exports_json: &str,
+    ) -> BuildRecord {
+        BuildRecord {
+            compiler_hash: compiler_hash().to_string(),
+            options: options.to_string(),
+            exports_hash: hash_of(exports_json),
+            files: files
+                .into_iter()
+                .map(|f| FileRecord {
+                    path: f.path.to_path_buf(),
+                    hash: hash_of(&f.content),
+                    requires: loader::resolve_requires(&f.path, &f.content),
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns the files which need to be compiled again compared to
+    /// `old` i.e. the changed files and the files which depend on them.
+    /// Returns all the files if the compiler, the options or the core
+    /// library is changed.
+    pub fn files_to_rebuild(&self, old: &BuildRecord) -> Vec<&Path> {
+        let all = self.files.iter().map(|f| f.path.as_path());
+        if self.compiler_hash != old.compiler_hash
+            || self.options != old.options
+            || self.exports_hash != old.exports_hash
+        {
+            return all.collect();
+        }
+        let mut dirty = self
+            .files
+            .iter()
+            .filter(|f| {
+                !old.files
+                    .iter()
+                    .any(|o| o.path == f.path && o.hash == f.hash)
+            })
+            .map(|f| f.path.as_path())
+            .collect::<HashSet<_>>();
+        // Files are loaded after the ones they require
+        for f in &self.files {
+            if f.requires.iter().any(|r| dirty.contains(r.as_path())) {
+                dirty.insert(&f.path);
+            }
+        }
+        all.filter(|p| dirty.contains(p)).collect()
+    }
+}
+
+/// A `require`d file which is compiled separately from the program
+#[derive(Debug)]
+pub struct LibraryUnit {
+    pub path: PathBuf,
+    /// Used for the name of the function to initialize the constants
+    pub name: String,
+    /// The files it depends on (directly or not) in the order of loading
+    pub deps: Vec<PathBuf>,
+    record: BuildRecord,
+}
+
+impl LibraryUnit {
+    /// Returns the units of `files` except the entry file (the last one).
+    /// Returns None if some of them require each other
+    pub fn list(files: &[SourceFile], options: &str, exports_json: &str) -> Option<Vec<Self>> {
+        let libs = &files[..files.len().saturating_sub(1)];
+        let mut units: Vec<LibraryUnit> = vec![];
+        for (i, file) in libs.iter().enumerate() {
+            let mut deps = HashSet::new();
+            for req in loader::resolve_requires(&file.path, &file.content) {
+                // Files are loaded after the ones they require unless circular
+                let unit = units.iter().find(|u| u.path == req)?;
+                deps.insert(unit.path.as_path());
+                deps.extend(unit.deps.iter().map(|p| p.as_path()));
+            }
+            let dep_files = libs[..i]
+                .iter()
+                .filter(|f| deps.contains(f.path.as_path()))
+                .collect::<Vec<_>>();
+            let name = file
+                .path
+                .to_string_lossy()
+                .replace(|c: char| !c.is_ascii_alphanumeric(), "_");
+            units.push(LibraryUnit {
+                path: file.path.to_path_buf(),
+                name: format!("lib_{}", name),
+                deps: dep_files.iter().map(|f| f.path.to_path_buf()).collect(),
+                record: BuildRecord::new(
+                    dep_files.into_iter().chain(Some(file)),
+                    options,
+                    exports_json,
+                ),
+            });
+        }
+        Some(units)
+    }
+
+    /// eg. `.shiika_cache/lib/lib_examples_lib_sk.bc`
+    pub fn bc_path(&self) -> String {
+        format!("{}/lib/{}.bc", CACHE_DIR, self.name)
+    }
+
+    /// Path of the json of `LibraryExports`
+    pub fn exports_path(&self) -> PathBuf {
+        Path::new(CACHE_DIR)
+            .join("lib")
+            .join(format!("{}.exports.json", self.name))
+    }
+
+    fn record_path(&self) -> PathBuf {
+        Path::new(CACHE_DIR)
+            .join("lib")
+            .join(format!("{}.json", self.name))
+    }
+
+    /// Returns true if the .bc and the exports are generated from the same
+    /// sources
+    pub fn is_up_to_date(&self) -> bool {
+        if !Path::new(&self.bc_path()).exists() || !self.exports_path().exists() {
+            return false;
+        }
+        match load(&self.record_path()) {
+            Some(old) => self.record.files_to_rebuild(&old).is_empty(),
+            None => false,
+        }
+    }
+
+    /// Create the directory for the .bc and the exports
+    pub fn create_dir() -> Result<()> {
+        fs::create_dir_all(Path::new(CACHE_DIR).join("lib"))
+            .context("failed to create the build cache")
+    }
+
+    /// Save the record (after the .bc and the exports are written)
+    pub fn save(&self) -> Result<()> {
+        write(&self.record_path(), &self.record)
+    }
+}
+
+/// Returns true if the .bc of `sk_path` is generated from the same sources
+pub fn is_up_to_date(sk_path: &str, record: &BuildRecord) -> bool {
+    if !Path::new(&format!("{}.bc", sk_path)).exists() {
+        return false;
+    }
+    match load(&record_path(sk_path)) {
+        Some(old) => record.files_to_rebuild(&old).is_empty(),
+        None => false,
+    }
+}
+
+/// Save the record of `sk_path`
+pub fn save(sk_path: &str, record: &BuildRecord) -> Result<()> {
+    fs::create_dir_all(CACHE_DIR).context("failed to create the build cache")?;
+    write(&record_path(sk_path), record)
+}
+
+/// Remove the record of `sk_path`, if any
+pub fn remove(sk_path: &str) {
+    let _ = fs::remove_file(record_path(sk_path));
+}
+
+/// Load the record of the last compilation, if any
+fn load(path: &Path) -> Option<BuildRecord> {
+    let json = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+fn write(path: &Path, record: &BuildRecord) -> Result<()> {
+    let json = serde_json::to_string_pretty(record)?;
+    fs::write(path, json).context("failed to write the build cache")?;
+    Ok(())
+}
+
+/// eg. `.shiika_cache/examples_hello.sk.json` for `examples/hello.sk`
+fn record_path(sk_path: &str) -> PathBuf {
+    let name = sk_path.replace(['/', '\\', ':'], "_");
+    Path::new(CACHE_DIR).join(format!("{}.json", name))
+}
+
+/// Returns the hash of the running compiler so that the outputs of another
+/// build of it are not reused
+fn compiler_hash() -> &'static str {
+    static HASH: OnceLock<String> = OnceLock::new();
+    HASH.get_or_init(|| {
+        let bytes = env::current_exe()
+            .and_then(fs::read)
+            .unwrap_or_else(|_| env!("CARGO_PKG_VERSION").into());
+        let mut hasher = DefaultHasher::new();
+        hasher.write(&bytes);
+        format!("{:016x}", hasher.finish())
+    })
+}
+
+fn hash_of(s: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    s.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
@@ -1,4 +1,5 @@
 use clap::{Parser, Subcommand};
+use skc_codegen::OptLevel;
 
 #[derive(clap::Parser, Debug)]
 #[clap(name = "subcommand", author, version, about)]
@@ -10,9 +11,44 @@ pub struct Arguments {
 #[derive(Debug, Subcommand)]
 pub enum Command {
     /// Compile shiika program
-    Compile { filepath: String },
+    Compile {
+        filepath: String,
+        /// Only type-check the program (does not generate code)
+        #[clap(long)]
+        check: bool,
+        /// Warn constant initializers which call non-@pure methods
+        #[clap(long)]
+        strict_constants: bool,
+        /// Emit method call logs (printed when SHIIKA_TRACE is set)
+        #[clap(long)]
+        trace_calls: bool,
+        /// Emit DWARF debug information
+        #[clap(long)]
+        debug_info: bool,
+        /// Optimization level (0-3, or none/less/default/aggressive).
+        /// Int overflow is not checked if optimized
+        #[clap(long, default_value = "0")]
+        opt: OptLevel,
+    },
     /// Compile and execute shiika program
-    Run { filepath: String },
+    Run {
+        filepath: String,
+        /// Warn constant initializers which call non-@pure methods
+        #[clap(long)]
+        strict_constants: bool,
+        /// Emit method call logs (printed when SHIIKA_TRACE is set)
+        #[clap(long)]
+        trace_calls: bool,
+        /// Emit DWARF debug information
+        #[clap(long)]
+        debug_info: bool,
+        /// Optimization level (0-3, or none/less/default/aggressive).
+        /// Int overflow is not checked if optimized
+        #[clap(long, default_value = "0")]
+        opt: OptLevel,
+    },
+    /// Start the interactive shell
+    Repl,
     /// Build corelib
     BuildCorelib,
 }
@@ -1,4 +1,5 @@
+mod build_cache;
 pub mod cli;
-mod loader;
+pub mod repl;
 pub mod runner;
 mod targets;
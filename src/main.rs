@@ -1,19 +1,55 @@
 use anyhow::Result;
 use shiika::cli;
-use shiika::runner;
+use shiika::repl;
+use shiika::runner::{self, CompileOptions};
 
 fn main() -> Result<()> {
     env_logger::init();
+    skc_error::set_color(atty::is(atty::Stream::Stdout));
     let args = cli::parse_command_line_args();
 
     match &args.command {
-        cli::Command::Compile { filepath } => {
-            runner::compile(filepath)?;
+        cli::Command::Compile {
+            filepath,
+            check,
+            strict_constants,
+            trace_calls,
+            debug_info,
+            opt,
+        } => {
+            let options = CompileOptions {
+                strict_constants: *strict_constants,
+                trace_calls: *trace_calls,
+                debug_info: *debug_info,
+                opt_level: *opt,
+                incremental: true,
+            };
+            if *check {
+                runner::check(filepath, &options)?;
+            } else {
+                runner::compile_with_options(filepath, &options)?;
+            }
         }
-        cli::Command::Run { filepath } => {
-            runner::compile(filepath)?;
+        cli::Command::Run {
+            filepath,
+            strict_constants,
+            trace_calls,
+            debug_info,
+            opt,
+        } => {
+            let options = CompileOptions {
+                strict_constants: *strict_constants,
+                trace_calls: *trace_calls,
+                debug_info: *debug_info,
+                opt_level: *opt,
+                incremental: true,
+            };
+            runner::compile_with_options(filepath, &options)?;
             runner::run(filepath)?;
         }
+        cli::Command::Repl => {
+            repl::start()?;
+        }
         cli::Command::BuildCorelib => {
             runner::build_corelib()?;
         }
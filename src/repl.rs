@@ -0,0 +1,359 @@
+//! Interactive shell (`shiika repl`)
+//!
+//! Each input is compiled into a program together with the classes and the
+//! constants defined so far and then executed. Expressions are wrapped in a
+//! class method so that the value can be printed with its type.
+//!
+//! Each input runs in a new process, so the local variables are not kept
+//! (the earlier inputs are not run again because they may have side
+//! effects.)
+use crate::runner;
+use anyhow::{anyhow, Context, Result};
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::Editor;
+use shiika_core::ty::{self, TermTy};
+use shiika_parser::{Parser, SourceFile};
+use skc_ast2hir::class_dict::ClassDict;
+use skc_hir::Hir;
+use skc_mir::LibraryExports;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Name of the class to wrap the input in
+const WRAPPER: &str = "ReplInput__";
+
+/// Printed before the value of the input (to separate it from the output of
+/// the input itself)
+const VALUE_MARKER: char = '\x01';
+
+pub struct Repl {
+    imports: LibraryExports,
+    /// Source of the classes and the constants defined so far
+    defs: String,
+    /// The lines of the input which is not complete yet
+    pending: String,
+    /// Where the programs are written
+    dir: PathBuf,
+    names: Names,
+}
+
+impl Repl {
+    pub fn new() -> Result<Repl> {
+        // Numbered so that the tests can run in parallel
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let dir = env::temp_dir().join(format!(
+            "shiika_repl_{}_{}",
+            process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&dir).context("failed to create the work directory")?;
+        let mut repl = Repl {
+            imports: runner::load_builtin_exports()?,
+            defs: String::new(),
+            pending: String::new(),
+            dir,
+            names: Default::default(),
+        };
+        repl.update_names()?;
+        Ok(repl)
+    }
+
+    /// Returns true if the input is not complete yet
+    pub fn is_continuing(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Discard the incomplete input
+    pub fn cancel(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Names for the tab completion
+    pub fn names(&self) -> &Names {
+        &self.names
+    }
+
+    /// Process a line. Returns None if more lines are needed (eg. in the
+    /// middle of a class definition.) Otherwise returns the output of the
+    /// input and its value (eg. `=> 3 : Int`)
+    pub fn feed(&mut self, line: &str) -> Result<Option<String>> {
+        self.pending.push_str(line);
+        self.pending.push('\n');
+        let input = std::mem::take(&mut self.pending);
+        let ast = match self.parse(&input) {
+            Ok(ast) => ast,
+            Err(e) if e.downcast_ref().map_or(false, is_incomplete) => {
+                self.pending = input;
+                return Ok(None);
+            }
+            Err(e) => return Err(e),
+        };
+        let n_defs = ast.defs().len();
+        let output = if ast.toplevel_items.is_empty() {
+            String::new()
+        } else if n_defs == ast.toplevel_items.len() {
+            self.define(&input)?;
+            String::new()
+        } else if n_defs == 0 {
+            self.eval(&input)?
+        } else {
+            return Err(anyhow!(
+                "classes and constants must be defined separately from other expressions"
+            ));
+        };
+        Ok(Some(output))
+    }
+
+    /// Read lines from `input` and write the outputs (and the errors) to
+    /// `output`. Used when the stdin is not a terminal
+    pub fn run_script(&mut self, input: &mut impl BufRead, output: &mut impl Write) -> Result<()> {
+        for line in input.lines() {
+            match self.feed(&line?) {
+                Ok(Some(out)) => write!(output, "{}", out)?,
+                Ok(None) => {}
+                Err(e) => writeln!(output, "{}", e)?,
+            }
+        }
+        if self.is_continuing() {
+            writeln!(output, "unexpected end of input")?;
+        }
+        Ok(())
+    }
+
+    /// Add classes or constants
+    fn define(&mut self, input: &str) -> Result<()> {
+        let src = format!("{}{}", self.defs, input);
+        let ast = self.parse(&src)?;
+        skc_ast2hir::make_hir(ast, &self.imports, false)?;
+        self.defs = src;
+        self.update_names()
+    }
+
+    /// Run expressions and returns the output and the value
+    fn eval(&mut self, input: &str) -> Result<String> {
+        let ast = self.parse(&format!("{}{}", self.defs, input))?;
+        let hir = skc_ast2hir::make_hir(ast, &self.imports, false)?;
+        let ty = hir.main_exprs.ty().clone();
+        let has_value = !ty.is_void_type() && !ty.is_never_type();
+        let main = if has_value {
+            format!("let v = {}.run\nprint \"{}\"\np v\n", WRAPPER, VALUE_MARKER)
+        } else {
+            format!("{}.run\n", WRAPPER)
+        };
+        let program = format!(
+            "{}class {}\n  def self.run -> {}\n{}  end\nend\n{}",
+            self.defs,
+            WRAPPER,
+            if has_value { "Object" } else { "Void" },
+            input,
+            main
+        );
+        let path = self.write_program(&program)?;
+        runner::compile(&path)?;
+        let result = runner::run_with(&path, &[], &[]);
+        runner::cleanup(&path)?;
+        let out = result?;
+        let stdout = String::from_utf8_lossy(&out.stdout);
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        if !out.status.success() {
+            return Err(anyhow!("{}{}", stdout, stderr));
+        }
+        let mut output = stderr.to_string();
+        match stdout.rsplit_once(VALUE_MARKER) {
+            Some((printed, value)) => {
+                output.insert_str(0, printed);
+                output.push_str(&format!("=> {} : {}\n", value.trim_end(), ty));
+            }
+            None => output.insert_str(0, &stdout),
+        }
+        Ok(output)
+    }
+
+    /// Parse the program
+    fn parse(&self, src: &str) -> Result<shiika_ast::Program> {
+        // Written to the file because the error report reads it
+        let path = self.write_program(src)?;
+        let file = SourceFile::new(path, src.to_string());
+        Ok(Parser::parse_files(&[file])?)
+    }
+
+    /// Write the program to the file and returns the path
+    fn write_program(&self, src: &str) -> Result<PathBuf> {
+        let path = self.dir.join("input.sk");
+        fs::write(&path, src).context("failed to write the program")?;
+        Ok(path)
+    }
+
+    /// Collect the names for the tab completion
+    fn update_names(&mut self) -> Result<()> {
+        let ast = self.parse(&self.defs)?;
+        let imports = &self.imports;
+        let (_, names) = skc_ast2hir::analyze(ast, imports, |hir, class_dict| {
+            Names::new(hir, class_dict, imports)
+        })?;
+        self.names = names;
+        Ok(())
+    }
+}
+
+impl Drop for Repl {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Returns true if the error is because the input ended too early
+fn is_incomplete(e: &shiika_parser::Error) -> bool {
+    match e {
+        // eg. "unexpected token: Eof", "missing `end' for class ...; got Eof"
+        shiika_parser::Error::ParseError(msg) => {
+            msg.lines().next().unwrap_or_default().contains("Eof")
+        }
+        shiika_parser::Error::LexError { msg, .. } => {
+            msg == "found unterminated string" || msg == "found unterminated heredoc"
+        }
+    }
+}
+
+/// Names for the tab completion
+#[derive(Debug, Default, Clone)]
+pub struct Names {
+    /// Toplevel constants (including the classes)
+    constants: Vec<String>,
+    /// Methods of the constants (eg. `new` of a class)
+    methods: HashMap<String, Vec<String>>,
+    /// Methods which can be called without the receiver (i.e. those of
+    /// `Object`)
+    functions: Vec<String>,
+}
+
+impl Names {
+    fn new(hir: &Hir, class_dict: &ClassDict, imports: &LibraryExports) -> Names {
+        let mut names = Names::default();
+        for (fullname, ty) in imports.constants.iter().chain(&hir.constants) {
+            let name = match fullname.0.strip_prefix("::") {
+                Some(name) if !name.contains("::") => name,
+                _ => continue,
+            };
+            names.constants.push(name.to_string());
+            names
+                .methods
+                .insert(name.to_string(), method_names(class_dict, ty));
+        }
+        names.constants.sort();
+        names.functions = method_names(class_dict, &ty::raw("Object"));
+        names
+    }
+
+    /// Returns the start of the word at `pos` and the names which start
+    /// with it. Methods are listed after `Foo.` and the constants and
+    /// the functions otherwise
+    pub fn candidates(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        let before = &line[..pos];
+        let start = word_start(before);
+        let prefix = &before[start..];
+        let names = match before[..start].strip_suffix('.') {
+            Some(receiver) => match self.methods.get(&receiver[word_start(receiver)..]) {
+                Some(methods) => methods.iter().collect::<Vec<_>>(),
+                None => vec![],
+            },
+            None => self.constants.iter().chain(&self.functions).collect(),
+        };
+        let found = names
+            .into_iter()
+            .filter(|name| name.starts_with(prefix))
+            .cloned()
+            .collect();
+        (start, found)
+    }
+}
+
+/// Returns the sorted names of the methods of `ty`
+fn method_names(class_dict: &ClassDict, ty: &TermTy) -> Vec<String> {
+    let mut names = class_dict
+        .method_names(ty)
+        .into_iter()
+        .map(|name| name.0)
+        // Operators are not completed
+        .filter(|name| name.starts_with(|c: char| c.is_alphabetic() || c == '_'))
+        .collect::<Vec<_>>();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Returns the index where the last identifier in `s` starts
+fn word_start(s: &str) -> usize {
+    s.char_indices()
+        .rev()
+        .find(|(_, c)| !(c.is_alphanumeric() || *c == '_'))
+        .map(|(i, c)| i + c.len_utf8())
+        .unwrap_or(0)
+}
+
+impl Completer for Names {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(self.candidates(line, pos))
+    }
+}
+
+impl Hinter for Names {
+    type Hint = String;
+}
+
+impl Highlighter for Names {}
+
+impl Validator for Names {}
+
+impl rustyline::Helper for Names {}
+
+/// Start the interactive shell. Reads the lines from the stdin without
+/// editing if it is not a terminal
+pub fn start() -> Result<()> {
+    let mut repl = Repl::new()?;
+    if !atty::is(atty::Stream::Stdin) {
+        let stdin = std::io::stdin();
+        return repl.run_script(&mut stdin.lock(), &mut std::io::stdout());
+    }
+    let mut editor = Editor::<Names>::new();
+    loop {
+        editor.set_helper(Some(repl.names().clone()));
+        let prompt = if repl.is_continuing() {
+            "shiika*> "
+        } else {
+            "shiika> "
+        };
+        match editor.readline(prompt) {
+            Ok(line) => {
+                editor.add_history_entry(line.as_str());
+                match repl.feed(&line) {
+                    Ok(Some(out)) => print!("{}", out),
+                    Ok(None) => {}
+                    Err(e) => eprintln!("{}", e),
+                }
+            }
+            // Ctrl-C
+            Err(ReadlineError::Interrupted) => repl.cancel(),
+            // Ctrl-D
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(())
+}
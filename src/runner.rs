@@ -1,48 +1,259 @@
-use crate::loader;
+use crate::build_cache::{self, BuildRecord, LibraryUnit};
 use crate::targets;
 use anyhow::{anyhow, Context, Error, Result};
-use shiika_parser::{Parser, SourceFile};
+use shiika_parser::{loader, Parser, SourceFile};
 use skc_ast2hir;
 use skc_codegen;
+use skc_codegen::{LibraryModule, ModuleKind, OptLevel};
 use skc_corelib;
 use skc_mir::LibraryExports;
 use std::env;
 use std::fs;
 use std::io::{Read, Write};
 use std::path::Path;
-use std::process::Command;
+use std::process::{Command, Output, Stdio};
+
+/// Options for `compile_with_options`
+#[derive(Debug, Default)]
+pub struct CompileOptions {
+    /// Warn constant initializers which call non-`@pure` methods
+    pub strict_constants: bool,
+    /// Emit logs of method calls (printed when `SHIIKA_TRACE` is set)
+    pub trace_calls: bool,
+    /// Emit DWARF debug information
+    pub debug_info: bool,
+    /// Optimization level of the generated code
+    pub opt_level: OptLevel,
+    /// Reuse the outputs of the last compilation (see `build_cache`)
+    pub incremental: bool,
+}
+
+impl CompileOptions {
+    /// Returns the string of the options which affect the generated code
+    fn cache_key(&self) -> String {
+        let options = CompileOptions {
+            incremental: false,
+            ..*self
+        };
+        format!("{:?}", options)
+    }
+}
 
 /// Generate .ll from .sk
 pub fn compile<P: AsRef<Path>>(filepath: P) -> Result<()> {
+    compile_with_options(filepath, &Default::default())
+}
+
+/// Generate .ll from .sk with the given options.
+/// With `options.incremental`, does nothing if the .bc is already generated
+/// from the same sources (see `build_cache`)
+pub fn compile_with_options<P: AsRef<Path>>(filepath: P, options: &CompileOptions) -> Result<()> {
     let path = filepath
         .as_ref()
         .to_str()
         .expect("failed to unwrap filepath")
         .to_string();
     let src = loader::load(filepath.as_ref())?;
-    let ast = Parser::parse_files(&src)?;
+    let exports_json = read_builtin_exports()?;
+    if !options.incremental {
+        // The .bc will not match the record anymore
+        build_cache::remove(&path);
+        return compile_program(&path, &src, &exports_json, options);
+    }
+    let record = BuildRecord::new(&src, &options.cache_key(), &exports_json);
+    if build_cache::is_up_to_date(&path, &record) {
+        log::debug!("{} is up to date", path);
+        return Ok(());
+    }
+    compile_program(&path, &src, &exports_json, options)?;
+    build_cache::save(&path, &record)?;
+    Ok(())
+}
+
+/// Generate .bc of the program. With `options.incremental`, the `require`d
+/// files are compiled separately if possible
+fn compile_program(
+    path: &str,
+    src: &[SourceFile],
+    exports_json: &str,
+    options: &CompileOptions,
+) -> Result<()> {
+    let libraries = if options.incremental {
+        compile_libraries(src, exports_json, options)?
+    } else {
+        None
+    };
+    let (ast, imports, libraries) = match libraries {
+        Some((imports, libraries)) => {
+            let entry = src.last().expect("[BUG] no source files");
+            let ast = Parser::parse_files(std::slice::from_ref(entry))?;
+            (ast, imports, libraries)
+        }
+        None => {
+            let ast = Parser::parse_files(src)?;
+            (ast, parse_builtin_exports(exports_json)?, vec![])
+        }
+    };
     log::debug!("created ast");
-    let imports = load_builtin_exports()?;
-    let hir = skc_ast2hir::make_hir(ast, &imports)?;
+    let hir = skc_ast2hir::make_hir(ast, &imports, options.strict_constants)?;
     log::debug!("created hir");
     let mir = skc_mir::build(hir, imports);
     log::debug!("created mir");
-    let bc_path = path.clone() + ".bc";
-    let ll_path = path + ".ll";
+    let bc_path = path.to_string() + ".bc";
+    let ll_path = path.to_string() + ".ll";
     let triple = targets::default_triple();
-    skc_codegen::run(&mir, &bc_path, Some(&ll_path), true, Some(&triple))?;
+    skc_codegen::run(
+        &mir,
+        &bc_path,
+        Some(&ll_path),
+        ModuleKind::Program { libraries },
+        options.trace_calls,
+        options.debug_info,
+        options.opt_level,
+        Some(&triple),
+    )?;
     log::debug!("created .bc");
     Ok(())
 }
 
+/// Compile the `require`d files one by one unless the cached ones are up to
+/// date. Returns the exports of them and the modules to link, or None if
+/// some of them cannot be compiled separately
+fn compile_libraries(
+    src: &[SourceFile],
+    exports_json: &str,
+    options: &CompileOptions,
+) -> Result<Option<(LibraryExports, Vec<LibraryModule>)>> {
+    let units = match LibraryUnit::list(src, &options.cache_key(), exports_json) {
+        Some(units) => units,
+        None => return Ok(None),
+    };
+    // Check all the files to compile before compiling any of them
+    let mut asts = vec![];
+    for (unit, file) in units.iter().zip(src) {
+        if unit.is_up_to_date() {
+            asts.push(None);
+            continue;
+        }
+        let ast = Parser::parse_files(std::slice::from_ref(file))?;
+        if !is_separable(&ast) {
+            log::debug!("{} cannot be compiled separately", unit.path.display());
+            return Ok(None);
+        }
+        asts.push(Some(ast));
+    }
+
+    LibraryUnit::create_dir()?;
+    let triple = targets::default_triple();
+    for (unit, ast) in units.iter().zip(asts) {
+        let ast = match ast {
+            Some(ast) => ast,
+            None => {
+                log::debug!("{} is up to date", unit.path.display());
+                continue;
+            }
+        };
+        let mut imports = parse_builtin_exports(exports_json)?;
+        for dep in &unit.deps {
+            let dep_unit = units
+                .iter()
+                .find(|u| &u.path == dep)
+                .expect("[BUG] unit not found");
+            imports.merge(load_library_exports(&dep_unit.exports_path())?);
+        }
+        let hir = skc_ast2hir::make_hir(ast, &imports, options.strict_constants)?;
+        let mir = skc_mir::build(hir, imports);
+        let exports = LibraryExports::new(&mir);
+        skc_codegen::run(
+            &mir,
+            &unit.bc_path(),
+            None,
+            ModuleKind::Library {
+                name: unit.name.clone(),
+            },
+            options.trace_calls,
+            options.debug_info,
+            options.opt_level,
+            Some(&triple),
+        )?;
+        let json = serde_json::to_string(&exports)?;
+        fs::write(unit.exports_path(), json).context("failed to write the exports")?;
+        unit.save()?;
+        log::debug!("compiled {}", unit.path.display());
+    }
+
+    let mut imports = parse_builtin_exports(exports_json)?;
+    let mut libraries = vec![];
+    for unit in &units {
+        imports.merge(load_library_exports(&unit.exports_path())?);
+        libraries.push(LibraryModule {
+            name: unit.name.clone(),
+            bc_path: unit.bc_path(),
+        });
+    }
+    Ok(Some((imports, libraries)))
+}
+
+/// Returns false if the file has toplevel expressions (which must be run
+/// in the main function) or `extension`s (which modify the types of other
+/// files)
+fn is_separable(ast: &shiika_ast::Program) -> bool {
+    ast.toplevel_items.iter().all(|item| match item {
+        shiika_ast::TopLevelItem::Def(def) => {
+            !matches!(def, shiika_ast::Definition::ExtensionDefinition { .. })
+        }
+        shiika_ast::TopLevelItem::Expr(_) => false,
+    })
+}
+
+/// Returns true if `compile_with_options` does not need to compile .sk again
+pub fn is_up_to_date<P: AsRef<Path>>(filepath: P, options: &CompileOptions) -> Result<bool> {
+    let path = filepath
+        .as_ref()
+        .to_str()
+        .expect("failed to unwrap filepath");
+    let src = loader::load(filepath.as_ref())?;
+    let exports_json = read_builtin_exports()?;
+    let record = BuildRecord::new(&src, &options.cache_key(), &exports_json);
+    Ok(build_cache::is_up_to_date(path, &record))
+}
+
+/// Parse and type-check .sk without generating code
+pub fn check<P: AsRef<Path>>(filepath: P, options: &CompileOptions) -> Result<()> {
+    let src = loader::load(filepath.as_ref())?;
+    let ast = Parser::parse_files(&src)?;
+    log::debug!("created ast");
+    let imports = load_builtin_exports()?;
+    skc_ast2hir::make_hir(ast, &imports, options.strict_constants)?;
+    log::debug!("created hir");
+    Ok(())
+}
+
 /// Load builtin/exports.json
-fn load_builtin_exports() -> Result<LibraryExports, Error> {
+pub(crate) fn load_builtin_exports() -> Result<LibraryExports, Error> {
+    parse_builtin_exports(&read_builtin_exports()?)
+}
+
+/// Read builtin/exports.json
+fn read_builtin_exports() -> Result<String, Error> {
     let mut f = fs::File::open("builtin/exports.json").context("builtin exports not found")?;
     let mut contents = String::new();
     f.read_to_string(&mut contents)
         .context("failed to read builtin exports")?;
+    Ok(contents)
+}
+
+fn parse_builtin_exports(contents: &str) -> Result<LibraryExports, Error> {
     let exports: LibraryExports =
-        serde_json::from_str(&contents).context("builtin exports is broken")?;
+        serde_json::from_str(contents).context("builtin exports is broken")?;
+    Ok(exports)
+}
+
+/// Load the exports of a library compiled separately
+fn load_library_exports(path: &Path) -> Result<LibraryExports, Error> {
+    let contents = fs::read_to_string(path).context("library exports not found")?;
+    let exports: LibraryExports =
+        serde_json::from_str(&contents).context("library exports is broken")?;
     Ok(exports)
 }
 
@@ -64,7 +275,10 @@ pub fn build_corelib() -> Result<(), Error> {
         &mir,
         "builtin/builtin.bc",
         Some("builtin/builtin.ll"),
+        ModuleKind::Builtin,
+        false,
         false,
+        OptLevel::None,
         Some(&triple),
     )?;
     log::debug!("created .bc");
@@ -92,7 +306,61 @@ pub fn run_and_capture<P: AsRef<Path>>(sk_path: P) -> Result<(String, String)> {
     run_(sk_path, true)
 }
 
+/// Execute compiled .ll with the command line arguments and the environment
+/// variables and return its output (for tests)
+pub fn run_with<P: AsRef<Path>>(
+    sk_path: P,
+    args: &[&str],
+    envs: &[(&str, &str)],
+) -> Result<Output> {
+    let exe_path = link(sk_path)?;
+    let output = Command::new(exe_path)
+        .args(args)
+        .envs(envs.iter().copied())
+        .output()
+        .context("failed to execute process")?;
+    Ok(output)
+}
+
+/// Execute compiled .ll with `input` given to the stdin and return its
+/// output (for tests)
+pub fn run_with_input<P: AsRef<Path>>(sk_path: P, input: &str) -> Result<Output> {
+    let exe_path = link(sk_path)?;
+    let mut child = Command::new(exe_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("failed to execute process")?;
+    // Dropped (i.e. closed) after writing so that the process gets EOF
+    child
+        .stdin
+        .take()
+        .expect("stdin is not piped")
+        .write_all(input.as_bytes())?;
+    let output = child
+        .wait_with_output()
+        .context("failed to wait for process")?;
+    Ok(output)
+}
+
 fn run_<P: AsRef<Path>>(sk_path: P, capture_out: bool) -> Result<(String, String)> {
+    let exe_path = link(sk_path)?;
+    let mut cmd = Command::new(exe_path);
+    if capture_out {
+        let output = cmd.output().context("failed to execute process")?;
+        let stdout = String::from_utf8(output.stdout).expect("invalid utf8 in stdout");
+        let stderr = String::from_utf8(output.stderr).expect("invalid utf8 in stderr");
+        Ok((stdout, stderr))
+    } else {
+        cmd.status()?;
+        Ok(("".to_string(), "".to_string()))
+    }
+}
+
+/// Link the compiled .bc with the runtime and return the path of the
+/// executable
+fn link<P: AsRef<Path>>(sk_path: P) -> Result<String> {
     let triple = targets::default_triple();
     let s = sk_path.as_ref().to_str().expect("failed to unwrap sk_path");
     //let ll_path = s.to_string() + ".ll";
@@ -143,30 +411,19 @@ fn run_<P: AsRef<Path>>(sk_path: P, capture_out: bool) -> Result<(String, String
     cmd.arg("builtin/builtin.bc");
     let cargo_target = env::var("SHIIKA_CARGO_TARGET").unwrap_or_else(|_| "target".to_string());
     cmd.arg(format!("{}/debug/libskc_rustlib.a", cargo_target));
-    cmd.arg(bc_path.clone());
+    cmd.arg(bc_path);
     cmd.arg("-ldl");
     cmd.arg("-lpthread");
     if !cmd.status()?.success() {
         return Err(anyhow!("clang failed"));
     }
 
-    fs::remove_file(bc_path)?;
-
     let exe_path = if out_path.starts_with('/') {
         out_path
     } else {
         format!("./{}", out_path)
     };
-    let mut cmd = Command::new(exe_path);
-    if capture_out {
-        let output = cmd.output().context("failed to execute process")?;
-        let stdout = String::from_utf8(output.stdout).expect("invalid utf8 in stdout");
-        let stderr = String::from_utf8(output.stderr).expect("invalid utf8 in stderr");
-        Ok((stdout, stderr))
-    } else {
-        cmd.status()?;
-        Ok(("".to_string(), "".to_string()))
-    }
+    Ok(exe_path)
 }
 
 /// Remove .bc and .out
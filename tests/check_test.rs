@@ -0,0 +1,49 @@
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+/// Run `shiika compile --check path`
+fn shiika_check(path: &str) -> Result<Output> {
+    let output = Command::new(env!("CARGO_BIN_EXE_shiika"))
+        .args(["compile", "--check", path])
+        .output()?;
+    Ok(output)
+}
+
+#[test]
+fn test_check_success() -> Result<()> {
+    let output = shiika_check("tests/check/ok.sk")?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    Ok(())
+}
+
+#[test]
+fn test_check_type_errors() -> Result<()> {
+    let output = shiika_check("tests/check/type_errors.sk")?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    // Not only the first one
+    assert!(stderr.contains("Meta:A#f should return"), "{}", stderr);
+    assert!(stderr.contains("Int does not have `foo'"), "{}", stderr);
+    assert!(stderr.contains("2 errors found"), "{}", stderr);
+    Ok(())
+}
+
+/// `--check` does not generate any code
+#[test]
+fn test_check_generates_no_code() -> Result<()> {
+    let path = "tests/check/ok.sk";
+    let bc_path = format!("{}.bc", path);
+    let ll_path = format!("{}.ll", path);
+    let _ = fs::remove_file(&bc_path);
+    let _ = fs::remove_file(&ll_path);
+
+    let output = shiika_check(path)?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    assert!(!Path::new(&bc_path).exists());
+    assert!(!Path::new(&ll_path).exists());
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use anyhow::{anyhow, Result};
+use shiika::runner;
+use std::fs;
+
+#[test]
+fn test_compile_errors() -> Result<()> {
+    let paths = fs::read_dir("tests/sk_error/")?;
+    for item in paths {
+        let pathbuf = item?.path();
+        let path = pathbuf
+            .to_str()
+            .ok_or_else(|| anyhow!("Filename not utf8"))?;
+        if path.ends_with(".sk") {
+            check_compile_error(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Compile tests/sk_error/x.sk
+/// Fail unless it fails with the message written in the first line
+/// (eg. `# error: some message`)
+fn check_compile_error(path: &str) -> Result<()> {
+    let src = fs::read_to_string(path)?;
+    let expected = src
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix("# error: "))
+        .ok_or_else(|| anyhow!("{}: expected error is not written", path))?;
+    match runner::compile(path) {
+        Ok(_) => {
+            runner::cleanup(path)?;
+            Err(anyhow!("{}: compiled successfully", path))
+        }
+        Err(e) => {
+            let msg = format!("{:?}", e);
+            assert!(
+                msg.contains(expected),
+                "{}: unexpected error: {}",
+                path,
+                msg
+            );
+            Ok(())
+        }
+    }
+}
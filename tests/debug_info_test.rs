@@ -0,0 +1,23 @@
+use anyhow::Result;
+use shiika::runner::{self, CompileOptions};
+use std::fs;
+
+#[test]
+fn test_debug_info() -> Result<()> {
+    let path = "tests/debug_info/methods.sk";
+    let options = CompileOptions {
+        debug_info: true,
+        ..Default::default()
+    };
+    runner::compile_with_options(path, &options)?;
+    let ll = fs::read_to_string(format!("{}.ll", path))?;
+    runner::cleanup(path)?;
+    fs::remove_file(format!("{}.ll", path))?;
+
+    assert!(ll.contains("!DICompileUnit("));
+    assert!(ll.contains("!DISubprogram(name: \"Meta:A#twice\""));
+    assert!(ll.contains("!DISubprogram(name: \"user_main\""));
+    assert!(ll.contains("!DILocalVariable(name: \"doubled\""));
+    assert!(ll.contains("!DILocalVariable(name: \"x\""));
+    Ok(())
+}
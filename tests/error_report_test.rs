@@ -0,0 +1,39 @@
+use anyhow::{anyhow, Result};
+use shiika::runner;
+
+/// Compile tests/error_report/x.sk and returns the error message
+fn compile_error(path: &str) -> Result<String> {
+    match runner::compile(path) {
+        Ok(_) => {
+            runner::cleanup(path)?;
+            Err(anyhow!("{}: compiled successfully", path))
+        }
+        Err(e) => Ok(format!("{:?}", e)),
+    }
+}
+
+#[test]
+fn test_type_mismatch() -> Result<()> {
+    let msg = compile_error("tests/error_report/type_mismatch.sk")?;
+    // The source line with the caret under the argument
+    assert!(msg.contains(r#"A.twice("2")"#), "{}", msg);
+    assert!(msg.contains("─┬─"), "{}", msg);
+    assert!(msg.contains("this is String"), "{}", msg);
+    // The declaration of the parameter
+    assert!(msg.contains("def self.twice(n: Int) -> Int"), "{}", msg);
+    assert!(msg.contains("parameter `n' is declared here"), "{}", msg);
+    // Not coloured
+    assert!(!msg.contains('\x1b'), "{:?}", msg);
+    Ok(())
+}
+
+#[test]
+fn test_missing_method() -> Result<()> {
+    let msg = compile_error("tests/error_report/missing_method.sk")?;
+    assert!(msg.contains("missing_method.sk:7"), "{}", msg);
+    assert!(msg.contains("c.cuont"), "{}", msg);
+    assert!(msg.contains("Counter does not have `cuont'"), "{}", msg);
+    assert!(msg.contains("did you mean `count'?"), "{}", msg);
+    assert!(!msg.contains('\x1b'), "{:?}", msg);
+    Ok(())
+}
@@ -0,0 +1,13 @@
+use anyhow::Result;
+use shiika::runner;
+
+#[test]
+fn test_printf() -> Result<()> {
+    let path = "tests/ffi/printf.sk";
+    runner::compile(path)?;
+    let (stdout, stderr) = runner::run_and_capture(path)?;
+    runner::cleanup(path)?;
+    assert_eq!(stderr, "");
+    assert_eq!(stdout, "printf: 42\n");
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use anyhow::Result;
+use shiika::runner;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Copy tests/incremental/*.sk into a temporary directory (so that they
+/// can be modified)
+fn setup() -> Result<PathBuf> {
+    let dir = std::env::temp_dir().join("shiika_incremental_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    for name in ["lib.sk", "main1.sk", "main2.sk"] {
+        fs::copy(Path::new("tests/incremental").join(name), dir.join(name))?;
+    }
+    Ok(dir)
+}
+
+fn options() -> runner::CompileOptions {
+    runner::CompileOptions {
+        incremental: true,
+        ..Default::default()
+    }
+}
+
+fn compile(path: &Path) -> Result<()> {
+    runner::compile_with_options(path, &options())
+}
+
+fn is_up_to_date(path: &Path) -> Result<bool> {
+    runner::is_up_to_date(path, &options())
+}
+
+#[test]
+fn test_incremental_compilation() -> Result<()> {
+    let dir = setup()?;
+    let lib = dir.join("lib.sk");
+    let main1 = dir.join("main1.sk");
+    let main2 = dir.join("main2.sk");
+    assert!(!is_up_to_date(&main1)?);
+
+    compile(&main1)?;
+    compile(&main2)?;
+    assert!(is_up_to_date(&main1)?);
+    assert!(is_up_to_date(&main2)?);
+
+    // main1.sk depends on lib.sk but main2.sk does not
+    let src = fs::read_to_string(&lib)?;
+    fs::write(&lib, src.replace("Hello", "Hi"))?;
+    assert!(!is_up_to_date(&main1)?);
+    assert!(is_up_to_date(&main2)?);
+
+    compile(&main1)?;
+    assert!(is_up_to_date(&main1)?);
+    let (stdout, _) = runner::run_and_capture(&main1)?;
+    assert_eq!(stdout, "Hi, world\n");
+    // The .bc is kept after linking
+    assert!(is_up_to_date(&main1)?);
+
+    // Changing main1.sk reuses the compiled lib.sk
+    let src = fs::read_to_string(&main1)?;
+    fs::write(&main1, src.replace("world", "Shiika"))?;
+    compile(&main1)?;
+    let (stdout, _) = runner::run_and_capture(&main1)?;
+    assert_eq!(stdout, "Hi, Shiika\n");
+
+    // Compile options also affect the generated code
+    let options = runner::CompileOptions {
+        trace_calls: true,
+        ..options()
+    };
+    assert!(!runner::is_up_to_date(&main2, &options)?);
+    // Compiling without `incremental` discards the record
+    runner::compile(&main2)?;
+    assert!(!is_up_to_date(&main2)?);
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
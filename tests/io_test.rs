@@ -0,0 +1,41 @@
+use anyhow::Result;
+use shiika::runner;
+use std::process::Output;
+
+fn run_read_line(input: &str) -> Result<Output> {
+    let path = "tests/io/read_line.sk";
+    runner::compile(path)?;
+    let output = runner::run_with_input(path, input)?;
+    runner::cleanup(path)?;
+    Ok(output)
+}
+
+#[test]
+fn test_read_line() -> Result<()> {
+    let output = run_read_line("foo\nbar baz\r\n\nlast")?;
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout)?,
+        "1: foo\n2: bar baz\n3: \n4: last\neof\nnone\n"
+    );
+
+    // Empty input
+    let output = run_read_line("")?;
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout)?, "eof\nnone\n");
+    Ok(())
+}
+
+#[test]
+fn test_read_all() -> Result<()> {
+    let path = "tests/io/read_all.sk";
+    runner::compile(path)?;
+    let output = runner::run_with_input(path, "one\ntwo\nthree\n")?;
+    runner::cleanup(path)?;
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout)?,
+        "first: one\ntwo\nthree\n[]\n"
+    );
+    Ok(())
+}
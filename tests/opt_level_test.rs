@@ -0,0 +1,74 @@
+use anyhow::Result;
+use shiika::runner::{self, CompileOptions};
+use skc_codegen::OptLevel;
+use std::fs;
+
+/// Returns the body of the llvm function `name` in `ll`
+fn llvm_func_body(ll: &str, name: &str) -> String {
+    let header = format!("@\"{}\"(", name);
+    ll.lines()
+        .skip_while(|line| !(line.starts_with("define ") && line.contains(&header)))
+        .take_while(|line| *line != "}")
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Compile tests/opt_level/lvar.sk with `level` and return the body of
+/// `A.twice` in the generated .ll
+fn compile_and_run(level: OptLevel) -> Result<String> {
+    let path = "tests/opt_level/lvar.sk";
+    let options = CompileOptions {
+        opt_level: level,
+        ..Default::default()
+    };
+    runner::compile_with_options(path, &options)?;
+    let ll = fs::read_to_string(format!("{}.ll", path))?;
+    let (stdout, stderr) = runner::run_and_capture(path)?;
+    runner::cleanup(path)?;
+    fs::remove_file(format!("{}.ll", path))?;
+
+    assert_eq!(stderr, "");
+    assert_eq!(stdout, "42\n");
+    Ok(llvm_func_body(&ll, "Meta:A#twice"))
+}
+
+/// Check the passes for each level are run on the generated code.
+/// (The levels are tested in one function because they share the .sk.)
+#[test]
+fn test_opt_levels() -> Result<()> {
+    let body = compile_and_run(OptLevel::None)?;
+    assert!(body.contains("alloca_End"), "{}", body);
+    assert!(body.contains(" alloca "), "{}", body);
+
+    // The blocks are merged by simplifycfg
+    let body = compile_and_run(OptLevel::Less)?;
+    assert!(!body.is_empty());
+    assert!(!body.contains("alloca_End"), "{}", body);
+
+    for level in [OptLevel::Default, OptLevel::Aggressive] {
+        let body = compile_and_run(level)?;
+        assert!(!body.is_empty());
+        assert!(!body.contains("alloca_End"), "{:?}: {}", level, body);
+        // The lvar is promoted to a register
+        assert!(!body.contains(" alloca "), "{:?}: {}", level, body);
+    }
+    Ok(())
+}
+
+/// Int overflow is not checked in the optimized program
+#[test]
+fn test_int_overflow_with_opt() -> Result<()> {
+    let path = "tests/opt_level/int_overflow.sk";
+    let options = CompileOptions {
+        opt_level: OptLevel::Default,
+        ..Default::default()
+    };
+    runner::compile_with_options(path, &options)?;
+    let (stdout, stderr) = runner::run_and_capture(path)?;
+    runner::cleanup(path)?;
+    fs::remove_file(format!("{}.ll", path))?;
+
+    assert_eq!(stderr, "");
+    assert_eq!(stdout, "-9223372036854775808\n");
+    Ok(())
+}
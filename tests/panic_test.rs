@@ -0,0 +1,102 @@
+use anyhow::Result;
+use shiika::runner;
+
+/// Run tests/panic/x.sk and check that it panics with `msg`
+fn check_panic(path: &str, msg: &str) -> Result<()> {
+    let stderr = run_panic(path)?;
+    assert!(
+        stderr.contains(msg),
+        "{}: unexpected stderr: {}",
+        path,
+        stderr
+    );
+    Ok(())
+}
+
+/// Run tests/panic/x.sk and returns the stderr
+fn run_panic(path: &str) -> Result<String> {
+    runner::compile(path)?;
+    let (stdout, stderr) = runner::run_and_capture(path)?;
+    runner::cleanup(path)?;
+    assert_eq!(stdout, "");
+    Ok(stderr)
+}
+
+#[test]
+fn test_array_index_out_of_range() -> Result<()> {
+    check_panic("tests/panic/array_index.sk", "Array#[]: idx too large")
+}
+
+#[test]
+fn test_array_pop_empty() -> Result<()> {
+    check_panic("tests/panic/array_pop.sk", "Array#pop: array is empty")
+}
+
+#[test]
+fn test_int_overflow() -> Result<()> {
+    check_panic("tests/panic/int_overflow.sk", "Int: overflow")
+}
+
+#[test]
+fn test_int_division_by_zero() -> Result<()> {
+    check_panic("tests/panic/int_division_by_zero.sk", "division by zero")
+}
+
+#[test]
+fn test_int_negative_shift() -> Result<()> {
+    check_panic("tests/panic/int_negative_shift.sk", "negative shift amount")
+}
+
+#[test]
+fn test_int_negative_times() -> Result<()> {
+    check_panic(
+        "tests/panic/int_negative_times.sk",
+        "Int#times: negative count",
+    )
+}
+
+#[test]
+fn test_frozen_ivar_assign() -> Result<()> {
+    check_panic(
+        "tests/panic/frozen_ivar_assign.sk",
+        "can't modify frozen Counter: @count",
+    )
+}
+
+#[test]
+fn test_let_pattern_mismatch() -> Result<()> {
+    check_panic(
+        "tests/panic/let_pattern.sk",
+        "let: the value does not match the pattern",
+    )
+}
+
+#[test]
+fn test_ffi_wrong_argument_count() -> Result<()> {
+    check_panic(
+        "tests/panic/ffi_arg_count.sk",
+        "Shiika::FFI.call: wrong number of arguments for labs (given 2, expected 1)",
+    )
+}
+
+#[test]
+fn test_string_format_wrong_argument_count() -> Result<()> {
+    check_panic(
+        "tests/panic/string_format_arg_count.sk",
+        "String.format: too few arguments (given 1",
+    )
+}
+
+#[test]
+fn test_stack_trace() -> Result<()> {
+    let stderr = run_panic("tests/panic/stacktrace.sk")?;
+    // The innermost first, with the location of `def`
+    let inner = stderr.find("  from Meta:StackTrace#inner (tests/panic/stacktrace.sk:6)");
+    let outer = stderr.find("  from Meta:StackTrace#outer (tests/panic/stacktrace.sk:2)");
+    assert!(
+        matches!((inner, outer), (Some(i), Some(o)) if i < o),
+        "unexpected stderr: {}",
+        stderr
+    );
+    Ok(())
+}
@@ -0,0 +1,24 @@
+use anyhow::Result;
+use shiika::runner;
+
+#[test]
+fn test_env_and_args() -> Result<()> {
+    let path = "tests/process/env_and_args.sk";
+    runner::compile(path)?;
+    let output = runner::run_with(path, &["foo", "bar"], &[("SHIIKA_TEST_VAR", "hello")])?;
+    runner::cleanup(path)?;
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout)?, "hello\n3\nfoo\nbar\n");
+    Ok(())
+}
+
+#[test]
+fn test_exit() -> Result<()> {
+    let path = "tests/process/exit.sk";
+    runner::compile(path)?;
+    let output = runner::run_with(path, &[], &[])?;
+    runner::cleanup(path)?;
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8(output.stdout)?, "before exit\n");
+    Ok(())
+}
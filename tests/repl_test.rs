@@ -0,0 +1,82 @@
+use anyhow::Result;
+use shiika::repl::Repl;
+
+fn eval(repl: &mut Repl, line: &str) -> Result<String> {
+    Ok(repl.feed(line)?.expect("input is not complete"))
+}
+
+#[test]
+fn test_repl() -> Result<()> {
+    let mut repl = Repl::new()?;
+    assert_eq!(eval(&mut repl, "1 + 2")?, "=> 3 : Int\n");
+
+    // Multi-line class definition
+    let lines = [
+        "class Counter",
+        "  def initialize(@n: Int)",
+        "  end",
+        "",
+        "  def succ -> Counter",
+        "    Counter.new(@n + 1)",
+        "  end",
+    ];
+    for line in lines {
+        assert_eq!(repl.feed(line)?, None);
+        assert!(repl.is_continuing());
+    }
+    assert_eq!(eval(&mut repl, "end")?, "");
+    assert_eq!(eval(&mut repl, "N = 10")?, "");
+
+    // Classes and constants are kept
+    assert_eq!(eval(&mut repl, "Counter.new(N).succ.n")?, "=> 11 : Int\n");
+    assert_eq!(eval(&mut repl, "puts \"hi\"")?, "hi\n");
+    assert_eq!(
+        eval(&mut repl, "print \"a\"; \"b\"")?,
+        "a=> \"b\" : String\n"
+    );
+
+    // Errors do not break the state
+    assert!(repl.feed("N + \"a\"").is_err());
+    assert!(repl.feed("A = 1; 2").is_err());
+    assert_eq!(eval(&mut repl, "N")?, "=> 10 : Int\n");
+
+    // Local variables are not kept (the input is not run again)
+    assert_eq!(eval(&mut repl, "puts \"x\"; let x = 1")?, "x\n=> 1 : Int\n");
+    assert!(repl.feed("x").is_err());
+    assert_eq!(eval(&mut repl, "puts \"y\"")?, "y\n");
+    Ok(())
+}
+
+#[test]
+fn test_repl_completion() -> Result<()> {
+    let mut repl = Repl::new()?;
+    eval(&mut repl, "class Counter; end")?;
+    assert_eq!(
+        repl.names().candidates("x = Cou", 7),
+        (4, vec!["Counter".to_string()])
+    );
+    assert_eq!(
+        repl.names().candidates("Counter.ne", 10),
+        (8, vec!["new".to_string()])
+    );
+    assert_eq!(
+        repl.names().candidates("pu", 2),
+        (0, vec!["puts".to_string()])
+    );
+    Ok(())
+}
+
+#[test]
+fn test_repl_script() -> Result<()> {
+    let script = r#"class Greeter
+  def self.greet(name: String) -> String
+    "Hello, #{name}"
+  end
+end
+Greeter.greet("world")
+"#;
+    let mut out = vec![];
+    Repl::new()?.run_script(&mut script.as_bytes(), &mut out)?;
+    assert_eq!(String::from_utf8(out)?, "=> \"Hello, world\" : String\n");
+    Ok(())
+}
@@ -0,0 +1,22 @@
+use anyhow::Result;
+use shiika::runner;
+use std::fs;
+
+/// Compile the program and return the generated .ll
+fn compile(path: &str) -> Result<String> {
+    runner::compile(path)?;
+    let ll = fs::read_to_string(format!("{}.ll", path))?;
+    runner::cleanup(path)?;
+    fs::remove_file(format!("{}.ll", path))?;
+    Ok(ll)
+}
+
+/// Check the output does not depend on the iteration order of HashMaps
+#[test]
+fn test_reproducible_output() -> Result<()> {
+    let path = "tests/reproducible/program.sk";
+    let ll1 = compile(path)?;
+    let ll2 = compile(path)?;
+    assert!(ll1 == ll2, "the generated .ll files differ");
+    Ok(())
+}
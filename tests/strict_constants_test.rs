@@ -0,0 +1,34 @@
+use anyhow::Result;
+use std::process::{Command, Output};
+
+/// Run `shiika compile --check --strict-constants path`
+fn shiika_check_strict(path: &str) -> Result<Output> {
+    let output = Command::new(env!("CARGO_BIN_EXE_shiika"))
+        .args(["compile", "--check", "--strict-constants", path])
+        .output()?;
+    Ok(output)
+}
+
+#[test]
+fn test_pure_initializers() -> Result<()> {
+    let output = shiika_check_strict("tests/strict_constants/pure.sk")?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    assert!(!stderr.contains("warning"), "{}", stderr);
+    Ok(())
+}
+
+#[test]
+fn test_impure_initializer() -> Result<()> {
+    let output = shiika_check_strict("tests/strict_constants/impure.sk")?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    assert!(
+        stderr.contains(
+            "warning: initializer of constant ::A calls a method not annotated with @pure (Meta:Counter#next)"
+        ),
+        "{}",
+        stderr
+    );
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use anyhow::Result;
+use shiika::runner;
+use std::fs;
+
+/// Returns the body of the llvm function `name` in `ll`
+fn llvm_func_body(ll: &str, name: &str) -> String {
+    let header = format!("@\"{}\"(", name);
+    ll.lines()
+        .skip_while(|line| !(line.starts_with("define ") && line.contains(&header)))
+        .take_while(|line| *line != "}")
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[test]
+fn test_tail_call() -> Result<()> {
+    let path = "tests/tail_call/methods.sk";
+    runner::compile(path)?;
+    let ll = fs::read_to_string(format!("{}.ll", path))?;
+    runner::cleanup(path)?;
+    fs::remove_file(format!("{}.ll", path))?;
+
+    let body = llvm_func_body(&ll, "Meta:A#sum");
+    assert!(!body.is_empty());
+    assert!(body.contains("TailCallLoop"));
+    // Mutual recursion
+    assert!(!llvm_func_body(&ll, "Meta:A#even?").contains("TailCallLoop"));
+    assert!(!llvm_func_body(&ll, "Meta:A#odd?").contains("TailCallLoop"));
+    // Virtual call
+    assert!(!llvm_func_body(&ll, "B#count").contains("TailCallLoop"));
+    Ok(())
+}
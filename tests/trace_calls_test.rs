@@ -0,0 +1,43 @@
+use anyhow::Result;
+use shiika::runner::{self, CompileOptions};
+use std::fs;
+
+#[test]
+fn test_trace_calls() -> Result<()> {
+    let path = "tests/trace_calls/methods.sk";
+    let options = CompileOptions {
+        trace_calls: true,
+        ..Default::default()
+    };
+    runner::compile_with_options(path, &options)?;
+    let traced = runner::run_with(path, &[], &[("SHIIKA_TRACE", "1")])?;
+    // Compile again because linking removes the .bc
+    runner::compile_with_options(path, &options)?;
+    let untraced = runner::run_with(path, &[], &[])?;
+    runner::cleanup(path)?;
+    fs::remove_file(format!("{}.ll", path))?;
+
+    assert!(traced.status.success());
+    // Only the methods in this file (the corelib is not compiled with
+    // `--trace-calls`)
+    let stderr = String::from_utf8(traced.stderr)?;
+    let lines = stderr
+        .lines()
+        .filter(|line| line.contains("Meta:A#"))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        vec![
+            "-> Meta:A#run",
+            "  -> Meta:A#twice",
+            "  <- Meta:A#twice",
+            "  -> Meta:A#twice",
+            "  <- Meta:A#twice",
+            "<- Meta:A#run",
+        ]
+    );
+    // Nothing is printed without `SHIIKA_TRACE`
+    assert!(untraced.status.success());
+    assert_eq!(String::from_utf8(untraced.stderr)?, "");
+    Ok(())
+}
@@ -0,0 +1,29 @@
+use anyhow::Result;
+use std::process::{Command, Output};
+
+/// Run `shiika compile --check path`
+fn shiika_check(path: &str) -> Result<Output> {
+    let output = Command::new(env!("CARGO_BIN_EXE_shiika"))
+        .args(["compile", "--check", path])
+        .output()?;
+    Ok(output)
+}
+
+#[test]
+fn test_dead_match_clause() -> Result<()> {
+    let output = shiika_check("tests/warning/dead_match_clause.sk")?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    assert!(
+        stderr.contains("this clause is never reached"),
+        "{}",
+        stderr
+    );
+    // Points the pattern of the third clause
+    assert!(
+        stderr.contains("tests/warning/dead_match_clause.sk:7:6"),
+        "{}",
+        stderr
+    );
+    Ok(())
+}